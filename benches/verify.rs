@@ -0,0 +1,76 @@
+//! Benchmarks comparing individual and batch Ed25519 JWS signature
+//! verification.
+
+// `criterion_group!` generates undocumented functions
+#![allow(missing_docs)]
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ed25519_dalek::{Signer as _, SigningKey};
+use rand::rngs::OsRng;
+use vercre_infosec::jose::jws::{verify_batch_ed25519, verify_compact, VerifyOptions};
+use vercre_infosec::{Curve, KeyType, PublicKeyJwk};
+
+// Signed compact JWSs and the public keys that verify them.
+fn signed(count: usize) -> Vec<(PublicKeyJwk, String)> {
+    let header = Base64UrlUnpadded::encode_string(br#"{"alg":"EdDSA","typ":"JWT","kid":"key-1"}"#);
+    (0..count)
+        .map(|i| {
+            let signing_key = SigningKey::generate(&mut OsRng);
+            let public_jwk = PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::Ed25519,
+                x: Base64UrlUnpadded::encode_string(signing_key.verifying_key().as_bytes()),
+                ..PublicKeyJwk::default()
+            };
+            let payload =
+                Base64UrlUnpadded::encode_string(format!(r#"{{"sub":"{i}"}}"#).as_bytes());
+            let sig = signing_key.sign(format!("{header}.{payload}").as_bytes());
+            let sig = Base64UrlUnpadded::encode_string(&sig.to_bytes());
+            (public_jwk, format!("{header}.{payload}.{sig}"))
+        })
+        .collect()
+}
+
+fn verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ed25519");
+
+    for count in [16, 256] {
+        let tokens = signed(count);
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("individual", count), &tokens, |b, tokens| {
+            let options = VerifyOptions::default();
+            b.iter(|| {
+                for (public_jwk, token) in tokens {
+                    verify_compact(token, public_jwk, &options).expect("should verify");
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", count), &tokens, |b, tokens| {
+            b.iter(|| {
+                let sigs = tokens
+                    .iter()
+                    .map(|(_, token)| {
+                        let (input, sig) = token.rsplit_once('.').expect("should be compact");
+                        (input, Base64UrlUnpadded::decode_vec(sig).expect("should decode"))
+                    })
+                    .collect::<Vec<_>>();
+                let items = tokens
+                    .iter()
+                    .zip(&sigs)
+                    .map(|((public_jwk, _), (input, sig))| {
+                        (public_jwk.clone(), *input, sig.as_slice())
+                    })
+                    .collect::<Vec<_>>();
+                verify_batch_ed25519(&items).expect("should verify");
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, verify);
+criterion_main!(benches);
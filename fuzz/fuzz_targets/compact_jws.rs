@@ -0,0 +1,18 @@
+//! Fuzz the compact JWS parser and verifier with arbitrary input.
+//!
+//! Run with `cargo fuzz run compact_jws` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vercre_infosec::jose::jws::{self, VerifyOptions};
+use vercre_infosec::{Jws, PublicKeyJwk};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(token) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = token.parse::<Jws>();
+    let _ = jws::peek_header(token);
+    let _ = jws::verify_compact(token, &PublicKeyJwk::default(), &VerifyOptions::default());
+});
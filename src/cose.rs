@@ -4,6 +4,7 @@
 
 pub mod cbor;
 mod key;
+pub mod mdoc;
 
 pub use cbor::Tag24;
 #[allow(clippy::module_name_repetitions)]
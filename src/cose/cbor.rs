@@ -98,12 +98,12 @@ mod test {
     use super::Tag24;
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "called `Result::unwrap()` on an `Err` value")]
     // A Tag24 cannot be serialized directly into a non-cbor format as it will lose the tag.
     fn non_cbor_roundtrip() {
         let original = Tag24(String::from("some data"));
         let json = serde_json::to_vec(&original).unwrap();
         let roundtripped = serde_json::from_slice(&json).unwrap();
-        assert_eq!(original, roundtripped)
+        assert_eq!(original, roundtripped);
     }
 }
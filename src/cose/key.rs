@@ -103,6 +103,9 @@ impl From<KeyType> for Value {
         match k {
             KeyType::Okp => Self::Integer(1.into()),
             KeyType::Ec => Self::Integer(2.into()),
+            KeyType::Rsa => Self::Integer(3.into()),
+            // provisional value from draft-ietf-cose-dilithium
+            KeyType::Akp => Self::Integer(7.into()),
             KeyType::Oct => Self::Integer(4.into()),
         }
     }
@@ -119,6 +122,8 @@ impl TryInto<KeyType> for Value {
         match integer.into() {
             1 => Ok(KeyType::Okp),
             2 => Ok(KeyType::Ec),
+            3 => Ok(KeyType::Rsa),
+            7 => Ok(KeyType::Akp),
             4 => Ok(KeyType::Oct),
             _ => Err(anyhow!("unsupported key type")),
         }
@@ -130,7 +135,13 @@ impl From<Curve> for Value {
         match crv {
             Curve::Ed25519 => Self::Integer(6.into()),
             Curve::Es256K => Self::Integer(8.into()),
-            Curve::X25519 => Self::Integer(1.into()),
+            Curve::X25519 => Self::Integer(4.into()),
+            Curve::P256 => Self::Integer(1.into()),
+            Curve::P384 => Self::Integer(2.into()),
+            Curve::P521 => Self::Integer(3.into()),
+            // ML-DSA parameter sets have no registered curve value
+            Curve::MlDsa44 => Self::Text("ML-DSA-44".into()),
+            Curve::MlDsa65 => Self::Text("ML-DSA-65".into()),
         }
     }
 }
@@ -139,6 +150,11 @@ impl TryInto<Curve> for Value {
     type Error = anyhow::Error;
 
     fn try_into(self) -> anyhow::Result<Curve> {
+        match self.as_text() {
+            Some("ML-DSA-44") => return Ok(Curve::MlDsa44),
+            Some("ML-DSA-65") => return Ok(Curve::MlDsa65),
+            _ => {}
+        }
         let Some(integer) = self.as_integer() else {
             return Err(anyhow!("issue deserializing curve"));
         };
@@ -146,6 +162,10 @@ impl TryInto<Curve> for Value {
         match integer.into() {
             6 => Ok(Curve::Ed25519),
             8 => Ok(Curve::Es256K),
+            4 => Ok(Curve::X25519),
+            1 => Ok(Curve::P256),
+            2 => Ok(Curve::P384),
+            3 => Ok(Curve::P521),
             _ => Err(anyhow!("unsupported curve: {integer:?}")),
         }
     }
@@ -0,0 +1,257 @@
+//! # Mobile Security Object (MSO)
+//!
+//! Support for verifying the issuer authentication (`IssuerAuth`) of an ISO/IEC
+//! 18013-5 mobile document (mdoc), such as a mobile driving licence.
+//!
+//! `IssuerAuth` is a `COSE_Sign1` whose payload is the Mobile Security Object
+//! (MSO), wrapped in a CBOR tag 24. The document signer's certificate chain is
+//! conveyed in the unprotected `x5chain` header ([RFC9360]).
+//!
+//! [RFC9360]: https://www.rfc-editor.org/rfc/rfc9360
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use ciborium::Value;
+use coset::{iana, CborSerializable, CoseSign1, Label, RegisteredLabelWithPrivate};
+use ecdsa::signature::Verifier as _;
+use serde::{Deserialize, Serialize};
+use x509_cert::der::Decode;
+
+use crate::cose::cbor::{self, Tag24};
+use crate::cose::CoseKey;
+use crate::x509::{self, Certificate};
+
+/// COSE header label for an X.509 certificate chain (`x5chain`).
+const X5CHAIN: i64 = 33;
+
+/// The Mobile Security Object signed by the document issuer.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MobileSecurityObject {
+    /// MSO version.
+    pub version: String,
+
+    /// Algorithm used to calculate the value digests (e.g. `SHA-256`).
+    pub digest_algorithm: String,
+
+    /// Digests of each data element, by namespace and digest ID.
+    pub value_digests: BTreeMap<String, BTreeMap<u64, Value>>,
+
+    /// The device (holder) key and its authorizations.
+    pub device_key_info: DeviceKeyInfo,
+
+    /// The document type (e.g. `org.iso.18013.5.1.mDL`).
+    pub doc_type: String,
+
+    /// The document's validity window.
+    pub validity_info: ValidityInfo,
+}
+
+/// The device key bound to the mdoc.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceKeyInfo {
+    /// The device's public key.
+    pub device_key: CoseKey,
+
+    /// Namespaces and data elements the device key is authorized to sign.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_authorizations: Option<Value>,
+
+    /// Additional information about the device key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_info: Option<Value>,
+}
+
+/// The MSO validity window. Times are RFC3339 date-time strings.
+///
+/// The caller is responsible for checking the current time falls within
+/// `valid_from` and `valid_until`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidityInfo {
+    /// When the MSO was signed.
+    pub signed: String,
+
+    /// The time from which the MSO is valid.
+    pub valid_from: String,
+
+    /// The time after which the MSO is no longer valid.
+    pub valid_until: String,
+
+    /// When the issuer expects to re-sign the MSO.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_update: Option<String>,
+}
+
+/// Verify an mdoc's `IssuerAuth` and return its Mobile Security Object.
+///
+/// The document signer certificate chain (`x5chain`) is verified up to one of
+/// the trust anchors, and the `COSE_Sign1` signature verified using the
+/// document signer's key. Supported algorithms are ES256 and `EdDSA`.
+///
+/// # Errors
+/// Returns an error if `issuer_auth` is not a valid `COSE_Sign1`, the
+/// certificate chain is missing or cannot be verified, the signature is
+/// invalid, or the payload is not a tag 24 encoded MSO.
+pub fn verify_mso(
+    issuer_auth: &[u8], trust_anchors: &[Certificate],
+) -> Result<MobileSecurityObject> {
+    tracing::debug!("verify_mso");
+
+    let sign1 = CoseSign1::from_slice(issuer_auth)
+        .map_err(|e| anyhow!("issue decoding `IssuerAuth`: {e}"))?;
+
+    let chain = x5chain(&sign1)?;
+    x509::verify_chain(&chain, trust_anchors)?;
+
+    let Some(RegisteredLabelWithPrivate::Assigned(alg)) = sign1.protected.header.alg else {
+        bail!("missing or unsupported `alg` header");
+    };
+    sign1.verify_signature(b"", |sig, tbs| verify_signature(alg, &chain[0], tbs, sig))?;
+
+    let Some(payload) = &sign1.payload else {
+        bail!("missing MSO payload");
+    };
+    let mso: Tag24<MobileSecurityObject> = cbor::from_slice(payload)?;
+    Ok(mso.0)
+}
+
+// Extract the `x5chain` certificates, leaf first.
+fn x5chain(sign1: &CoseSign1) -> Result<Vec<Certificate>> {
+    let Some((_, value)) = sign1.unprotected.rest.iter().find(|(l, _)| *l == Label::Int(X5CHAIN))
+    else {
+        bail!("missing `x5chain` header");
+    };
+
+    let ders = match value {
+        Value::Bytes(der) => vec![der],
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_bytes().ok_or_else(|| anyhow!("invalid `x5chain` entry")))
+            .collect::<Result<_>>()?,
+        _ => bail!("invalid `x5chain` header"),
+    };
+    ders.into_iter()
+        .map(|der| {
+            Certificate::from_der(der).map_err(|e| anyhow!("issue parsing certificate: {e}"))
+        })
+        .collect()
+}
+
+// Verify the signature using the certificate's public key.
+fn verify_signature(
+    alg: iana::Algorithm, cert: &Certificate, msg: &[u8], sig: &[u8],
+) -> Result<()> {
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    let Some(key) = spki.subject_public_key.as_bytes() else {
+        bail!("invalid document signer key");
+    };
+
+    match alg {
+        iana::Algorithm::ES256 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key)?;
+            let sig = p256::ecdsa::Signature::from_slice(sig)?;
+            key.verify(msg, &sig).map_err(|e| anyhow!("invalid `IssuerAuth` signature: {e}"))
+        }
+        iana::Algorithm::EdDSA => {
+            let key = ed25519_dalek::VerifyingKey::try_from(key)
+                .map_err(|e| anyhow!("invalid document signer key: {e}"))?;
+            let sig = ed25519_dalek::Signature::from_slice(sig)?;
+            key.verify(msg, &sig).map_err(|e| anyhow!("invalid `IssuerAuth` signature: {e}"))
+        }
+        _ => bail!("unsupported `IssuerAuth` algorithm: {alg:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::{CoseSign1Builder, HeaderBuilder};
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use rand::rngs::OsRng;
+    use x509_cert::der::Encode;
+
+    use super::*;
+    use crate::x509::tests::{issue, root};
+    use crate::{Curve, KeyType};
+
+    fn mso() -> MobileSecurityObject {
+        MobileSecurityObject {
+            version: "1.0".to_string(),
+            digest_algorithm: "SHA-256".to_string(),
+            value_digests: BTreeMap::from([(
+                "org.iso.18013.5.1".to_string(),
+                BTreeMap::from([(0, Value::Bytes(vec![1; 32]))]),
+            )]),
+            device_key_info: DeviceKeyInfo {
+                device_key: CoseKey {
+                    kty: KeyType::Okp,
+                    crv: Curve::Ed25519,
+                    x: vec![2; 32],
+                    y: None,
+                },
+                key_authorizations: None,
+                key_info: None,
+            },
+            doc_type: "org.iso.18013.5.1.mDL".to_string(),
+            validity_info: ValidityInfo {
+                signed: "2024-01-01T00:00:00Z".to_string(),
+                valid_from: "2024-01-01T00:00:00Z".to_string(),
+                valid_until: "2034-01-01T00:00:00Z".to_string(),
+                expected_update: None,
+            },
+        }
+    }
+
+    fn sign_mso(
+        alg: iana::Algorithm, cert: &Certificate, sign: impl FnOnce(&[u8]) -> Vec<u8>,
+    ) -> Vec<u8> {
+        let payload = cbor::to_vec(&Tag24(mso())).unwrap();
+        CoseSign1Builder::new()
+            .protected(HeaderBuilder::new().algorithm(alg).build())
+            .unprotected(
+                HeaderBuilder::new().value(X5CHAIN, Value::Bytes(cert.to_der().unwrap())).build(),
+            )
+            .payload(payload)
+            .create_signature(b"", sign)
+            .build()
+            .to_vec()
+            .unwrap()
+    }
+
+    #[test]
+    fn eddsa() {
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let anchor = root(&ca_key, "CN=IACA");
+        let signer_key = SigningKey::generate(&mut OsRng);
+        let signer = issue(&ca_key, "CN=IACA", "CN=Document Signer", &signer_key);
+
+        let issuer_auth = sign_mso(iana::Algorithm::EdDSA, &signer, |tbs| {
+            signer_key.sign(tbs).to_bytes().to_vec()
+        });
+        let verified = verify_mso(&issuer_auth, &[anchor]).expect("should verify");
+        assert_eq!(verified, mso());
+        assert_eq!(verified.validity_info.valid_until, "2034-01-01T00:00:00Z");
+
+        // untrusted issuer
+        let other = root(&p256::ecdsa::SigningKey::random(&mut OsRng), "CN=Other");
+        assert!(verify_mso(&issuer_auth, &[other]).is_err());
+    }
+
+    #[test]
+    fn es256() {
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let anchor = root(&ca_key, "CN=IACA");
+
+        let issuer_auth = sign_mso(iana::Algorithm::ES256, &anchor, |tbs| {
+            let sig: p256::ecdsa::Signature = ca_key.sign(tbs);
+            sig.to_vec()
+        });
+        verify_mso(&issuer_auth, std::slice::from_ref(&anchor)).expect("should verify");
+
+        // tampered signature
+        let tampered = sign_mso(iana::Algorithm::ES256, &anchor, |_| vec![0; 64]);
+        assert!(verify_mso(&tampered, &[anchor]).is_err());
+    }
+}
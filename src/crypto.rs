@@ -0,0 +1,29 @@
+//! # Cryptographic Utilities
+//!
+//! Primitives shared across JOSE and COSE processing.
+
+use subtle::ConstantTimeEq;
+
+/// Compare two byte strings in constant time.
+///
+/// Use when comparing MACs, authentication tags, or digests derived from
+/// secret material, where an early-exit comparison would leak timing
+/// information. Strings of differing lengths compare unequal; only their
+/// lengths are revealed.
+#[must_use]
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare() {
+        assert!(ct_eq(b"tag", b"tag"));
+        assert!(!ct_eq(b"tag", b"tab"));
+        assert!(!ct_eq(b"tag", b"tags"));
+        assert!(ct_eq(b"", b""));
+    }
+}
@@ -0,0 +1,224 @@
+//! # Decentralized Identifiers (DIDs)
+//!
+//! Minimal DID Document ([DID-CORE]) support for resolving the public key
+//! referenced by a signature's key ID (a DID URL such as
+//! `did:example:alice#key-1`).
+//!
+//! DID resolution itself is left to implementers of [`DidResolver`].
+//!
+//! [DID-CORE]: https://www.w3.org/TR/did-core
+
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::jose::jwk::PublicKeyJwk;
+
+/// `DidResolver` is implemented by callers to resolve a DID to its DID
+/// Document.
+pub trait DidResolver: Send + Sync {
+    /// Resolve the DID (without any fragment) to its DID Document.
+    fn resolve(&self, did: &str) -> impl Future<Output = Result<DidDocument>> + Send;
+}
+
+/// A DID Document, restricted to the properties needed to locate
+/// verification keys.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    /// The DID the document describes.
+    pub id: String,
+
+    /// Verification methods (keys) associated with the DID.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verification_method: Vec<VerificationMethod>,
+}
+
+impl DidDocument {
+    /// Find the public key of the verification method with the given ID.
+    ///
+    /// `id` may be an absolute DID URL (`did:example:alice#key-1`) or a
+    /// fragment relative to the document (`#key-1`). Returns `None` if no
+    /// method matches or the method's key cannot be converted to a JWK.
+    #[must_use]
+    pub fn verification_method(&self, id: &str) -> Option<PublicKeyJwk> {
+        let relative = |id: &str| id.strip_prefix(&self.id).unwrap_or(id).to_owned();
+        let id = relative(id);
+        let method = self.verification_method.iter().find(|vm| relative(&vm.id) == id)?;
+        method.public_key_jwk()
+    }
+}
+
+/// A verification method (public key) in a DID Document.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    /// The method's ID, either a DID URL or a fragment relative to the
+    /// document.
+    pub id: String,
+
+    /// The method type (e.g. `JsonWebKey2020` or `Multikey`).
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// The DID of the entity controlling the key.
+    pub controller: String,
+
+    /// The public key as a JWK.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key_jwk: Option<PublicKeyJwk>,
+
+    /// The public key as a multibase encoded string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+}
+
+impl VerificationMethod {
+    /// The method's public key as a JWK, converting from multibase if
+    /// required.
+    #[must_use]
+    pub fn public_key_jwk(&self) -> Option<PublicKeyJwk> {
+        if let Some(jwk) = &self.public_key_jwk {
+            return Some(jwk.clone());
+        }
+        PublicKeyJwk::from_multibase(self.public_key_multibase.as_deref()?).ok()
+    }
+}
+
+/// A verification method ID: a DID URL whose fragment identifies a method
+/// in the DID's document (e.g. `did:example:alice#key-1`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VerificationMethodId {
+    did: String,
+    fragment: String,
+}
+
+impl VerificationMethodId {
+    /// Parse a verification method reference, resolving a fragment-only
+    /// reference (`#key-1`) against `base_did`.
+    ///
+    /// # Errors
+    /// Returns an error if the reference (or `base_did`, for relative
+    /// references) is not a valid DID URL with a fragment.
+    pub fn resolve(reference: &str, base_did: &str) -> Result<Self> {
+        if reference.starts_with('#') {
+            return format!("{base_did}{reference}").parse();
+        }
+        reference.parse()
+    }
+
+    /// The DID, used to resolve the DID Document.
+    #[must_use]
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// The fragment (without `#`) identifying the method within the DID
+    /// Document.
+    #[must_use]
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+}
+
+impl FromStr for VerificationMethodId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((did, fragment)) = s.split_once('#') else {
+            bail!("verification method ID {s} has no fragment");
+        };
+        if fragment.is_empty() {
+            bail!("verification method ID {s} has an empty fragment");
+        }
+        validate_did(did)?;
+
+        Ok(Self {
+            did: did.to_string(),
+            fragment: fragment.to_string(),
+        })
+    }
+}
+
+impl Display for VerificationMethodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.did, self.fragment)
+    }
+}
+
+// Check `did` conforms to the DID syntax:
+// `did:` method-name `:` method-specific-id.
+fn validate_did(did: &str) -> Result<()> {
+    let invalid = || anyhow!("invalid DID: {did}");
+
+    let rest = did.strip_prefix("did:").ok_or_else(invalid)?;
+    let (method, id) = rest.split_once(':').ok_or_else(invalid)?;
+    if method.is_empty() || !method.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    // method-specific-id: idchars, `:`-separated, not ending in `:`
+    let idchar = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'%');
+    if id.is_empty() || id.ends_with(':') || !id.bytes().all(|b| b == b':' || idchar(b)) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn verification_method() {
+        let doc: DidDocument = serde_json::from_value(json!({
+            "id": "did:example:alice",
+            "verificationMethod": [{
+                "id": "did:example:alice#key-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:example:alice",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "key-1"}
+            }, {
+                "id": "#key-2",
+                "type": "JsonWebKey2020",
+                "controller": "did:example:alice",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "key-2"}
+            }]
+        }))
+        .expect("should deserialize");
+
+        let jwk = doc.verification_method("did:example:alice#key-1").expect("should find");
+        assert_eq!(jwk.x, "key-1");
+        let jwk = doc.verification_method("#key-1").expect("should find");
+        assert_eq!(jwk.x, "key-1");
+        let jwk = doc.verification_method("did:example:alice#key-2").expect("should find");
+        assert_eq!(jwk.x, "key-2");
+
+        assert!(doc.verification_method("did:example:alice#key-3").is_none());
+        assert!(doc.verification_method("did:example:bob#key-1").is_none());
+    }
+
+    #[test]
+    fn verification_method_id() {
+        let id: VerificationMethodId = "did:web:example.com:user:alice#key-1".parse().unwrap();
+        assert_eq!(id.did(), "did:web:example.com:user:alice");
+        assert_eq!(id.fragment(), "key-1");
+        assert_eq!(id.to_string(), "did:web:example.com:user:alice#key-1");
+
+        let id = VerificationMethodId::resolve("#key-2", "did:example:alice").unwrap();
+        assert_eq!(id.did(), "did:example:alice");
+        assert_eq!(id.fragment(), "key-2");
+
+        assert!("did:example:alice".parse::<VerificationMethodId>().is_err());
+        assert!("did:example:alice#".parse::<VerificationMethodId>().is_err());
+        assert!("did:Example:alice#key-1".parse::<VerificationMethodId>().is_err());
+        assert!("did:example:#key-1".parse::<VerificationMethodId>().is_err());
+        assert!("https://example.com#key-1".parse::<VerificationMethodId>().is_err());
+        assert!("#key-1".parse::<VerificationMethodId>().is_err());
+    }
+}
@@ -36,14 +36,251 @@
 //! [VC-JOSE-COSE]: https://w3c.github.io/vc-jose-cose
 //! [OpenID4VP]: https://openid.net/specs/openid-4-verifiable-presentations-1_0.html
 
+pub mod cnf;
+#[cfg(feature = "didcomm")]
+pub mod didcomm;
+pub mod dpop;
+pub mod federation;
 pub mod jwa;
 pub mod jwe;
 pub mod jwk;
 pub mod jws;
 pub mod jwt;
+pub mod profile;
+pub mod proof;
+pub mod sd_jwt;
+pub mod status;
 
+use std::future::Future;
+
+use anyhow::{bail, Result};
+use jwa::Algorithm;
 pub use jwe::{ContentAlgorithm, Jwe};
+use jwe::{JweBuilder, KeyAlgorithm, PublicKey};
 pub use jwk::PublicKeyJwk;
 pub use jws::{Jws, JwsBuilder};
+use jwt::Jwt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 pub use crate::{Curve, KeyType};
+use crate::{Receiver, Signer};
+
+/// Sign the payload, then encrypt the resulting JWT for the recipient,
+/// returning a compact JWE with the `cty` header set to `JWT`.
+///
+/// Uses the JWE defaults of A256GCM content encryption and ECDH-ES key
+/// agreement.
+///
+/// # Errors
+/// Returns an error if the payload cannot be signed, or the recipient's key is
+/// not a supported key agreement key.
+pub async fn sign_and_encrypt<T>(
+    payload: &T, signer: &impl Signer, recipient_jwk: &PublicKeyJwk,
+) -> Result<String>
+where
+    T: Serialize + Send + Sync,
+{
+    tracing::debug!("sign_and_encrypt");
+
+    let jwt = jws::encode(payload, signer).await?;
+    let recipient_public = PublicKey::try_from(recipient_jwk)?;
+
+    JweBuilder::new()
+        .content_type("JWT")
+        .payload(jwt)
+        .add_recipient(recipient_jwk.kid.clone().unwrap_or_default(), recipient_public)
+        .build()?
+        .encode()
+}
+
+/// Decrypt a compact JWE containing a signed JWT (`cty: JWT`), then verify
+/// the JWT and return its claims.
+///
+/// The JWE content encryption (`enc`) and JWS signature (`alg`) algorithms
+/// are validated independently: the former during decryption, the latter
+/// when verifying the signature against the key returned by `resolver`.
+///
+/// # Errors
+/// Returns an error if the JWE cannot be decrypted, does not contain a JWT,
+/// or the JWT cannot be verified.
+pub async fn decrypt_and_verify<F, Fut, T>(
+    compact_jwe: &str, receiver: &impl Receiver, resolver: F,
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decrypt_and_verify");
+
+    let jwe: Jwe = compact_jwe.parse()?;
+    let is_nested = jwe.protected.cty.as_deref().is_some_and(|cty| cty.eq_ignore_ascii_case("JWT"));
+    if !is_nested {
+        bail!("JWE does not contain a nested JWT");
+    }
+
+    let jwt: String = jwe::decrypt(&jwe, receiver).await?;
+    jws::decode(&jwt, resolver).await
+}
+
+/// The JWS signing algorithms that can be verified by this build of the
+/// crate, for advertising capabilities such as `alg_values_supported` in
+/// issuer or verifier metadata.
+///
+/// ML-DSA algorithms are only included when the `pqc` feature is enabled.
+#[must_use]
+pub const fn supported_signing_algorithms() -> &'static [Algorithm] {
+    &[
+        Algorithm::ES256,
+        Algorithm::ES256K,
+        Algorithm::ES384,
+        Algorithm::ES512,
+        Algorithm::EdDSA,
+        #[cfg(feature = "pqc")]
+        Algorithm::MlDsa44,
+        #[cfg(feature = "pqc")]
+        Algorithm::MlDsa65,
+    ]
+}
+
+/// The JWE key management algorithms (`alg`) supported by this build of the
+/// crate.
+#[must_use]
+pub const fn supported_key_algorithms() -> &'static [KeyAlgorithm] {
+    &[
+        KeyAlgorithm::EcdhEs,
+        KeyAlgorithm::EcdhEsA256Kw,
+        KeyAlgorithm::Ecdh1PuA256Kw,
+        KeyAlgorithm::EciesEs256K,
+        KeyAlgorithm::A256Kw,
+        KeyAlgorithm::A256GcmKw,
+        #[cfg(feature = "rsa")]
+        KeyAlgorithm::RsaOaep,
+        #[cfg(feature = "rsa")]
+        KeyAlgorithm::RsaOaep256,
+        KeyAlgorithm::Dir,
+    ]
+}
+
+/// The JWE content encryption algorithms (`enc`) supported by this build of
+/// the crate.
+#[must_use]
+pub const fn supported_content_algorithms() -> &'static [ContentAlgorithm] {
+    &[
+        ContentAlgorithm::A128Gcm,
+        ContentAlgorithm::A192Gcm,
+        ContentAlgorithm::A256Gcm,
+        ContentAlgorithm::A128CbcHs256,
+        ContentAlgorithm::A256CbcHs512,
+        ContentAlgorithm::XChaCha20Poly1305,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use base64ct::{Base64UrlUnpadded, Encoding};
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use rand::rngs::OsRng;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::jose::jwe::test::X25519;
+    use crate::{Algorithm, SyncSigner};
+
+    struct Ed25519(SigningKey);
+
+    impl SyncSigner for Ed25519 {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.0.sign(msg).to_bytes().to_vec())
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            Ok(self.0.verifying_key().as_bytes().to_vec())
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:alice#key-1".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn sign_then_encrypt() {
+        let signer = Ed25519(SigningKey::generate(&mut OsRng));
+        let receiver = X25519::new();
+        let recipient_jwk = PublicKey::from(receiver.public_key).to_jwk();
+        let claims = json!({"iss": "did:example:alice", "credential": "confidential"});
+
+        let compact_jwe =
+            sign_and_encrypt(&claims, &signer, &recipient_jwk).await.expect("should encrypt");
+        let jwe: Jwe = compact_jwe.parse().expect("should parse");
+        assert_eq!(jwe.protected.cty.as_deref(), Some("JWT"));
+
+        let signer_jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(signer.0.verifying_key().as_bytes()),
+            ..PublicKeyJwk::default()
+        };
+        let resolver = |_| {
+            let jwk = signer_jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let jwt: Jwt<Value> =
+            decrypt_and_verify(&compact_jwe, &receiver, resolver).await.expect("should verify");
+        assert_eq!(jwt.claims, claims);
+
+        // inner signature verified independently of decryption
+        let other = Ed25519(SigningKey::generate(&mut OsRng));
+        let other_jwk = PublicKeyJwk {
+            x: Base64UrlUnpadded::encode_string(other.0.verifying_key().as_bytes()),
+            ..signer_jwk.clone()
+        };
+        let wrong_key = |_| {
+            let jwk = other_jwk.clone();
+            async move { Ok(jwk) }
+        };
+        assert!(decrypt_and_verify::<_, _, Value>(&compact_jwe, &receiver, wrong_key)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn not_nested() {
+        let receiver = X25519::new();
+        let compact_jwe = jwe::encrypt("plaintext", PublicKey::from(receiver.public_key))
+            .and_then(|jwe| jwe.encode())
+            .expect("should encrypt");
+
+        let err = decrypt_and_verify::<_, _, Value>(&compact_jwe, &receiver, |_| async {
+            bail!("should not resolve")
+        })
+        .await
+        .expect_err("should fail");
+        assert_eq!(err.to_string(), "JWE does not contain a nested JWT");
+    }
+
+    #[test]
+    fn supported_algorithms() {
+        let signing = supported_signing_algorithms();
+        assert!(signing.contains(&Algorithm::EdDSA));
+        assert_eq!(signing.contains(&Algorithm::MlDsa44), cfg!(feature = "pqc"));
+
+        // suitable for metadata such as `alg_values_supported`
+        let values = serde_json::to_value(supported_key_algorithms()).expect("should serialize");
+        assert!(values.as_array().is_some_and(|algs| algs.contains(&json!("ECDH-ES"))));
+        assert_eq!(
+            supported_key_algorithms().contains(&KeyAlgorithm::RsaOaep256),
+            cfg!(feature = "rsa")
+        );
+        let values =
+            serde_json::to_value(supported_content_algorithms()).expect("should serialize");
+        assert!(values.as_array().is_some_and(|encs| encs.contains(&json!("A256GCM"))));
+    }
+}
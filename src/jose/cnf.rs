@@ -0,0 +1,127 @@
+//! # Confirmation (`cnf`) Claim
+//!
+//! The `cnf` claim ([RFC7800]) binds a credential or token to the holder's
+//! key, either by embedding the key (`cnf.jwk`) or by referencing it
+//! (`cnf.kid`), typically using a DID URL.
+//!
+//! The holder proves possession of the key by signing a Key Binding JWT or
+//! proof JWT, which is then verified using the key returned by
+//! [`key_from_claims`].
+//!
+//! [RFC7800]: https://www.rfc-editor.org/rfc/rfc7800
+
+use std::future::Future;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+use crate::jose::jwk::PublicKeyJwk;
+use crate::jose::jws::Key;
+
+/// The holder's key as confirmed by the `cnf` claim of `claims`: the
+/// embedded key (`cnf.jwk`), or the key ID (`cnf.kid`) when no key is
+/// embedded.
+///
+/// # Errors
+/// Returns an error if the claims have no `cnf` claim, `cnf` has neither a
+/// `jwk` nor a `kid`, or the `jwk` is not a valid public key.
+pub fn confirmation_key(claims: &Value) -> Result<Key> {
+    let Some(cnf) = claims.get("cnf") else {
+        bail!("missing `cnf` claim");
+    };
+    if let Some(jwk) = cnf.get("jwk") {
+        let jwk: PublicKeyJwk = serde_json::from_value(jwk.clone())
+            .map_err(|e| anyhow!("issue deserializing `cnf.jwk`: {e}"))?;
+        jwk.validate()?;
+        return Ok(Key::Jwk(jwk));
+    }
+    match cnf.get("kid") {
+        Some(Value::String(kid)) if !kid.is_empty() => Ok(Key::KeyId(kid.clone())),
+        Some(_) => bail!("`cnf.kid` must be a non-empty string"),
+        None => bail!("`cnf` claim has neither a `jwk` nor a `kid`"),
+    }
+}
+
+/// Extract the holder's public key from the `cnf` claim of `claims`.
+///
+/// An embedded `cnf.jwk` is returned directly. A `cnf.kid` is dereferenced by
+/// calling `resolver`, which is not called otherwise.
+///
+/// # Errors
+/// Returns an error if the `cnf` claim is missing or invalid (see
+/// [`confirmation_key`]), or a `kid` cannot be resolved.
+pub async fn key_from_claims<F, Fut>(claims: &Value, resolver: F) -> Result<PublicKeyJwk>
+where
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("key_from_claims");
+
+    match confirmation_key(claims)? {
+        Key::Jwk(jwk) => Ok(jwk),
+        Key::KeyId(kid) => resolver(kid).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64ct::{Base64UrlUnpadded, Encoding};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{Curve, KeyType};
+
+    fn holder_jwk() -> PublicKeyJwk {
+        PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(&[
+                0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64,
+                0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68,
+                0xf7, 0x07, 0x51, 0x1a,
+            ]),
+            ..PublicKeyJwk::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn embedded_jwk() {
+        let claims = json!({"iss": "https://issuer.example.com", "cnf": {"jwk": holder_jwk()}});
+        let jwk = key_from_claims(&claims, |_| async { bail!("should not resolve") })
+            .await
+            .expect("should extract");
+        assert_eq!(jwk, holder_jwk());
+    }
+
+    #[tokio::test]
+    async fn key_id() {
+        let claims = json!({"cnf": {"kid": "did:example:holder#key-1"}});
+        assert_eq!(
+            confirmation_key(&claims).expect("should parse"),
+            Key::KeyId("did:example:holder#key-1".to_string())
+        );
+
+        let jwk = key_from_claims(&claims, |kid| async move {
+            assert_eq!(kid, "did:example:holder#key-1");
+            Ok(holder_jwk())
+        })
+        .await
+        .expect("should resolve");
+        assert_eq!(jwk, holder_jwk());
+    }
+
+    #[test]
+    fn invalid() {
+        let error = |claims: Value| confirmation_key(&claims).expect_err("should fail").to_string();
+
+        assert_eq!(error(json!({"iss": "https://issuer.example.com"})), "missing `cnf` claim");
+        assert_eq!(error(json!({"cnf": {}})), "`cnf` claim has neither a `jwk` nor a `kid`");
+        assert_eq!(error(json!({"cnf": {"kid": ""}})), "`cnf.kid` must be a non-empty string");
+
+        let truncated = PublicKeyJwk {
+            x: "AAAA".to_string(),
+            ..holder_jwk()
+        };
+        assert!(confirmation_key(&json!({"cnf": {"jwk": truncated}})).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! # DIDComm Encrypted Messages
+//!
+//! Packs and unpacks [DIDComm v2] encrypted message envelopes using the
+//! crate's multi-recipient JWE support.
+//!
+//! - **authcrypt** (sender-authenticated): `ECDH-1PU+A256KW` with
+//!   `A256CBC-HS512` content encryption, with the sender's key ID in the
+//!   `skid` and `apu` headers.
+//! - **anoncrypt** (anonymous sender): `ECDH-ES+A256KW` with `A256GCM`
+//!   content encryption.
+//!
+//! In both cases, recipient `kid`s must be DID URLs and `apv` is the SHA-256
+//! hash of the sorted recipient `kid`s joined with `.`.
+//!
+//! [DIDComm v2]: https://identity.foundation/didcomm-messaging/spec/v2.1
+
+use std::future::Future;
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::did::VerificationMethodId;
+use crate::jose::jwe::{
+    self, ContentAlgorithm, Jwe, JweBuilder, KeyAlgorithm, PublicKey, Recipients, SecretKey,
+};
+use crate::jose::jwk::PublicKeyJwk;
+use crate::Receiver;
+
+/// The sender's static key agreement key, used for authcrypt.
+pub struct Sender {
+    /// The DID URL of the sender's key agreement key, set as the `skid`.
+    pub kid: String,
+
+    /// The sender's secret key.
+    pub secret_key: SecretKey,
+}
+
+/// An unpacked DIDComm message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unpacked<T> {
+    /// The decrypted message.
+    pub message: T,
+
+    /// The authenticated sender's key ID (`skid`) for an authcrypt message,
+    /// or `None` for an anoncrypt message.
+    pub sender_kid: Option<String>,
+}
+
+/// Encrypt `message` for each of the recipient keys in `to`.
+///
+/// The message is authcrypted when `from` is provided, and anoncrypted
+/// otherwise. Each recipient key must have a `kid` that is a DID URL, and all
+/// keys must be on the same curve as the sender's key.
+///
+/// # Errors
+/// Returns an error if there are no recipients, a `kid` is missing or not a
+/// DID URL, a key is not a supported key agreement key, or the message cannot
+/// be encrypted.
+pub fn pack_encrypted<T>(message: &T, from: Option<Sender>, to: &[PublicKeyJwk]) -> Result<Jwe>
+where
+    T: Serialize + Sync,
+{
+    tracing::debug!("pack_encrypted");
+
+    if to.is_empty() {
+        bail!("no recipients");
+    }
+    let mut kids = to.iter().map(did_url_kid).collect::<Result<Vec<_>>>()?;
+
+    let mut builder = JweBuilder::new();
+    for (kid, jwk) in kids.iter().zip(to) {
+        builder = builder.add_recipient(*kid, PublicKey::try_from(jwk)?);
+    }
+    kids.sort_unstable();
+    builder = builder.apv(Sha256::digest(kids.join(".")).to_vec());
+
+    builder = match from {
+        Some(sender) => {
+            sender.kid.parse::<VerificationMethodId>()?;
+            builder
+                .content_algorithm(ContentAlgorithm::A256CbcHs512)
+                .key_algorithm(KeyAlgorithm::Ecdh1PuA256Kw)
+                .apu(sender.kid.as_bytes())
+                .sender(sender.kid, sender.secret_key)
+        }
+        None => builder
+            .content_algorithm(ContentAlgorithm::A256Gcm)
+            .key_algorithm(KeyAlgorithm::EcdhEsA256Kw),
+    };
+
+    builder.payload(message).build()
+}
+
+/// Decrypt a DIDComm encrypted message for `receiver`.
+///
+/// For authcrypt messages, the sender's public key is obtained by calling
+/// `resolver` with the `skid` header value. The `skid` must match the `apu`
+/// header. `resolver` is not called for anoncrypt messages.
+///
+/// # Errors
+/// Returns an error if the JWE is not a DIDComm authcrypt or anoncrypt
+/// envelope, the sender's key cannot be resolved, or the message cannot be
+/// decrypted.
+pub async fn unpack<F, Fut, T>(
+    jwe: &Jwe, receiver: &impl Receiver, resolver: F,
+) -> Result<Unpacked<T>>
+where
+    T: DeserializeOwned,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("unpack");
+
+    let Recipients::Many { recipients } = &jwe.recipients else {
+        bail!("DIDComm envelope must use general JSON serialization");
+    };
+    let Some(recipient) = recipients.first() else {
+        bail!("no recipients");
+    };
+    for recipient in recipients {
+        let kid = recipient.header.kid.as_deref().unwrap_or_default();
+        kid.parse::<VerificationMethodId>()
+            .map_err(|e| anyhow!("recipient `kid` is not a DID URL: {e}"))?;
+    }
+
+    match recipient.header.alg {
+        KeyAlgorithm::EcdhEsA256Kw => Ok(Unpacked {
+            message: jwe::decrypt(jwe, receiver).await?,
+            sender_kid: None,
+        }),
+        KeyAlgorithm::Ecdh1PuA256Kw => {
+            if jwe.protected.enc != ContentAlgorithm::A256CbcHs512 {
+                bail!("authcrypt envelope must use `A256CBC-HS512`");
+            }
+            let Some(skid) = &jwe.protected.skid else {
+                bail!("authcrypt envelope is missing `skid`");
+            };
+            let apu = jwe.protected.apu.as_deref().map(Base64UrlUnpadded::decode_vec);
+            if !matches!(apu, Some(Ok(apu)) if apu == skid.as_bytes()) {
+                bail!("`apu` does not match `skid`");
+            }
+
+            let sender_jwk = resolver(skid.clone()).await?;
+            let sender_public = PublicKey::try_from(&sender_jwk)?;
+            Ok(Unpacked {
+                message: jwe::decrypt_authenticated(jwe, receiver, sender_public).await?,
+                sender_kid: Some(skid.clone()),
+            })
+        }
+        _ => bail!("unsupported DIDComm key management algorithm"),
+    }
+}
+
+// The recipient key's `kid`, which must be a DID URL.
+fn did_url_kid(jwk: &PublicKeyJwk) -> Result<&str> {
+    let Some(kid) = jwk.kid.as_deref() else {
+        bail!("recipient key is missing `kid`");
+    };
+    kid.parse::<VerificationMethodId>()?;
+    Ok(kid)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::jose::jwe::test::X25519;
+
+    #[tokio::test]
+    async fn authcrypt() {
+        let alice = X25519::new();
+        let bob = X25519::new();
+        let message = json!({"type": "https://didcomm.org/basicmessage/2.0/message"});
+
+        let to = PublicKeyJwk {
+            kid: Some(alice.key_id()),
+            ..PublicKey::from(alice.public_key).to_jwk()
+        };
+        let bob_jwk = PublicKeyJwk {
+            kid: Some("did:example:bob#key-x25519-1".to_string()),
+            ..PublicKey::from(bob.public_key).to_jwk()
+        };
+        let from = Sender {
+            kid: "did:example:bob#key-x25519-1".to_string(),
+            secret_key: SecretKey::from(bob.secret_key.to_bytes()),
+        };
+
+        let jwe = pack_encrypted(&message, Some(from), &[to]).expect("should pack");
+        assert_eq!(jwe.protected.skid.as_deref(), Some("did:example:bob#key-x25519-1"));
+        assert_eq!(jwe.protected.enc, ContentAlgorithm::A256CbcHs512);
+
+        let unpacked: Unpacked<Value> = unpack(&jwe, &alice, |_| {
+            let jwk = bob_jwk.clone();
+            async move { Ok(jwk) }
+        })
+        .await
+        .expect("should unpack");
+        assert_eq!(unpacked.message, message);
+        assert_eq!(unpacked.sender_kid.as_deref(), Some("did:example:bob#key-x25519-1"));
+
+        // authcrypt requires A256CBC-HS512
+        let mut downgraded = jwe;
+        downgraded.protected.enc = ContentAlgorithm::A256Gcm;
+        let err =
+            unpack::<_, _, Value>(&downgraded, &alice, |_| async { bail!("should not resolve") })
+                .await
+                .expect_err("should fail");
+        assert_eq!(err.to_string(), "authcrypt envelope must use `A256CBC-HS512`");
+    }
+
+    #[tokio::test]
+    async fn anoncrypt() {
+        let alice = X25519::new();
+        let message = json!({"type": "https://didcomm.org/basicmessage/2.0/message"});
+
+        let to = PublicKeyJwk {
+            kid: Some(alice.key_id()),
+            ..PublicKey::from(alice.public_key).to_jwk()
+        };
+        let jwe = pack_encrypted(&message, None, std::slice::from_ref(&to)).expect("should pack");
+        assert!(jwe.protected.skid.is_none());
+        assert_eq!(jwe.protected.enc, ContentAlgorithm::A256Gcm);
+
+        let unpacked: Unpacked<Value> =
+            unpack(&jwe, &alice, |_| async { bail!("should not resolve") })
+                .await
+                .expect("should unpack");
+        assert_eq!(unpacked.message, message);
+        assert_eq!(unpacked.sender_kid, None);
+
+        // recipient `kid`s must be DID URLs
+        let to = PublicKeyJwk {
+            kid: Some("key-1".to_string()),
+            ..to
+        };
+        assert!(pack_encrypted(&message, None, &[to]).is_err());
+    }
+}
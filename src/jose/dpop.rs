@@ -0,0 +1,280 @@
+//! # Demonstrating Proof of Possession (`DPoP`)
+//!
+//! A `DPoP` proof ([RFC9449]) is a JWT, signed by the client, binding an HTTP
+//! request to a key held by the client. The public key is embedded in the
+//! proof's `jwk` header and the request's method and URL are carried in the
+//! `htm` and `htu` claims.
+//!
+//! Access tokens are bound to the client's key using the key's JWK Thumbprint,
+//! as returned by [`verify_proof`].
+//!
+//! [RFC9449]: https://www.rfc-editor.org/rfc/rfc9449
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::jose::jws::{Jws, JwsBuilder};
+use crate::Signer;
+
+/// The `typ` header of a `DPoP` proof.
+const DPOP_TYPE: &str = "dpop+jwt";
+
+/// The maximum age, in seconds, of a proof's `iat`.
+const MAX_AGE: i64 = 300;
+
+/// Allowance, in seconds, for the client's clock running ahead of ours.
+const CLOCK_SKEW: i64 = 60;
+
+/// The claims of a `DPoP` proof.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DpopClaims {
+    /// A unique identifier for the proof, used to detect replay.
+    pub jti: String,
+
+    /// The HTTP method of the request.
+    pub htm: String,
+
+    /// The HTTP URL of the request, without query or fragment.
+    pub htu: String,
+
+    /// The time, in seconds since the Unix epoch, the proof was created.
+    pub iat: i64,
+
+    /// A nonce provided by the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+
+    /// The base64url-encoded SHA-256 hash of the access token the proof is
+    /// presented with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ath: Option<String>,
+}
+
+/// Create a `DPoP` proof for an HTTP request, signed by `signer` with its
+/// public key embedded in the `jwk` header.
+///
+/// When presented with an access token, pass the token as `access_token` and
+/// its hash is included as the `ath` claim.
+///
+/// # Errors
+/// Returns an error if the signer's key cannot be retrieved or signing fails.
+pub async fn create_proof(
+    signer: &impl Signer, method: &str, url: &str, nonce: Option<&str>, access_token: Option<&str>,
+) -> Result<String> {
+    tracing::debug!("create_proof");
+
+    let mut jti = [0; 16];
+    OsRng.fill_bytes(&mut jti);
+
+    let claims = DpopClaims {
+        jti: Base64UrlUnpadded::encode_string(&jti),
+        htm: method.to_string(),
+        htu: url.to_string(),
+        iat: now()?,
+        nonce: nonce.map(ToString::to_string),
+        ath: access_token.map(token_hash),
+    };
+
+    JwsBuilder::new()
+        .jwt_type(DPOP_TYPE)
+        .embed_jwk(true)
+        .payload(claims)
+        .add_signer(signer)
+        .build()
+        .await?
+        .to_compact()
+}
+
+/// Verify a `DPoP` proof for an HTTP request, returning the JWK Thumbprint of
+/// the proof's key for binding to (or checking against) an access token.
+///
+/// The signature is verified using the embedded `jwk`, the `htm` and `htu`
+/// claims checked against the request, and the proof rejected if its `iat`
+/// is older than 5 minutes. Callers are responsible for checking `jti` has not
+/// been seen before and, where used, the `nonce` and `ath` claims.
+///
+/// # Errors
+/// Returns an error if the proof is malformed, has the wrong `typ`, has no
+/// embedded `jwk`, fails signature verification, or its claims do not match
+/// the request.
+pub fn verify_proof(proof: &str, expected_method: &str, expected_url: &str) -> Result<String> {
+    tracing::debug!("verify_proof");
+    verify_at(proof, expected_method, expected_url, now()?)
+}
+
+// Verify the proof against the provided time (seconds since Unix epoch).
+fn verify_at(proof: &str, expected_method: &str, expected_url: &str, now: i64) -> Result<String> {
+    let jws: Jws = proof.parse()?;
+    let [signature] = jws.signatures.as_slice() else {
+        bail!("DPoP proof must have a single signature");
+    };
+
+    let typ = &signature.protected.typ;
+    if !typ.eq_ignore_ascii_case(DPOP_TYPE) {
+        bail!("unexpected `typ` header: {typ}");
+    }
+    let Some(jwk) = signature.protected.jwk() else {
+        bail!("DPoP proof requires an embedded `jwk`");
+    };
+    signature.verify(&jws.payload, jwk)?;
+
+    let claims = jws.jwt::<DpopClaims>(false)?.claims;
+    if claims.htm != expected_method {
+        bail!("`htm` does not match the request method");
+    }
+    if strip_url(&claims.htu) != strip_url(expected_url) {
+        bail!("`htu` does not match the request URL");
+    }
+    // `iat` is client-chosen: reject values too extreme to compare
+    let Some(age) = now.checked_sub(claims.iat) else {
+        bail!("DPoP proof `iat` is out of range");
+    };
+    if age > MAX_AGE {
+        bail!("DPoP proof has expired");
+    }
+    if age < -CLOCK_SKEW {
+        bail!("DPoP proof `iat` is in the future");
+    }
+
+    jwk.thumbprint()
+}
+
+/// Compute the `ath` claim value for an access token.
+#[must_use]
+pub fn token_hash(access_token: &str) -> String {
+    Base64UrlUnpadded::encode_string(&Sha256::digest(access_token.as_bytes()))
+}
+
+// URLs are compared without query and fragment parts (RFC9449 §4.3).
+fn strip_url(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or_default()
+}
+
+fn now() -> Result<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    i64::try_from(now).map_err(|e| anyhow!("invalid time: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer as _, SigningKey};
+
+    use super::*;
+    use crate::jose::jws;
+    use crate::{Algorithm, SyncSigner};
+
+    struct Client(SigningKey);
+
+    impl SyncSigner for Client {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.0.sign(msg).to_bytes().to_vec())
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            Ok(self.0.verifying_key().as_bytes().to_vec())
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:client#key-1".to_string())
+        }
+    }
+
+    const URL: &str = "https://server.example.com/token";
+
+    #[tokio::test]
+    async fn round_trip() {
+        let client = Client(SigningKey::generate(&mut OsRng));
+        let proof = create_proof(&client, "POST", URL, Some("n-0S6_WzA2Mj"), Some("token"))
+            .await
+            .expect("should create");
+
+        let thumbprint =
+            verify_proof(&proof, "POST", &format!("{URL}?a=b")).expect("should verify");
+        let jwk = client.public_key_jwk().await.expect("should get key");
+        assert_eq!(thumbprint, jwk.thumbprint().unwrap());
+
+        let claims: DpopClaims = jws::peek_payload(&proof).expect("should peek");
+        assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+        assert_eq!(claims.ath, Some(token_hash("token")));
+
+        let err = verify_proof(&proof, "GET", URL).expect_err("should fail");
+        assert_eq!(err.to_string(), "`htm` does not match the request method");
+        let err = verify_proof(&proof, "POST", "https://other.example.com/token")
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "`htu` does not match the request URL");
+
+        let err =
+            verify_at(&proof, "POST", URL, now().unwrap() + MAX_AGE + 1).expect_err("should fail");
+        assert_eq!(err.to_string(), "DPoP proof has expired");
+    }
+
+    // RFC9449 §4.2 orders `typ` first, and clients order `jwk` members freely
+    #[test]
+    fn header_order() {
+        let client = Client(SigningKey::generate(&mut OsRng));
+        let x = Base64UrlUnpadded::encode_string(client.0.verifying_key().as_bytes());
+        let header = Base64UrlUnpadded::encode_string(
+            format!(r#"{{"typ":"dpop+jwt","alg":"EdDSA","jwk":{{"x":"{x}","crv":"Ed25519","kty":"OKP"}}}}"#)
+                .as_bytes(),
+        );
+        let claims = serde_json::json!({
+            "jti": "e1j3V_bKic8-LAEB", "htm": "POST", "htu": URL, "iat": now().unwrap()
+        });
+        let payload = Base64UrlUnpadded::encode_string(claims.to_string().as_bytes());
+        let sig = client.0.sign(format!("{header}.{payload}").as_bytes());
+        let proof =
+            format!("{header}.{payload}.{}", Base64UrlUnpadded::encode_string(&sig.to_bytes()));
+
+        verify_proof(&proof, "POST", URL).expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn extreme_iat() {
+        let client = Client(SigningKey::generate(&mut OsRng));
+
+        for (iat, expected) in [
+            (i64::MIN, "DPoP proof `iat` is out of range"),
+            (i64::MAX, "DPoP proof `iat` is in the future"),
+        ] {
+            let proof = JwsBuilder::new()
+                .jwt_type(DPOP_TYPE)
+                .embed_jwk(true)
+                .payload(serde_json::json!({"jti": "jti", "htm": "POST", "htu": URL, "iat": iat}))
+                .add_signer(&client)
+                .build()
+                .await
+                .expect("should build")
+                .to_compact()
+                .expect("should serialize");
+            let err = verify_proof(&proof, "POST", URL).expect_err("should fail");
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn requires_embedded_jwk() {
+        let client = Client(SigningKey::generate(&mut OsRng));
+        let proof = JwsBuilder::new()
+            .jwt_type(DPOP_TYPE)
+            .payload(serde_json::json!({"htm": "POST", "htu": URL, "iat": now().unwrap()}))
+            .add_signer(&client)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+
+        let err = verify_proof(&proof, "POST", URL).expect_err("should fail");
+        assert_eq!(err.to_string(), "DPoP proof requires an embedded `jwk`");
+    }
+}
@@ -0,0 +1,575 @@
+//! # OpenID Federation Trust Chains
+//!
+//! Verification of [OpenID Federation] Trust Chains, such as those conveyed in
+//! the JWS `trust_chain` header.
+//!
+//! A Trust Chain is an ordered list of Entity Statements (signed JWTs),
+//! starting with the leaf Entity's self-signed Entity Configuration and ending
+//! with a statement issued by the Trust Anchor. Each statement is signed by a
+//! key published in the `jwks` of the following statement, with the final
+//! statement signed by one of the Trust Anchor's (out-of-band) keys.
+//!
+//! [OpenID Federation]: https://openid.net/specs/openid-federation-1_0.html
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::jose::jwk::{Jwks, PublicKeyJwk};
+use crate::jose::jws::Jws;
+use crate::jose::jwt::{RegisteredClaims, Validation};
+
+/// An Entity Statement: either an Entity Configuration (self-signed, `iss`
+/// equals `sub`) or a Subordinate Statement issued by a superior Entity.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EntityStatement {
+    /// The Entity issuing the statement.
+    pub iss: String,
+
+    /// The Entity the statement is about.
+    pub sub: String,
+
+    /// The time, in seconds since the Unix epoch, the statement was issued.
+    pub iat: i64,
+
+    /// The time, in seconds since the Unix epoch, the statement expires.
+    pub exp: i64,
+
+    /// The subject's Federation Entity Keys.
+    pub jwks: Jwks,
+
+    /// Immediate superiors of the Entity. Only used in Entity Configurations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authority_hints: Option<Vec<String>>,
+
+    /// Entity metadata, keyed by Entity Type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Map<String, Value>>,
+
+    /// Metadata policy applied to subordinate metadata, keyed by Entity Type
+    /// then metadata parameter. Only used in Subordinate Statements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_policy: Option<Map<String, Value>>,
+}
+
+/// The leaf Entity of a verified Trust Chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolvedEntity {
+    /// The key the leaf Entity used to sign its Entity Configuration, as
+    /// attested by its immediate superior.
+    pub key: PublicKeyJwk,
+
+    /// The leaf Entity's metadata, keyed by Entity Type, after applying the
+    /// Trust Chain's metadata policy.
+    pub metadata: Map<String, Value>,
+}
+
+/// Verify a Trust Chain, returning the leaf Entity's signing key and resolved
+/// metadata.
+///
+/// Each Entity Statement is checked for expiry and verified using the
+/// matching (`kid`) key in the following statement's `jwks`. The final
+/// statement is verified using the Trust Anchor's keys. The metadata policies
+/// of superior Entities are then merged, from the Trust Anchor down, and the
+/// merged policy applied to the leaf Entity's metadata.
+///
+/// # Errors
+/// Returns an error if any statement cannot be verified or has expired, if
+/// the chain is not contiguous (`iss` of each statement must equal `sub` of
+/// the next), if metadata policies conflict, or if the leaf Entity's metadata
+/// violates the merged metadata policy.
+pub fn verify_trust_chain(chain: &[String], trust_anchor_jwks: &Jwks) -> Result<ResolvedEntity> {
+    if chain.is_empty() {
+        bail!("trust chain is empty");
+    }
+
+    let statements = chain.iter().map(|es| Jws::from_str(es)).collect::<Result<Vec<Jws>>>()?;
+    let claims = statements
+        .iter()
+        .map(|jws| Ok(jws.jwt::<EntityStatement>(false)?.claims))
+        .collect::<Result<Vec<EntityStatement>>>()?;
+
+    let leaf = &claims[0];
+    if leaf.iss != leaf.sub {
+        bail!("first trust chain statement must be an Entity Configuration");
+    }
+
+    let validation = Validation::default();
+    let mut leaf_key = None;
+
+    for (j, jws) in statements.iter().enumerate() {
+        let statement = &claims[j];
+        validation.validate(&RegisteredClaims {
+            exp: Some(statement.exp),
+            iat: Some(statement.iat),
+            ..RegisteredClaims::default()
+        })?;
+
+        // keys for verifying are published by the next statement in the chain
+        let jwks = match claims.get(j + 1) {
+            Some(next) => {
+                if statement.iss != next.sub {
+                    bail!("statement issued by {} is not followed by its subject", statement.iss);
+                }
+                &next.jwks
+            }
+            None => trust_anchor_jwks,
+        };
+
+        let signature = &jws.signatures[0];
+        let Some(kid) = signature.kid() else {
+            bail!("missing key ID in Entity Statement");
+        };
+        let Some(jwk) = jwks.keys.iter().find(|k| k.kid.as_deref() == Some(kid)) else {
+            bail!("no key found for {kid}");
+        };
+        signature.verify(&jws.payload, jwk)?;
+
+        if j == 0 {
+            leaf_key = Some(jwk.clone());
+        }
+    }
+
+    // merge policies from the Trust Anchor down to the leaf, then apply once
+    let policies = claims.iter().skip(1).rev().filter_map(|s| s.metadata_policy.as_ref());
+    let policy = merge_policies(policies)?;
+    let mut metadata = leaf.metadata.clone().unwrap_or_default();
+    apply_policy(&mut metadata, &policy)?;
+
+    Ok(ResolvedEntity {
+        key: leaf_key.ok_or_else(|| anyhow!("no leaf key found"))?,
+        metadata,
+    })
+}
+
+// Merge metadata policies, ordered from the Trust Anchor down, as per
+// OpenID Federation §6.1.4.2.
+fn merge_policies<'a>(
+    policies: impl Iterator<Item = &'a Map<String, Value>>,
+) -> Result<Map<String, Value>> {
+    let mut merged = Map::new();
+    for policy in policies {
+        for (entity_type, params) in policy {
+            let Some(params) = params.as_object() else {
+                bail!("invalid metadata policy for {entity_type}");
+            };
+            let entry = merged.entry(entity_type).or_insert_with(|| Value::Object(Map::new()));
+            let Some(merged_params) = entry.as_object_mut() else {
+                bail!("invalid metadata policy for {entity_type}");
+            };
+
+            for (param, operators) in params {
+                let Some(operators) = operators.as_object() else {
+                    bail!("invalid metadata policy for {param}");
+                };
+                let entry = merged_params.entry(param).or_insert_with(|| Value::Object(Map::new()));
+                let Some(merged_operators) = entry.as_object_mut() else {
+                    bail!("invalid metadata policy for {param}");
+                };
+                merge_operators(merged_operators, param, operators)?;
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+// Merge a subordinate's policy operators for a single metadata parameter into
+// those of its superiors, rejecting conflicts.
+fn merge_operators(
+    merged: &mut Map<String, Value>, param: &str, operators: &Map<String, Value>,
+) -> Result<()> {
+    for (op, value) in operators {
+        let Some(current) = merged.get(op) else {
+            merged.insert(op.clone(), value.clone());
+            continue;
+        };
+
+        let combined = match op.as_str() {
+            "value" | "default" => {
+                if current != value {
+                    bail!("conflicting `{op}` policies for {param}");
+                }
+                continue;
+            }
+            "add" | "superset_of" => {
+                let mut union = array(current, op, param)?.clone();
+                for v in array(value, op, param)? {
+                    if !union.contains(v) {
+                        union.push(v.clone());
+                    }
+                }
+                union
+            }
+            "one_of" | "subset_of" => {
+                let other = array(value, op, param)?;
+                let intersection = array(current, op, param)?
+                    .iter()
+                    .filter(|v| other.contains(v))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if op == "one_of" && intersection.is_empty() {
+                    bail!("conflicting `one_of` policies for {param}");
+                }
+                intersection
+            }
+            "essential" => {
+                let essential = current == &Value::Bool(true) || value == &Value::Bool(true);
+                merged.insert(op.clone(), Value::Bool(essential));
+                continue;
+            }
+            _ => bail!("unsupported metadata policy operator: {op}"),
+        };
+        merged.insert(op.clone(), Value::Array(combined));
+    }
+
+    check_operators(merged, param)
+}
+
+// Check the combination of operators for a metadata parameter is consistent
+// (OpenID Federation §6.1.3.2), for example that a `value` set by a
+// subordinate is permitted by a superior's `one_of`.
+fn check_operators(operators: &Map<String, Value>, param: &str) -> Result<()> {
+    let get = |op: &str| operators.get(op).and_then(Value::as_array);
+    let contains_all =
+        |values: &[Value], required: &[Value]| required.iter().all(|v| values.contains(v));
+
+    let mut consistent = true;
+    if let Some(value) = operators.get("value").filter(|value| !value.is_null()) {
+        if let Some(one_of) = get("one_of") {
+            consistent &= one_of.contains(value);
+        }
+        if let Some(values) = value.as_array() {
+            consistent &=
+                get("subset_of").map_or(true, |subset_of| contains_all(subset_of, values));
+            consistent &=
+                get("superset_of").map_or(true, |superset_of| contains_all(values, superset_of));
+            consistent &= get("add").map_or(true, |add| contains_all(values, add));
+        }
+    }
+    if let Some(subset_of) = get("subset_of") {
+        consistent &= get("add").map_or(true, |add| contains_all(subset_of, add));
+        consistent &=
+            get("superset_of").map_or(true, |superset_of| contains_all(subset_of, superset_of));
+    }
+
+    if !consistent {
+        bail!("conflicting metadata policy operators for {param}");
+    }
+    Ok(())
+}
+
+// The array value of a policy operator.
+fn array<'a>(value: &'a Value, op: &str, param: &str) -> Result<&'a Vec<Value>> {
+    value.as_array().ok_or_else(|| anyhow!("`{op}` requires an array value for {param}"))
+}
+
+// Apply a metadata policy (keyed by Entity Type) to Entity metadata.
+fn apply_policy(metadata: &mut Map<String, Value>, policy: &Map<String, Value>) -> Result<()> {
+    for (entity_type, params) in policy {
+        let Some(params) = params.as_object() else {
+            bail!("invalid metadata policy for {entity_type}");
+        };
+        let entry = metadata.entry(entity_type).or_insert_with(|| Value::Object(Map::new()));
+        let Some(entity_metadata) = entry.as_object_mut() else {
+            bail!("invalid metadata for {entity_type}");
+        };
+
+        for (param, operators) in params {
+            let Some(operators) = operators.as_object() else {
+                bail!("invalid metadata policy for {param}");
+            };
+            apply_operators(entity_metadata, param, operators)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Apply the policy operators for a single metadata parameter, in the order
+// specified by OpenID Federation §6.1.3.1.
+fn apply_operators(
+    metadata: &mut Map<String, Value>, param: &str, operators: &Map<String, Value>,
+) -> Result<()> {
+    for op in operators.keys() {
+        if !["value", "add", "default", "one_of", "subset_of", "superset_of", "essential"]
+            .contains(&op.as_str())
+        {
+            bail!("unsupported metadata policy operator: {op}");
+        }
+    }
+
+    if let Some(value) = operators.get("value") {
+        if value.is_null() {
+            metadata.remove(param);
+        } else {
+            metadata.insert(param.to_string(), value.clone());
+        }
+    }
+    if let Some(add) = operators.get("add") {
+        let entry = metadata.entry(param).or_insert_with(|| Value::Array(vec![]));
+        let (Some(current), Some(add)) = (entry.as_array_mut(), add.as_array()) else {
+            bail!("`add` requires array values for {param}");
+        };
+        for value in add {
+            if !current.contains(value) {
+                current.push(value.clone());
+            }
+        }
+    }
+    if let Some(default) = operators.get("default") {
+        metadata.entry(param).or_insert_with(|| default.clone());
+    }
+    if let Some(one_of) = operators.get("one_of").and_then(Value::as_array) {
+        if metadata.get(param).is_some_and(|value| !one_of.contains(value)) {
+            bail!("{param} is not one of the permitted values");
+        }
+    }
+    if let Some(subset_of) = operators.get("subset_of").and_then(Value::as_array) {
+        if let Some(Value::Array(values)) = metadata.get_mut(param) {
+            values.retain(|value| subset_of.contains(value));
+        }
+    }
+    if let Some(superset_of) = operators.get("superset_of").and_then(Value::as_array) {
+        if let Some(Value::Array(values)) = metadata.get(param) {
+            if !superset_of.iter().all(|value| values.contains(value)) {
+                bail!("{param} does not contain all required values");
+            }
+        }
+    }
+    if operators.get("essential") == Some(&Value::Bool(true)) && !metadata.contains_key(param) {
+        bail!("essential metadata parameter {param} is missing");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use base64ct::{Base64UrlUnpadded, Encoding};
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use rand::rngs::OsRng;
+    use serde_json::json;
+
+    use super::*;
+    use crate::jose::jws::{Key, Protected};
+    use crate::{Algorithm, Curve, KeyType};
+
+    const LEAF: &str = "https://rp.example.com";
+    const ANCHOR: &str = "https://ta.example.com";
+
+    pub fn jwk(key: &SigningKey, kid: &str) -> PublicKeyJwk {
+        PublicKeyJwk {
+            kid: Some(kid.to_string()),
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(key.verifying_key().as_bytes()),
+            ..PublicKeyJwk::default()
+        }
+    }
+
+    // Sign the statement as a compact JWS.
+    fn sign(key: &SigningKey, kid: &str, statement: &Value) -> String {
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            typ: "entity-statement+jwt".to_string(),
+            key: Key::KeyId(kid.to_string()),
+            ..Protected::default()
+        };
+        let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected).unwrap());
+        let payload = Base64UrlUnpadded::encode_string(&serde_json::to_vec(statement).unwrap());
+        let sig = key.sign(format!("{header}.{payload}").as_bytes());
+        format!("{header}.{payload}.{}", Base64UrlUnpadded::encode_string(&sig.to_bytes()))
+    }
+
+    // Build a leaf -> Trust Anchor chain with the provided metadata policy.
+    pub fn chain(leaf_key: &SigningKey, anchor_key: &SigningKey, policy: &Value) -> Vec<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let leaf_jwks = json!({"keys": [jwk(leaf_key, "leaf-1")]});
+        let anchor_jwks = json!({"keys": [jwk(anchor_key, "ta-1")]});
+
+        let configuration = json!({
+            "iss": LEAF, "sub": LEAF, "iat": now, "exp": now + 3600,
+            "jwks": leaf_jwks,
+            "authority_hints": [ANCHOR],
+            "metadata": {"openid_relying_party": {"grant_types": ["authorization_code", "implicit"]}},
+        });
+        let subordinate = json!({
+            "iss": ANCHOR, "sub": LEAF, "iat": now, "exp": now + 3600,
+            "jwks": leaf_jwks,
+            "metadata_policy": policy,
+        });
+        let anchor = json!({
+            "iss": ANCHOR, "sub": ANCHOR, "iat": now, "exp": now + 3600,
+            "jwks": anchor_jwks,
+        });
+
+        vec![
+            sign(leaf_key, "leaf-1", &configuration),
+            sign(anchor_key, "ta-1", &subordinate),
+            sign(anchor_key, "ta-1", &anchor),
+        ]
+    }
+
+    #[test]
+    fn trust_chain() {
+        let leaf_key = SigningKey::generate(&mut OsRng);
+        let anchor_key = SigningKey::generate(&mut OsRng);
+        let policy = json!({"openid_relying_party": {
+            "grant_types": {"subset_of": ["authorization_code"]},
+            "token_endpoint_auth_method": {"default": "private_key_jwt"},
+        }});
+        let chain = chain(&leaf_key, &anchor_key, &policy);
+
+        let anchors = Jwks {
+            keys: vec![jwk(&anchor_key, "ta-1")],
+        };
+        let resolved = verify_trust_chain(&chain, &anchors).expect("should verify");
+        assert_eq!(resolved.key, jwk(&leaf_key, "leaf-1"));
+        assert_eq!(
+            resolved.metadata["openid_relying_party"],
+            json!({"grant_types": ["authorization_code"], "token_endpoint_auth_method": "private_key_jwt"})
+        );
+
+        // unknown Trust Anchor
+        let other = Jwks {
+            keys: vec![jwk(&SigningKey::generate(&mut OsRng), "ta-1")],
+        };
+        assert!(verify_trust_chain(&chain, &other).is_err());
+    }
+
+    // Build a leaf -> intermediate -> Trust Anchor chain, with a metadata
+    // policy in each Subordinate Statement.
+    fn intermediate_chain(
+        anchor_key: &SigningKey, anchor_policy: &Value, intermediate_policy: &Value,
+    ) -> Vec<String> {
+        const INTERMEDIATE: &str = "https://int.example.com";
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let leaf_key = SigningKey::generate(&mut OsRng);
+        let intermediate_key = SigningKey::generate(&mut OsRng);
+        let leaf_jwks = json!({"keys": [jwk(&leaf_key, "leaf-1")]});
+        let intermediate_jwks = json!({"keys": [jwk(&intermediate_key, "int-1")]});
+
+        let configuration = json!({
+            "iss": LEAF, "sub": LEAF, "iat": now, "exp": now + 3600,
+            "jwks": leaf_jwks,
+            "metadata": {"openid_relying_party": {"client_name": "RP"}},
+        });
+        let about_leaf = json!({
+            "iss": INTERMEDIATE, "sub": LEAF, "iat": now, "exp": now + 3600,
+            "jwks": leaf_jwks,
+            "metadata_policy": {"openid_relying_party": intermediate_policy},
+        });
+        let about_intermediate = json!({
+            "iss": ANCHOR, "sub": INTERMEDIATE, "iat": now, "exp": now + 3600,
+            "jwks": intermediate_jwks,
+            "metadata_policy": {"openid_relying_party": anchor_policy},
+        });
+        let anchor = json!({
+            "iss": ANCHOR, "sub": ANCHOR, "iat": now, "exp": now + 3600,
+            "jwks": {"keys": [jwk(anchor_key, "ta-1")]},
+        });
+
+        vec![
+            sign(&leaf_key, "leaf-1", &configuration),
+            sign(&intermediate_key, "int-1", &about_leaf),
+            sign(anchor_key, "ta-1", &about_intermediate),
+            sign(anchor_key, "ta-1", &anchor),
+        ]
+    }
+
+    #[test]
+    fn merged_policies() {
+        let anchor_key = SigningKey::generate(&mut OsRng);
+        let anchors = Jwks {
+            keys: vec![jwk(&anchor_key, "ta-1")],
+        };
+        let anchor_policy = json!({
+            "token_endpoint_auth_method": {"one_of": ["private_key_jwt", "tls_client_auth"]},
+            "contacts": {"add": ["ops@ta.example.com"]},
+        });
+
+        // a subordinate may narrow a superior's policy
+        let intermediate_policy = json!({
+            "token_endpoint_auth_method": {"value": "private_key_jwt"},
+            "contacts": {"add": ["ops@int.example.com"]},
+        });
+        let chain = intermediate_chain(&anchor_key, &anchor_policy, &intermediate_policy);
+        let resolved = verify_trust_chain(&chain, &anchors).expect("should verify");
+        assert_eq!(
+            resolved.metadata["openid_relying_party"],
+            json!({
+                "client_name": "RP",
+                "contacts": ["ops@ta.example.com", "ops@int.example.com"],
+                "token_endpoint_auth_method": "private_key_jwt",
+            })
+        );
+
+        // but not override it
+        let intermediate_policy = json!({
+            "token_endpoint_auth_method": {"value": "client_secret_basic"},
+        });
+        let chain = intermediate_chain(&anchor_key, &anchor_policy, &intermediate_policy);
+        let err = verify_trust_chain(&chain, &anchors).expect_err("should fail");
+        assert_eq!(
+            err.to_string(),
+            "conflicting metadata policy operators for token_endpoint_auth_method"
+        );
+
+        let intermediate_policy = json!({
+            "token_endpoint_auth_method": {"one_of": ["client_secret_basic"]},
+        });
+        let chain = intermediate_chain(&anchor_key, &anchor_policy, &intermediate_policy);
+        let err = verify_trust_chain(&chain, &anchors).expect_err("should fail");
+        assert_eq!(err.to_string(), "conflicting `one_of` policies for token_endpoint_auth_method");
+    }
+
+    #[test]
+    fn policy_violation() {
+        let leaf_key = SigningKey::generate(&mut OsRng);
+        let anchor_key = SigningKey::generate(&mut OsRng);
+        let policy = json!({"openid_relying_party": {
+            "grant_types": {"superset_of": ["refresh_token"]},
+        }});
+        let chain = chain(&leaf_key, &anchor_key, &policy);
+
+        let anchors = Jwks {
+            keys: vec![jwk(&anchor_key, "ta-1")],
+        };
+        let err = verify_trust_chain(&chain, &anchors).expect_err("should fail");
+        assert_eq!(err.to_string(), "grant_types does not contain all required values");
+    }
+
+    #[test]
+    fn operators() {
+        let mut metadata = json!({"scope": "openid", "response_types": ["code", "id_token"]})
+            .as_object()
+            .cloned()
+            .unwrap();
+        let policy = json!({
+            "scope": {"value": "openid profile"},
+            "response_types": {"add": ["code token"], "subset_of": ["code", "code token"]},
+            "client_name": {"essential": true},
+        });
+
+        let err = apply_operators(
+            &mut metadata,
+            "client_name",
+            policy["client_name"].as_object().unwrap(),
+        )
+        .expect_err("should fail");
+        assert_eq!(err.to_string(), "essential metadata parameter client_name is missing");
+
+        for param in ["scope", "response_types"] {
+            apply_operators(&mut metadata, param, policy[param].as_object().unwrap())
+                .expect("should apply");
+        }
+        assert_eq!(metadata["scope"], json!("openid profile"));
+        assert_eq!(metadata["response_types"], json!(["code", "code token"]));
+    }
+}
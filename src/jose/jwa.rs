@@ -12,24 +12,333 @@
 //! [IANA]: https://www.iana.org/assignments/jose/jose.xhtml
 
 use std::fmt::{Debug, Display};
+use std::str::FromStr;
 
+use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::Curve;
+
+/// The encoded length of an ML-DSA-44 public key.
+pub(crate) const ML_DSA_44_KEY_LEN: usize = 1312;
+
+/// The encoded length of an ML-DSA-65 public key.
+pub(crate) const ML_DSA_65_KEY_LEN: usize = 1952;
 
 /// Algorithm is used to specify the signing algorithm used by the signer.
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+///
+/// There is no `none` algorithm: a header can never describe an unsigned
+/// token.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub enum Algorithm {
+    /// ECDSA using the P-256 curve and SHA-256
+    #[serde(rename = "ES256")]
+    ES256,
+
     /// Algorithm for the secp256k1 curve
     #[serde(rename = "ES256K")]
     ES256K,
 
+    /// ECDSA using the P-384 curve and SHA-384
+    #[serde(rename = "ES384")]
+    ES384,
+
+    /// ECDSA using the P-521 curve and SHA-512
+    #[serde(rename = "ES512")]
+    ES512,
+
     /// Algorithm for the Ed25519 curve
-    #[default]
     #[serde(rename = "EdDSA")]
     EdDSA,
+
+    /// Module-Lattice-Based Digital Signature Algorithm (FIPS 204) using the
+    /// ML-DSA-44 parameter set. Verification requires the `pqc` feature.
+    #[serde(rename = "ML-DSA-44")]
+    MlDsa44,
+
+    /// ML-DSA using the ML-DSA-65 parameter set. Verification requires the
+    /// `pqc` feature.
+    #[serde(rename = "ML-DSA-65")]
+    MlDsa65,
+}
+
+/// Defaults to [`Algorithm::EdDSA`].
+///
+/// The default is a placeholder for header types such as `Protected` that
+/// derive `Default`. Builders always set the algorithm from the signer.
+impl Default for Algorithm {
+    fn default() -> Self {
+        Self::EdDSA
+    }
+}
+
+impl Algorithm {
+    /// Returns `true` if the algorithm is sanctioned for use with keys on the
+    /// specified curve.
+    #[must_use]
+    pub const fn is_compatible(&self, crv: &Curve) -> bool {
+        matches!(
+            (self, crv),
+            (Self::ES256, Curve::P256)
+                | (Self::ES256K, Curve::Es256K)
+                | (Self::ES384, Curve::P384)
+                | (Self::ES512, Curve::P521)
+                | (Self::EdDSA, Curve::Ed25519)
+                | (Self::MlDsa44, Curve::MlDsa44)
+                | (Self::MlDsa65, Curve::MlDsa65)
+        )
+    }
+
+    /// Infer the signing algorithm from a raw public key, such as one
+    /// returned by [`Signer::verifying_key`](crate::Signer::verifying_key),
+    /// for signing backends whose key type is only known at runtime.
+    ///
+    /// A 32-byte key is taken to be Ed25519, a SEC1 encoded point is
+    /// matched to its curve by length and by checking the point lies on the
+    /// curve, and ML-DSA keys are identified by length.
+    ///
+    /// secp256k1 and P-256 points have the same length, so a 256-bit point
+    /// is only attributed to a curve when it is valid on that curve alone.
+    /// This is always the case in practice for uncompressed points, but
+    /// roughly half of all compressed points have an `x` coordinate that is
+    /// also on the other curve, and are ambiguous. Returns `None` if the key is ambiguous or
+    /// matches no supported algorithm.
+    #[must_use]
+    pub fn infer_from_public_key(key: &[u8]) -> Option<Self> {
+        match key.len() {
+            32 => {
+                let bytes = key.try_into().ok()?;
+                ed25519_dalek::VerifyingKey::from_bytes(bytes).ok().map(|_| Self::EdDSA)
+            }
+            33 | 65 => {
+                let secp256k1 = k256::PublicKey::from_sec1_bytes(key).is_ok();
+                let p256 = p256::PublicKey::from_sec1_bytes(key).is_ok();
+                match (secp256k1, p256) {
+                    (true, false) => Some(Self::ES256K),
+                    (false, true) => Some(Self::ES256),
+                    _ => None,
+                }
+            }
+            49 | 97 => p384::PublicKey::from_sec1_bytes(key).ok().map(|_| Self::ES384),
+            67 | 133 => p521::PublicKey::from_sec1_bytes(key).ok().map(|_| Self::ES512),
+            ML_DSA_44_KEY_LEN => Some(Self::MlDsa44),
+            ML_DSA_65_KEY_LEN => Some(Self::MlDsa65),
+            _ => None,
+        }
+    }
+
+    /// The digest of `msg` signed by the algorithm, for signing backends that
+    /// only accept a pre-hashed message (see
+    /// [`Signer::try_sign_prehashed`](crate::Signer::try_sign_prehashed)).
+    ///
+    /// # Errors
+    /// Returns an error for algorithms that sign the full message, such as
+    /// `EdDSA` and ML-DSA.
+    pub fn prehash(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::ES256 | Self::ES256K => Ok(Sha256::digest(msg).to_vec()),
+            Self::ES384 => Ok(Sha384::digest(msg).to_vec()),
+            Self::ES512 => Ok(Sha512::digest(msg).to_vec()),
+            Self::EdDSA | Self::MlDsa44 | Self::MlDsa65 => {
+                bail!("{self} does not support prehashed signing")
+            }
+        }
+    }
+
+    /// The algorithm's label in the IANA "COSE Algorithms" registry, for use
+    /// as a COSE `alg` header.
+    ///
+    /// ML-DSA labels are those provisionally assigned by
+    /// draft-ietf-cose-dilithium.
+    #[must_use]
+    pub const fn cose_label(&self) -> i64 {
+        match self {
+            Self::ES256 => -7,
+            Self::ES256K => -47,
+            Self::ES384 => -35,
+            Self::ES512 => -36,
+            Self::EdDSA => -8,
+            Self::MlDsa44 => -48,
+            Self::MlDsa65 => -49,
+        }
+    }
+
+    /// The algorithm for an IANA "COSE Algorithms" registry label.
+    ///
+    /// # Errors
+    /// Returns an error if the label is not a supported signing algorithm.
+    pub fn from_cose_label(label: i64) -> Result<Self> {
+        match label {
+            -7 => Ok(Self::ES256),
+            -47 => Ok(Self::ES256K),
+            -35 => Ok(Self::ES384),
+            -36 => Ok(Self::ES512),
+            -8 => Ok(Self::EdDSA),
+            -48 => Ok(Self::MlDsa44),
+            -49 => Ok(Self::MlDsa65),
+            _ => Err(anyhow!("unsupported COSE algorithm: {label}")),
+        }
+    }
 }
 
+/// Formats the algorithm using its IANA "JSON Web Signature and Encryption
+/// Algorithms" identifier.
 impl Display for Algorithm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        let alg = match self {
+            Self::ES256 => "ES256",
+            Self::ES256K => "ES256K",
+            Self::ES384 => "ES384",
+            Self::ES512 => "ES512",
+            Self::EdDSA => "EdDSA",
+            Self::MlDsa44 => "ML-DSA-44",
+            Self::MlDsa65 => "ML-DSA-65",
+        };
+        write!(f, "{alg}")
+    }
+}
+
+/// Parses an IANA "JSON Web Signature and Encryption Algorithms" identifier.
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ES256" => Ok(Self::ES256),
+            "ES256K" => Ok(Self::ES256K),
+            "ES384" => Ok(Self::ES384),
+            "ES512" => Ok(Self::ES512),
+            "EdDSA" => Ok(Self::EdDSA),
+            "ML-DSA-44" => Ok(Self::MlDsa44),
+            "ML-DSA-65" => Ok(Self::MlDsa65),
+            _ => Err(anyhow!("unsupported algorithm: {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iana_identifiers() {
+        for alg in [
+            Algorithm::ES256,
+            Algorithm::ES256K,
+            Algorithm::ES384,
+            Algorithm::ES512,
+            Algorithm::EdDSA,
+            Algorithm::MlDsa44,
+            Algorithm::MlDsa65,
+        ] {
+            let display = alg.to_string();
+            assert_eq!(display.parse::<Algorithm>().expect("should parse"), alg);
+
+            // matches serde representation
+            let json = serde_json::to_value(&alg).expect("should serialize");
+            assert_eq!(json, display);
+        }
+    }
+
+    #[test]
+    fn cose_labels() {
+        for (alg, label) in [
+            (Algorithm::EdDSA, -8),
+            (Algorithm::ES256, -7),
+            (Algorithm::ES256K, -47),
+            (Algorithm::ES384, -35),
+            (Algorithm::ES512, -36),
+            (Algorithm::MlDsa44, -48),
+            (Algorithm::MlDsa65, -49),
+        ] {
+            assert_eq!(alg.cose_label(), label);
+            assert_eq!(Algorithm::from_cose_label(label).expect("should map"), alg);
+        }
+
+        let err = Algorithm::from_cose_label(-257).expect_err("should fail");
+        assert_eq!(err.to_string(), "unsupported COSE algorithm: -257");
+    }
+
+    #[test]
+    fn curve_compatibility() {
+        assert!(Algorithm::EdDSA.is_compatible(&Curve::Ed25519));
+        assert!(Algorithm::ES256.is_compatible(&Curve::P256));
+        assert!(Algorithm::ES256K.is_compatible(&Curve::Es256K));
+        assert!(Algorithm::ES384.is_compatible(&Curve::P384));
+        assert!(Algorithm::ES512.is_compatible(&Curve::P521));
+
+        assert!(!Algorithm::EdDSA.is_compatible(&Curve::Es256K));
+        assert!(!Algorithm::EdDSA.is_compatible(&Curve::X25519));
+        assert!(!Algorithm::ES256K.is_compatible(&Curve::Ed25519));
+        assert!(!Algorithm::ES256.is_compatible(&Curve::Es256K));
+        assert!(!Algorithm::ES256K.is_compatible(&Curve::P256));
+        assert!(!Algorithm::ES384.is_compatible(&Curve::P521));
+        assert!(!Algorithm::ES512.is_compatible(&Curve::P384));
+    }
+
+    #[test]
+    fn infer() {
+        use rand::rngs::OsRng;
+
+        let ed25519 = ed25519_dalek::SigningKey::generate(&mut OsRng);
+        let key = ed25519.verifying_key().to_bytes();
+        assert_eq!(Algorithm::infer_from_public_key(&key), Some(Algorithm::EdDSA));
+
+        let secp256k1 = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let key = secp256k1.verifying_key().to_encoded_point(false);
+        assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), Some(Algorithm::ES256K));
+
+        let p384 = p384::ecdsa::SigningKey::random(&mut OsRng);
+        let key = p384.verifying_key().to_encoded_point(true);
+        assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), Some(Algorithm::ES384));
+
+        let p521 = p521::ecdsa::SigningKey::random(&mut OsRng);
+        let key = p521::ecdsa::VerifyingKey::from(&p521).to_encoded_point(false);
+        assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), Some(Algorithm::ES512));
+
+        let p256 = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let key = p256.verifying_key().to_encoded_point(false);
+        assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), Some(Algorithm::ES256));
+
+        assert_eq!(Algorithm::infer_from_public_key(&[0; 20]), None);
+    }
+
+    #[test]
+    fn infer_compressed() {
+        use rand::rngs::OsRng;
+
+        // a compressed 256-bit point is inferred only when its `x` coordinate
+        // is not also on the other curve
+        for _ in 0..32 {
+            let secp256k1 = k256::ecdsa::SigningKey::random(&mut OsRng);
+            let key = secp256k1.verifying_key().to_encoded_point(true);
+            let ambiguous = p256::PublicKey::from_sec1_bytes(key.as_bytes()).is_ok();
+            let expected = (!ambiguous).then_some(Algorithm::ES256K);
+            assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), expected);
+
+            let p256 = p256::ecdsa::SigningKey::random(&mut OsRng);
+            let key = p256.verifying_key().to_encoded_point(true);
+            let ambiguous = k256::PublicKey::from_sec1_bytes(key.as_bytes()).is_ok();
+            let expected = (!ambiguous).then_some(Algorithm::ES256);
+            assert_eq!(Algorithm::infer_from_public_key(key.as_bytes()), expected);
+        }
+
+        // an `x` coordinate valid on both curves
+        let mut key = [0; 33];
+        key[0] = 0x02;
+        key[32] = 1;
+        while !(k256::PublicKey::from_sec1_bytes(&key).is_ok()
+            && p256::PublicKey::from_sec1_bytes(&key).is_ok())
+        {
+            key[32] += 1;
+        }
+        assert_eq!(Algorithm::infer_from_public_key(&key), None);
+    }
+
+    #[test]
+    fn unknown() {
+        assert!("none".parse::<Algorithm>().is_err());
+        assert!("eddsa".parse::<Algorithm>().is_err());
     }
 }
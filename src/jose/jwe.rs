@@ -40,15 +40,16 @@
 //! }
 //! ```
 
-// TODO: investigate PartyUInfo and PartyVInfo more thoroughly
 // The ephemeral public key for the Agreement is stored alongside the wrapped
 // per-file key. The KDF is Concatenation Key Derivation Function (Approved
-// Alternative 1) as described in 5.8.1 of NIST SP 800-56A. AlgorithmID is
-// omitted. PartyUInfo and PartyVInfo are the ephemeral and static public keys,
-// respectively. SHA256 is used as the hashing function.
+// Alternative 1) as described in 5.8.1 of NIST SP 800-56A. PartyUInfo and
+// PartyVInfo are taken from the optional `apu` and `apv` headers. SHA256 is
+// used as the hashing function.
 
+mod cbc_hs;
 mod decrypt;
 mod encrypt;
+mod kdf;
 mod key;
 
 use anyhow::{bail, Result};
@@ -62,7 +63,7 @@ pub use self::encrypt::{
     Payload, Recipient,
 };
 pub use self::key::{PublicKey, SecretKey, SharedSecret};
-use crate::jose::jwk::PublicKeyJwk;
+use crate::jose::jwk::{OctJwk, PublicKeyJwk};
 use crate::Receiver;
 
 /// Encrypt plaintext using the defaults of A256GCM content encryption and
@@ -89,6 +90,56 @@ pub async fn decrypt<T: DeserializeOwned>(jwe: &Jwe, receiver: &impl Receiver) -
     decrypt::decrypt(jwe, receiver).await
 }
 
+/// Decrypt a sender-authenticated (ECDH-1PU+A256KW) JWE and return the
+/// plaintext.
+///
+/// `sender_public` is the sender's static public key, typically resolved
+/// using the JWE's `skid` header. Successful decryption assures the
+/// recipient the JWE was produced by the holder of the corresponding
+/// private key.
+///
+/// # Errors
+///
+/// Returns an error if the JWE does not use the ECDH-1PU+A256KW key
+/// management algorithm, or cannot be decrypted using the sender's key.
+pub async fn decrypt_authenticated<T: DeserializeOwned>(
+    jwe: &Jwe, receiver: &impl Receiver, sender_public: PublicKey,
+) -> Result<T> {
+    decrypt::decrypt_authenticated(jwe, receiver, sender_public).await
+}
+
+/// Decrypt a JWE whose CEK is wrapped (A256KW or A256GCMKW) using a
+/// pre-shared key encryption key, or is the pre-shared key itself (`dir`),
+/// and return the plaintext.
+///
+/// # Errors
+///
+/// Returns an error if the key encryption key is invalid for the JWE's key
+/// management algorithm, or the JWE cannot be decrypted.
+pub fn decrypt_with_kek<T: DeserializeOwned>(jwe: &Jwe, kek: &OctJwk) -> Result<T> {
+    decrypt::decrypt_with_kek(jwe, kek)
+}
+
+/// Decrypt a JWE whose CEK is encrypted to an RSA key (RSA-OAEP or
+/// RSA-OAEP-256), and return the plaintext.
+///
+/// To avoid acting as a padding oracle, a CEK that fails to decrypt is
+/// replaced with a random key so the failure is only reported once content
+/// decryption fails — callers should likewise avoid distinguishing between
+/// decryption errors in responses or timing.
+///
+/// # Errors
+///
+/// Returns an error if the private key is smaller than 2048 bits, the JWE
+/// does not use an RSA key management algorithm, or the JWE cannot be
+/// decrypted.
+#[cfg(feature = "rsa")]
+pub fn decrypt_with_rsa<T: DeserializeOwned>(
+    jwe: &Jwe, private_key: &rsa::RsaPrivateKey,
+) -> Result<T> {
+    decrypt::decrypt_with_rsa(jwe, private_key)
+}
+
 /// In JWE JSON serialization, one or more of the JWE Protected Header, JWE
 /// Shared Unprotected Header, and JWE Per-Recipient Unprotected Header MUST be
 /// present.
@@ -135,9 +186,19 @@ impl Jwe {
         };
 
         // add recipient data to protected header
+        let header = &recipient.header;
+        let key_agreement = matches!(
+            header.alg,
+            KeyAlgorithm::EcdhEs
+                | KeyAlgorithm::EcdhEsA256Kw
+                | KeyAlgorithm::Ecdh1PuA256Kw
+                | KeyAlgorithm::EciesEs256K
+        );
         let mut protected = ProtectedFlat {
             inner: self.protected.clone(),
-            epk: recipient.header.epk.clone(),
+            epk: key_agreement.then(|| header.epk.clone()),
+            iv: header.iv.clone(),
+            tag: header.tag.clone(),
         };
         protected.inner.alg = Some(recipient.header.alg.clone());
 
@@ -165,13 +226,42 @@ pub struct Protected {
     /// to produce the ciphertext and the Authentication Tag. MUST be an AEAD
     /// algorithm.
     pub enc: ContentAlgorithm,
+
+    /// The media type of the plaintext. Set to `JWT` when the plaintext is a
+    /// signed JWT (a nested JWT).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cty: Option<String>,
+
+    /// Agreement `PartyUInfo`: information about the producer, base64url
+    /// encoded, bound into the ECDH-ES key derivation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apu: Option<String>,
+
+    /// Agreement `PartyVInfo`: information about the recipient, base64url
+    /// encoded, bound into the ECDH-ES key derivation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apv: Option<String>,
+
+    /// The compression applied to the plaintext before encryption.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zip: Option<Zip>,
+
+    /// The key ID of the sender's static key used in sender-authenticated
+    /// (ECDH-1PU) key agreement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skid: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
 struct ProtectedFlat {
     #[serde(flatten)]
     inner: Protected,
-    epk: PublicKeyJwk,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epk: Option<PublicKeyJwk>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
 }
 
 /// JWE serialization is affected by the number of recipients. In the case of a
@@ -181,6 +271,7 @@ struct ProtectedFlat {
 /// The "recipients" member is flattened into the top-level JSON object instead
 /// of being nested within the "recipients" member.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Recipients {
     /// Single recipient (uses flattened JWE JSON syntax).
     One(KeyEncryption),
@@ -244,11 +335,31 @@ pub struct Header {
 /// Tag. MUST be an AEAD algorithm.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub enum ContentAlgorithm {
+    /// AES GCM using a 128-bit key.
+    #[serde(rename = "A128GCM")]
+    A128Gcm,
+
+    /// AES GCM using a 192-bit key.
+    #[serde(rename = "A192GCM")]
+    A192Gcm,
+
     /// AES GCM using a 256-bit key.
     #[default]
     #[serde(rename = "A256GCM")]
     A256Gcm,
 
+    /// AES CBC using a 128-bit key, authenticated using HMAC SHA-256
+    /// truncated to 128 bits. The 256-bit CEK holds both the MAC and
+    /// encryption keys.
+    #[serde(rename = "A128CBC-HS256")]
+    A128CbcHs256,
+
+    /// AES CBC using a 256-bit key, authenticated using HMAC SHA-512
+    /// truncated to 256 bits. The 512-bit CEK holds both the MAC and
+    /// encryption keys. Required for `DIDComm` authcrypt.
+    #[serde(rename = "A256CBC-HS512")]
+    A256CbcHs512,
+
     /// XChaCha20-Poly1305 is a competitive alternative to AES-256-GCM because
     /// it’s fast and constant-time without hardware acceleration (resistent
     /// to cache-timing attacks). It also has longer nonce length to alleviate
@@ -257,6 +368,20 @@ pub enum ContentAlgorithm {
     XChaCha20Poly1305,
 }
 
+impl ContentAlgorithm {
+    /// The length, in bytes, of the content encryption key (CEK) used by the
+    /// algorithm.
+    #[must_use]
+    pub const fn key_len(&self) -> usize {
+        match self {
+            Self::A128Gcm => 16,
+            Self::A192Gcm => 24,
+            Self::A256Gcm | Self::A128CbcHs256 | Self::XChaCha20Poly1305 => 32,
+            Self::A256CbcHs512 => 64,
+        }
+    }
+}
+
 /// The algorithm used to encrypt (key encryption) or derive (key agreement)
 /// the value of the shared content encryption key (CEK).
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -279,13 +404,55 @@ pub enum KeyAlgorithm {
     #[serde(rename = "ECDH-ES+A256KW")]
     EcdhEsA256Kw,
 
+    /// Elliptic Curve Diffie-Hellman One-Pass Unified Model key agreement
+    /// using Concat KDF and CEK wrapped with "A256KW".
+    ///
+    /// Sender-authenticated: the key encryption key is derived from both an
+    /// ephemeral-static and a static-static shared secret, so only the
+    /// holder of the sender's static key (identified by `skid`) could have
+    /// produced the JWE. Supports X25519 and P-256 keys.
+    ///
+    /// See [draft-madden-jose-ecdh-1pu-04].
+    ///
+    /// [draft-madden-jose-ecdh-1pu-04]: https://datatracker.ietf.org/doc/html/draft-madden-jose-ecdh-1pu-04
+    #[serde(rename = "ECDH-1PU+A256KW")]
+    Ecdh1PuA256Kw,
+
     /// Elliptic Curve Integrated Encryption Scheme for secp256k1.
     /// Uses AES 256 GCM and HKDF-SHA256.
     #[serde(rename = "ECIES-ES256K")]
     EciesEs256K,
+
+    /// AES Key Wrap ([RFC3394]) of the CEK using a pre-shared 256-bit key.
+    ///
+    /// [RFC3394]: https://www.rfc-editor.org/rfc/rfc3394
+    #[serde(rename = "A256KW")]
+    A256Kw,
+
+    /// AES GCM encryption of the CEK using a pre-shared 256-bit key. The
+    /// `iv` and `tag` are carried in the header.
+    #[serde(rename = "A256GCMKW")]
+    A256GcmKw,
+
+    /// RSAES OAEP using SHA-1 and MGF1 with SHA-1. Supported for
+    /// interoperability only: prefer `RSA-OAEP-256`. Requires the `rsa`
+    /// feature.
+    #[serde(rename = "RSA-OAEP")]
+    RsaOaep,
+
+    /// RSAES OAEP using SHA-256 and MGF1 with SHA-256. Requires the `rsa`
+    /// feature.
+    #[serde(rename = "RSA-OAEP-256")]
+    RsaOaep256,
+
+    /// Direct encryption: a pre-shared symmetric key is used as the CEK, so
+    /// its length must match the content encryption algorithm.
+    #[serde(rename = "dir")]
+    Dir,
 }
 
 /// The compression algorithm applied to the plaintext before encryption.
+/// Unrecognized values are rejected.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub enum Zip {
     /// DEFLATE compression algorithm.
@@ -295,12 +462,16 @@ pub enum Zip {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
+    use std::future::{self, Future};
+
     use ed25519_dalek::PUBLIC_KEY_LENGTH;
     use rand::rngs::OsRng;
     use sha2::Digest;
 
     use super::*;
+    #[cfg(feature = "rsa")]
+    use crate::jose::jwk::RsaPublicJwk;
 
     // Use top-level encrypt method to shortcut using the builder
     #[tokio::test]
@@ -309,7 +480,7 @@ mod test {
         let plaintext = "The true sign of intelligence is not knowledge but imagination.";
         let public_key = PublicKey::from(key_store.public_key);
 
-        let jwe = encrypt(&plaintext, public_key).expect("should encrypt");
+        let jwe = encrypt(plaintext, public_key).expect("should encrypt");
         let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
         assert_eq!(plaintext, decrypted);
     }
@@ -321,7 +492,7 @@ mod test {
         let plaintext = "The true sign of intelligence is not knowledge but imagination.";
         let public_key = PublicKey::from(key_store.public_key);
 
-        let jwe = encrypt(&plaintext, public_key).expect("should encrypt");
+        let jwe = encrypt(plaintext, public_key).expect("should encrypt");
 
         // serialize/deserialize
         let compact_jwe = jwe.encode().expect("should encode jwe");
@@ -344,10 +515,42 @@ mod test {
             .build()
             .expect("should encrypt");
 
+        let Recipients::One(recipient) = &jwe.recipients else {
+            panic!("should have a single recipient");
+        };
+        assert_eq!(recipient.header.epk.crv, crate::Curve::X25519);
+
         let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
         assert_eq!(plaintext, decrypted);
     }
 
+    // P-256 sender <-> P-256 receiver
+    #[tokio::test]
+    async fn p256() {
+        let key_store = P256::new();
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+
+        for key_algorithm in [KeyAlgorithm::EcdhEs, KeyAlgorithm::EcdhEsA256Kw] {
+            let jwe = JweBuilder::new()
+                .key_algorithm(key_algorithm)
+                .payload(&plaintext)
+                .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+                .build()
+                .expect("should encrypt");
+
+            // round trip through compact serialization for ECDH-ES
+            let jwe = match &jwe.recipients {
+                Recipients::One(_) => {
+                    jwe.encode().expect("should encode").parse().expect("should parse")
+                }
+                Recipients::Many { .. } => jwe,
+            };
+
+            let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+        }
+    }
+
     #[tokio::test]
     async fn ecdh_es_a256kw() {
         let key_store = X25519::new();
@@ -366,6 +569,352 @@ mod test {
         assert_eq!(plaintext, decrypted);
     }
 
+    // sender-authenticated key agreement using fixed static keys
+    #[tokio::test]
+    async fn ecdh_1pu_a256kw() {
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+        let static_key = |secret: [u8; 32]| {
+            let secret_key = x25519_dalek::StaticSecret::from(secret);
+            X25519 {
+                public_key: x25519_dalek::PublicKey::from(&secret_key),
+                secret_key,
+            }
+        };
+        let alice = static_key([1; 32]);
+        let bob = static_key([2; 32]);
+
+        let jwe = JweBuilder::new()
+            .key_algorithm(KeyAlgorithm::Ecdh1PuA256Kw)
+            .apu("Alice")
+            .apv("Bob")
+            .sender("did:example:bob#key-id", SecretKey::from(bob.secret_key.to_bytes()))
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(alice.public_key))
+            .build()
+            .expect("should encrypt");
+        assert_eq!(jwe.protected.skid.as_deref(), Some("did:example:bob#key-id"));
+
+        let bob_public = PublicKey::from(bob.public_key);
+        let decrypted: String =
+            decrypt_authenticated(&jwe, &alice, bob_public).await.expect("should decrypt");
+        assert_eq!(plaintext, decrypted);
+
+        // only the sender's key authenticates the JWE
+        let mallory = PublicKey::from(static_key([3; 32]).public_key);
+        assert!(decrypt_authenticated::<String>(&jwe, &alice, mallory).await.is_err());
+
+        let err = decrypt::<String>(&jwe, &alice).await.expect_err("should fail");
+        assert!(err.to_string().contains("use `decrypt_authenticated`"));
+
+        // a sender key is required
+        let err = JweBuilder::new()
+            .key_algorithm(KeyAlgorithm::Ecdh1PuA256Kw)
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(alice.public_key))
+            .build()
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "ECDH-1PU requires a sender key: use `sender`");
+    }
+
+    #[tokio::test]
+    async fn key_wrap() {
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+        let kek = OctJwk::new(&[7; 32]);
+
+        for key_algorithm in [KeyAlgorithm::A256Kw, KeyAlgorithm::A256GcmKw] {
+            let jwe = JweBuilder::new()
+                .key_algorithm(key_algorithm.clone())
+                .payload(&plaintext)
+                .build_with_kek("kek-1", &kek)
+                .expect("should encrypt");
+
+            let decrypted: String = decrypt_with_kek(&jwe, &kek).expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+
+            // compact serialization carries `iv`/`tag` but no `epk`
+            let compact = jwe.encode().expect("should encode");
+            let header = compact.split('.').next().unwrap();
+            let header: Value =
+                serde_json::from_slice(&Base64UrlUnpadded::decode_vec(header).unwrap()).unwrap();
+            assert!(header.get("epk").is_none());
+            assert_eq!(header.get("iv").is_some(), key_algorithm == KeyAlgorithm::A256GcmKw);
+
+            let jwe: Jwe = compact.parse().expect("should parse");
+            let decrypted: String = decrypt_with_kek(&jwe, &kek).expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+
+            assert!(decrypt_with_kek::<String>(&jwe, &OctJwk::new(&[8; 32])).is_err());
+        }
+
+        // KEK length must match the algorithm
+        let err = JweBuilder::new()
+            .key_algorithm(KeyAlgorithm::A256Kw)
+            .payload(&plaintext)
+            .build_with_kek("kek-1", &OctJwk::new(&[7; 16]))
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "A256KW and A256GCMKW require a 256-bit key");
+    }
+
+    #[cfg(feature = "rsa")]
+    #[test]
+    fn rsa_oaep() {
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+        let private_key = rsa::RsaPrivateKey::new(&mut OsRng, 2048).expect("should generate");
+        let public_jwk = RsaPublicJwk::new(&private_key.to_public_key());
+
+        for key_algorithm in [KeyAlgorithm::RsaOaep, KeyAlgorithm::RsaOaep256] {
+            let compact = JweBuilder::new()
+                .key_algorithm(key_algorithm)
+                .payload(&plaintext)
+                .build_with_rsa("did:example:alice#key-rsa", &public_jwk)
+                .expect("should encrypt")
+                .encode()
+                .expect("should encode");
+
+            let jwe: Jwe = compact.parse().expect("should parse");
+            let decrypted: String = decrypt_with_rsa(&jwe, &private_key).expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+        }
+
+        // keys under 2048 bits are rejected
+        let small_key = rsa::RsaPrivateKey::new(&mut OsRng, 1024).expect("should generate");
+        let err = JweBuilder::new()
+            .key_algorithm(KeyAlgorithm::RsaOaep256)
+            .payload(&plaintext)
+            .build_with_rsa(
+                "did:example:alice#key-rsa",
+                &RsaPublicJwk::new(&small_key.to_public_key()),
+            )
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "RSA keys must be at least 2048 bits");
+    }
+
+    // `apu`/`apv` are carried in the protected header and bound into the KDF
+    #[tokio::test]
+    async fn party_info() {
+        let key_store = X25519::new();
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+
+        for key_algorithm in [KeyAlgorithm::EcdhEs, KeyAlgorithm::EcdhEsA256Kw] {
+            let jwe = JweBuilder::new()
+                .key_algorithm(key_algorithm)
+                .apu(b"Alice".as_slice())
+                .apv(b"Bob".as_slice())
+                .payload(&plaintext)
+                .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+                .build()
+                .expect("should encrypt");
+            assert_eq!(jwe.protected.apu.as_deref(), Some("QWxpY2U"));
+            assert_eq!(jwe.protected.apv.as_deref(), Some("Qm9i"));
+
+            let jwe = match &jwe.recipients {
+                Recipients::One(_) => {
+                    jwe.encode().expect("should encode").parse().expect("should parse")
+                }
+                Recipients::Many { .. } => jwe,
+            };
+            let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+
+            // altered party info derives a different key
+            let mut altered = jwe;
+            altered.protected.apv = Some("RXZl".to_string());
+            assert!(decrypt::<String>(&altered, &key_store).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn compression() {
+        let key_store = X25519::new();
+        let plaintext =
+            "The true sign of intelligence is not knowledge but imagination. ".repeat(100);
+
+        let jwe = JweBuilder::new()
+            .compress(true)
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+            .build()
+            .expect("should encrypt");
+        assert_eq!(jwe.protected.zip, Some(Zip::Deflate));
+        assert!(jwe.ciphertext.len() < plaintext.len() / 4);
+
+        let jwe: Jwe = jwe.encode().expect("should encode").parse().expect("should parse");
+        let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
+        assert_eq!(plaintext, decrypted);
+
+        // unknown `zip` values are rejected
+        let header = Base64UrlUnpadded::encode_string(br#"{"enc":"A256GCM","zip":"GZ"}"#);
+        assert!(format!("{header}.e30.e30.e30.e30").parse::<Jwe>().is_err());
+    }
+
+    #[tokio::test]
+    async fn decompression_limit() {
+        let key_store = X25519::new();
+        let plaintext = "a".repeat(decrypt::MAX_INFLATED_LEN);
+
+        let jwe = JweBuilder::new()
+            .compress(true)
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+            .build()
+            .expect("should encrypt");
+
+        let err = decrypt::<String>(&jwe, &key_store).await.expect_err("should fail");
+        assert!(err.to_string().starts_with("issue decompressing plaintext"));
+    }
+
+    #[tokio::test]
+    async fn xchacha20_poly1305() {
+        let key_store = X25519::new();
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+
+        let jwe = JweBuilder::new()
+            .content_algorithm(ContentAlgorithm::XChaCha20Poly1305)
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+            .build()
+            .expect("should encrypt");
+
+        let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
+        assert_eq!(plaintext, decrypted);
+    }
+
+    // each content encryption algorithm with each kind of key management
+    #[tokio::test]
+    async fn content_algorithms() {
+        let key_store = X25519::new();
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+        let kek = OctJwk::new(&[7; 32]);
+
+        for enc in [
+            ContentAlgorithm::A128Gcm,
+            ContentAlgorithm::A192Gcm,
+            ContentAlgorithm::A256Gcm,
+            ContentAlgorithm::A128CbcHs256,
+            ContentAlgorithm::A256CbcHs512,
+            ContentAlgorithm::XChaCha20Poly1305,
+        ] {
+            for key_algorithm in [KeyAlgorithm::EcdhEs, KeyAlgorithm::EcdhEsA256Kw] {
+                let jwe = JweBuilder::new()
+                    .content_algorithm(enc.clone())
+                    .key_algorithm(key_algorithm)
+                    .payload(&plaintext)
+                    .add_recipient(
+                        "did:example:alice#key-id",
+                        PublicKey::from(key_store.public_key),
+                    )
+                    .build()
+                    .expect("should encrypt");
+                let decrypted: String = decrypt(&jwe, &key_store).await.expect("should decrypt");
+                assert_eq!(plaintext, decrypted);
+            }
+
+            // the wrapped CEK is sized for `enc`
+            let compact = JweBuilder::new()
+                .content_algorithm(enc.clone())
+                .key_algorithm(KeyAlgorithm::A256Kw)
+                .payload(&plaintext)
+                .build_with_kek("kek-1", &kek)
+                .expect("should encrypt")
+                .encode()
+                .expect("should encode");
+            let encrypted_key = compact.split('.').nth(1).unwrap();
+            let wrapped_len = Base64UrlUnpadded::decode_vec(encrypted_key).unwrap().len();
+            assert_eq!(wrapped_len, enc.key_len() + 8);
+
+            let jwe: Jwe = compact.parse().expect("should parse");
+            assert_eq!(jwe.protected.enc, enc);
+            let decrypted: String = decrypt_with_kek(&jwe, &kek).expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+        }
+    }
+
+    #[tokio::test]
+    async fn direct() {
+        let plaintext = "The true sign of intelligence is not knowledge but imagination.";
+
+        for (enc, key) in [
+            (ContentAlgorithm::A128Gcm, OctJwk::new(&[7; 16])),
+            (ContentAlgorithm::A128CbcHs256, OctJwk::new(&[7; 32])),
+        ] {
+            let compact = JweBuilder::new()
+                .content_algorithm(enc.clone())
+                .key_algorithm(KeyAlgorithm::Dir)
+                .payload(&plaintext)
+                .build_with_kek("key-1", &key)
+                .expect("should encrypt")
+                .encode()
+                .expect("should encode");
+            assert_eq!(compact.split('.').nth(1), Some(""));
+
+            let jwe: Jwe = compact.parse().expect("should parse");
+            let decrypted: String = decrypt_with_kek(&jwe, &key).expect("should decrypt");
+            assert_eq!(plaintext, decrypted);
+
+            // `dir` cannot be used with an asymmetric key
+            let key_store = X25519::new();
+            assert!(decrypt::<String>(&jwe, &key_store).await.is_err());
+        }
+
+        // the key must be the CEK length for `enc`
+        let err = JweBuilder::new()
+            .content_algorithm(ContentAlgorithm::A256Gcm)
+            .key_algorithm(KeyAlgorithm::Dir)
+            .payload(&plaintext)
+            .build_with_kek("key-1", &OctJwk::new(&[7; 16]))
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "direct encryption requires a 256-bit key for `enc`");
+
+        let key_store = X25519::new();
+        let err = JweBuilder::new()
+            .key_algorithm(KeyAlgorithm::Dir)
+            .payload(&plaintext)
+            .add_recipient("did:example:alice#key-id", PublicKey::from(key_store.public_key))
+            .build()
+            .expect_err("should fail");
+        assert!(err.to_string().starts_with("direct encryption requires a symmetric key"));
+    }
+
+    #[test]
+    fn compact_segments() {
+        let error = |compact: &str| compact.parse::<Jwe>().expect_err("should fail").to_string();
+        let header = |json: &str| Base64UrlUnpadded::encode_string(json.as_bytes());
+        let dir = header(r#"{"alg":"dir","enc":"A128GCM"}"#);
+        let kw = header(r#"{"alg":"A256KW","enc":"A128GCM"}"#);
+
+        // exactly five segments
+        assert_eq!(error("a.b.c.d"), "compact JWE must have 5 segments, found 4");
+        assert_eq!(error(&format!("{dir}..a.b.c.d")), "compact JWE must have 5 segments, found 6");
+
+        // only `encrypted_key` may be empty, and only for `dir` and `ECDH-ES`
+        let jwe: Jwe = format!("{dir}..aXY.Y3Q.dGFn").parse().expect("should parse");
+        let Recipients::One(recipient) = &jwe.recipients else {
+            panic!("should have one recipient");
+        };
+        assert_eq!(recipient.header.alg, KeyAlgorithm::Dir);
+        assert_eq!(jwe.protected.enc, ContentAlgorithm::A128Gcm);
+        let ecdh_es = header(r#"{"alg":"ECDH-ES","enc":"A256GCM"}"#);
+        assert!(format!("{ecdh_es}..aXY.Y3Q.dGFn").parse::<Jwe>().is_ok());
+
+        assert_eq!(error(&format!("{kw}..aXY.Y3Q.dGFn")), "compact JWE `encrypted_key` is empty");
+        assert_eq!(
+            error(&format!("{dir}.a2V5.aXY.Y3Q.dGFn")),
+            "`encrypted_key` must be empty for direct encryption"
+        );
+        assert_eq!(error(&format!("{dir}...Y3Q.dGFn")), "compact JWE `iv` is empty");
+        assert_eq!(error(&format!("{dir}..aXY.Y3Q.")), "compact JWE `tag` is empty");
+
+        // segments must be base64url encoded
+        assert_eq!(
+            error(&format!("{dir}..aXY.Y3Q+.dGFn")),
+            "compact JWE ciphertext is not base64url encoded"
+        );
+        assert_eq!(
+            error(&format!("{}..aXY.Y3Q.dGFn", header(r#"{"enc":"A128GCM"}"#))),
+            "protected header is missing `alg`"
+        );
+    }
+
     #[tokio::test]
     async fn ed25519() {
         let key_store = Ed25519::new();
@@ -406,7 +955,7 @@ mod test {
         let ephemeral_dh = ephemeral_secret.diffie_hellman(&alice_montgomery.to_bytes().into());
 
         // RECEIVER: diffie-hellman using ephemeral public
-        let hash = sha2::Sha512::digest(&alice_secret);
+        let hash = sha2::Sha512::digest(alice_secret);
         let mut hashed = [0u8; PUBLIC_KEY_LENGTH];
         hashed.copy_from_slice(&hash[..PUBLIC_KEY_LENGTH]);
         let alice_x_secret = x25519_dalek::StaticSecret::from(hashed);
@@ -454,15 +1003,15 @@ mod test {
     // }
 
     // Basic key store for testing
-    struct X25519 {
-        public_key: x25519_dalek::PublicKey,
-        secret_key: x25519_dalek::StaticSecret,
+    pub struct X25519 {
+        pub public_key: x25519_dalek::PublicKey,
+        pub secret_key: x25519_dalek::StaticSecret,
     }
 
     impl X25519 {
-        fn new() -> Self {
+        pub fn new() -> Self {
             let secret_key = x25519_dalek::StaticSecret::random_from_rng(OsRng);
-            let public_key = x25519_dalek::PublicKey::from(&secret_key).into();
+            let public_key = x25519_dalek::PublicKey::from(&secret_key);
 
             Self {
                 public_key,
@@ -476,9 +1025,11 @@ mod test {
             "did:example:alice#key-id".to_string()
         }
 
-        async fn shared_secret(&self, sender_public: PublicKey) -> Result<SharedSecret> {
+        fn shared_secret(
+            &self, sender_public: PublicKey,
+        ) -> impl Future<Output = Result<SharedSecret>> + Send {
             let secret_key = SecretKey::from(self.secret_key.to_bytes());
-            secret_key.shared_secret(sender_public)
+            future::ready(secret_key.shared_secret(sender_public))
         }
     }
 
@@ -503,10 +1054,41 @@ mod test {
             "did:example:alice#key-id".to_string()
         }
 
-        async fn shared_secret(&self, sender_public: PublicKey) -> Result<SharedSecret> {
+        fn shared_secret(
+            &self, sender_public: PublicKey,
+        ) -> impl Future<Output = Result<SharedSecret>> + Send {
             let secret: [u8; PUBLIC_KEY_LENGTH] = self.secret_key.serialize();
-            let secret_key = SecretKey::try_from(secret).expect("should convert");
-            secret_key.shared_secret(sender_public)
+            let secret_key = SecretKey::from(secret);
+            future::ready(secret_key.shared_secret(sender_public))
+        }
+    }
+
+    // Basic key store for testing
+    struct P256 {
+        public_key: p256::PublicKey,
+        secret_key: p256::SecretKey,
+    }
+
+    impl P256 {
+        fn new() -> Self {
+            let secret_key = p256::SecretKey::random(&mut OsRng);
+            Self {
+                public_key: secret_key.public_key(),
+                secret_key,
+            }
+        }
+    }
+
+    impl Receiver for P256 {
+        fn key_id(&self) -> String {
+            "did:example:alice#key-id".to_string()
+        }
+
+        fn shared_secret(
+            &self, sender_public: PublicKey,
+        ) -> impl Future<Output = Result<SharedSecret>> + Send {
+            let secret: [u8; 32] = self.secret_key.to_bytes().into();
+            future::ready(SecretKey::from(secret).shared_secret(sender_public))
         }
     }
 
@@ -543,9 +1125,11 @@ mod test {
             "did:example:alice#key-id".to_string()
         }
 
-        async fn shared_secret(&self, sender_public: PublicKey) -> Result<SharedSecret> {
+        fn shared_secret(
+            &self, sender_public: PublicKey,
+        ) -> impl Future<Output = Result<SharedSecret>> + Send {
             let secret_key = SecretKey::from(self.secret_key.to_bytes());
-            secret_key.shared_secret(sender_public)
+            future::ready(secret_key.shared_secret(sender_public))
         }
     }
 }
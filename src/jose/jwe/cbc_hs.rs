@@ -0,0 +1,237 @@
+//! # AES-CBC with HMAC-SHA2
+//!
+//! Authenticated encryption using AES in CBC mode with PKCS#7 padding, where
+//! the ciphertext is authenticated by a truncated HMAC over the AAD, IV,
+//! ciphertext, and AAD length, as specified by [RFC7518 §5.2] for
+//! `A128CBC-HS256` and `A256CBC-HS512`.
+//!
+//! The key is split into a MAC key followed by an encryption key of equal
+//! length.
+//!
+//! [RFC7518 §5.2]: https://www.rfc-editor.org/rfc/rfc7518#section-5.2
+
+use aes_gcm::aes::cipher::consts::U16;
+use aes_gcm::aes::cipher::generic_array::GenericArray;
+use aes_gcm::aes::cipher::{BlockDecrypt, BlockEncrypt, BlockSizeUser, KeyInit};
+use aes_gcm::aes::{Aes128, Aes256};
+use anyhow::{anyhow, bail, Result};
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+
+use crate::crypto::ct_eq;
+
+/// The length of the AES block and IV.
+pub const BLOCK_LEN: usize = 16;
+
+/// An AES-CBC with HMAC-SHA2 algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CbcHs {
+    /// AES-128 with HMAC-SHA-256 truncated to 128 bits.
+    A128CbcHs256,
+
+    /// AES-256 with HMAC-SHA-512 truncated to 256 bits.
+    A256CbcHs512,
+}
+
+impl CbcHs {
+    // The length of each of the MAC and encryption keys, and of the tag.
+    const fn half_len(self) -> usize {
+        match self {
+            Self::A128CbcHs256 => 16,
+            Self::A256CbcHs512 => 32,
+        }
+    }
+
+    /// Encrypt `plaintext`, returning the ciphertext and authentication tag.
+    ///
+    /// # Errors
+    /// Returns an error if the key is the wrong length for the algorithm.
+    pub fn encrypt(
+        self, key: &[u8], iv: &[u8; BLOCK_LEN], plaintext: &[u8], aad: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (mac_key, enc_key) = self.split_key(key)?;
+
+        // PKCS#7 padding: always at least one byte
+        let pad = BLOCK_LEN - plaintext.len() % BLOCK_LEN;
+        let mut buffer = plaintext.to_vec();
+        buffer.resize(plaintext.len() + pad, u8::try_from(pad)?);
+
+        match self {
+            Self::A128CbcHs256 => cbc_encrypt::<Aes128>(enc_key, iv, &mut buffer)?,
+            Self::A256CbcHs512 => cbc_encrypt::<Aes256>(enc_key, iv, &mut buffer)?,
+        }
+
+        let tag = self.compute_tag(mac_key, aad, iv, &buffer)?;
+        Ok((buffer, tag))
+    }
+
+    /// Decrypt `ciphertext` after checking its authentication tag.
+    ///
+    /// # Errors
+    /// Returns an error if the key is the wrong length for the algorithm, the
+    /// IV or ciphertext length is invalid, or the tag does not authenticate
+    /// the ciphertext.
+    pub fn decrypt(
+        self, key: &[u8], iv: &[u8], ciphertext: &[u8], aad: &[u8], tag: &[u8],
+    ) -> Result<Vec<u8>> {
+        let (mac_key, enc_key) = self.split_key(key)?;
+        if iv.len() != BLOCK_LEN || ciphertext.is_empty() || ciphertext.len() % BLOCK_LEN != 0 {
+            bail!("issue decrypting: invalid `iv` or ciphertext length");
+        }
+
+        // authenticate before decrypting so padding errors cannot be observed
+        let expected = self.compute_tag(mac_key, aad, iv, ciphertext)?;
+        if !ct_eq(&expected, tag) {
+            bail!("issue decrypting: authentication failed");
+        }
+
+        let mut buffer = ciphertext.to_vec();
+        match self {
+            Self::A128CbcHs256 => cbc_decrypt::<Aes128>(enc_key, iv, &mut buffer)?,
+            Self::A256CbcHs512 => cbc_decrypt::<Aes256>(enc_key, iv, &mut buffer)?,
+        }
+
+        let pad = usize::from(buffer.last().copied().unwrap_or_default());
+        let Some(len) = buffer.len().checked_sub(pad) else {
+            bail!("issue decrypting: invalid padding");
+        };
+        if pad == 0 || pad > BLOCK_LEN || buffer[len..].iter().any(|b| usize::from(*b) != pad) {
+            bail!("issue decrypting: invalid padding");
+        }
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    // Split the key into the MAC key and the encryption key.
+    fn split_key(self, key: &[u8]) -> Result<(&[u8], &[u8])> {
+        if key.len() != 2 * self.half_len() {
+            bail!("{self:?} requires a {}-bit key", self.half_len() * 16);
+        }
+        Ok(key.split_at(self.half_len()))
+    }
+
+    // The first half of the HMAC over `A || IV || E || AL`, where `AL` is the
+    // AAD length in bits as a 64-bit big-endian integer.
+    fn compute_tag(
+        self, mac_key: &[u8], aad: &[u8], iv: &[u8], ciphertext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let al = (u64::try_from(aad.len())? * 8).to_be_bytes();
+        let input = [aad, iv, ciphertext, al.as_slice()];
+        let mut tag = match self {
+            Self::A128CbcHs256 => hmac::<Hmac<Sha256>>(mac_key, &input)?,
+            Self::A256CbcHs512 => hmac::<Hmac<Sha512>>(mac_key, &input)?,
+        };
+        tag.truncate(self.half_len());
+        Ok(tag)
+    }
+}
+
+// Encrypt the padded buffer in place using AES-CBC.
+fn cbc_encrypt<C>(key: &[u8], iv: &[u8; BLOCK_LEN], buffer: &mut [u8]) -> Result<()>
+where
+    C: BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    let cipher = C::new_from_slice(key).map_err(|e| anyhow!("invalid key: {e}"))?;
+    let mut previous = *iv;
+    for block in buffer.chunks_exact_mut(BLOCK_LEN) {
+        block.iter_mut().zip(previous).for_each(|(b, p)| *b ^= p);
+        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        previous.copy_from_slice(block);
+    }
+    Ok(())
+}
+
+// Decrypt the buffer in place using AES-CBC.
+fn cbc_decrypt<C>(key: &[u8], iv: &[u8], buffer: &mut [u8]) -> Result<()>
+where
+    C: BlockDecrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    let cipher = C::new_from_slice(key).map_err(|e| anyhow!("invalid key: {e}"))?;
+    let mut previous = [0; BLOCK_LEN];
+    previous.copy_from_slice(iv);
+    for block in buffer.chunks_exact_mut(BLOCK_LEN) {
+        let mut encrypted = [0; BLOCK_LEN];
+        encrypted.copy_from_slice(block);
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+        block.iter_mut().zip(previous).for_each(|(b, p)| *b ^= p);
+        previous = encrypted;
+    }
+    Ok(())
+}
+
+// HMAC over the concatenated `input`.
+fn hmac<M: Mac + KeyInit>(key: &[u8], input: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).map_err(|e| anyhow!("invalid key: {e}"))?;
+    for part in input {
+        mac.update(part);
+    }
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"A cipher system must not be required to be secret, and it must be able to fall into the hands of the enemy without inconvenience";
+    const IV: &str = "1af38c2dc2b96ffdd86694092341bc04";
+    const AAD: &[u8] = b"The second principle of Auguste Kerckhoffs";
+
+    // RFC7518 Appendix B.1 and B.3
+    #[test]
+    fn rfc7518_vectors() {
+        for (alg, key_len, expected_ciphertext, expected_tag) in [
+            (
+                CbcHs::A128CbcHs256,
+                32,
+                "c80edfa32ddf39d5ef00c0b468834279a2e46a1b8049f792f76bfe54b903a9c9a94ac9b47ad2655c5f10f9aef71427e2fc6f9b3f399a221489f16362c703233609d45ac69864e3321cf82935ac4096c86e133314c54019e8ca7980dfa4b9cf1b384c486f3a54c51078158ee5d79de59fbd34d848b3d69550a67646344427ade54b8851ffb598f7f80074b9473c82e2db",
+                "652c3fa36b0a7c5b3219fab3a30bc1c4",
+            ),
+            (
+                CbcHs::A256CbcHs512,
+                64,
+                "4affaaadb78c31c5da4b1b590d10ffbd3dd8d5d302423526912da037ecbcc7bd822c301dd67c373bccb584ad3e9279c2e6d12a1374b77f077553df829410446b36ebd97066296ae6427ea75c2e0846a11a09ccf5370dc80bfecbad28c73f09b3a3b75e662a2594410ae496b2e2e6609e31e6e02cc837f053d21f37ff4f51950bbe2638d09dd7a4930930806d0703b1f6",
+                "4dd3b4c088a7f45c216839645b2012bf2e6269a8c56a816dbc1b267761955bc5",
+            ),
+        ] {
+            let key = (0..key_len).collect::<Vec<u8>>();
+            let iv = hex::decode(IV).unwrap().try_into().unwrap();
+
+            let (ciphertext, tag) = alg.encrypt(&key, &iv, PLAINTEXT, AAD).expect("should encrypt");
+            assert_eq!(hex::encode(&ciphertext), expected_ciphertext);
+            assert_eq!(hex::encode(&tag), expected_tag);
+
+            let decrypted = alg.decrypt(&key, &iv, &ciphertext, AAD, &tag).expect("should decrypt");
+            assert_eq!(decrypted, PLAINTEXT);
+
+            // any change to the AAD or ciphertext fails authentication
+            assert!(alg.decrypt(&key, &iv, &ciphertext, b"other", &tag).is_err());
+            let mut tampered = ciphertext;
+            tampered[0] ^= 1;
+            assert!(alg.decrypt(&key, &iv, &tampered, AAD, &tag).is_err());
+        }
+    }
+
+    #[test]
+    fn padding() {
+        let alg = CbcHs::A128CbcHs256;
+        let key = [7; 32];
+        let iv = [1; BLOCK_LEN];
+
+        // a block-aligned plaintext gains a full block of padding
+        for (len, expected) in [(0, 16), (15, 16), (16, 32), (17, 32)] {
+            let plaintext = vec![b'a'; len];
+            let (ciphertext, tag) =
+                alg.encrypt(&key, &iv, &plaintext, b"").expect("should encrypt");
+            assert_eq!(ciphertext.len(), expected);
+            let decrypted = alg.decrypt(&key, &iv, &ciphertext, b"", &tag).expect("should decrypt");
+            assert_eq!(decrypted, plaintext);
+
+            // the tag is not truncated further
+            assert!(alg.decrypt(&key, &iv, &ciphertext, b"", &tag[..8]).is_err());
+        }
+
+        // each algorithm requires its own key length
+        assert!(alg.encrypt(&key[..16], &iv, b"", b"").is_err());
+        assert!(CbcHs::A256CbcHs512.encrypt(&key, &iv, b"", b"").is_err());
+    }
+}
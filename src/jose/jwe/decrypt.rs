@@ -1,19 +1,44 @@
-use std::fmt;
 use std::str::FromStr;
 
+use aes_gcm::aead::consts::U12;
+use aes_gcm::aead::generic_array::GenericArray;
 use aes_gcm::aead::KeyInit; // heapless,
-use aes_gcm::{AeadInPlace, Aes256Gcm, Key, Nonce, Tag};
+use aes_gcm::aes::Aes192;
+use aes_gcm::{AeadInPlace, Aes128Gcm, Aes256Gcm, AesGcm, Key, Nonce, Tag};
 use aes_kw::Kek;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use base64ct::{Base64UrlUnpadded, Encoding};
+use chacha20poly1305::XChaCha20Poly1305;
+#[cfg(feature = "rsa")]
+use rand::rngs::OsRng;
+#[cfg(feature = "rsa")]
+use rand::RngCore;
+#[cfg(feature = "rsa")]
+use rsa::traits::PublicKeyParts;
+#[cfg(feature = "rsa")]
+use rsa::{Oaep, RsaPrivateKey};
 use serde::de::DeserializeOwned;
+#[cfg(feature = "rsa")]
+use sha1::Sha1;
+#[cfg(feature = "rsa")]
+use sha2::Sha256;
+use zeroize::Zeroize;
 
-use super::key;
+use crate::jose::jwe::cbc_hs::CbcHs;
+use crate::jose::jwe::encrypt::kek_bytes;
 use crate::jose::jwe::key::PublicKey;
 use crate::jose::jwe::{
-    Header, Jwe, KeyAlgorithm, KeyEncryption, Protected, ProtectedFlat, Recipients,
+    kdf, ContentAlgorithm, Header, Jwe, KeyAlgorithm, KeyEncryption, Protected, ProtectedFlat,
+    Recipients, Zip,
 };
-use crate::Receiver;
+#[cfg(feature = "rsa")]
+use crate::jose::jwk::check_rsa_size;
+use crate::jose::jwk::OctJwk;
+use crate::{Curve, Receiver};
+
+/// The maximum size of a decompressed (`zip: DEF`) plaintext, guarding
+/// against decompression bombs.
+pub(super) const MAX_INFLATED_LEN: usize = 4 * 1024 * 1024;
 
 /// Decrypt the JWE and return the plaintext.
 ///
@@ -22,44 +47,42 @@ use crate::Receiver;
 /// Returns an error if the JWE cannot be decrypted.
 #[allow(dead_code)]
 pub async fn decrypt<T: DeserializeOwned>(jwe: &Jwe, receiver: &impl Receiver) -> Result<T> {
-    let recipient = match &jwe.recipients {
-        Recipients::One(recipient) => recipient,
-        Recipients::Many { recipients } => {
-            let Some(found) = recipients.iter().find(|r| r.header.kid == Some(receiver.key_id()))
-            else {
-                return Err(anyhow!("no recipient found"));
-            };
-            found
-        }
-    };
-
-    // get sender's ephemeral public key (used in key agreement)
-    let mut public_key = Base64UrlUnpadded::decode_vec(&recipient.header.epk.x)
-        .map_err(|e| anyhow!("issue decoding sender public key `x`: {e}"))?;
+    let recipient = find_recipient(jwe, receiver)?;
 
-    if let Some(y) = &recipient.header.epk.y {
-        let y = Base64UrlUnpadded::decode_vec(y)
-            .map_err(|e| anyhow!("issue decoding sender public key `y`: {e}"))?;
-        public_key.extend_from_slice(&y);
-        public_key.insert(0, key::TAG_PUBKEY_FULL);
+    let key_wrap_err = || anyhow!("key wrapping requires a symmetric key: use `decrypt_with_kek`");
+    let rsa_err = || anyhow!("RSA key encryption requires an RSA key: use `decrypt_with_rsa`");
+    let one_pu_err =
+        || anyhow!("ECDH-1PU requires the sender's public key: use `decrypt_authenticated`");
+    match recipient.header.alg {
+        KeyAlgorithm::A256Kw | KeyAlgorithm::A256GcmKw | KeyAlgorithm::Dir => {
+            return Err(key_wrap_err())
+        }
+        KeyAlgorithm::RsaOaep | KeyAlgorithm::RsaOaep256 => return Err(rsa_err()),
+        KeyAlgorithm::Ecdh1PuA256Kw => return Err(one_pu_err()),
+        _ => {}
     }
 
-    let sender_public = PublicKey::try_from(public_key)?;
+    // get sender's ephemeral public key (used in key agreement), using the
+    // `epk` curve to identify the key agreement algorithm
+    let sender_public = PublicKey::try_from(&recipient.header.epk)?;
 
     // derive shared_secret from recipient's private key and sender's public key
     let shared_secret = receiver.shared_secret(sender_public).await?;
 
     let cek = match recipient.header.alg {
-        KeyAlgorithm::EcdhEs => shared_secret.to_bytes(),
+        KeyAlgorithm::EcdhEs => kdf::derive_cek(shared_secret.as_bytes(), &jwe.protected)?,
         KeyAlgorithm::EcdhEsA256Kw => {
             let encrypted_key = Base64UrlUnpadded::decode_vec(&recipient.encrypted_key)
                 .map_err(|e| anyhow!("issue decoding `encrypted_key`: {e}"))?;
+            let kek = kdf::derive_key(
+                shared_secret.as_bytes(),
+                &KeyAlgorithm::EcdhEsA256Kw,
+                &jwe.protected,
+            )?;
 
-            Kek::from(shared_secret.to_bytes())
+            Kek::from(kek)
                 .unwrap_vec(encrypted_key.as_slice())
                 .map_err(|e| anyhow!("issue unwrapping cek: {e}"))?
-                .try_into()
-                .map_err(|_| anyhow!("issue unwrapping cek"))?
         }
         KeyAlgorithm::EciesEs256K => {
             let Some(base64_iv) = &recipient.header.iv else {
@@ -83,10 +106,189 @@ pub async fn decrypt<T: DeserializeOwned>(jwe: &Jwe, receiver: &impl Receiver) -
             Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(shared_secret.as_bytes()))
                 .decrypt_in_place_detached(nonce, &[], &mut buffer, tag)
                 .map_err(|e| anyhow!("issue decrypting: {e}"))?;
+            buffer
+        }
+        KeyAlgorithm::A256Kw | KeyAlgorithm::A256GcmKw | KeyAlgorithm::Dir => {
+            return Err(key_wrap_err())
+        }
+        KeyAlgorithm::RsaOaep | KeyAlgorithm::RsaOaep256 => return Err(rsa_err()),
+        KeyAlgorithm::Ecdh1PuA256Kw => return Err(one_pu_err()),
+    };
+
+    decrypt_content(jwe, &cek)
+}
+
+/// Decrypt a sender-authenticated (ECDH-1PU+A256KW) JWE using the sender's
+/// static public key and return the plaintext.
+///
+/// # Errors
+///
+/// Returns an error if the JWE cannot be decrypted.
+pub async fn decrypt_authenticated<T: DeserializeOwned>(
+    jwe: &Jwe, receiver: &impl Receiver, sender_public: PublicKey,
+) -> Result<T> {
+    let recipient = find_recipient(jwe, receiver)?;
+    if recipient.header.alg != KeyAlgorithm::Ecdh1PuA256Kw {
+        return Err(anyhow!("`decrypt_authenticated` requires the ECDH-1PU+A256KW algorithm"));
+    }
+
+    // the ephemeral and static keys must be on the same curve
+    let ephemeral_public = PublicKey::try_from(&recipient.header.epk)?;
+    if !matches!(sender_public.curve(), Curve::X25519 | Curve::P256) {
+        return Err(anyhow!("unsupported ECDH-1PU curve: {:?}", sender_public.curve()));
+    }
+    if ephemeral_public.curve() != sender_public.curve() {
+        return Err(anyhow!("sender and ephemeral keys are on different curves"));
+    }
+
+    // Ze from the ephemeral key, Zs from the sender's static key
+    let ze = receiver.shared_secret(ephemeral_public).await?;
+    let zs = receiver.shared_secret(sender_public).await?;
+
+    let cc_tag = Base64UrlUnpadded::decode_vec(&jwe.tag)
+        .map_err(|e| anyhow!("issue decoding `tag`: {e}"))?;
+    let kek = kdf::derive_key_1pu(ze.as_bytes(), zs.as_bytes(), &jwe.protected, &cc_tag)?;
+
+    let encrypted_key = Base64UrlUnpadded::decode_vec(&recipient.encrypted_key)
+        .map_err(|e| anyhow!("issue decoding `encrypted_key`: {e}"))?;
+    let cek = Kek::from(kek)
+        .unwrap_vec(encrypted_key.as_slice())
+        .map_err(|e| anyhow!("issue unwrapping cek: {e}"))?;
+
+    decrypt_content(jwe, &cek)
+}
+
+// Find the key encryption information for the receiver.
+fn find_recipient<'a>(jwe: &'a Jwe, receiver: &impl Receiver) -> Result<&'a KeyEncryption> {
+    match &jwe.recipients {
+        Recipients::One(recipient) => Ok(recipient),
+        Recipients::Many { recipients } => recipients
+            .iter()
+            .find(|r| r.header.kid == Some(receiver.key_id()))
+            .ok_or_else(|| anyhow!("no recipient found")),
+    }
+}
+
+/// Decrypt a JWE whose CEK is wrapped using a pre-shared key encryption key,
+/// or is the pre-shared key itself (`dir`), and return the plaintext.
+///
+/// # Errors
+///
+/// Returns an error if the JWE cannot be decrypted.
+pub fn decrypt_with_kek<T: DeserializeOwned>(jwe: &Jwe, kek: &OctJwk) -> Result<T> {
+    let recipient = match &jwe.recipients {
+        Recipients::One(recipient) => recipient,
+        Recipients::Many { recipients } => {
+            let Some(found) = recipients.iter().find(|r| r.header.kid == kek.kid) else {
+                return Err(anyhow!("no recipient found"));
+            };
+            found
+        }
+    };
+
+    let encrypted_key = Base64UrlUnpadded::decode_vec(&recipient.encrypted_key)
+        .map_err(|e| anyhow!("issue decoding `encrypted_key`: {e}"))?;
+
+    let mut cek = match recipient.header.alg {
+        KeyAlgorithm::Dir => kek.to_bytes()?,
+        KeyAlgorithm::A256Kw => Kek::from(kek_bytes(kek)?)
+            .unwrap_vec(&encrypted_key)
+            .map_err(|e| anyhow!("issue unwrapping cek: {e}"))?,
+        KeyAlgorithm::A256GcmKw => {
+            let Some(base64_iv) = &recipient.header.iv else {
+                return Err(anyhow!("missing `iv`"));
+            };
+            let Some(base64_tag) = &recipient.header.tag else {
+                return Err(anyhow!("missing `tag`"));
+            };
+            let iv = Base64UrlUnpadded::decode_vec(base64_iv)
+                .map_err(|e| anyhow!("issue decoding `iv`: {e}"))?;
+            let tag = Base64UrlUnpadded::decode_vec(base64_tag)
+                .map_err(|e| anyhow!("issue decoding `tag`: {e}"))?;
+            if iv.len() != 12 || tag.len() != 16 {
+                return Err(anyhow!("invalid `iv` or `tag` length"));
+            }
+
+            let mut buffer = encrypted_key;
+            Aes256Gcm::new(&kek_bytes(kek)?.into())
+                .decrypt_in_place_detached(
+                    Nonce::from_slice(&iv),
+                    &[],
+                    &mut buffer,
+                    Tag::from_slice(&tag),
+                )
+                .map_err(|e| anyhow!("issue unwrapping cek: {e}"))?;
+            buffer
+        }
+        _ => {
+            return Err(anyhow!(
+                "`decrypt_with_kek` requires the A256KW, A256GCMKW, or dir algorithm"
+            ))
+        }
+    };
+
+    let plaintext = decrypt_content(jwe, &cek);
+    cek.zeroize();
+    plaintext
+}
+
+/// Decrypt a JWE whose CEK is encrypted using RSA-OAEP or RSA-OAEP-256 and
+/// return the plaintext.
+///
+/// # Errors
+///
+/// Returns an error if the JWE cannot be decrypted.
+#[cfg(feature = "rsa")]
+pub fn decrypt_with_rsa<T: DeserializeOwned>(jwe: &Jwe, private_key: &RsaPrivateKey) -> Result<T> {
+    check_rsa_size(private_key.size())?;
+
+    let recipient = match &jwe.recipients {
+        Recipients::One(recipient) => recipient,
+        Recipients::Many { recipients } => {
+            let is_rsa = |alg: &KeyAlgorithm| {
+                matches!(alg, KeyAlgorithm::RsaOaep | KeyAlgorithm::RsaOaep256)
+            };
+            let Some(found) = recipients.iter().find(|r| is_rsa(&r.header.alg)) else {
+                return Err(anyhow!("no recipient found"));
+            };
+            found
+        }
+    };
 
-            buffer.try_into().map_err(|_| anyhow!("issue unwrapping cek"))?
+    let padding = match recipient.header.alg {
+        KeyAlgorithm::RsaOaep => Oaep::new::<Sha1>(),
+        KeyAlgorithm::RsaOaep256 => Oaep::new::<Sha256>(),
+        _ => {
+            return Err(anyhow!(
+                "`decrypt_with_rsa` requires the RSA-OAEP or RSA-OAEP-256 algorithm"
+            ))
         }
     };
+    let encrypted_key = Base64UrlUnpadded::decode_vec(&recipient.encrypted_key)
+        .map_err(|e| anyhow!("issue decoding `encrypted_key`: {e}"))?;
+
+    // RFC 7516 §11.5: substitute a random CEK when unwrapping fails so that
+    // padding errors are indistinguishable from content decryption errors
+    let mut cek = vec![0; jwe.protected.enc.key_len()];
+    OsRng.fill_bytes(&mut cek);
+    if let Ok(mut decrypted) = private_key.decrypt(padding, &encrypted_key) {
+        if decrypted.len() == cek.len() {
+            cek.copy_from_slice(&decrypted);
+        }
+        decrypted.zeroize();
+    }
+
+    let plaintext = decrypt_content(jwe, &cek);
+    cek.zeroize();
+    plaintext
+}
+
+// Decrypt the JWE ciphertext using the CEK.
+fn decrypt_content<T: DeserializeOwned>(jwe: &Jwe, cek: &[u8]) -> Result<T> {
+    let enc = &jwe.protected.enc;
+    if cek.len() != enc.key_len() {
+        bail!("CEK length is not valid for `enc`");
+    }
 
     // unpack JWE
     let iv =
@@ -99,37 +301,102 @@ pub async fn decrypt<T: DeserializeOwned>(jwe: &Jwe, receiver: &impl Receiver) -
         .map_err(|e| anyhow!("issue decoding `ciphertext`: {e}"))?;
 
     // decrypt ciphertext using CEK, iv, aad, and tag
-    let mut buffer = ciphertext;
+    let mut buffer = match enc {
+        ContentAlgorithm::A128Gcm => decrypt_aead::<Aes128Gcm>(cek, &iv, &aad, ciphertext, &tag)?,
+        ContentAlgorithm::A192Gcm => {
+            decrypt_aead::<AesGcm<Aes192, U12>>(cek, &iv, &aad, ciphertext, &tag)?
+        }
+        ContentAlgorithm::A256Gcm => decrypt_aead::<Aes256Gcm>(cek, &iv, &aad, ciphertext, &tag)?,
+        ContentAlgorithm::A128CbcHs256 => {
+            CbcHs::A128CbcHs256.decrypt(cek, &iv, &ciphertext, &aad, &tag)?
+        }
+        ContentAlgorithm::A256CbcHs512 => {
+            CbcHs::A256CbcHs512.decrypt(cek, &iv, &ciphertext, &aad, &tag)?
+        }
+        ContentAlgorithm::XChaCha20Poly1305 => {
+            decrypt_aead::<XChaCha20Poly1305>(cek, &iv, &aad, ciphertext, &tag)?
+        }
+    };
 
-    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&cek))
-        .decrypt_in_place_detached(Nonce::from_slice(&iv), &aad, &mut buffer, Tag::from_slice(&tag))
-        .map_err(|e| anyhow!("issue decrypting: {e}"))?;
+    if jwe.protected.zip == Some(Zip::Deflate) {
+        buffer = miniz_oxide::inflate::decompress_to_vec_with_limit(&buffer, MAX_INFLATED_LEN)
+            .map_err(|e| anyhow!("issue decompressing plaintext: {e}"))?;
+    }
 
     Ok(serde_json::from_slice(&buffer)?)
 }
 
+// Decrypt the ciphertext using an AEAD content encryption algorithm.
+fn decrypt_aead<A: AeadInPlace + KeyInit>(
+    cek: &[u8], iv: &[u8], aad: &[u8], mut buffer: Vec<u8>, tag: &[u8],
+) -> Result<Vec<u8>> {
+    let nonce = GenericArray::from_exact_iter(iv.iter().copied())
+        .ok_or_else(|| anyhow!("invalid `iv` length"))?;
+    let tag = GenericArray::from_exact_iter(tag.iter().copied())
+        .ok_or_else(|| anyhow!("invalid `tag` length"))?;
+
+    A::new_from_slice(cek)
+        .map_err(|_| anyhow!("invalid CEK length"))?
+        .decrypt_in_place_detached(&nonce, aad, &mut buffer, &tag)
+        .map_err(|e| anyhow!("issue decrypting: {e}"))?;
+    Ok(buffer)
+}
+
 /// Deserialize JWE from Compact Serialization format.
+///
+/// The JWE must have exactly five `.`-separated base64url segments. The
+/// `encrypted_key` segment may only be empty for direct encryption (`dir`)
+/// and direct key agreement (`ECDH-ES`).
 impl FromStr for Jwe {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split('.').collect();
-        if parts.len() != 5 {
-            return Err(anyhow!("invalid JWE"));
+        let segments = s.split('.').collect::<Vec<_>>();
+        let [header, encrypted_key, iv, ciphertext, tag] = segments[..] else {
+            bail!("compact JWE must have 5 segments, found {}", segments.len());
+        };
+        for (name, segment, required) in [
+            ("protected header", header, true),
+            ("`encrypted_key`", encrypted_key, false),
+            ("`iv`", iv, true),
+            ("ciphertext", ciphertext, true),
+            ("`tag`", tag, true),
+        ] {
+            if required && segment.is_empty() {
+                bail!("compact JWE {name} is empty");
+            }
+            if !segment.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') {
+                bail!("compact JWE {name} is not base64url encoded");
+            }
         }
 
         // unpack flattened Protected header
-        let bytes = Base64UrlUnpadded::decode_vec(parts[0]).map_err(|_| fmt::Error)?;
-        let protected: ProtectedFlat = serde_json::from_slice(&bytes).map_err(|_| fmt::Error)?;
+        let bytes = Base64UrlUnpadded::decode_vec(header)
+            .map_err(|e| anyhow!("issue decoding protected header: {e}"))?;
+        let protected: ProtectedFlat = serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow!("issue deserializing protected header: {e}"))?;
 
         // reconstruct fields
-        let enc = protected.inner.enc;
-        let alg = protected.inner.alg.unwrap_or_default();
-        let epk = protected.epk;
+        let Some(alg) = protected.inner.alg else {
+            bail!("protected header is missing `alg`");
+        };
+        match alg {
+            KeyAlgorithm::Dir if !encrypted_key.is_empty() => {
+                bail!("`encrypted_key` must be empty for direct encryption");
+            }
+            KeyAlgorithm::Dir | KeyAlgorithm::EcdhEs => {}
+            _ if encrypted_key.is_empty() => bail!("compact JWE `encrypted_key` is empty"),
+            _ => {}
+        }
+        let epk = protected.epk.unwrap_or_default();
+        let (key_iv, key_tag) = (protected.iv, protected.tag);
 
         // calculate AAD
-        let protected = Protected { alg: None, enc };
-        let aad_bytes = serde_json::to_vec(&protected).map_err(|_| fmt::Error)?;
+        let protected = Protected {
+            alg: None,
+            ..protected.inner
+        };
+        let aad_bytes = serde_json::to_vec(&protected)?;
 
         Ok(Self {
             protected,
@@ -137,14 +404,16 @@ impl FromStr for Jwe {
                 header: Header {
                     alg,
                     epk,
+                    iv: key_iv,
+                    tag: key_tag,
                     ..Header::default()
                 },
-                encrypted_key: parts[1].to_string(),
+                encrypted_key: encrypted_key.to_string(),
             }),
             aad: Base64UrlUnpadded::encode_string(&aad_bytes),
-            iv: parts[2].to_string(),
-            ciphertext: parts[3].to_string(),
-            tag: parts[4].to_string(),
+            iv: iv.to_string(),
+            ciphertext: ciphertext.to_string(),
+            tag: tag.to_string(),
             ..Self::default()
         })
     }
@@ -1,31 +1,51 @@
 //! # JWE Builder
 
+use aes_gcm::aead::consts::U12;
 use aes_gcm::aead::KeyInit;
-use aes_gcm::{AeadCore, AeadInPlace, Aes256Gcm};
-// use aes_gcm::aes::cipher::consts::U12;
+use aes_gcm::aes::Aes192;
+use aes_gcm::{AeadCore, AeadInPlace, Aes128Gcm, Aes256Gcm, AesGcm};
 use aes_kw::Kek;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use base64ct::{Base64UrlUnpadded, Encoding};
 use chacha20poly1305::XChaCha20Poly1305;
 // use ecies::consts::{AEAD_TAG_LENGTH, NONCE_LENGTH, UNCOMPRESSED_PUBLIC_KEY_SIZE};
 use ed25519_dalek::PUBLIC_KEY_LENGTH;
 use rand::rngs::OsRng;
+use rand::RngCore;
+#[cfg(feature = "rsa")]
+use rsa::{Oaep, RsaPublicKey};
 use serde::Serialize;
+#[cfg(feature = "rsa")]
+use sha1::Sha1;
+#[cfg(feature = "rsa")]
+use sha2::Sha256;
 use x25519_dalek::EphemeralSecret;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::jose::jwe::cbc_hs::{CbcHs, BLOCK_LEN};
 use crate::jose::jwe::{
-    ContentAlgorithm, Header, Jwe, KeyAlgorithm, KeyEncryption, Protected, PublicKey, Recipients,
+    kdf, ContentAlgorithm, Header, Jwe, KeyAlgorithm, KeyEncryption, Protected, PublicKey,
+    Recipients, SecretKey, Zip,
 };
-use crate::jose::jwk::PublicKeyJwk;
+#[cfg(feature = "rsa")]
+use crate::jose::jwk::RsaPublicJwk;
+use crate::jose::jwk::{OctJwk, PublicKeyJwk};
 use crate::{Curve, KeyType};
 
+/// The DEFLATE compression level, trading speed for size.
+const DEFLATE_LEVEL: u8 = 6;
+
 /// Builds a JWE object using provided options.
 pub struct JweBuilder<P> {
     content_algorithm: ContentAlgorithm,
+    content_type: Option<String>,
+    apu: Option<Vec<u8>>,
+    apv: Option<Vec<u8>>,
     key_algorithm: KeyAlgorithm,
+    compress: bool,
     payload: P,
     recipients: Vec<Recipient>,
+    sender: Option<Sender>,
 }
 
 impl Default for JweBuilder<NoPayload> {
@@ -52,15 +72,26 @@ pub struct Recipient {
     pub public_key: PublicKey,
 }
 
+// The sender's static key used in sender-authenticated key agreement.
+struct Sender {
+    key_id: String,
+    secret_key: SecretKey,
+}
+
 impl JweBuilder<NoPayload> {
     /// Create a new JWE builder.
     #[must_use]
     pub const fn new() -> Self {
         Self {
             content_algorithm: ContentAlgorithm::A256Gcm,
+            content_type: None,
+            apu: None,
+            apv: None,
             key_algorithm: KeyAlgorithm::EcdhEs,
+            compress: false,
             payload: NoPayload,
             recipients: vec![],
+            sender: None,
         }
     }
 
@@ -68,9 +99,14 @@ impl JweBuilder<NoPayload> {
     pub fn payload<T: Serialize + Send>(self, payload: T) -> JweBuilder<Payload<T>> {
         JweBuilder {
             content_algorithm: self.content_algorithm,
+            content_type: self.content_type,
+            apu: self.apu,
+            apv: self.apv,
             key_algorithm: self.key_algorithm,
+            compress: self.compress,
             payload: Payload(payload),
             recipients: self.recipients,
+            sender: self.sender,
         }
     }
 }
@@ -83,6 +119,28 @@ impl<P> JweBuilder<P> {
         self
     }
 
+    /// The media type (`cty`) of the payload. Use `JWT` when the payload is a
+    /// signed JWT.
+    #[must_use]
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Agreement `PartyUInfo` (`apu`) bound into the ECDH-ES key derivation.
+    #[must_use]
+    pub fn apu(mut self, apu: impl Into<Vec<u8>>) -> Self {
+        self.apu = Some(apu.into());
+        self
+    }
+
+    /// Agreement `PartyVInfo` (`apv`) bound into the ECDH-ES key derivation.
+    #[must_use]
+    pub fn apv(mut self, apv: impl Into<Vec<u8>>) -> Self {
+        self.apv = Some(apv.into());
+        self
+    }
+
     /// The key management algorithm to use for encrypting the JWE CEK.
     #[must_use]
     pub const fn key_algorithm(mut self, algorithm: KeyAlgorithm) -> Self {
@@ -90,6 +148,29 @@ impl<P> JweBuilder<P> {
         self
     }
 
+    /// Compress the payload using DEFLATE before encryption (`zip: DEF`).
+    /// Worthwhile for large payloads, such as JSON-LD credentials.
+    #[must_use]
+    pub const fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// The sender's static key for sender-authenticated key agreement
+    /// (ECDH-1PU+A256KW).
+    ///
+    /// `key_id` is set as the `skid` header, allowing recipients to resolve
+    /// the sender's public key. The secret key must be on the same curve as
+    /// the recipients' keys.
+    #[must_use]
+    pub fn sender(mut self, key_id: impl Into<String>, secret_key: SecretKey) -> Self {
+        self.sender = Some(Sender {
+            key_id: key_id.into(),
+            secret_key,
+        });
+        self
+    }
+
     /// Add key encryption material for a JWE recipient.
     ///
     /// # Arguments
@@ -110,7 +191,7 @@ impl<P> JweBuilder<P> {
     }
 }
 
-impl<T: Serialize + Send> JweBuilder<Payload< T>> {
+impl<T: Serialize + Send> JweBuilder<Payload<T>> {
     /// Build the JWE.
     ///
     /// # Errors
@@ -120,6 +201,8 @@ impl<T: Serialize + Send> JweBuilder<Payload< T>> {
             return Err(anyhow!("no recipients set"));
         }
 
+        let protected = self.protected();
+
         // generate CEK and encrypt for each recipient
         let recipients = self.recipients.as_slice();
         let key_encrypter: &dyn KeyEncypter = match self.key_algorithm {
@@ -127,46 +210,142 @@ impl<T: Serialize + Send> JweBuilder<Payload< T>> {
                 if recipients.len() != 1 {
                     return Err(anyhow!("ECDH-ES requires a single recipient"));
                 }
-                &EcdhEs::from(&recipients[0])
+                &EcdhEs::new(&recipients[0], &protected)?
+            }
+            KeyAlgorithm::EcdhEsA256Kw => &EcdhEsA256Kw::new(recipients, &protected),
+            KeyAlgorithm::Ecdh1PuA256Kw => {
+                let Some(sender) = &self.sender else {
+                    return Err(anyhow!("ECDH-1PU requires a sender key: use `sender`"));
+                };
+                &Ecdh1PuA256Kw::new(recipients, &protected, sender)
+            }
+            KeyAlgorithm::EciesEs256K => &EciesEs256K::new(recipients, &protected.enc),
+            KeyAlgorithm::A256Kw | KeyAlgorithm::A256GcmKw => {
+                return Err(anyhow!("key wrapping requires a symmetric key: use `build_with_kek`"));
+            }
+            KeyAlgorithm::Dir => {
+                return Err(anyhow!(
+                    "direct encryption requires a symmetric key: use `build_with_kek`"
+                ));
+            }
+            KeyAlgorithm::RsaOaep | KeyAlgorithm::RsaOaep256 => {
+                return Err(anyhow!(
+                    "RSA key encryption requires an RSA key: use `build_with_rsa`"
+                ));
             }
-            KeyAlgorithm::EcdhEsA256Kw => &EcdhEsA256Kw::from(recipients),
-            KeyAlgorithm::EciesEs256K => &EciesEs256K::from(recipients),
         };
 
-        // encrypt content
-        let protected = Protected {
+        seal(self.payload.0, &protected, key_encrypter)
+    }
+
+    /// Build the JWE, wrapping the CEK with a pre-shared key encryption key
+    /// using the A256KW or A256GCMKW key management algorithm, or using the
+    /// pre-shared key as the CEK with the `dir` algorithm.
+    ///
+    /// Recipients added using [`JweBuilder::add_recipient`] are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the key management algorithm is not A256KW,
+    /// A256GCMKW, or `dir`, the key is not a 256-bit `oct` key (or, for
+    /// `dir`, the CEK length of the content encryption algorithm), or the
+    /// payload cannot be encrypted.
+    pub fn build_with_kek(self, key_id: impl Into<String>, kek: &OctJwk) -> Result<Jwe> {
+        let protected = self.protected();
+        if self.key_algorithm == KeyAlgorithm::Dir {
+            let key_encrypter = Direct::new(&protected.enc, key_id.into(), kek)?;
+            return seal(self.payload.0, &protected, &key_encrypter);
+        }
+        let key_encrypter = AesKw::new(&self.key_algorithm, &protected.enc, key_id.into(), kek)?;
+        seal(self.payload.0, &protected, &key_encrypter)
+    }
+
+    /// Build the JWE, encrypting the CEK to the recipient's RSA public key
+    /// using the RSA-OAEP or RSA-OAEP-256 key management algorithm.
+    ///
+    /// Recipients added using [`JweBuilder::add_recipient`] are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the key management algorithm is not RSA-OAEP or
+    /// RSA-OAEP-256, the public key is invalid or smaller than 2048 bits, or
+    /// the payload cannot be encrypted.
+    #[cfg(feature = "rsa")]
+    pub fn build_with_rsa(
+        self, key_id: impl Into<String>, public_key: &RsaPublicJwk,
+    ) -> Result<Jwe> {
+        let protected = self.protected();
+        let key_encrypter =
+            RsaOaep::new(&self.key_algorithm, &protected.enc, key_id.into(), public_key)?;
+        seal(self.payload.0, &protected, &key_encrypter)
+    }
+
+    // The JWE protected header.
+    fn protected(&self) -> Protected {
+        let encode =
+            |value: &Option<Vec<u8>>| value.as_deref().map(Base64UrlUnpadded::encode_string);
+        Protected {
             enc: self.content_algorithm.clone(),
             alg: None,
-        };
-        let aad = serde_json::to_vec(&protected)?;
+            cty: self.content_type.clone(),
+            apu: encode(&self.apu),
+            apv: encode(&self.apv),
+            zip: self.compress.then_some(Zip::Deflate),
+            skid: self.sender.as_ref().map(|s| s.key_id.clone()),
+        }
+    }
+}
 
-        let encrypted = match self.content_algorithm {
-            ContentAlgorithm::A256Gcm => a256gcm(self.payload.0, &key_encrypter.cek(), &aad)?,
-            ContentAlgorithm::XChaCha20Poly1305 => {
-                xchacha20_poly1305(self.payload.0, &key_encrypter.cek(), &aad)?
-            }
-        };
+// Encrypt the payload using the CEK generated by the key encrypter.
+fn seal<T: Serialize>(
+    payload: T, protected: &Protected, key_encrypter: &dyn KeyEncypter,
+) -> Result<Jwe> {
+    let aad = serde_json::to_vec(protected)?;
 
-        Ok(Jwe {
-            protected,
-            recipients: key_encrypter.recipients()?,
-            aad: Base64UrlUnpadded::encode_string(&aad),
-            iv: encrypted.iv,
-            tag: encrypted.tag,
-            ciphertext: Base64UrlUnpadded::encode_string(&encrypted.ciphertext),
-            ..Jwe::default()
-        })
+    let mut plaintext = serde_json::to_vec(&payload)?;
+    if protected.zip == Some(Zip::Deflate) {
+        plaintext = miniz_oxide::deflate::compress_to_vec(&plaintext, DEFLATE_LEVEL);
     }
+
+    let cek = key_encrypter.cek();
+    let encrypted = match protected.enc {
+        ContentAlgorithm::A128Gcm => encrypt_aead::<Aes128Gcm>(plaintext, cek, &aad)?,
+        ContentAlgorithm::A192Gcm => encrypt_aead::<AesGcm<Aes192, U12>>(plaintext, cek, &aad)?,
+        ContentAlgorithm::A256Gcm => encrypt_aead::<Aes256Gcm>(plaintext, cek, &aad)?,
+        ContentAlgorithm::A128CbcHs256 => {
+            encrypt_cbc_hs(CbcHs::A128CbcHs256, &plaintext, cek, &aad)?
+        }
+        ContentAlgorithm::A256CbcHs512 => {
+            encrypt_cbc_hs(CbcHs::A256CbcHs512, &plaintext, cek, &aad)?
+        }
+        ContentAlgorithm::XChaCha20Poly1305 => {
+            encrypt_aead::<XChaCha20Poly1305>(plaintext, cek, &aad)?
+        }
+    };
+
+    let cc_tag = Base64UrlUnpadded::decode_vec(&encrypted.tag)
+        .map_err(|e| anyhow!("issue decoding `tag`: {e}"))?;
+
+    Ok(Jwe {
+        recipients: key_encrypter.recipients(&cc_tag)?,
+        protected: protected.clone(),
+        aad: Base64UrlUnpadded::encode_string(&aad),
+        iv: encrypted.iv,
+        tag: encrypted.tag,
+        ciphertext: Base64UrlUnpadded::encode_string(&encrypted.ciphertext),
+        ..Jwe::default()
+    })
 }
 
 // Trait to accommodate for differences in the way key encryption is handled for
 // each Key Management Algorithm ("alg" parameter).
 trait KeyEncypter {
-    // Generate a Content Encryption Key (CEK) for the JWE.
-    fn cek(&self) -> [u8; PUBLIC_KEY_LENGTH];
-
-    // Generate the key encryption material for the JWE recipients.
-    fn recipients(&self) -> Result<Recipients>;
+    // The Content Encryption Key (CEK) for the JWE, sized for the content
+    // encryption algorithm.
+    fn cek(&self) -> &[u8];
+
+    // Generate the key encryption material for the JWE recipients. The
+    // content encryption authentication tag (`cc_tag`) is bound into the key
+    // derivation by ECDH-1PU.
+    fn recipients(&self, cc_tag: &[u8]) -> Result<Recipients>;
 }
 
 // ----------------
@@ -174,40 +353,35 @@ trait KeyEncypter {
 // ----------------
 #[derive(Zeroize, ZeroizeOnDrop)]
 struct EcdhEs {
-    ephemeral_public: [u8; PUBLIC_KEY_LENGTH],
-    cek: [u8; PUBLIC_KEY_LENGTH],
+    #[zeroize(skip)]
+    ephemeral_public: PublicKeyJwk,
+    cek: Vec<u8>,
 }
 
-impl From<&Recipient> for EcdhEs {
-    fn from(recipient: &Recipient) -> Self {
+impl EcdhEs {
+    fn new(recipient: &Recipient, protected: &Protected) -> Result<Self> {
         // generate CEK using ECDH-ES
-        let ephemeral_secret = EphemeralSecret::random_from_rng(rand::thread_rng());
-        let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral_secret).to_bytes();
-        let cek = ephemeral_secret.diffie_hellman(&recipient.public_key.into()).to_bytes();
+        let (shared_secret, ephemeral_public) = key_agreement(&recipient.public_key)?;
+        let cek = kdf::derive_cek(&shared_secret, protected)?;
 
-        Self {
+        Ok(Self {
             ephemeral_public,
             cek,
-        }
+        })
     }
 }
 
 impl KeyEncypter for EcdhEs {
-    fn cek(&self) -> [u8; PUBLIC_KEY_LENGTH] {
-        self.cek
+    fn cek(&self) -> &[u8] {
+        &self.cek
     }
 
-    fn recipients(&self) -> Result<Recipients> {
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
         let key_encryption = KeyEncryption {
             header: Header {
                 alg: KeyAlgorithm::EcdhEs,
                 kid: None,
-                epk: PublicKeyJwk {
-                    kty: KeyType::Okp,
-                    crv: Curve::Ed25519,
-                    x: Base64UrlUnpadded::encode_string(&self.ephemeral_public),
-                    ..PublicKeyJwk::default()
-                },
+                epk: self.ephemeral_public.clone(),
                 ..Header::default()
             },
             encrypted_key: Base64UrlUnpadded::encode_string(&[0; PUBLIC_KEY_LENGTH]),
@@ -224,27 +398,87 @@ impl KeyEncypter for EcdhEs {
 struct EcdhEsA256Kw<'a> {
     #[zeroize(skip)]
     recipients: &'a [Recipient],
-    cek: [u8; PUBLIC_KEY_LENGTH],
+    #[zeroize(skip)]
+    protected: &'a Protected,
+    cek: Vec<u8>,
 }
 
-impl<'a> From<&'a [Recipient]> for EcdhEsA256Kw<'a> {
-    fn from(recipients: &'a [Recipient]) -> Self {
+impl<'a> EcdhEsA256Kw<'a> {
+    fn new(recipients: &'a [Recipient], protected: &'a Protected) -> Self {
         Self {
             recipients,
-            cek: Aes256Gcm::generate_key(&mut rand::thread_rng()).into(),
+            protected,
+            cek: generate_cek(&protected.enc),
         }
     }
 }
 
 impl KeyEncypter for EcdhEsA256Kw<'_> {
-    fn cek(&self) -> [u8; PUBLIC_KEY_LENGTH] {
-        self.cek
+    fn cek(&self) -> &[u8] {
+        &self.cek
     }
 
-    fn recipients(&self) -> Result<Recipients> {
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
         let mut recipients = vec![];
         for r in self.recipients {
-            recipients.push(ecdh_a256kw(&self.cek, r)?);
+            recipients.push(ecdh_a256kw(&self.cek, r, self.protected)?);
+        }
+        Ok(Recipients::Many { recipients })
+    }
+}
+
+// ----------------
+// ECDH-1PU+A256KW
+// ----------------
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Ecdh1PuA256Kw<'a> {
+    #[zeroize(skip)]
+    recipients: &'a [Recipient],
+    #[zeroize(skip)]
+    protected: &'a Protected,
+    #[zeroize(skip)]
+    sender: &'a Sender,
+    cek: Vec<u8>,
+}
+
+impl<'a> Ecdh1PuA256Kw<'a> {
+    fn new(recipients: &'a [Recipient], protected: &'a Protected, sender: &'a Sender) -> Self {
+        Self {
+            recipients,
+            protected,
+            sender,
+            cek: generate_cek(&protected.enc),
+        }
+    }
+}
+
+impl KeyEncypter for Ecdh1PuA256Kw<'_> {
+    fn cek(&self) -> &[u8] {
+        &self.cek
+    }
+
+    fn recipients(&self, cc_tag: &[u8]) -> Result<Recipients> {
+        let mut recipients = vec![];
+        for r in self.recipients {
+            // Ze from an ephemeral key, Zs from the sender's static key
+            let (mut ze, ephemeral_public) = key_agreement(&r.public_key)?;
+            let mut zs = self.sender.secret_key.diffie_hellman(&r.public_key)?;
+            let kek = kdf::derive_key_1pu(&ze, &zs, self.protected, cc_tag);
+            ze.zeroize();
+            zs.zeroize();
+
+            let encrypted_key = Kek::from(kek?)
+                .wrap_vec(&self.cek)
+                .map_err(|e| anyhow!("issue wrapping cek: {e}"))?;
+            recipients.push(KeyEncryption {
+                header: Header {
+                    alg: KeyAlgorithm::Ecdh1PuA256Kw,
+                    kid: Some(r.key_id.clone()),
+                    epk: ephemeral_public,
+                    ..Header::default()
+                },
+                encrypted_key: Base64UrlUnpadded::encode_string(&encrypted_key),
+            });
         }
         Ok(Recipients::Many { recipients })
     }
@@ -257,24 +491,24 @@ impl KeyEncypter for EcdhEsA256Kw<'_> {
 struct EciesEs256K<'a> {
     #[zeroize(skip)]
     recipients: &'a [Recipient],
-    cek: [u8; PUBLIC_KEY_LENGTH],
+    cek: Vec<u8>,
 }
 
-impl<'a> From<&'a [Recipient]> for EciesEs256K<'a> {
-    fn from(recipients: &'a [Recipient]) -> Self {
+impl<'a> EciesEs256K<'a> {
+    fn new(recipients: &'a [Recipient], enc: &ContentAlgorithm) -> Self {
         Self {
             recipients,
-            cek: Aes256Gcm::generate_key(&mut rand::thread_rng()).into(),
+            cek: generate_cek(enc),
         }
     }
 }
 
 impl KeyEncypter for EciesEs256K<'_> {
-    fn cek(&self) -> [u8; PUBLIC_KEY_LENGTH] {
-        self.cek
+    fn cek(&self) -> &[u8] {
+        &self.cek
     }
 
-    fn recipients(&self) -> Result<Recipients> {
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
         let mut recipients = vec![];
         for r in self.recipients {
             recipients.push(ecies_es256k(&self.cek, r)?);
@@ -283,6 +517,191 @@ impl KeyEncypter for EciesEs256K<'_> {
     }
 }
 
+// ----------------
+// A256KW / A256GCMKW
+// ----------------
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct AesKw {
+    #[zeroize(skip)]
+    alg: KeyAlgorithm,
+    #[zeroize(skip)]
+    key_id: String,
+    kek: [u8; 32],
+    cek: Vec<u8>,
+}
+
+impl AesKw {
+    fn new(
+        alg: &KeyAlgorithm, enc: &ContentAlgorithm, key_id: String, kek: &OctJwk,
+    ) -> Result<Self> {
+        if !matches!(alg, KeyAlgorithm::A256Kw | KeyAlgorithm::A256GcmKw) {
+            return Err(anyhow!("key wrapping requires the A256KW or A256GCMKW key algorithm"));
+        }
+        let kek = kek_bytes(kek)?;
+
+        Ok(Self {
+            alg: alg.clone(),
+            key_id,
+            kek,
+            cek: generate_cek(enc),
+        })
+    }
+}
+
+impl KeyEncypter for AesKw {
+    fn cek(&self) -> &[u8] {
+        &self.cek
+    }
+
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
+        let mut header = Header {
+            alg: self.alg.clone(),
+            kid: Some(self.key_id.clone()),
+            ..Header::default()
+        };
+
+        let encrypted_key = if self.alg == KeyAlgorithm::A256GcmKw {
+            let iv = Aes256Gcm::generate_nonce(&mut OsRng);
+            let mut encrypted_key = self.cek.clone();
+            let tag = Aes256Gcm::new(&self.kek.into())
+                .encrypt_in_place_detached(&iv, &[], &mut encrypted_key)
+                .map_err(|e| anyhow!("issue wrapping cek: {e}"))?;
+            header.iv = Some(Base64UrlUnpadded::encode_string(&iv));
+            header.tag = Some(Base64UrlUnpadded::encode_string(&tag));
+            encrypted_key
+        } else {
+            Kek::from(self.kek)
+                .wrap_vec(&self.cek)
+                .map_err(|e| anyhow!("issue wrapping cek: {e}"))?
+        };
+
+        Ok(Recipients::One(KeyEncryption {
+            header,
+            encrypted_key: Base64UrlUnpadded::encode_string(&encrypted_key),
+        }))
+    }
+}
+
+// ----------------
+// dir
+// ----------------
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Direct {
+    #[zeroize(skip)]
+    key_id: String,
+    cek: Vec<u8>,
+}
+
+impl Direct {
+    fn new(enc: &ContentAlgorithm, key_id: String, key: &OctJwk) -> Result<Self> {
+        let direct = Self {
+            key_id,
+            cek: key.to_bytes()?,
+        };
+        if direct.cek.len() != enc.key_len() {
+            bail!("direct encryption requires a {}-bit key for `enc`", enc.key_len() * 8);
+        }
+        Ok(direct)
+    }
+}
+
+impl KeyEncypter for Direct {
+    fn cek(&self) -> &[u8] {
+        &self.cek
+    }
+
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
+        Ok(Recipients::One(KeyEncryption {
+            header: Header {
+                alg: KeyAlgorithm::Dir,
+                kid: Some(self.key_id.clone()),
+                ..Header::default()
+            },
+            encrypted_key: String::new(),
+        }))
+    }
+}
+
+// Generate a random CEK of the length required by the content encryption
+// algorithm.
+fn generate_cek(enc: &ContentAlgorithm) -> Vec<u8> {
+    let mut cek = vec![0; enc.key_len()];
+    OsRng.fill_bytes(&mut cek);
+    cek
+}
+
+// The 256-bit key encryption key used by A256KW and A256GCMKW.
+pub(super) fn kek_bytes(kek: &OctJwk) -> Result<[u8; 32]> {
+    let mut bytes = kek.to_bytes()?;
+    let kek = <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| anyhow!("A256KW and A256GCMKW require a 256-bit key"));
+    bytes.zeroize();
+    kek
+}
+
+// ----------------
+// RSA-OAEP / RSA-OAEP-256
+// ----------------
+#[cfg(feature = "rsa")]
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct RsaOaep {
+    #[zeroize(skip)]
+    alg: KeyAlgorithm,
+    #[zeroize(skip)]
+    key_id: String,
+    #[zeroize(skip)]
+    public_key: RsaPublicKey,
+    cek: Vec<u8>,
+}
+
+#[cfg(feature = "rsa")]
+impl RsaOaep {
+    fn new(
+        alg: &KeyAlgorithm, enc: &ContentAlgorithm, key_id: String, public_key: &RsaPublicJwk,
+    ) -> Result<Self> {
+        if !matches!(alg, KeyAlgorithm::RsaOaep | KeyAlgorithm::RsaOaep256) {
+            return Err(anyhow!(
+                "RSA key encryption requires the RSA-OAEP or RSA-OAEP-256 key algorithm"
+            ));
+        }
+
+        Ok(Self {
+            alg: alg.clone(),
+            key_id,
+            public_key: public_key.to_public_key()?,
+            cek: generate_cek(enc),
+        })
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl KeyEncypter for RsaOaep {
+    fn cek(&self) -> &[u8] {
+        &self.cek
+    }
+
+    fn recipients(&self, _: &[u8]) -> Result<Recipients> {
+        let padding = if self.alg == KeyAlgorithm::RsaOaep {
+            Oaep::new::<Sha1>()
+        } else {
+            Oaep::new::<Sha256>()
+        };
+        let encrypted_key = self
+            .public_key
+            .encrypt(&mut OsRng, padding, &self.cek)
+            .map_err(|e| anyhow!("issue encrypting cek: {e}"))?;
+
+        Ok(Recipients::One(KeyEncryption {
+            header: Header {
+                alg: self.alg.clone(),
+                kid: Some(self.key_id.clone()),
+                ..Header::default()
+            },
+            encrypted_key: Base64UrlUnpadded::encode_string(&encrypted_key),
+        }))
+    }
+}
+
 /// Encrypted content.
 #[derive(Clone, Debug, Default)]
 pub struct Encrypted {
@@ -303,17 +722,7 @@ pub struct Encrypted {
 pub fn a256gcm<T: Serialize>(
     plaintext: T, cek: &[u8; PUBLIC_KEY_LENGTH], aad: &[u8],
 ) -> Result<Encrypted> {
-    let mut buffer = serde_json::to_vec(&plaintext)?;
-    let nonce = Aes256Gcm::generate_nonce(&mut rand::thread_rng());
-    let tag = Aes256Gcm::new(cek.into())
-        .encrypt_in_place_detached(&nonce, aad, &mut buffer)
-        .map_err(|e| anyhow!("issue encrypting: {e}"))?;
-
-    Ok(Encrypted {
-        iv: Base64UrlUnpadded::encode_string(&nonce),
-        tag: Base64UrlUnpadded::encode_string(&tag),
-        ciphertext: buffer,
-    })
+    encrypt_aead::<Aes256Gcm>(serde_json::to_vec(&plaintext)?, cek, aad)
 }
 
 /// Encrypt the payload using XChacha20+Poly1305.
@@ -323,9 +732,17 @@ pub fn a256gcm<T: Serialize>(
 pub fn xchacha20_poly1305<T: Serialize>(
     plaintext: T, cek: &[u8; PUBLIC_KEY_LENGTH], aad: &[u8],
 ) -> Result<Encrypted> {
-    let mut buffer = serde_json::to_vec(&plaintext)?;
-    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
-    let tag = XChaCha20Poly1305::new(cek.into())
+    encrypt_aead::<XChaCha20Poly1305>(serde_json::to_vec(&plaintext)?, cek, aad)
+}
+
+// Encrypt the buffer using an AEAD content encryption algorithm and a random
+// nonce.
+fn encrypt_aead<A: AeadInPlace + KeyInit>(
+    mut buffer: Vec<u8>, cek: &[u8], aad: &[u8],
+) -> Result<Encrypted> {
+    let nonce = A::generate_nonce(&mut OsRng);
+    let tag = A::new_from_slice(cek)
+        .map_err(|_| anyhow!("invalid CEK length"))?
         .encrypt_in_place_detached(&nonce, aad, &mut buffer)
         .map_err(|e| anyhow!("issue encrypting: {e}"))?;
 
@@ -336,44 +753,77 @@ pub fn xchacha20_poly1305<T: Serialize>(
     })
 }
 
+// Encrypt the buffer using AES-CBC with HMAC-SHA2 and a random IV.
+fn encrypt_cbc_hs(alg: CbcHs, buffer: &[u8], cek: &[u8], aad: &[u8]) -> Result<Encrypted> {
+    let mut iv = [0; BLOCK_LEN];
+    OsRng.fill_bytes(&mut iv);
+    let (ciphertext, tag) = alg.encrypt(cek, &iv, buffer, aad)?;
+
+    Ok(Encrypted {
+        iv: Base64UrlUnpadded::encode_string(&iv),
+        tag: Base64UrlUnpadded::encode_string(&tag),
+        ciphertext,
+    })
+}
+
 /// Encrypt the content encryption key (CEK)for the specified recipient using
 /// ECDH-ES+A256KW.
 ///
 /// # Errors
 /// LATER: add error docs
-pub fn ecdh_a256kw(cek: &[u8; PUBLIC_KEY_LENGTH], recipient: &Recipient) -> Result<KeyEncryption> {
-    // derive shared secret
-    let ephemeral_secret = EphemeralSecret::random_from_rng(rand::thread_rng());
-    let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral_secret);
-    let shared_secret = ephemeral_secret.diffie_hellman(&recipient.public_key.into());
+pub fn ecdh_a256kw(
+    cek: &[u8], recipient: &Recipient, protected: &Protected,
+) -> Result<KeyEncryption> {
+    // derive shared secret and key encryption key
+    let (shared_secret, ephemeral_public) = key_agreement(&recipient.public_key)?;
+    let kek = kdf::derive_key(&shared_secret, &KeyAlgorithm::EcdhEsA256Kw, protected)?;
 
     // encrypt (wrap) CEK
-    let encrypted_key = Kek::from(*shared_secret.as_bytes())
-        .wrap_vec(cek)
-        .map_err(|e| anyhow!("issue wrapping cek: {e}"))?;
+    let encrypted_key =
+        Kek::from(kek).wrap_vec(cek).map_err(|e| anyhow!("issue wrapping cek: {e}"))?;
 
     Ok(KeyEncryption {
         header: Header {
             alg: KeyAlgorithm::EcdhEsA256Kw,
             kid: Some(recipient.key_id.clone()),
-            epk: PublicKeyJwk {
-                kty: KeyType::Okp,
-                crv: Curve::Ed25519,
-                x: Base64UrlUnpadded::encode_string(ephemeral_public.as_bytes()),
-                ..PublicKeyJwk::default()
-            },
+            epk: ephemeral_public,
             ..Header::default()
         },
         encrypted_key: Base64UrlUnpadded::encode_string(&encrypted_key),
     })
 }
 
+// Perform ECDH key agreement with the recipient's public key using an
+// ephemeral key pair on the same curve, returning the shared secret and the
+// ephemeral public key (`epk`).
+fn key_agreement(recipient_public: &PublicKey) -> Result<([u8; 32], PublicKeyJwk)> {
+    match recipient_public.curve() {
+        Curve::X25519 => {
+            let ephemeral_secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+            let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral_secret);
+            let shared_secret = ephemeral_secret.diffie_hellman(&(*recipient_public).into());
+            Ok((shared_secret.to_bytes(), PublicKey::from(ephemeral_public).to_jwk()))
+        }
+        Curve::P256 => {
+            let ephemeral_secret = p256::ecdh::EphemeralSecret::random(&mut OsRng);
+            let ephemeral_public = ephemeral_secret.public_key();
+            let shared_secret =
+                ephemeral_secret.diffie_hellman(&p256::PublicKey::try_from(*recipient_public)?);
+            Ok((
+                (*shared_secret.raw_secret_bytes()).into(),
+                PublicKey::from(ephemeral_public).to_jwk(),
+            ))
+        }
+        crv => Err(anyhow!("unsupported ECDH-ES curve: {crv:?}")),
+    }
+}
+
 /// Encrypt the content encryption key (CEK)for the specified recipient using
 /// ECIES-ES256K.
 ///
 /// # Errors
 /// LATER: add error docs
-pub fn ecies_es256k(cek: &[u8; PUBLIC_KEY_LENGTH], recipient: &Recipient) -> Result<KeyEncryption> {
+pub fn ecies_es256k(cek: &[u8], recipient: &Recipient) -> Result<KeyEncryption> {
     // ----------------------------------------------------------------
     // Using the `ecies` library's top-level `encrypt`.
     // ----------------------------------------------------------------
@@ -401,7 +851,7 @@ pub fn ecies_es256k(cek: &[u8; PUBLIC_KEY_LENGTH], recipient: &Recipient) -> Res
 
     // encrypt (wrap) CEK
     let iv = Aes256Gcm::generate_nonce(&mut OsRng);
-    let mut encrypted_key = *cek;
+    let mut encrypted_key = cek.to_vec();
     let tag = Aes256Gcm::new(&shared_secret.into())
         .encrypt_in_place_detached(&iv, &[], &mut encrypted_key)
         .map_err(|e| anyhow!("issue encrypting: {e}"))?;
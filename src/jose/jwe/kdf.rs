@@ -0,0 +1,183 @@
+//! # Concat KDF
+//!
+//! Derives the key agreement key from the ECDH shared secret (`Z`) using the
+//! Concat KDF ([NIST.800-56A] §5.8.1) as profiled by [RFC7518 §4.6.2].
+//!
+//! [NIST.800-56A]: https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-56Ar2.pdf
+//! [RFC7518 §4.6.2]: https://www.rfc-editor.org/rfc/rfc7518#section-4.6.2
+
+use anyhow::{anyhow, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::jose::jwe::{KeyAlgorithm, Protected};
+
+/// Derive the content encryption key for direct key agreement (ECDH-ES),
+/// binding the `enc` identifier and the `apu`/`apv` header values.
+///
+/// The key is the length required by the `enc` algorithm.
+pub fn derive_cek(z: &[u8], protected: &Protected) -> Result<Vec<u8>> {
+    let alg_id = serde_json::to_value(&protected.enc)?;
+    let alg_id = alg_id.as_str().unwrap_or_default();
+    let (apu, apv) = party_info(protected)?;
+
+    Ok(concat_kdf(z, alg_id, &apu, &apv, &[], protected.enc.key_len()))
+}
+
+/// Derive the 256-bit key encryption key for key agreement with key
+/// wrapping, binding the `alg` identifier and the `apu`/`apv` header values.
+pub fn derive_key(z: &[u8], alg: &KeyAlgorithm, protected: &Protected) -> Result<[u8; 32]> {
+    let alg_id = serde_json::to_value(alg)?;
+    let alg_id = alg_id.as_str().unwrap_or_default();
+    let (apu, apv) = party_info(protected)?;
+
+    let mut key = [0; 32];
+    key.copy_from_slice(&concat_kdf(z, alg_id, &apu, &apv, &[], 32));
+    Ok(key)
+}
+
+/// Derive the 256-bit key encryption key for ECDH-1PU+A256KW from the
+/// ephemeral-static (`ze`) and static-static (`zs`) shared secrets.
+///
+/// As per [draft-madden-jose-ecdh-1pu-04] §2.3, `Z` is `Ze || Zs` and the
+/// content encryption authentication tag (`cc_tag`) is appended to
+/// `SuppPubInfo`, binding the wrapped key to the ciphertext.
+///
+/// [draft-madden-jose-ecdh-1pu-04]: https://datatracker.ietf.org/doc/html/draft-madden-jose-ecdh-1pu-04#section-2.3
+pub fn derive_key_1pu(
+    ze: &[u8], zs: &[u8], protected: &Protected, cc_tag: &[u8],
+) -> Result<[u8; 32]> {
+    let alg_id = serde_json::to_value(KeyAlgorithm::Ecdh1PuA256Kw)?;
+    derive_1pu(ze, zs, alg_id.as_str().unwrap_or_default(), protected, cc_tag)
+}
+
+// Derive a 256-bit ECDH-1PU key for `alg_id`, with `Z` set to `Ze || Zs`.
+fn derive_1pu(
+    ze: &[u8], zs: &[u8], alg_id: &str, protected: &Protected, cc_tag: &[u8],
+) -> Result<[u8; 32]> {
+    let (apu, apv) = party_info(protected)?;
+
+    let mut z = [ze, zs].concat();
+    let mut key = [0; 32];
+    key.copy_from_slice(&concat_kdf(&z, alg_id, &apu, &apv, cc_tag, 32));
+    z.zeroize();
+    Ok(key)
+}
+
+// Decode the `apu` and `apv` header values.
+fn party_info(protected: &Protected) -> Result<(Vec<u8>, Vec<u8>)> {
+    let decode = |value: &Option<String>, name: &str| -> Result<Vec<u8>> {
+        value.as_ref().map_or_else(
+            || Ok(vec![]),
+            |v| {
+                Base64UrlUnpadded::decode_vec(v)
+                    .map_err(|e| anyhow!("issue decoding `{name}`: {e}"))
+            },
+        )
+    };
+    Ok((decode(&protected.apu, "apu")?, decode(&protected.apv, "apv")?))
+}
+
+// Concat KDF using SHA-256, returning `key_len` bytes of key material. A
+// non-empty `cc_tag` is appended to `SuppPubInfo` (ECDH-1PU key wrapping).
+fn concat_kdf(
+    z: &[u8], alg_id: &str, apu: &[u8], apv: &[u8], cc_tag: &[u8], key_len: usize,
+) -> Vec<u8> {
+    let len_prefixed = |data: &[u8]| {
+        let len = u32::try_from(data.len()).unwrap_or(u32::MAX);
+        [&len.to_be_bytes(), data].concat()
+    };
+
+    // OtherInfo = AlgorithmID || PartyUInfo || PartyVInfo || SuppPubInfo
+    let key_bits = u32::try_from(key_len * 8).unwrap_or(u32::MAX);
+    let mut other_info = [
+        len_prefixed(alg_id.as_bytes()),
+        len_prefixed(apu),
+        len_prefixed(apv),
+        key_bits.to_be_bytes().to_vec(),
+    ]
+    .concat();
+    if !cc_tag.is_empty() {
+        other_info.extend(len_prefixed(cc_tag));
+    }
+
+    let mut derived = Vec::with_capacity(key_len);
+    let mut counter = 1u32;
+    while derived.len() < key_len {
+        let round = Sha256::new()
+            .chain_update(counter.to_be_bytes())
+            .chain_update(z)
+            .chain_update(&other_info)
+            .finalize();
+        derived.extend_from_slice(&round);
+        counter += 1;
+    }
+    derived.truncate(key_len);
+    derived
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jose::jwe::ContentAlgorithm;
+
+    // RFC7518 Appendix C
+    #[test]
+    fn rfc7518_vector() {
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let key = concat_kdf(&z, "A128GCM", b"Alice", b"Bob", &[], 16);
+        assert_eq!(Base64UrlUnpadded::encode_string(&key), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    // RFC7518 Appendix C, using the header values
+    #[test]
+    fn rfc7518_cek() {
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let protected = Protected {
+            enc: ContentAlgorithm::A128Gcm,
+            apu: Some("QWxpY2U".to_string()),
+            apv: Some("Qm9i".to_string()),
+            ..Protected::default()
+        };
+        let cek = derive_cek(&z, &protected).expect("should derive");
+        assert_eq!(Base64UrlUnpadded::encode_string(&cek), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    // draft-madden-jose-ecdh-1pu-04 Appendix A, using the header values.
+    // The vector uses direct key agreement, so its AlgorithmID is the `enc`.
+    #[test]
+    fn ecdh_1pu_vector() {
+        let ze = hex::decode("9e56d91d817135d372834283bf84269cfb316ea3da806a48f6daa7798cfe90c4")
+            .unwrap();
+        let zs = hex::decode("e3ca3474384c9f62b30bfd4c688b3e7d4110a1b4badc3cc54ef7b81241efd50d")
+            .unwrap();
+        let protected = Protected {
+            enc: ContentAlgorithm::A256Gcm,
+            apu: Some("QWxpY2U".to_string()),
+            apv: Some("Qm9i".to_string()),
+            ..Protected::default()
+        };
+        let key = derive_1pu(&ze, &zs, "A256GCM", &protected, &[]).expect("should derive");
+        assert_eq!(
+            hex::encode(key),
+            "6caf13723d14850ad4b42cd6dde935bffd2fff00a9ba70de05c203a5e1722ca7"
+        );
+
+        // key wrapping binds the algorithm and the content tag
+        let kek = derive_key_1pu(&ze, &zs, &protected, &[]).expect("should derive");
+        assert_eq!(
+            kek,
+            derive_1pu(&ze, &zs, "ECDH-1PU+A256KW", &protected, &[]).expect("should derive")
+        );
+        assert_ne!(kek, key);
+        let bound = derive_key_1pu(&ze, &zs, &protected, &[0; 32]).expect("should derive");
+        assert_ne!(bound, kek);
+    }
+}
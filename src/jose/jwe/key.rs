@@ -1,7 +1,11 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use base64ct::{Base64UrlUnpadded, Encoding};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::jose::jwk::{KeyUse, PublicKeyJwk};
+use crate::{Curve, KeyType};
+
 pub const TAG_PUBKEY_FULL: u8 = 0x04;
 // const TAG_PUBKEY_EVEN: u8 = 0x02;
 // const TAG_PUBKEY_ODD: u8 = 0x03;
@@ -26,6 +30,7 @@ impl From<[u8; 32]> for SecretKey {
     }
 }
 
+#[allow(clippy::try_from_instead_of_from_str)]
 impl TryFrom<&str> for SecretKey {
     type Error = anyhow::Error;
 
@@ -51,17 +56,34 @@ impl SecretKey {
     /// # Errors
     /// LATER: document errors
     pub fn shared_secret(self, sender_public: PublicKey) -> Result<SharedSecret> {
-        // x25519
-        if sender_public.y.is_none() {
-            let sender_public = x25519_dalek::PublicKey::from(sender_public.to_bytes());
-            let secret = x25519_dalek::StaticSecret::from(self.0);
-            let shared_secret = secret.diffie_hellman(&sender_public);
-            return Ok(SharedSecret(shared_secret.to_bytes()));
+        if sender_public.crv == KeyCurve::Secp256k1 {
+            let aes_key = ecies::utils::decapsulate(&sender_public.try_into()?, &self.try_into()?)?;
+            return Ok(SharedSecret(aes_key));
         }
+        Ok(SharedSecret(self.diffie_hellman(&sender_public)?))
+    }
 
-        // secp256k1
-        let aes_key = ecies::utils::decapsulate(&sender_public.try_into()?, &self.try_into()?)?;
-        Ok(SharedSecret(aes_key))
+    // Raw X25519 or P-256 Diffie-Hellman with `public`. Unlike
+    // `shared_secret`, the secret key is not consumed, allowing a static key
+    // (such as the sender's key in ECDH-1PU) to be used with several
+    // recipients.
+    pub(crate) fn diffie_hellman(&self, public: &PublicKey) -> Result<[u8; 32]> {
+        match public.crv {
+            KeyCurve::X25519 => {
+                let public = x25519_dalek::PublicKey::from(public.to_bytes());
+                let secret = x25519_dalek::StaticSecret::from(self.0);
+                Ok(secret.diffie_hellman(&public).to_bytes())
+            }
+            KeyCurve::P256 => {
+                let secret = p256::SecretKey::from_slice(&self.0)
+                    .map_err(|e| anyhow!("issue parsing secret key: {e}"))?;
+                let public = p256::PublicKey::try_from(*public)?;
+                let shared_secret =
+                    p256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine());
+                Ok((*shared_secret.raw_secret_bytes()).into())
+            }
+            KeyCurve::Secp256k1 => bail!("unsupported Diffie-Hellman curve: secp256k1"),
+        }
     }
 }
 
@@ -91,14 +113,50 @@ impl SharedSecret {
     }
 }
 
+/// The curve a key agreement public key belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyCurve {
+    X25519,
+    Secp256k1,
+    P256,
+}
+
 /// The public key of the key pair used in encryption.
+///
+/// Keys converted from 32 bytes are X25519 keys and keys converted from 65
+/// bytes are secp256k1 keys. Use [`PublicKey::try_from`] with a
+/// [`PublicKeyJwk`] (such as a JWE `epk`) or a `p256::PublicKey` to
+/// explicitly identify the curve.
 #[derive(Clone, Copy)]
 pub struct PublicKey {
+    crv: KeyCurve,
     x: [u8; 32],
     y: Option<[u8; 32]>,
 }
 
 impl PublicKey {
+    /// The curve the public key belongs to.
+    #[must_use]
+    pub const fn curve(&self) -> Curve {
+        match self.crv {
+            KeyCurve::X25519 => Curve::X25519,
+            KeyCurve::Secp256k1 => Curve::Es256K,
+            KeyCurve::P256 => Curve::P256,
+        }
+    }
+
+    /// The public key as a JWK, for use as an ephemeral public key (`epk`).
+    #[must_use]
+    pub fn to_jwk(&self) -> PublicKeyJwk {
+        PublicKeyJwk {
+            kty: if self.crv == KeyCurve::X25519 { KeyType::Okp } else { KeyType::Ec },
+            crv: self.curve(),
+            x: Base64UrlUnpadded::encode_string(&self.x),
+            y: self.y.map(|y| Base64UrlUnpadded::encode_string(&y)),
+            ..PublicKeyJwk::default()
+        }
+    }
+
     /// Return the public key as an array of bytes.
     #[must_use]
     pub fn to_vec(&self) -> Vec<u8> {
@@ -130,7 +188,11 @@ impl PublicKey {
 
 impl From<[u8; 32]> for PublicKey {
     fn from(val: [u8; 32]) -> Self {
-        Self { x: val, y: None }
+        Self {
+            crv: KeyCurve::X25519,
+            x: val,
+            y: None,
+        }
     }
 }
 
@@ -140,27 +202,39 @@ impl From<[u8; 65]> for PublicKey {
         let mut y = [0; 32];
         x.copy_from_slice(&val[1..33]);
         y.copy_from_slice(&val[33..65]);
-        Self { x, y: Some(y) }
+        Self {
+            crv: KeyCurve::Secp256k1,
+            x,
+            y: Some(y),
+        }
     }
 }
 
 impl From<x25519_dalek::PublicKey> for PublicKey {
     fn from(val: x25519_dalek::PublicKey) -> Self {
-        Self {
-            x: val.to_bytes(),
-            y: None,
-        }
+        Self::from(val.to_bytes())
     }
 }
 
 impl From<ecies::PublicKey> for PublicKey {
     fn from(val: ecies::PublicKey) -> Self {
-        let key: [u8; 65] = val.serialize();
+        Self::from(val.serialize())
+    }
+}
+
+impl From<p256::PublicKey> for PublicKey {
+    fn from(val: p256::PublicKey) -> Self {
+        // tagged uncompressed point is 65 bytes
+        let point = val.to_encoded_point(false);
         let mut x = [0; 32];
         let mut y = [0; 32];
-        x.copy_from_slice(&key[1..33]);
-        y.copy_from_slice(&key[33..65]);
-        Self { x, y: Some(y) }
+        x.copy_from_slice(&point.as_bytes()[1..33]);
+        y.copy_from_slice(&point.as_bytes()[33..65]);
+        Self {
+            crv: KeyCurve::P256,
+            x,
+            y: Some(y),
+        }
     }
 }
 
@@ -168,23 +242,53 @@ impl TryFrom<&[u8]> for PublicKey {
     type Error = anyhow::Error;
 
     fn try_from(val: &[u8]) -> Result<Self> {
-        if val.len() == 32 {
-            let mut x = [0; 32];
-            x.copy_from_slice(&val[0..32]);
-            return Ok(Self { x, y: None });
+        if let Ok(x) = <[u8; 32]>::try_from(val) {
+            return Ok(Self::from(x));
         }
-
-        if val.len() == 65 {
-            let mut x = [0; 32];
-            let mut y = [0; 32];
-            x.copy_from_slice(&val[1..33]);
-            y.copy_from_slice(&val[33..65]);
-            return Ok(Self { x, y: Some(y) });
+        if let Ok(key) = <[u8; 65]>::try_from(val) {
+            return Ok(Self::from(key));
         }
 
         Err(anyhow!("invalid public key length"))
     }
 }
+
+/// Convert a JWK, such as a JWE `epk` header, using its `crv` to identify the
+/// key agreement curve.
+///
+/// For compatibility with earlier senders, `Ed25519` is treated as `X25519`.
+impl TryFrom<&PublicKeyJwk> for PublicKey {
+    type Error = anyhow::Error;
+
+    fn try_from(jwk: &PublicKeyJwk) -> Result<Self> {
+        jwk.check_use(&KeyUse::Encryption)?;
+
+        let decode = |coord: &str| -> Result<[u8; 32]> {
+            Base64UrlUnpadded::decode_vec(coord)
+                .map_err(|e| anyhow!("issue decoding public key: {e}"))?
+                .try_into()
+                .map_err(|_| anyhow!("invalid public key length"))
+        };
+
+        let crv = match jwk.crv {
+            Curve::X25519 | Curve::Ed25519 => {
+                return Ok(Self::from(decode(&jwk.x)?));
+            }
+            Curve::Es256K => KeyCurve::Secp256k1,
+            Curve::P256 => KeyCurve::P256,
+            _ => bail!("unsupported key agreement curve: {:?}", jwk.crv),
+        };
+        let Some(y) = &jwk.y else {
+            bail!("public key is missing `y` coordinate");
+        };
+
+        Ok(Self {
+            crv,
+            x: decode(&jwk.x)?,
+            y: Some(decode(y)?),
+        })
+    }
+}
 impl TryFrom<Vec<u8>> for PublicKey {
     type Error = anyhow::Error;
 
@@ -193,6 +297,7 @@ impl TryFrom<Vec<u8>> for PublicKey {
     }
 }
 
+#[allow(clippy::try_from_instead_of_from_str)]
 impl TryFrom<&str> for PublicKey {
     type Error = anyhow::Error;
 
@@ -217,6 +322,14 @@ impl From<PublicKey> for x25519_dalek::PublicKey {
     }
 }
 
+impl TryFrom<PublicKey> for p256::PublicKey {
+    type Error = anyhow::Error;
+
+    fn try_from(val: PublicKey) -> Result<Self> {
+        Self::from_sec1_bytes(&val.to_vec()).map_err(|e| anyhow!("issue parsing public key: {e}"))
+    }
+}
+
 impl TryFrom<PublicKey> for ecies::PublicKey {
     type Error = anyhow::Error;
 
@@ -1,6 +1,6 @@
 //! # JSON Web Key (JWK)
 //!
-//! A JWK ([RFC7517]) is a JSON representation of a cryptographic key.  
+//! A JWK ([RFC7517]) is a JSON representation of a cryptographic key.
 //! Additionally, a JWK Set (JWKS) is used to represent a set of JWKs.
 //!
 //! See [RFC7517] for more detail.
@@ -25,16 +25,33 @@
 //! [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
 //! [RFC7517]: https://www.rfc-editor.org/rfc/rfc7517
 
-use anyhow::{anyhow, Result};
+mod resolver;
+mod typed;
+
+use std::fmt::{self, Display};
+
+use anyhow::{anyhow, bail, Result};
 use base64ct::{Base64UrlUnpadded, Encoding};
 use multibase::Base;
+#[cfg(feature = "rsa")]
+use rsa::traits::PublicKeyParts;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+#[cfg(feature = "reqwest")]
+pub use self::resolver::{http_jwks_resolver, ReqwestFetcher};
+pub use self::resolver::{Fetched, JwksFetcher, JwksResolver};
+pub(crate) use self::typed::Jwk;
+use crate::jose::jwa::{ML_DSA_44_KEY_LEN, ML_DSA_65_KEY_LEN};
 use crate::jose::jwe::KeyAlgorithm;
-use crate::{Curve, KeyType};
+use crate::{Algorithm, Curve, KeyType};
 
 const ED25519_CODEC: [u8; 2] = [0xed, 0x01];
 const X25519_CODEC: [u8; 2] = [0xec, 0x01];
+const SECP256K1_CODEC: [u8; 2] = [0xe7, 0x01];
+#[cfg(feature = "rsa")]
+const MIN_RSA_BITS: usize = 2048;
 
 /// Alias for multi-base encoded string.
 pub type MultiKey = String;
@@ -54,56 +71,487 @@ pub struct PublicKeyJwk {
     /// Cryptographic curve type.
     pub crv: Curve,
 
-    /// X coordinate.
+    /// X coordinate. Not used by `AKP` keys.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub x: String,
 
     /// Y coordinate. Not required for `EdDSA` verification keys.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub y: Option<String>,
 
-    /// Algorithm intended for use with the key.
+    /// The public key of an `AKP` (e.g. ML-DSA) key.
+    #[serde(rename = "pub")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pub_: Option<String>,
+
+    /// Algorithm intended for use with the key. When set to a signature
+    /// algorithm, JWS verification requires the header `alg` to match.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub alg: Option<KeyAlgorithm>,
+    pub alg: Option<JwkAlgorithm>,
 
     /// Use of the key.
     #[serde(rename = "use")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub use_: Option<KeyUse>,
+
+    /// Operations the key is intended to be used for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_ops: Option<Vec<KeyOp>>,
 }
 
 impl PublicKeyJwk {
-    /// Convert a multi-base encoded key into a JWK.
+    /// Convert a multibase encoded multicodec public key, such as a `did:key`
+    /// identifier or a verification method's `publicKeyMultibase`, into a
+    /// JWK.
+    ///
+    /// The key must be base58btc encoded (`z` prefix) with an `ed25519-pub`,
+    /// `x25519-pub`, or `secp256k1-pub` multicodec prefix.
     ///
     /// # Errors
-    /// LATER: document errors.
+    /// Returns an error if the key is not base58btc encoded, the multicodec
+    /// prefix is unknown, or the key is not valid for its codec.
     pub fn from_multibase(key: &str) -> Result<Self> {
-        let (_, key_bytes) =
+        let (base, key_bytes) =
             multibase::decode(key).map_err(|e| anyhow!("issue decoding key: {e}"))?;
-        if key_bytes.len() - 2 != 32 {
-            return Err(anyhow!("key is not 32 bytes long"));
-        }
-        if key_bytes[0..2] != ED25519_CODEC && key_bytes[0..2] != X25519_CODEC {
-            return Err(anyhow!("not Ed25519"));
+        if base != Base::Base58Btc {
+            bail!("multibase key must be base58btc encoded");
         }
+        let Some((codec, key)) = key_bytes.split_first_chunk::<2>() else {
+            bail!("key is missing multicodec prefix");
+        };
 
-        Ok(Self {
-            kty: KeyType::Okp,
-            crv: Curve::Ed25519,
-            x: Base64UrlUnpadded::encode_string(&key_bytes[2..]),
-            ..Self::default()
-        })
+        match *codec {
+            ED25519_CODEC => Self::from_bytes(key, &Algorithm::EdDSA),
+            SECP256K1_CODEC => Self::from_bytes(key, &Algorithm::ES256K),
+            X25519_CODEC => {
+                if key.len() != 32 {
+                    bail!("X25519 key is not 32 bytes long");
+                }
+                Ok(Self {
+                    kty: KeyType::Okp,
+                    crv: Curve::X25519,
+                    x: Base64UrlUnpadded::encode_string(key),
+                    ..Self::default()
+                })
+            }
+            _ => bail!("unsupported multicodec prefix: {codec:02x?}"),
+        }
     }
 
-    /// Convert a JWK into a multi-base encoded key.
+    /// Convert a JWK into a base58btc multibase encoded multicodec key.
+    /// secp256k1 keys are encoded as a compressed SEC1 point.
     ///
     /// # Errors
-    /// LATER: document errors.
+    /// Returns an error if the key is not an Ed25519, X25519, or secp256k1
+    /// key, or its coordinates cannot be decoded.
     pub fn to_multibase(&self) -> Result<String> {
-        let mut key_bytes = Vec::new();
-        key_bytes.extend_from_slice(&ED25519_CODEC);
-        key_bytes.extend_from_slice(&Base64UrlUnpadded::decode_vec(&self.x)?);
+        let (codec, key) = match self.crv {
+            Curve::Ed25519 => (ED25519_CODEC, self.to_raw_bytes()?),
+            Curve::X25519 => (X25519_CODEC, self.to_raw_bytes()?),
+            Curve::Es256K => {
+                let point = k256::ecdsa::VerifyingKey::from_sec1_bytes(&self.to_sec1()?)
+                    .map_err(|e| anyhow!("invalid secp256k1 key: {e}"))?
+                    .to_encoded_point(true);
+                (SECP256K1_CODEC, point.as_bytes().to_vec())
+            }
+            _ => bail!("unsupported curve for multibase: {:?}", self.crv),
+        };
+
+        let mut key_bytes = codec.to_vec();
+        key_bytes.extend_from_slice(&key);
         Ok(multibase::encode(Base::Base58Btc, &key_bytes))
     }
+
+    /// Build a JWK from raw public key bytes for the signature algorithm, such
+    /// as those returned by [`Signer::verifying_key`](crate::Signer::verifying_key).
+    ///
+    /// Ed25519 keys are the 32-byte public key. EC (secp256k1, P-384, and
+    /// P-521) keys are a SEC1 encoded point (compressed or uncompressed).
+    /// ML-DSA keys are the encoded public key, held in `pub`.
+    ///
+    /// # Errors
+    /// Returns an error if the bytes are not a valid key for the algorithm.
+    pub fn from_bytes(key: &[u8], alg: &Algorithm) -> Result<Self> {
+        match alg {
+            Algorithm::EdDSA => {
+                if key.len() != 32 {
+                    return Err(anyhow!("Ed25519 key is not 32 bytes long"));
+                }
+                Ok(Self {
+                    kty: KeyType::Okp,
+                    crv: Curve::Ed25519,
+                    x: Base64UrlUnpadded::encode_string(key),
+                    ..Self::default()
+                })
+            }
+            Algorithm::MlDsa44 | Algorithm::MlDsa65 => {
+                let (crv, len) = if *alg == Algorithm::MlDsa44 {
+                    (Curve::MlDsa44, ML_DSA_44_KEY_LEN)
+                } else {
+                    (Curve::MlDsa65, ML_DSA_65_KEY_LEN)
+                };
+                if key.len() != len {
+                    bail!("{alg} key is not {len} bytes long");
+                }
+                Ok(Self {
+                    kty: KeyType::Akp,
+                    crv,
+                    pub_: Some(Base64UrlUnpadded::encode_string(key)),
+                    ..Self::default()
+                })
+            }
+            Algorithm::ES256K => {
+                let point = k256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                    .map_err(|e| anyhow!("invalid secp256k1 key: {e}"))?
+                    .to_encoded_point(false);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(anyhow!("invalid secp256k1 key"));
+                };
+                Ok(Self {
+                    kty: KeyType::Ec,
+                    crv: Curve::Es256K,
+                    x: Base64UrlUnpadded::encode_string(x),
+                    y: Some(Base64UrlUnpadded::encode_string(y)),
+                    ..Self::default()
+                })
+            }
+            Algorithm::ES256 => {
+                let point = p256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                    .map_err(|e| anyhow!("invalid P-256 key: {e}"))?
+                    .to_encoded_point(false);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(anyhow!("invalid P-256 key"));
+                };
+                Ok(Self {
+                    kty: KeyType::Ec,
+                    crv: Curve::P256,
+                    x: Base64UrlUnpadded::encode_string(x),
+                    y: Some(Base64UrlUnpadded::encode_string(y)),
+                    ..Self::default()
+                })
+            }
+            Algorithm::ES384 => {
+                let point = p384::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                    .map_err(|e| anyhow!("invalid P-384 key: {e}"))?
+                    .to_encoded_point(false);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(anyhow!("invalid P-384 key"));
+                };
+                Ok(Self {
+                    kty: KeyType::Ec,
+                    crv: Curve::P384,
+                    x: Base64UrlUnpadded::encode_string(x),
+                    y: Some(Base64UrlUnpadded::encode_string(y)),
+                    ..Self::default()
+                })
+            }
+            Algorithm::ES512 => {
+                let point = p521::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                    .map_err(|e| anyhow!("invalid P-521 key: {e}"))?
+                    .to_encoded_point(false);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(anyhow!("invalid P-521 key"));
+                };
+                Ok(Self {
+                    kty: KeyType::Ec,
+                    crv: Curve::P521,
+                    x: Base64UrlUnpadded::encode_string(x),
+                    y: Some(Base64UrlUnpadded::encode_string(y)),
+                    ..Self::default()
+                })
+            }
+        }
+    }
+
+    /// The key's canonical raw encoding: the raw public key bytes for OKP
+    /// keys (e.g. the 32-byte Ed25519 key) or the uncompressed SEC1 point for
+    /// EC keys.
+    ///
+    /// # Errors
+    /// Returns an error if the key coordinates cannot be decoded or the key
+    /// type is unsupported.
+    pub fn to_raw_bytes(&self) -> Result<Vec<u8>> {
+        match self.kty {
+            KeyType::Okp => Base64UrlUnpadded::decode_vec(&self.x)
+                .map_err(|e| anyhow!("issue decoding `x`: {e}")),
+            KeyType::Ec => self.to_sec1_uncompressed(),
+            KeyType::Oct => Err(anyhow!("unsupported key type: oct")),
+            KeyType::Rsa => Err(anyhow!("unsupported key type: RSA")),
+            KeyType::Akp => {
+                let Some(public_key) = &self.pub_ else {
+                    bail!("AKP key is missing `pub`");
+                };
+                Base64UrlUnpadded::decode_vec(public_key)
+                    .map_err(|e| anyhow!("issue decoding `pub`: {e}"))
+            }
+        }
+    }
+
+    /// The uncompressed SEC1 encoding (`0x04 || x || y`) of an EC key.
+    ///
+    /// # Errors
+    /// Returns an error if the key is not an EC key, is missing `y`, or the
+    /// coordinates cannot be decoded.
+    pub fn to_sec1_uncompressed(&self) -> Result<Vec<u8>> {
+        if self.kty != KeyType::Ec {
+            return Err(anyhow!("SEC1 encoding requires an EC key"));
+        }
+        let Some(y) = &self.y else {
+            return Err(anyhow!("EC key is missing `y` coordinate"));
+        };
+
+        let mut sec1 = vec![0x04]; // uncompressed format
+        sec1.append(
+            &mut Base64UrlUnpadded::decode_vec(&self.x)
+                .map_err(|e| anyhow!("issue decoding `x`: {e}"))?,
+        );
+        sec1.append(
+            &mut Base64UrlUnpadded::decode_vec(y)
+                .map_err(|e| anyhow!("issue decoding `y`: {e}"))?,
+        );
+        Ok(sec1)
+    }
+
+    // The SEC1 encoding of an EC key: uncompressed when `y` is present,
+    // otherwise `x` must hold a compressed point (`0x02`/`0x03` parity prefix
+    // followed by the x-coordinate), as used by some `did:key` keys.
+    pub(crate) fn to_sec1(&self) -> Result<Vec<u8>> {
+        if self.y.is_some() {
+            return self.to_sec1_uncompressed();
+        }
+        if self.kty != KeyType::Ec {
+            bail!("SEC1 encoding requires an EC key");
+        }
+        let x = Base64UrlUnpadded::decode_vec(&self.x)
+            .map_err(|e| anyhow!("issue decoding `x`: {e}"))?;
+        if !matches!(x.first(), Some(0x02 | 0x03)) {
+            bail!("EC key is missing `y` coordinate");
+        }
+        Ok(x)
+    }
+
+    /// Compute the key's JWK Thumbprint ([RFC7638]): the base64url-encoded
+    /// SHA-256 digest of the required members `crv`, `kty`, `x`, and (for EC
+    /// keys) `y`, in lexicographic order.
+    ///
+    /// The `crv` member uses the curve's registered JOSE name, so secp256k1
+    /// keys are hashed with `"crv":"secp256k1"` ([RFC8812]).
+    ///
+    /// [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
+    /// [RFC8812]: https://www.rfc-editor.org/rfc/rfc8812#section-3.1
+    ///
+    /// # Errors
+    /// Returns an error if an EC key has no `y` coordinate or an `AKP` key
+    /// has no `pub` value.
+    pub fn thumbprint(&self) -> Result<String> {
+        let crv = if self.crv == Curve::Es256K {
+            r#""secp256k1""#.to_string()
+        } else {
+            serde_json::to_string(&self.crv)?
+        };
+        let kty = serde_json::to_string(&self.kty)?;
+        let x = serde_json::to_string(&self.x)?;
+
+        if self.kty == KeyType::Akp {
+            let Some(public_key) = &self.pub_ else {
+                bail!("AKP key is missing `pub`");
+            };
+            let public_key = serde_json::to_string(public_key)?;
+            let members = format!(r#"{{"kty":{kty},"pub":{public_key}}}"#);
+            return Ok(Base64UrlUnpadded::encode_string(&Sha256::digest(members.as_bytes())));
+        }
+
+        let members = if self.kty == KeyType::Ec {
+            let Some(y) = &self.y else {
+                bail!("EC key is missing `y`");
+            };
+            let y = serde_json::to_string(y)?;
+            format!(r#"{{"crv":{crv},"kty":{kty},"x":{x},"y":{y}}}"#)
+        } else {
+            format!(r#"{{"crv":{crv},"kty":{kty},"x":{x}}}"#)
+        };
+
+        Ok(Base64UrlUnpadded::encode_string(&Sha256::digest(members.as_bytes())))
+    }
+
+    /// Compare keys using only the required members used to compute a JWK
+    /// Thumbprint ([RFC7638]): `kty`, `crv`, `x`, and (for EC keys) `y`, or
+    /// `kty` and `pub` for `AKP` keys.
+    ///
+    /// Unlike the derived `PartialEq`, optional members such as `kid`, `alg`,
+    /// and `use` are ignored.
+    ///
+    /// [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
+    #[must_use]
+    pub fn eq_thumbprint(&self, other: &Self) -> bool {
+        if self.kty == KeyType::Akp {
+            return other.kty == KeyType::Akp && self.pub_ == other.pub_;
+        }
+        if self.kty != other.kty || self.crv != other.crv || self.x != other.x {
+            return false;
+        }
+        self.kty != KeyType::Ec || self.y == other.y
+    }
+
+    /// Validate the key's internal consistency before it is trusted.
+    ///
+    /// The key's members must be valid for its `kty`, its coordinates must
+    /// have the length required by `crv`, and the public key must be a valid
+    /// point: EC points must be on the curve (guarding against invalid-curve
+    /// attacks) and Ed25519 keys must not be small-order. Additionally,
+    /// `alg`, `use`, and `key_ops` must not contradict each other or `crv`.
+    ///
+    /// # Errors
+    /// Returns an error describing the first inconsistency found.
+    pub fn validate(&self) -> Result<()> {
+        match Jwk::try_from(self)? {
+            Jwk::Okp {
+                crv: Curve::Ed25519, ..
+            } => {
+                let bytes: [u8; 32] = self
+                    .to_raw_bytes()?
+                    .try_into()
+                    .map_err(|_| anyhow!("Ed25519 key is not 32 bytes long"))?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+                    .map_err(|_| anyhow!("Ed25519 key is not a valid point"))?;
+                if key.is_weak() {
+                    bail!("Ed25519 key is a small-order point");
+                }
+            }
+            Jwk::Okp { crv, .. } => {
+                if self.to_raw_bytes()?.len() != 32 {
+                    bail!("{crv:?} key is not 32 bytes long");
+                }
+            }
+            Jwk::Ec { crv, x, y } => {
+                let len = match crv {
+                    Curve::P384 => 48,
+                    Curve::P521 => 66,
+                    _ => 32,
+                };
+                let x = Base64UrlUnpadded::decode_vec(&x)
+                    .map_err(|e| anyhow!("issue decoding `x`: {e}"))?;
+                // a compressed point carries a parity prefix
+                let x_len = if y.is_some() { len } else { len + 1 };
+                if x.len() != x_len {
+                    bail!("{crv:?} key `x` is not {x_len} bytes long");
+                }
+                if let Some(y) = y {
+                    let y = Base64UrlUnpadded::decode_vec(&y)
+                        .map_err(|e| anyhow!("issue decoding `y`: {e}"))?;
+                    if y.len() != len {
+                        bail!("{crv:?} key `y` is not {len} bytes long");
+                    }
+                }
+
+                let sec1 = self.to_sec1()?;
+                let on_curve = match crv {
+                    Curve::Es256K => k256::PublicKey::from_sec1_bytes(&sec1).is_ok(),
+                    Curve::P256 => p256::PublicKey::from_sec1_bytes(&sec1).is_ok(),
+                    Curve::P384 => p384::PublicKey::from_sec1_bytes(&sec1).is_ok(),
+                    _ => p521::ecdsa::VerifyingKey::from_sec1_bytes(&sec1).is_ok(),
+                };
+                if !on_curve {
+                    bail!("EC public key is not a valid point on the curve");
+                }
+            }
+            Jwk::Akp { crv, .. } => {
+                let len = if crv == Curve::MlDsa44 { ML_DSA_44_KEY_LEN } else { ML_DSA_65_KEY_LEN };
+                if self.to_raw_bytes()?.len() != len {
+                    bail!("{crv:?} key is not {len} bytes long");
+                }
+            }
+            Jwk::Rsa { .. } | Jwk::Oct { .. } => bail!("unsupported key type"),
+        }
+
+        // members must not contradict each other or the curve
+        let signature_only = matches!(self.crv, Curve::Ed25519 | Curve::MlDsa44 | Curve::MlDsa65);
+        match &self.alg {
+            Some(JwkAlgorithm::Signature(alg)) if !alg.is_compatible(&self.crv) => {
+                bail!("key `alg` {alg} is not valid for curve {:?}", self.crv);
+            }
+            Some(JwkAlgorithm::KeyManagement(_)) if signature_only => {
+                bail!("key management `alg` is not valid for curve {:?}", self.crv);
+            }
+            Some(JwkAlgorithm::Signature(_)) => self.check_use(&KeyUse::Signature)?,
+            Some(JwkAlgorithm::KeyManagement(_)) => self.check_use(&KeyUse::Encryption)?,
+            None => {}
+        }
+        if signature_only {
+            self.check_use(&KeyUse::Signature)?;
+        } else if self.crv == Curve::X25519 {
+            self.check_use(&KeyUse::Encryption)?;
+        }
+        if let Some(use_) = &self.use_ {
+            self.check_use(use_)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the key's `alg` (when set) is the signature algorithm `alg`.
+    ///
+    /// # Errors
+    /// Returns an error if the key declares a different algorithm.
+    pub fn check_alg(&self, alg: &Algorithm) -> Result<()> {
+        match &self.alg {
+            Some(JwkAlgorithm::Signature(declared)) if declared == alg => Ok(()),
+            Some(_) => bail!("algorithm {alg} does not match key `alg`"),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the key's `use` and `key_ops` (when set) permit the intended
+    /// usage. Unrecognized `use` values and operations place no restriction
+    /// on the key.
+    ///
+    /// # Errors
+    /// Returns an error if the key is restricted to another usage.
+    pub fn check_use(&self, intended: &KeyUse) -> Result<()> {
+        let (ops, other) = match intended {
+            KeyUse::Signature => (&[KeyOp::Verify][..], KeyUse::Encryption),
+            KeyUse::Encryption => (
+                &[KeyOp::Encrypt, KeyOp::WrapKey, KeyOp::DeriveKey, KeyOp::DeriveBits][..],
+                KeyUse::Signature,
+            ),
+            KeyUse::Other(_) => return Ok(()),
+        };
+
+        if self.use_.as_ref() == Some(&other) {
+            bail!("key `use` does not permit {intended}");
+        }
+        if let Some(key_ops) = &self.key_ops {
+            let known = key_ops.iter().any(|op| !matches!(op, KeyOp::Other(_)));
+            if known && !key_ops.iter().any(|op| ops.contains(op)) {
+                bail!("key `key_ops` does not permit {intended}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The algorithm a key is intended for use with: either a JWS signature
+/// algorithm or a JWE key management algorithm.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum JwkAlgorithm {
+    /// A JWS signature algorithm.
+    Signature(Algorithm),
+
+    /// A JWE key management algorithm.
+    KeyManagement(KeyAlgorithm),
+}
+
+impl From<Algorithm> for JwkAlgorithm {
+    fn from(alg: Algorithm) -> Self {
+        Self::Signature(alg)
+    }
+}
+
+impl From<KeyAlgorithm> for JwkAlgorithm {
+    fn from(alg: KeyAlgorithm) -> Self {
+        Self::KeyManagement(alg)
+    }
 }
 
 /// The intended usage of the public `KeyType`. This enum is serialized
@@ -118,6 +566,84 @@ pub enum KeyUse {
     /// Public key is to be used for encryption
     #[serde(rename = "enc")]
     Encryption,
+
+    /// An unrecognized usage, treated as unrestricted.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl Display for KeyUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Signature => write!(f, "signature verification"),
+            Self::Encryption => write!(f, "encryption"),
+            Self::Other(other) => write!(f, "{other}"),
+        }
+    }
+}
+
+/// An operation a key is intended to be used for.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyOp {
+    /// Compute a digital signature or MAC.
+    Sign,
+
+    /// Verify a digital signature or MAC.
+    Verify,
+
+    /// Encrypt content.
+    Encrypt,
+
+    /// Decrypt content and validate decryption, if applicable.
+    Decrypt,
+
+    /// Encrypt a key.
+    WrapKey,
+
+    /// Decrypt a key and validate decryption, if applicable.
+    UnwrapKey,
+
+    /// Derive a key.
+    DeriveKey,
+
+    /// Derive bits not to be used as a key.
+    DeriveBits,
+
+    /// An unrecognized operation.
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// A public key in either of the forms used by DID document verification
+/// methods.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationKey {
+    /// A JWK (`publicKeyJwk`).
+    Jwk(PublicKeyJwk),
+
+    /// A multibase encoded multicodec key (`publicKeyMultibase`).
+    Multibase(String),
+}
+
+impl VerificationKey {
+    /// The key as a JWK, converting from multibase if required.
+    ///
+    /// # Errors
+    /// Returns an error if a multibase key cannot be converted (see
+    /// [`PublicKeyJwk::from_multibase`]).
+    pub fn to_jwk(&self) -> Result<PublicKeyJwk> {
+        match self {
+            Self::Jwk(jwk) => Ok(jwk.clone()),
+            Self::Multibase(key) => PublicKeyJwk::from_multibase(key),
+        }
+    }
+}
+
+impl From<PublicKeyJwk> for VerificationKey {
+    fn from(jwk: PublicKeyJwk) -> Self {
+        Self::Jwk(jwk)
+    }
 }
 
 /// A set of JWKs.
@@ -127,10 +653,289 @@ pub struct Jwks {
     pub keys: Vec<PublicKeyJwk>,
 }
 
+/// A symmetric (`oct`) JWK, such as a pre-shared key encryption key.
+///
+/// The key value is zeroized on drop and redacted from `Debug` output.
+#[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct OctJwk {
+    /// Key identifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[zeroize(skip)]
+    pub kid: Option<String>,
+
+    /// Key type. Always `oct`.
+    #[zeroize(skip)]
+    pub kty: KeyType,
+
+    /// The base64url-encoded key value.
+    pub k: String,
+}
+
+impl OctJwk {
+    /// Create a symmetric JWK from raw key bytes.
+    #[must_use]
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            kid: None,
+            kty: KeyType::Oct,
+            k: Base64UrlUnpadded::encode_string(key),
+        }
+    }
+
+    /// The raw key bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the key type is not `oct` or `k` is not valid
+    /// base64url.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.kty != KeyType::Oct {
+            bail!("symmetric key must have key type `oct`");
+        }
+        Base64UrlUnpadded::decode_vec(&self.k).map_err(|e| anyhow!("issue decoding `k`: {e}"))
+    }
+}
+
+impl fmt::Debug for OctJwk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OctJwk")
+            .field("kid", &self.kid)
+            .field("kty", &self.kty)
+            .field("k", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// An RSA public key, used as a JWE recipient key for the `RSA-OAEP` and
+/// `RSA-OAEP-256` key management algorithms. Converting to and from an RSA
+/// public key requires the `rsa` feature.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct RsaPublicJwk {
+    /// Key identifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+
+    /// Key type. Always `RSA`.
+    pub kty: KeyType,
+
+    /// The base64url-encoded modulus.
+    pub n: String,
+
+    /// The base64url-encoded public exponent.
+    pub e: String,
+}
+
+#[cfg(feature = "rsa")]
+impl RsaPublicJwk {
+    /// Create an RSA JWK from an RSA public key.
+    #[must_use]
+    pub fn new(public_key: &rsa::RsaPublicKey) -> Self {
+        Self {
+            kid: None,
+            kty: KeyType::Rsa,
+            n: Base64UrlUnpadded::encode_string(&public_key.n().to_bytes_be()),
+            e: Base64UrlUnpadded::encode_string(&public_key.e().to_bytes_be()),
+        }
+    }
+
+    /// Convert the JWK to an RSA public key.
+    ///
+    /// # Errors
+    /// Returns an error if the key type is not `RSA`, `n` or `e` cannot be
+    /// decoded, or the modulus is smaller than 2048 bits.
+    pub fn to_public_key(&self) -> Result<rsa::RsaPublicKey> {
+        if self.kty != KeyType::Rsa {
+            bail!("RSA key must have key type `RSA`");
+        }
+        let decode = |value: &str, name: &str| {
+            Base64UrlUnpadded::decode_vec(value)
+                .map(|bytes| rsa::BigUint::from_bytes_be(&bytes))
+                .map_err(|e| anyhow!("issue decoding `{name}`: {e}"))
+        };
+        let public_key = rsa::RsaPublicKey::new(decode(&self.n, "n")?, decode(&self.e, "e")?)
+            .map_err(|e| anyhow!("invalid RSA key: {e}"))?;
+        check_rsa_size(public_key.size())?;
+        Ok(public_key)
+    }
+}
+
+// RSA keys smaller than 2048 bits are rejected (RFC 7518 §4.2/§4.3).
+#[cfg(feature = "rsa")]
+pub(crate) fn check_rsa_size(size_bytes: usize) -> Result<()> {
+    if size_bytes * 8 < MIN_RSA_BITS {
+        bail!("RSA keys must be at least {MIN_RSA_BITS} bits");
+    }
+    Ok(())
+}
+
+impl Jwks {
+    /// Find the key identified by `kid`.
+    ///
+    /// Keys are matched on their `kid` member. A key without a `kid` matches
+    /// when its RFC7638 thumbprint equals `kid`, as recommended by RFC7638
+    /// for key identifiers.
+    #[must_use]
+    pub fn find(&self, kid: &str) -> Option<&PublicKeyJwk> {
+        self.keys.iter().find(|k| k.kid.as_deref() == Some(kid)).or_else(|| {
+            self.keys
+                .iter()
+                .filter(|k| k.kid.is_none())
+                .find(|k| k.thumbprint().is_ok_and(|t| t == kid))
+        })
+    }
+
+    /// Remove keys that are thumbprint-duplicates of an earlier key in the
+    /// set, keeping the first occurrence.
+    ///
+    /// See [`PublicKeyJwk::eq_thumbprint`].
+    pub fn dedup(&mut self) {
+        let mut unique: Vec<PublicKeyJwk> = Vec::with_capacity(self.keys.len());
+        for key in self.keys.drain(..) {
+            if !unique.iter().any(|k| k.eq_thumbprint(&key)) {
+                unique.push(key);
+            }
+        }
+        self.keys = unique;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // example from the module documentation
+    #[test]
+    fn thumbprint() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".to_string(),
+            kid: Some("ignored".to_string()),
+            ..PublicKeyJwk::default()
+        };
+        assert_eq!(jwk.thumbprint().unwrap(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+
+        let ec = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::P256,
+            x: "x".to_string(),
+            ..PublicKeyJwk::default()
+        };
+        assert!(ec.thumbprint().is_err());
+    }
+
+    // the secp256k1 generator point, hashed with its registered `crv` name
+    #[test]
+    fn thumbprint_secp256k1() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: "eb5mfvncu6xVoGKVzocLBwKb_NstzijZWfKBWxb4F5g".to_string(),
+            y: Some("SDradyajxGVdpPv8DhEIqP0XtEimhVQZnEfQj_sQ1Lg".to_string()),
+            ..PublicKeyJwk::default()
+        };
+        assert_eq!(jwk.thumbprint().unwrap(), "2JF8vg9etJzjFwZwmkvhBLLZ0bfMVVOPivYR5lFtcec");
+    }
+
+    #[test]
+    fn validate() {
+        let ed25519 = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".to_string(),
+            ..PublicKeyJwk::default()
+        };
+        ed25519.validate().expect("should be valid");
+
+        // P-256 key from RFC7517 Appendix A.1
+        let p256 = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::P256,
+            x: "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_string(),
+            y: Some("4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM".to_string()),
+            ..PublicKeyJwk::default()
+        };
+        p256.validate().expect("should be valid");
+
+        let identity = Base64UrlUnpadded::encode_string(&[[1].as_slice(), &[0; 31]].concat());
+        for (jwk, expected) in [
+            (
+                PublicKeyJwk {
+                    y: Some("4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyA".to_string()),
+                    ..p256.clone()
+                },
+                "EC public key is not a valid point on the curve",
+            ),
+            (
+                PublicKeyJwk {
+                    x: "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7A".to_string(),
+                    ..p256.clone()
+                },
+                "P256 key `x` is not 32 bytes long",
+            ),
+            (
+                PublicKeyJwk {
+                    crv: Curve::P384,
+                    ..p256.clone()
+                },
+                "P384 key `x` is not 48 bytes long",
+            ),
+            (
+                PublicKeyJwk {
+                    x: identity,
+                    ..ed25519.clone()
+                },
+                "Ed25519 key is a small-order point",
+            ),
+            (
+                PublicKeyJwk {
+                    x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHUQ".to_string(),
+                    ..ed25519.clone()
+                },
+                "Ed25519 key is not 32 bytes long",
+            ),
+            (
+                PublicKeyJwk {
+                    y: Some("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".to_string()),
+                    ..ed25519.clone()
+                },
+                "`y` is not a valid member for a Okp key",
+            ),
+            (
+                PublicKeyJwk {
+                    alg: Some(Algorithm::ES256K.into()),
+                    ..ed25519.clone()
+                },
+                "key `alg` ES256K is not valid for curve Ed25519",
+            ),
+            (
+                PublicKeyJwk {
+                    use_: Some(KeyUse::Encryption),
+                    ..ed25519.clone()
+                },
+                "key `use` does not permit signature verification",
+            ),
+            (
+                PublicKeyJwk {
+                    crv: Curve::X25519,
+                    use_: Some(KeyUse::Signature),
+                    ..ed25519
+                },
+                "key `use` does not permit encryption",
+            ),
+            (
+                PublicKeyJwk {
+                    use_: Some(KeyUse::Signature),
+                    key_ops: Some(vec![KeyOp::Encrypt]),
+                    ..p256
+                },
+                "key `key_ops` does not permit signature verification",
+            ),
+        ] {
+            let err = jwk.validate().expect_err("should be invalid");
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
     #[test]
     fn round_trip() {
         let jwk = PublicKeyJwk {
@@ -149,6 +954,137 @@ mod tests {
         assert_eq!("z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss", converted_multi);
     }
 
+    #[test]
+    fn dedup() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: "q6rjRnEH_XK72jvB8FNBJtOl9_gDs6NW49cAz6p2sW4".to_string(),
+            ..PublicKeyJwk::default()
+        };
+        let with_kid = PublicKeyJwk {
+            kid: Some("key-1".to_string()),
+            use_: Some(KeyUse::Signature),
+            ..jwk.clone()
+        };
+        let x25519 = PublicKeyJwk {
+            crv: Curve::X25519,
+            ..jwk.clone()
+        };
+        assert!(jwk.eq_thumbprint(&with_kid));
+        assert!(!jwk.eq_thumbprint(&x25519));
+
+        let ml_dsa = PublicKeyJwk::from_bytes(&[1; ML_DSA_44_KEY_LEN], &Algorithm::MlDsa44)
+            .expect("should create");
+        let other = PublicKeyJwk::from_bytes(&[2; ML_DSA_44_KEY_LEN], &Algorithm::MlDsa44)
+            .expect("should create");
+        assert!(ml_dsa.eq_thumbprint(&ml_dsa.clone()));
+        assert!(!ml_dsa.eq_thumbprint(&other));
+
+        let mut jwks = Jwks {
+            keys: vec![with_kid.clone(), x25519.clone(), jwk],
+        };
+        jwks.dedup();
+        assert_eq!(jwks.keys, vec![with_kid, x25519]);
+    }
+
+    #[test]
+    fn find() {
+        let jwk: PublicKeyJwk = serde_json::from_value(serde_json::json!({
+            "kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+        }))
+        .expect("should deserialize without kid");
+        assert_eq!(jwk.kid, None);
+
+        let with_kid = PublicKeyJwk {
+            kid: Some("key-1".to_string()),
+            x: "q6rjRnEH_XK72jvB8FNBJtOl9_gDs6NW49cAz6p2sW4".to_string(),
+            ..jwk.clone()
+        };
+        let jwks = Jwks {
+            keys: vec![with_kid.clone(), jwk.clone()],
+        };
+        assert_eq!(jwks.find("key-1"), Some(&with_kid));
+        assert_eq!(jwks.find("kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k"), Some(&jwk));
+        assert_eq!(jwks.find("key-2"), None);
+    }
+
+    #[test]
+    fn key_use() {
+        let jwk: PublicKeyJwk = serde_json::from_value(serde_json::json!({
+            "kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+            "use": "enc", "key_ops": ["deriveKey", "frobnicate"],
+        }))
+        .expect("should deserialize");
+        assert_eq!(jwk.key_ops.as_ref().map(Vec::len), Some(2));
+        assert!(jwk.check_use(&KeyUse::Encryption).is_ok());
+        let err = jwk.check_use(&KeyUse::Signature).expect_err("should fail");
+        assert_eq!(err.to_string(), "key `use` does not permit signature verification");
+
+        // unknown `use` is unrestricted, but `key_ops` still applies
+        let jwk = PublicKeyJwk {
+            use_: Some(KeyUse::Other("attest".to_string())),
+            ..jwk
+        };
+        assert_eq!(serde_json::to_value(&jwk).unwrap()["use"], "attest");
+        let err = jwk.check_use(&KeyUse::Signature).expect_err("should fail");
+        assert_eq!(err.to_string(), "key `key_ops` does not permit signature verification");
+
+        let jwk = PublicKeyJwk {
+            key_ops: Some(vec![KeyOp::Other("frobnicate".to_string())]),
+            ..jwk
+        };
+        assert!(jwk.check_use(&KeyUse::Signature).is_ok());
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: Base64UrlUnpadded::encode_string(&[1; 32]),
+            y: Some(Base64UrlUnpadded::encode_string(&[2; 32])),
+            ..PublicKeyJwk::default()
+        };
+        let sec1 = jwk.to_raw_bytes().expect("should encode");
+        assert_eq!(sec1.len(), 65);
+        assert_eq!(sec1[0], 0x04);
+        assert_eq!(sec1[1..33], [1; 32]);
+        assert_eq!(sec1[33..], [2; 32]);
+
+        let missing_y = PublicKeyJwk { y: None, ..jwk };
+        let err = missing_y.to_sec1_uncompressed().expect_err("should fail");
+        assert_eq!(err.to_string(), "EC key is missing `y` coordinate");
+
+        let okp = PublicKeyJwk::from_multibase("z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss")
+            .expect("should convert");
+        assert_eq!(okp.to_raw_bytes().expect("should decode").len(), 32);
+        assert!(okp.to_sec1_uncompressed().is_err());
+    }
+
+    #[test]
+    fn multibase_secp256k1() {
+        let signing_key = k256::ecdsa::SigningKey::random(&mut rand::rngs::OsRng);
+        let sec1 = signing_key.verifying_key().to_sec1_bytes();
+        let jwk = PublicKeyJwk::from_bytes(&sec1, &Algorithm::ES256K).expect("should convert");
+
+        let multibase = jwk.to_multibase().expect("should convert");
+        assert!(multibase.starts_with("zQ3s"));
+        assert_eq!(PublicKeyJwk::from_multibase(&multibase).expect("should convert"), jwk);
+
+        // unknown multicodec prefix (p256-pub)
+        let mut key_bytes = vec![0x80, 0x24];
+        key_bytes.extend_from_slice(&signing_key.verifying_key().to_sec1_bytes()[..33]);
+        let err = PublicKeyJwk::from_multibase(&multibase::encode(Base::Base58Btc, &key_bytes))
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "unsupported multicodec prefix: [80, 24]");
+
+        // base58btc only
+        let base64 = multibase::encode(Base::Base64Url, [0xed, 0x01]);
+        assert!(PublicKeyJwk::from_multibase(&base64).is_err());
+        assert!(PublicKeyJwk::from_multibase("z").is_err());
+    }
+
     #[test]
     fn to_jwk() {
         let jwk = PublicKeyJwk::from_multibase("z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX")
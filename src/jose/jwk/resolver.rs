@@ -0,0 +1,267 @@
+//! # JWKS Resolver
+//!
+//! Resolves a `kid` to a [`PublicKeyJwk`] from a JWK Set published at a
+//! `jwks_uri`, such as an OpenID Provider's.
+//!
+//! The JWKS is cached for a configurable TTL and revalidated using its
+//! `ETag`. A `kid` missing from the cache triggers a refetch so rotated keys
+//! are picked up without waiting for the TTL to expire.
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::jose::jwk::{Jwks, PublicKeyJwk};
+
+/// How long a fetched JWKS is used before it is revalidated.
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Minimum time between refetches triggered by an unknown `kid`, limiting
+/// the requests an attacker can cause by presenting random key IDs.
+const MIN_REFETCH: Duration = Duration::from_secs(30);
+
+/// The result of fetching a JWKS.
+#[derive(Clone, Debug)]
+pub enum Fetched {
+    /// The JWKS has not changed since the provided `ETag`.
+    NotModified,
+
+    /// The current JWKS.
+    Jwks {
+        /// The JWK Set.
+        jwks: Jwks,
+
+        /// The response's `ETag`, if any.
+        etag: Option<String>,
+    },
+}
+
+/// `JwksFetcher` is implemented to retrieve a JWKS over HTTP, allowing any
+/// HTTP client to be used by [`JwksResolver`].
+pub trait JwksFetcher: Send + Sync {
+    /// Fetch the JWKS at `uri`. When `etag` is provided it should be sent as
+    /// `If-None-Match` and [`Fetched::NotModified`] returned for a `304`
+    /// response.
+    fn fetch(&self, uri: &str, etag: Option<&str>) -> impl Future<Output = Result<Fetched>> + Send;
+}
+
+/// Resolves key IDs to public keys from a cached, remotely hosted JWKS.
+///
+/// Use with [`jws::decode`](crate::jose::jws::decode):
+///
+/// ```rust,ignore
+/// let resolver = http_jwks_resolver("https://issuer.example.com/jwks");
+/// let jwt: Jwt<Claims> =
+///     jws::decode(token, |kid| async move { resolver.resolve(&kid).await }).await?;
+/// ```
+pub struct JwksResolver<F: JwksFetcher> {
+    uri: String,
+    fetcher: F,
+    ttl: Duration,
+    cache: Mutex<Option<Cached>>,
+}
+
+#[derive(Clone)]
+struct Cached {
+    jwks: Jwks,
+    etag: Option<String>,
+    fetched_at: Instant,
+}
+
+impl<F: JwksFetcher> JwksResolver<F> {
+    /// Create a resolver for the JWKS at `jwks_uri`, fetched using `fetcher`.
+    pub fn new(jwks_uri: impl Into<String>, fetcher: F) -> Self {
+        Self {
+            uri: jwks_uri.into(),
+            fetcher,
+            ttl: DEFAULT_TTL,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// How long a fetched JWKS is used before it is revalidated. Defaults to
+    /// 5 minutes.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Resolve `kid` to its public key, fetching the JWKS if the cache has
+    /// expired or does not contain the key.
+    ///
+    /// # Errors
+    /// Returns an error if the JWKS cannot be fetched or does not contain a
+    /// key with the ID.
+    pub async fn resolve(&self, kid: &str) -> Result<PublicKeyJwk> {
+        let cached = self.cache.lock().map_err(|_| anyhow!("JWKS cache poisoned"))?.clone();
+
+        if let Some(cached) = &cached {
+            let age = cached.fetched_at.elapsed();
+            let key = cached.jwks.find(kid).cloned();
+            if (age < self.ttl && key.is_some()) || age < MIN_REFETCH {
+                return key.ok_or_else(|| anyhow!("key {kid} not found in JWKS"));
+            }
+        }
+
+        let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+        let refreshed = match self.fetcher.fetch(&self.uri, etag).await? {
+            Fetched::Jwks { jwks, etag } => Cached {
+                jwks,
+                etag,
+                fetched_at: Instant::now(),
+            },
+            Fetched::NotModified => {
+                let Some(cached) = cached else {
+                    return Err(anyhow!("JWKS not modified but no JWKS is cached"));
+                };
+                Cached {
+                    fetched_at: Instant::now(),
+                    ..cached
+                }
+            }
+        };
+
+        let key = refreshed.jwks.find(kid).cloned();
+        *self.cache.lock().map_err(|_| anyhow!("JWKS cache poisoned"))? = Some(refreshed);
+        key.ok_or_else(|| anyhow!("key {kid} not found in JWKS"))
+    }
+}
+
+/// Create a [`JwksResolver`] for `jwks_uri` using `reqwest`.
+#[cfg(feature = "reqwest")]
+#[must_use]
+pub fn http_jwks_resolver(jwks_uri: impl Into<String>) -> JwksResolver<ReqwestFetcher> {
+    JwksResolver::new(jwks_uri, ReqwestFetcher::default())
+}
+
+/// A [`JwksFetcher`] using `reqwest`.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestFetcher {
+    /// Create a fetcher using the provided client.
+    #[must_use]
+    pub const fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl JwksFetcher for ReqwestFetcher {
+    async fn fetch(&self, uri: &str, etag: Option<&str>) -> Result<Fetched> {
+        let mut request = self.client.get(uri);
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
+
+        let response = response.error_for_status()?;
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string);
+        let jwks = response.json::<Jwks>().await?;
+        Ok(Fetched::Jwks { jwks, etag })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::jose::jws;
+
+    struct MockFetcher {
+        jwks: Mutex<Jwks>,
+        fetches: AtomicUsize,
+    }
+
+    impl MockFetcher {
+        fn new(kids: &[&str]) -> Self {
+            Self {
+                jwks: Mutex::new(jwks(kids)),
+                fetches: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl JwksFetcher for MockFetcher {
+        fn fetch(
+            &self, _: &str, etag: Option<&str>,
+        ) -> impl Future<Output = Result<Fetched>> + Send {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            let jwks = self.jwks.lock().unwrap().clone();
+            let current = format!("\"{}\"", jwks.keys.len());
+            let fetched = if etag == Some(current.as_str()) {
+                Fetched::NotModified
+            } else {
+                Fetched::Jwks {
+                    jwks,
+                    etag: Some(current),
+                }
+            };
+            future::ready(Ok(fetched))
+        }
+    }
+
+    fn jwks(kids: &[&str]) -> Jwks {
+        let keys = kids
+            .iter()
+            .map(|kid| PublicKeyJwk {
+                kid: Some((*kid).to_string()),
+                x: (*kid).to_string(),
+                ..PublicKeyJwk::default()
+            })
+            .collect();
+        Jwks { keys }
+    }
+
+    #[tokio::test]
+    async fn caching() {
+        let resolver = JwksResolver::new("https://example.com/jwks", MockFetcher::new(&["key-1"]));
+        let fetches = || resolver.fetcher.fetches.load(Ordering::SeqCst);
+
+        let jwk = resolver.resolve("key-1").await.expect("should resolve");
+        assert_eq!(jwk.x, "key-1");
+        resolver.resolve("key-1").await.expect("should resolve");
+        assert_eq!(fetches(), 1);
+
+        // unknown keys are not refetched within the minimum interval
+        *resolver.fetcher.jwks.lock().unwrap() = jwks(&["key-1", "key-2"]);
+        let err = resolver.resolve("key-2").await.expect_err("should not resolve");
+        assert_eq!(err.to_string(), "key key-2 not found in JWKS");
+        assert_eq!(fetches(), 1);
+
+        // rollover: a miss on an older cache triggers a refetch
+        resolver.cache.lock().unwrap().as_mut().unwrap().fetched_at -= MIN_REFETCH;
+        let jwk = resolver.resolve("key-2").await.expect("should resolve");
+        assert_eq!(jwk.x, "key-2");
+        assert_eq!(fetches(), 2);
+
+        // expired cache is revalidated using the ETag
+        let resolver = resolver.ttl(Duration::ZERO);
+        resolver.cache.lock().unwrap().as_mut().unwrap().fetched_at -= MIN_REFETCH;
+        resolver.resolve("key-1").await.expect("should resolve");
+        assert_eq!(resolver.fetcher.fetches.load(Ordering::SeqCst), 3);
+
+        // usable as a `jws::decode` resolver
+        let resolver = &resolver;
+        let result = jws::decode::<_, _, serde_json::Value>("not.a.jws", |kid| async move {
+            resolver.resolve(&kid).await
+        });
+        assert!(result.await.is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! # Typed JWK
+//!
+//! A JWK's key members depend on its `kty`. [`Jwk`] models each key type as
+//! a separate variant so that only valid combinations of members can be
+//! represented, for example an `OKP` key can never have a `y` coordinate.
+//!
+//! [`PublicKeyJwk`] remains the public representation of a key. Converting
+//! to a [`Jwk`] validates its members.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::jose::jwk::{OctJwk, PublicKeyJwk, RsaPublicJwk};
+use crate::{Curve, KeyType};
+
+/// A JWK's key type and key members. Key values are held base64url encoded,
+/// exactly as in the JWK.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Jwk {
+    /// An octet key pair: `Ed25519` or `X25519`.
+    Okp { crv: Curve, x: String },
+
+    /// An elliptic curve key. `y` is absent for a compressed point.
+    Ec { crv: Curve, x: String, y: Option<String> },
+
+    /// An algorithm key pair, such as ML-DSA, identified by its parameter
+    /// set.
+    Akp { crv: Curve, public_key: String },
+
+    /// An RSA public key.
+    Rsa { n: String, e: String },
+
+    /// A symmetric key.
+    Oct { k: String },
+}
+
+// The key members of a JWK, as serialized. Members not related to the key
+// value (`kid`, `alg`, `use`, etc.) are ignored.
+#[derive(Default, Deserialize, Serialize)]
+struct Members {
+    kty: KeyType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crv: Option<Curve>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<String>,
+    #[serde(rename = "pub")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    k: Option<String>,
+}
+
+impl TryFrom<Members> for Jwk {
+    type Error = anyhow::Error;
+
+    fn try_from(members: Members) -> Result<Self> {
+        let present = [
+            ("crv", members.crv.is_some()),
+            ("x", members.x.is_some()),
+            ("y", members.y.is_some()),
+            ("pub", members.pub_.is_some()),
+            ("n", members.n.is_some()),
+            ("e", members.e.is_some()),
+            ("k", members.k.is_some()),
+        ];
+        let kty = members.kty.clone();
+        let missing = |name: &str| anyhow!("{kty:?} key is missing `{name}`");
+
+        // key members permitted for the key type
+        let (allowed, jwk) = match members.kty {
+            KeyType::Okp => {
+                let crv = members.crv.ok_or_else(|| missing("crv"))?;
+                if !matches!(crv, Curve::Ed25519 | Curve::X25519) {
+                    bail!("curve {crv:?} is not valid for an OKP key");
+                }
+                let x = members.x.ok_or_else(|| missing("x"))?;
+                (["crv", "x"].as_slice(), Self::Okp { crv, x })
+            }
+            KeyType::Ec => {
+                let crv = members.crv.ok_or_else(|| missing("crv"))?;
+                if !matches!(crv, Curve::Es256K | Curve::P256 | Curve::P384 | Curve::P521) {
+                    bail!("curve {crv:?} is not valid for an EC key");
+                }
+                let x = members.x.ok_or_else(|| missing("x"))?;
+                let y = members.y;
+                (["crv", "x", "y"].as_slice(), Self::Ec { crv, x, y })
+            }
+            KeyType::Akp => {
+                let crv = members.crv.ok_or_else(|| missing("crv"))?;
+                if !matches!(crv, Curve::MlDsa44 | Curve::MlDsa65) {
+                    bail!("parameter set {crv:?} is not valid for an AKP key");
+                }
+                let public_key = members.pub_.ok_or_else(|| missing("pub"))?;
+                (["crv", "pub"].as_slice(), Self::Akp { crv, public_key })
+            }
+            KeyType::Rsa => {
+                let n = members.n.ok_or_else(|| missing("n"))?;
+                let e = members.e.ok_or_else(|| missing("e"))?;
+                (["n", "e"].as_slice(), Self::Rsa { n, e })
+            }
+            KeyType::Oct => {
+                let k = members.k.ok_or_else(|| missing("k"))?;
+                (["k"].as_slice(), Self::Oct { k })
+            }
+        };
+
+        if let Some((name, _)) = present.iter().find(|(n, p)| *p && !allowed.contains(n)) {
+            bail!("`{name}` is not a valid member for a {kty:?} key");
+        }
+
+        Ok(jwk)
+    }
+}
+
+impl From<&Jwk> for Members {
+    fn from(jwk: &Jwk) -> Self {
+        match jwk.clone() {
+            Jwk::Okp { crv, x } => Self {
+                kty: KeyType::Okp,
+                crv: Some(crv),
+                x: Some(x),
+                ..Self::default()
+            },
+            Jwk::Ec { crv, x, y } => Self {
+                kty: KeyType::Ec,
+                crv: Some(crv),
+                x: Some(x),
+                y,
+                ..Self::default()
+            },
+            Jwk::Akp { crv, public_key } => Self {
+                kty: KeyType::Akp,
+                crv: Some(crv),
+                pub_: Some(public_key),
+                ..Self::default()
+            },
+            Jwk::Rsa { n, e } => Self {
+                kty: KeyType::Rsa,
+                n: Some(n),
+                e: Some(e),
+                ..Self::default()
+            },
+            Jwk::Oct { k } => Self {
+                kty: KeyType::Oct,
+                k: Some(k),
+                ..Self::default()
+            },
+        }
+    }
+}
+
+impl Serialize for Jwk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Members::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Jwk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let members = Members::deserialize(deserializer)?;
+        Self::try_from(members).map_err(de::Error::custom)
+    }
+}
+
+impl TryFrom<&PublicKeyJwk> for Jwk {
+    type Error = anyhow::Error;
+
+    fn try_from(jwk: &PublicKeyJwk) -> Result<Self> {
+        Self::try_from(Members {
+            kty: jwk.kty.clone(),
+            crv: Some(jwk.crv.clone()),
+            x: Some(jwk.x.clone()).filter(|x| !x.is_empty()),
+            y: jwk.y.clone(),
+            pub_: jwk.pub_.clone(),
+            ..Members::default()
+        })
+    }
+}
+
+impl From<&RsaPublicJwk> for Jwk {
+    fn from(jwk: &RsaPublicJwk) -> Self {
+        Self::Rsa {
+            n: jwk.n.clone(),
+            e: jwk.e.clone(),
+        }
+    }
+}
+
+impl From<&OctJwk> for Jwk {
+    fn from(jwk: &OctJwk) -> Self {
+        Self::Oct { k: jwk.k.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for value in [
+            json!({"kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}),
+            json!({
+                "kty": "EC", "crv": "P-384",
+                "x": "ZlsdAQ4lAi0mG3YPfDDzhzpu4lp9a1rkiCANkt9cIXw6H39HEsuTSu6sXOGS7J2y",
+                "y": "HeltuSNt6gJTGtftJ43_gjEPtYQmhYD8p2CKtMv0xZbTGzhrBAkxJ_y2FpIVnfbe",
+            }),
+            json!({"kty": "EC", "crv": "ES256K", "x": "AsTQtSLnY4zuFSEiVbGNbsN4-QhpcY3KGL65Z-EpgjPj"}),
+            json!({"kty": "RSA", "n": "0vx7agoebGcQSuuPiLJXZpt", "e": "AQAB"}),
+            json!({"kty": "oct", "k": "GawgguFyGrWKav7AX4VKUg"}),
+        ] {
+            let jwk: Jwk = serde_json::from_value(value.clone()).expect("should deserialize");
+            assert_eq!(serde_json::to_value(&jwk).expect("should serialize"), value);
+        }
+    }
+
+    #[test]
+    fn invalid_members() {
+        let cases = [
+            (
+                json!({"kty": "OKP", "crv": "Ed25519", "x": "AA", "y": "AA"}),
+                "`y` is not a valid member for a Okp key",
+            ),
+            (json!({"kty": "EC", "crv": "P-384"}), "Ec key is missing `x`"),
+            (
+                json!({"kty": "EC", "crv": "Ed25519", "x": "AA"}),
+                "curve Ed25519 is not valid for an EC key",
+            ),
+            (json!({"kty": "RSA", "n": "AA"}), "Rsa key is missing `e`"),
+            (
+                json!({"kty": "oct", "k": "AA", "x": "AA"}),
+                "`x` is not a valid member for a Oct key",
+            ),
+        ];
+        for (value, expected) in cases {
+            let err = serde_json::from_value::<Jwk>(value).expect_err("should fail");
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+
+        // the facade is validated on conversion
+        let facade = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::P384,
+            x: "AA".to_string(),
+            ..PublicKeyJwk::default()
+        };
+        assert!(Jwk::try_from(&facade).is_err());
+    }
+}
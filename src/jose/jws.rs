@@ -7,71 +7,490 @@
 //! [RFC7515]: https://www.rfc-editor.org/rfc/rfc7515
 //! [RFC7518]: https://www.rfc-editor.org/rfc/rfc7518
 
+mod jcs;
+mod verify;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::future::Future;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{io, slice};
 
 use anyhow::{anyhow, bail, Result};
-use base64ct::{Base64UrlUnpadded, Encoding};
-use ecdsa::signature::Verifier as _;
+use base64ct::{Base64, Base64UrlUnpadded, Encoding};
+use futures::future::join_all;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
 
-use crate::jose::jwk::PublicKeyJwk;
+pub use self::verify::{verify_batch_ed25519, verify_compact, CompactLimits, VerifyOptions};
+use crate::did::{DidResolver, VerificationMethodId};
+use crate::jose::federation;
+use crate::jose::jwk::{Jwks, PublicKeyJwk, VerificationKey};
 pub use crate::jose::jwt::Jwt;
-use crate::{Algorithm, Curve, Signer};
+use crate::jose::jwt::{RegisteredClaims, Validation};
+use crate::x509::{self, Certificate};
+use crate::{Algorithm, DynSigner};
 
 /// Encode the provided header and claims payload and sign, returning a JWT in
 /// compact JWS form.
 ///
 /// # Errors
 /// TODO: document errors
-pub async fn encode<T>(payload: &T, signer: &impl Signer) -> Result<String>
+#[tracing::instrument(level = "debug", skip_all, fields(alg = %signer.algorithm()), err)]
+pub async fn encode<T>(payload: &T, signer: &dyn DynSigner) -> Result<String>
 where
     T: Serialize + Send + Sync,
 {
-    tracing::debug!("encode");
-
     let jws = JwsBuilder::new().payload(payload).add_signer(signer).build().await?;
-    let Some(signature) = jws.signatures.first() else {
-        bail!("no signature found");
+    jws.to_compact()
+}
+
+/// Sign each payload, returning a JWS per payload in input order.
+///
+/// The signer's verification method and algorithm are fetched once and reused
+/// for every payload, avoiding repeated (potentially remote) key lookups when
+/// issuing in bulk.
+///
+/// # Errors
+/// Returns an error if the signer's verification method cannot be resolved,
+/// any payload cannot be serialized, or signing fails.
+pub async fn batch_sign<T>(payloads: &[T], signer: &dyn DynSigner) -> Result<Vec<Jws>>
+where
+    T: Serialize + Send + Sync,
+{
+    tracing::debug!("batch_sign");
+
+    let protected = Protected {
+        alg: signer.algorithm(),
+        typ: "jwt".into(),
+        key: Key::KeyId(signer.verification_method().await?),
+        ..Protected::default()
     };
+    let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected)?);
 
-    let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&signature.protected)?);
-    let payload = jws.payload;
-    let signature = &signature.signature;
+    let mut batch = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        let payload = Base64UrlUnpadded::encode_string(&serde_json::to_vec(payload)?);
+        let sig = signer.try_sign(format!("{header}.{payload}").as_bytes()).await?;
 
-    Ok(format!("{header}.{payload}.{signature}"))
-}
+        batch.push(Jws {
+            payload,
+            signatures: vec![Signature {
+                protected: protected.clone(),
+                protected_b64: Some(header.clone()),
+                signature: Base64UrlUnpadded::encode_string(&sig),
+                ..Signature::default()
+            }],
+        });
+    }
 
-// TODO: allow passing verifier into this method
+    Ok(batch)
+}
 
 /// Decode the JWT token and return the claims.
 ///
 /// # Errors
 /// TODO: document errors
+#[tracing::instrument(level = "debug", skip_all, fields(alg, typ, kid), err)]
 pub async fn decode<F, Fut, T>(compact_jws: &str, resolver: F) -> Result<Jwt<T>>
 where
     T: DeserializeOwned + Send,
     F: Fn(String) -> Fut + Send,
     Fut: Future<Output = Result<PublicKeyJwk>> + Send,
 {
-    tracing::debug!("decode");
+    let jws: Jws = compact_jws.parse()?;
+    if let Some(signature) = jws.signatures.first() {
+        signature.record_header();
+    }
+    jws.verify(resolver).await?;
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, rejecting header or payload segments longer than
+/// `limits` before they are decoded, and return the claims.
+///
+/// Internet-facing verifiers should set limits appropriate to the tokens
+/// they accept. [`decode`] applies [`CompactLimits::default`].
+///
+/// # Errors
+/// Returns an error if a segment exceeds its limit
+/// ([`CompactError::TooLarge`]), or the token cannot be decoded and verified
+/// (see [`decode`]).
+pub async fn decode_with_limits<F, Fut, T>(
+    compact_jws: &str, resolver: F, limits: &CompactLimits,
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_with_limits");
+
+    let jws = Jws::from_compact_with_limits(compact_jws, limits)?;
+    jws.verify(resolver).await?;
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, requiring the `typ` header to be one of `expected`,
+/// and return the claims.
+///
+/// Prevents a JWT issued for one purpose being accepted for another, for
+/// example requiring `openid4vci-proof+jwt` for a proof JWT. As per RFC7515
+/// §4.1.9, `typ` is compared case-insensitively and the `application/` prefix
+/// is optional. Use [`decode`] to accept any `typ`.
+///
+/// # Errors
+/// Returns an error if the `typ` header does not match any of `expected`, or
+/// the token cannot be decoded and verified (see [`decode`]).
+pub async fn decode_typed<F, Fut, T>(
+    compact_jws: &str, resolver: F, expected: &[&str],
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_typed");
 
     let jws: Jws = compact_jws.parse()?;
+    for signature in &jws.signatures {
+        let typ = &signature.protected.typ;
+        if !expected.iter().any(|e| media_type(e).eq_ignore_ascii_case(media_type(typ))) {
+            bail!("unexpected `typ` header: {typ}");
+        }
+    }
     jws.verify(resolver).await?;
+    jws.jwt(false)
+}
+
+// Strip the optional `application/` prefix from a `typ` media type.
+pub(crate) fn media_type(typ: &str) -> &str {
+    typ.get(..12)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("application/"))
+        .map_or(typ, |_| &typ[12..])
+}
 
-    let claims = Base64UrlUnpadded::decode_vec(&jws.payload)
-        .map_err(|e| anyhow!("issue decoding claims: {e}"))?;
-    let claims =
-        serde_json::from_slice(&claims).map_err(|e| anyhow!("issue deserializing claims:{e}"))?;
+/// Decode and verify a batch of JWT tokens, returning the result for each
+/// token in input order.
+///
+/// Each distinct `kid` across the batch is resolved once, with resolution
+/// performed concurrently. Tokens are then verified concurrently against the
+/// resolved keys. A failure to parse, resolve, or verify one token does not
+/// affect the others.
+pub async fn decode_batch<F, Fut, T>(tokens: &[&str], resolver: F) -> Vec<Result<Jwt<T>>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_batch");
 
-    let Some(signature) = jws.signatures.first() else {
-        bail!("no signature found");
-    };
+    let parsed = tokens.iter().map(|token| token.parse::<Jws>()).collect::<Vec<_>>();
+
+    // resolve each distinct `kid` once
+    let mut kids = parsed
+        .iter()
+        .flatten()
+        .flat_map(|jws| jws.signatures.iter().filter_map(Signature::kid))
+        .collect::<Vec<_>>();
+    kids.sort_unstable();
+    kids.dedup();
+    let public_jwks = join_all(kids.iter().map(|kid| resolver((*kid).to_owned()))).await;
+    let keys = kids.into_iter().zip(public_jwks).collect::<HashMap<_, _>>();
+
+    let keys = &keys;
+    join_all(parsed.iter().map(|jws| async move {
+        let jws = jws.as_ref().map_err(|e| anyhow!("{e}"))?;
+        for signature in &jws.signatures {
+            let Some(kid) = signature.kid() else {
+                bail!("Missing key ID in JWS signature");
+            };
+            let public_jwk = match keys.get(kid) {
+                Some(Ok(jwk)) => jwk,
+                Some(Err(e)) => bail!("issue resolving {kid}: {e}"),
+                None => bail!("no key resolved for {kid}"),
+            };
+            signature.verify(&jws.payload, public_jwk)?;
+        }
+        jws.jwt(false)
+    }))
+    .await
+}
+
+/// Decode the JWT token and return the claims, accepting a payload encoded
+/// using standard (padded) base64 as well as base64url.
+///
+/// Intended for interoperating with legacy producers only: [`decode`] should
+/// be preferred. The payload is first decoded as unpadded base64url, falling
+/// back to standard base64. Signatures are always verified over the payload
+/// exactly as received.
+///
+/// # Errors
+/// Returns an error if the token cannot be parsed or verified, or if the
+/// payload is neither valid base64url nor standard base64.
+pub async fn decode_lenient<F, Fut, T>(compact_jws: &str, resolver: F) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_lenient");
+
+    let jws: Jws = compact_jws.parse()?;
+    jws.verify(resolver).await?;
+    jws.jwt(true)
+}
+
+/// Decode the JWT token, rejecting a protected header or claims containing
+/// duplicate member names, and return the claims.
+///
+/// By default the last of a duplicated member is silently used, which can be
+/// exploited where producers and consumers disagree on which value applies.
+/// Required by security profiles that forbid duplicate members.
+///
+/// # Errors
+/// Returns an error if the header or claims (at any depth) contain a
+/// duplicate member name, or the token cannot be decoded and verified (see
+/// [`decode`]).
+pub async fn decode_strict<F, Fut, T>(compact_jws: &str, resolver: F) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_strict");
+
+    let [header, ..] = verify::split_compact(compact_jws, &CompactLimits::default())?;
+    let header = verify::decode_segment(Segment::Header, header)?;
+    verify::check_unique_members(&header).map_err(|e| anyhow!("invalid header: {e}"))?;
+
+    let jws: Jws = compact_jws.parse()?;
+    verify::check_unique_members(&jws.payload_bytes(false)?)
+        .map_err(|e| anyhow!("invalid claims: {e}"))?;
+
+    jws.verify(resolver).await?;
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, verifying each signature against a set of candidate
+/// keys, and return the claims.
+///
+/// For use where a single `kid` can map to several valid keys, for example
+/// during key rotation. Candidates are tried in the order returned by
+/// `resolver`, stopping at the first key that verifies, so the most likely
+/// key should be returned first. Use [`decode`] when a `kid` identifies a
+/// single key.
+///
+/// # Errors
+/// Returns an error if the token cannot be parsed, a signature has no key
+/// ID, its candidate keys cannot be resolved, or no candidate verifies the
+/// signature. In the latter case, the error from the last candidate is
+/// returned.
+pub async fn decode_multi_key<F, Fut, T>(compact_jws: &str, resolver: F) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<Vec<PublicKeyJwk>>> + Send,
+{
+    tracing::debug!("decode_multi_key");
+
+    let jws: Jws = compact_jws.parse()?;
+    for signature in &jws.signatures {
+        let Some(kid) = signature.kid() else {
+            bail!("Missing key ID in JWS signature");
+        };
+
+        let mut result = Err(anyhow!("no keys resolved for {kid}"));
+        for public_jwk in resolver(kid.to_owned()).await? {
+            result = signature.verify(&jws.payload, &public_jwk);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result?;
+    }
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, validate its registered claims, and return the
+/// claims.
+///
+/// # Errors
+/// Returns an error if the `typ` header is not permitted by `validation`,
+/// the token cannot be decoded and verified (see [`decode`]), or the
+/// registered claims fail validation.
+pub async fn decode_validated<F, Fut, T>(
+    compact_jws: &str, resolver: F, validation: &Validation,
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_validated");
+
+    let jws: Jws = compact_jws.parse()?;
+    for signature in &jws.signatures {
+        validation.validate_typ(&signature.protected.typ)?;
+    }
+    jws.verify(resolver).await?;
+
+    let registered: Jwt<RegisteredClaims> = jws.jwt(false)?;
+    validation.validate(&registered.claims)?;
+
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, verifying the signature against the provided public
+/// key, and return the claims.
+///
+/// Use when the signer's key is already held (e.g. from pinned
+/// configuration). The `kid` header is ignored.
+///
+/// # Errors
+/// Returns an error if the token cannot be parsed, the signature does not
+/// verify against `public_jwk`, or the claims cannot be deserialized.
+pub fn decode_with_key<T>(compact_jws: &str, public_jwk: &PublicKeyJwk) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned,
+{
+    tracing::debug!("decode_with_key");
+
+    let jws: Jws = compact_jws.parse()?;
+    for signature in &jws.signatures {
+        signature.verify(&jws.payload, public_jwk)?;
+    }
+    jws.jwt(false)
+}
+
+/// Decode the JWT token, resolving each signature's key from the DID Document
+/// of the DID in its `kid`, and return the claims.
+///
+/// The `kid` must be a DID URL referencing a verification method in the
+/// resolved document (e.g. `did:example:alice#key-1`).
+///
+/// # Errors
+/// Returns an error if the token cannot be parsed, a DID cannot be resolved,
+/// the referenced verification method is not found, or a signature does not
+/// verify.
+pub async fn decode_with_resolver<T>(
+    compact_jws: &str, resolver: &impl DidResolver,
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+{
+    tracing::debug!("decode_with_resolver");
+
+    let jws: Jws = compact_jws.parse()?;
+    jws.verify(|kid: String| async move {
+        let Ok(id) = kid.parse::<VerificationMethodId>() else {
+            bail!("key ID {kid} is not a DID URL");
+        };
+        let document = resolver.resolve(id.did()).await?;
+        document
+            .verification_method(&kid)
+            .ok_or_else(|| anyhow!("verification method {kid} not found in DID document"))
+    })
+    .await?;
+    jws.jwt(false)
+}
+
+/// Verify a compact JWS against the provided public key without decoding the
+/// payload.
+///
+/// Unlike [`decode`], verification is synchronous and places no `Send`
+/// bounds on the caller, making it suitable for single-threaded runtimes
+/// such as `wasm32` in the browser, where the key has already been fetched.
+///
+/// # Errors
+/// Returns an error if the token is not a compact JWS, `public_jwk` is not a
+/// valid key, or the signature does not verify against it.
+pub fn verify_sync(compact_jws: &str, public_jwk: &PublicKeyJwk) -> Result<()> {
+    tracing::debug!("verify_sync");
+    verify_compact(compact_jws, public_jwk, &VerifyOptions::default())
+}
+
+/// Decode the protected header of a compact JWS without verifying the
+/// signature.
+///
+/// Intended for routing decisions made before verification, such as selecting
+/// a resolver by `kid` or `alg`. The header is **untrusted**: nothing in it
+/// should be relied on until the JWS has been verified.
+///
+/// # Errors
+/// Returns an error if the token is not a compact JWS or the header cannot be
+/// decoded.
+pub fn peek_header(compact_jws: &str) -> Result<Protected> {
+    let [header, _, _] = verify::split_compact(compact_jws, &CompactLimits::default())?;
+    verify::decode_header(header)
+}
+
+/// Decode the claims of a compact JWS without verifying the signature.
+///
+/// Supports the "resolve by claim, then verify" pattern, for example using
+/// the unverified `iss` to select a resolver or trust anchor.
+///
+/// **Warning:** the claims are **untrusted** until the JWS has been verified
+/// (e.g. using [`decode`]). Never use them for authorization decisions.
+///
+/// # Errors
+/// Returns an error if the token is not a compact JWS or the payload cannot
+/// be decoded and deserialized.
+pub fn peek_payload<T: DeserializeOwned>(compact_jws: &str) -> Result<T> {
+    let jws: Jws = compact_jws.parse()?;
+    Ok(jws.jwt(false)?.claims)
+}
+
+/// A nested JWT: the headers of each enclosing JWS and the innermost JWT.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NestedJwt<T> {
+    /// Headers of the enclosing (`cty: JWT`) layers, outermost first.
+    pub outer: Vec<Protected>,
 
-    Ok(Jwt {
-        header: signature.protected.clone(),
-        claims,
+    /// The innermost JWT.
+    pub inner: Jwt<T>,
+}
+
+/// Decode and verify a nested JWT, where the payload of each enclosing JWS
+/// (identified by the `cty: JWT` header) is itself a compact JWS.
+///
+/// Every layer is verified using `resolver`. A regular (non-nested) JWT is
+/// returned with no outer headers.
+///
+/// # Errors
+/// Returns an error if any layer cannot be decoded or verified, or nesting
+/// exceeds `max_depth` enclosing layers.
+pub async fn decode_nested<F, Fut, T>(
+    compact_jws: &str, resolver: F, max_depth: usize,
+) -> Result<NestedJwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("decode_nested");
+
+    let mut jws: Jws = compact_jws.parse()?;
+    let mut outer = vec![];
+
+    loop {
+        jws.verify(&resolver).await?;
+        let Some(inner) = jws.nested()? else {
+            break;
+        };
+        if outer.len() == max_depth {
+            bail!("JWT nesting exceeds maximum depth of {max_depth}");
+        }
+        outer.extend(jws.signatures.first().map(|signature| signature.protected.clone()));
+        jws = inner.parse()?;
+    }
+
+    Ok(NestedJwt {
+        outer,
+        inner: jws.jwt(false)?,
     })
 }
 
@@ -91,103 +510,729 @@ impl Jws {
     ///
     /// # Errors
     /// TODO: document errors
+    #[tracing::instrument(level = "debug", skip_all, fields(alg, typ, kid), err)]
     pub async fn verify<F, Fut>(&self, resolver: F) -> Result<()>
     where
         F: Fn(String) -> Fut + Send,
         Fut: Future<Output = Result<PublicKeyJwk>> + Send,
     {
-        for signature in &self.signatures {
-            let header = &signature.protected;
-            let Some(kid) = header.kid() else {
-                return Err(anyhow!("Missing key ID in JWS signature"));
-            };
-
-            // dereference `kid` to JWK matching key ID
-            let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&header)?);
-            let sig = Base64UrlUnpadded::decode_vec(&signature.signature)?;
+        self.verify_with_options(resolver, &VerifyOptions::default()).await
+    }
 
-            let public_jwk = resolver(kid.to_owned()).await?;
-            public_jwk.verify(&format!("{header}.{}", self.payload), &sig)?;
-        }
+    /// Verify JWS signatures using a resolver that returns keys as either a
+    /// JWK or a multibase encoded key, as expressed by a DID document's
+    /// `publicKeyJwk` or `publicKeyMultibase`.
+    ///
+    /// # Errors
+    /// Returns an error if a multibase key cannot be converted to a JWK, or
+    /// verification fails (see [`Jws::verify`]).
+    pub async fn verify_verification_key<F, Fut>(&self, resolver: F) -> Result<()>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<VerificationKey>> + Send,
+    {
+        self.verify(move |kid| {
+            let key = resolver(kid);
+            async move { key.await?.to_jwk() }
+        })
+        .await
+    }
 
-        Ok(())
+    /// Verify JWS signatures, requiring every resolved key to have the
+    /// [RFC7638] thumbprint `expected_thumbprint`.
+    ///
+    /// The pin is checked before the signature, so a key returned by a
+    /// compromised resolver is rejected even when it produced the signature.
+    ///
+    /// [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
+    ///
+    /// # Errors
+    /// Returns a [`ThumbprintMismatch`] error (wrapped in [`anyhow::Error`])
+    /// if a resolved key does not match the pin, or an error if verification
+    /// otherwise fails (see [`Jws::verify`]).
+    pub async fn verify_pinned<F, Fut>(&self, resolver: F, expected_thumbprint: &str) -> Result<()>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        self.verify(move |kid| {
+            let key = resolver(kid);
+            async move {
+                let jwk = key.await?;
+                let actual = jwk.thumbprint()?;
+                if actual != expected_thumbprint {
+                    return Err(ThumbprintMismatch {
+                        expected: expected_thumbprint.to_string(),
+                        actual,
+                    }
+                    .into());
+                }
+                Ok(jwk)
+            }
+        })
+        .await
     }
 
-    /// Encode the provided header and claims payload and sign, returning a JWT
-    /// in compact JWS form.
+    /// Verify a JWS with a detached payload ([RFC7515 Appendix F]), using the
+    /// payload supplied out of band.
+    ///
+    /// The signing input is reconstructed from the raw `payload` when the
+    /// signatures' `b64` header is `false` ([RFC7797]), and from its
+    /// base64url encoding otherwise. An unencoded payload must be UTF-8.
+    ///
+    /// [RFC7515 Appendix F]: https://www.rfc-editor.org/rfc/rfc7515#appendix-F
+    /// [RFC7797]: https://www.rfc-editor.org/rfc/rfc7797
     ///
     /// # Errors
-    /// An error is returned if there is no signature on the JWS or if the
-    /// serialization (for encoding) of the header fails.
-    pub fn encode(&self) -> Result<String> {
-        let Some(signature) = self.signatures.first() else {
+    /// Returns an error if the JWS also carries an inline payload, its
+    /// signatures disagree on `b64`, or verification fails (see
+    /// [`Jws::verify`]).
+    pub async fn verify_detached<F, Fut>(&self, payload: &[u8], resolver: F) -> Result<()>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        if !self.payload.is_empty() {
+            bail!("detached JWS must not carry an inline payload");
+        }
+        let Some(first) = self.signatures.first() else {
             bail!("no signature found");
         };
+        let unencoded = first.protected.b64 == Some(false);
+        if self.signatures.iter().any(|s| (s.protected.b64 == Some(false)) != unencoded) {
+            bail!("signatures disagree on `b64`");
+        }
 
-        let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&signature.protected)?);
-        let payload = &self.payload;
-        let signature = &signature.signature;
+        let payload = if unencoded {
+            String::from_utf8(payload.to_vec())
+                .map_err(|_| anyhow!("unencoded payload is not valid UTF-8"))?
+        } else {
+            Base64UrlUnpadded::encode_string(payload)
+        };
+        let attached = Self {
+            payload,
+            signatures: self.signatures.clone(),
+        };
+        attached.verify(resolver).await
+    }
 
-        Ok(format!("{header}.{payload}.{signature}"))
+    /// Verify JWS signatures using the provided verification options.
+    ///
+    /// # Errors
+    /// Returns an error if a signature has no key ID, its key cannot be
+    /// resolved, or it fails verification under `options`.
+    pub async fn verify_with_options<F, Fut>(
+        &self, resolver: F, options: &VerifyOptions,
+    ) -> Result<()>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        self.verify_signatures(resolver, options).await.map(|_| ())
     }
 
-    /// Extracts the signer's DID from the `kid` of the first JWS signature.
+    /// Verify JWS signatures, returning the key and header that verified each
+    /// signature, in signature order.
+    ///
+    /// Intended for audit logging and authorization decisions that depend on
+    /// exactly which key validated the JWS.
     ///
     /// # Errors
-    /// LATER: Add errors
-    pub fn did(&self) -> Result<String> {
-        let Some(kid) = self.signatures[0].protected.kid() else {
-            return Err(anyhow!("Invalid `kid`"));
-        };
-        let Some(did) = kid.split('#').next() else {
-            return Err(anyhow!("Invalid DID"));
-        };
-        Ok(did.to_owned())
+    /// Returns an error if a signature has no key ID, its key cannot be
+    /// resolved, or it fails verification.
+    pub async fn verify_detailed<F, Fut>(&self, resolver: F) -> Result<Vec<Verified>>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        self.verify_signatures(resolver, &VerifyOptions::default()).await
     }
-}
 
-impl FromStr for Jws {
-    type Err = anyhow::Error;
+    // Verify every signature, collecting the verifying key for each.
+    async fn verify_signatures<F, Fut>(
+        &self, resolver: F, options: &VerifyOptions,
+    ) -> Result<Vec<Verified>>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        let mut verified = Vec::with_capacity(self.signatures.len());
 
-    // TODO: cater for different key types
-    fn from_str(s: &str) -> Result<Self> {
-        let parts = s.split('.').collect::<Vec<&str>>();
-        if parts.len() != 3 {
-            bail!("invalid Compact JWS format");
-        }
+        for (signature_index, signature) in self.signatures.iter().enumerate() {
+            signature.record_header();
+            let Some(kid) = signature.kid() else {
+                return Err(anyhow!("Missing key ID in JWS signature"));
+            };
 
-        // deserialize header
-        let decoded = Base64UrlUnpadded::decode_vec(parts[0])
-            .map_err(|e| anyhow!("issue decoding header: {e}"))?;
-        let protected = serde_json::from_slice(&decoded)
-            .map_err(|e| anyhow!("issue deserializing header: {e}"))?;
+            // dereference `kid` to JWK matching key ID
+            let public_jwk = resolver(kid.to_owned()).await?;
+            signature.verify_with_options(&self.payload, &public_jwk, options)?;
+
+            verified.push(Verified {
+                key: public_jwk,
+                protected: signature.protected.clone(),
+                signature_index,
+            });
+        }
+
+        Ok(verified)
+    }
+
+    /// Verify JWS signatures, succeeding when at least `threshold` signatures
+    /// are valid.
+    ///
+    /// Useful during key rotation, where a JWS carries a signature for each of
+    /// the signer's verification methods and the verifier may only be able to
+    /// resolve one of them.
+    ///
+    /// # Errors
+    /// Returns an error if `threshold` is zero or fewer than `threshold`
+    /// signatures can be verified.
+    pub async fn verify_threshold<F, Fut>(&self, resolver: F, threshold: usize) -> Result<()>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        if threshold == 0 {
+            bail!("threshold must be at least 1");
+        }
+
+        let mut verified = 0;
+        for signature in &self.signatures {
+            let Some(kid) = signature.kid() else {
+                continue;
+            };
+
+            let Ok(public_jwk) = resolver(kid.to_owned()).await else {
+                continue;
+            };
+
+            if signature.verify(&self.payload, &public_jwk).is_ok() {
+                verified += 1;
+                if verified >= threshold {
+                    return Ok(());
+                }
+            }
+        }
+
+        bail!("only {verified} of {threshold} required signatures verified")
+    }
+
+    /// Verify JWS signatures using the key certified by each signature's `x5c`
+    /// certificate chain.
+    ///
+    /// The chain is validated up to one of the provided trust anchors and the
+    /// leaf certificate's public key is used to verify the signature. Where
+    /// the header also embeds a `jwk`, it must match the leaf key.
+    ///
+    /// A signature with an `x5t#S256` header but no `x5c` references a
+    /// trusted certificate directly: the trust anchor with the matching
+    /// SHA-256 thumbprint is selected and its key used. When both are
+    /// present, `x5t#S256` must match the leaf certificate.
+    ///
+    /// # Errors
+    /// Returns an error if a signature has neither an `x5c` nor an `x5t#S256`
+    /// header, the chain cannot be validated, `x5t#S256` does not match the
+    /// leaf or any trust anchor, or the signature does not verify.
+    pub fn verify_x5c(&self, trust_anchors: &[Certificate]) -> Result<()> {
+        for signature in &self.signatures {
+            let x5t_s256 = signature.protected.x5t_s256.as_deref();
+            let leaf_jwk = match (&signature.protected.x5c, x5t_s256) {
+                (Some(x5c), _) => {
+                    let chain = x509::from_x5c(x5c)?;
+                    x509::verify_chain(&chain, trust_anchors)?;
+                    if let Some(x5t_s256) = x5t_s256 {
+                        if x509::thumbprint_s256(&chain[0])? != x5t_s256 {
+                            bail!("`x5t#S256` does not match the `x5c` certificate");
+                        }
+                    }
+                    x509::public_key_jwk(&chain[0])?
+                }
+                (None, Some(x5t_s256)) => {
+                    let Some(anchor) = trust_anchors.iter().find(|cert| {
+                        x509::thumbprint_s256(cert).is_ok_and(|thumbprint| thumbprint == x5t_s256)
+                    }) else {
+                        bail!("no trusted certificate matches `x5t#S256`");
+                    };
+                    x509::verify_chain(slice::from_ref(anchor), trust_anchors)?;
+                    x509::public_key_jwk(anchor)?
+                }
+                (None, None) => bail!("missing `x5c` header"),
+            };
+
+            if let Some(jwk) = signature.protected.jwk() {
+                if !jwk.eq_thumbprint(&leaf_jwk) {
+                    bail!("embedded `jwk` does not match the `x5c` certificate key");
+                }
+            }
+
+            signature.verify(&self.payload, &leaf_jwk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify JWS signatures using the signing key resolved from each
+    /// signature's OpenID Federation `trust_chain` header.
+    ///
+    /// # Errors
+    /// Returns an error if a signature has no `trust_chain` header, the trust
+    /// chain cannot be verified against the Trust Anchor's keys, or the
+    /// signature does not verify.
+    pub fn verify_trust_chain(&self, trust_anchor_jwks: &Jwks) -> Result<()> {
+        for signature in &self.signatures {
+            let Some(trust_chain) = &signature.protected.trust_chain else {
+                bail!("missing `trust_chain` header");
+            };
+            let public_jwk = federation::verify_trust_chain(trust_chain, trust_anchor_jwks)?.key;
+            signature.verify(&self.payload, &public_jwk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encode the provided header and claims payload and sign, returning a JWT
+    /// in compact JWS form.
+    ///
+    /// # Errors
+    /// An error is returned if there is no signature on the JWS or if the
+    /// serialization (for encoding) of the header fails.
+    pub fn encode(&self) -> Result<String> {
+        let Some(signature) = self.signatures.first() else {
+            bail!("no signature found");
+        };
+
+        let header = signature.encoded_protected()?;
+        let payload = &self.payload;
+        let signature = &signature.signature;
+
+        Ok(format!("{header}.{payload}.{signature}"))
+    }
+
+    /// Assemble a JWS from a protected header, payload, and signature produced
+    /// out-of-band (e.g. by a cloud KMS signing the input returned by
+    /// [`JwsBuilder::signing_input`]).
+    ///
+    /// # Errors
+    /// Returns an error if the payload cannot be serialized.
+    pub fn from_parts<T: Serialize>(
+        protected: Protected, payload: &T, signature: &[u8],
+    ) -> Result<Self> {
+        Ok(Self {
+            payload: Base64UrlUnpadded::encode_string(&serde_json::to_vec(payload)?),
+            signatures: vec![Signature {
+                protected,
+                signature: Base64UrlUnpadded::encode_string(signature),
+                ..Signature::default()
+            }],
+        })
+    }
+
+    /// Replace the JWS signatures with a single signature by `signer`, keeping
+    /// the encoded payload verbatim.
+    ///
+    /// The protected header is rebuilt from `signer`, which provides the `alg`
+    /// and `kid` headers. Only `typ`, `cty`, `b64`, and `crit` are carried
+    /// over from the original (first) header. The signing input uses the
+    /// stored payload string exactly, so the payload is never re-serialized.
+    ///
+    /// # Errors
+    /// Returns an error if the JWS has no signature, the signer's verification
+    /// method cannot be retrieved, or signing fails.
+    pub async fn replace_signature(&mut self, signer: &dyn DynSigner) -> Result<()> {
+        let Some(original) = self.signatures.first() else {
+            bail!("no signature found");
+        };
+        let protected = Protected {
+            alg: signer.algorithm(),
+            typ: original.protected.typ.clone(),
+            cty: original.protected.cty.clone(),
+            key: Key::KeyId(signer.verification_method().await?),
+            crit: original.protected.crit.clone(),
+            b64: original.protected.b64,
+            ..Protected::default()
+        };
+
+        let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected)?);
+        let sig = signer.try_sign(format!("{header}.{}", self.payload).as_bytes()).await?;
+        self.signatures = vec![Signature {
+            protected,
+            protected_b64: Some(header),
+            signature: Base64UrlUnpadded::encode_string(&sig),
+            ..Signature::default()
+        }];
+
+        Ok(())
+    }
+
+    /// Parse a compact JWS, rejecting segments longer than `limits` before
+    /// they are decoded. Parsing using [`FromStr`] applies the default limits.
+    ///
+    /// # Errors
+    /// Returns an error if the token is not a compact JWS, a segment exceeds
+    /// its limit ([`CompactError::TooLarge`]), or the header cannot be decoded.
+    pub fn from_compact_with_limits(compact_jws: &str, limits: &CompactLimits) -> Result<Self> {
+        let [header, payload, signature] = verify::split_compact(compact_jws, limits)?;
+        let protected = verify::decode_header(header)?;
 
         Ok(Self {
-            payload: parts[1].to_string(),
+            payload: payload.to_string(),
             signatures: vec![Signature {
                 protected,
-                signature: parts[2].to_string(),
+                protected_b64: Some(header.to_string()),
+                signature: signature.to_string(),
+                ..Signature::default()
             }],
         })
     }
+
+    /// Serialize the JWS in compact form (`header.payload.signature`).
+    ///
+    /// This is the inverse of parsing a compact JWS using [`FromStr`]. Use
+    /// [`Jws::to_json_string`] for a JWS with multiple signatures.
+    ///
+    /// # Errors
+    /// An error is returned if the JWS does not have exactly one signature, as
+    /// compact serialization cannot represent multiple signatures, or if the
+    /// serialization of the header fails.
+    pub fn to_compact(&self) -> Result<String> {
+        if self.signatures.len() != 1 {
+            bail!("compact serialization requires exactly one signature");
+        }
+        self.encode()
+    }
+
+    /// Serialize the JWS using the general JWS JSON Serialization ([RFC7515
+    /// §7.2.1]): a JSON object with `payload` and a `signatures` array,
+    /// each entry holding a base64url-encoded `protected` header and
+    /// `signature`.
+    ///
+    /// Unlike [`Jws::to_compact`], any number of signatures can be
+    /// represented. This is the inverse of [`Jws::from_json_str`].
+    ///
+    /// [RFC7515 §7.2.1]: https://www.rfc-editor.org/rfc/rfc7515#section-7.2.1
+    ///
+    /// # Errors
+    /// Returns an error if a protected header cannot be serialized.
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| anyhow!("issue serializing JWS: {e}"))
+    }
+
+    /// Parse a JWS from the general JWS JSON Serialization, as produced by
+    /// [`Jws::to_json_string`]. Use [`FromStr`] to parse a compact JWS.
+    ///
+    /// # Errors
+    /// Returns an error if the string is not a general JWS JSON object or a
+    /// protected header cannot be decoded.
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| anyhow!("issue deserializing JWS: {e}"))
+    }
+
+    // Deserialize the payload into claims, returning a JWT using the first
+    // signature's protected header. When `lenient`, a standard base64 encoded
+    // payload is also accepted.
+    pub(crate) fn jwt<T: DeserializeOwned>(&self, lenient: bool) -> Result<Jwt<T>> {
+        let Some(signature) = self.signatures.first() else {
+            bail!("no signature found");
+        };
+        let claims = serde_json::from_slice(&self.payload_bytes(lenient)?)
+            .map_err(|e| anyhow!("issue deserializing claims:{e}"))?;
+
+        Ok(Jwt {
+            header: signature.protected.clone(),
+            claims,
+        })
+    }
+
+    // The decoded payload.
+    fn payload_bytes(&self, lenient: bool) -> Result<Vec<u8>> {
+        let Some(signature) = self.signatures.first() else {
+            bail!("no signature found");
+        };
+
+        // unencoded payloads (RFC7797) are used as-is
+        if signature.protected.b64 == Some(false) {
+            return Ok(self.payload.as_bytes().to_vec());
+        }
+        match verify::decode_segment(Segment::Payload, &self.payload) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if lenient => Base64::decode_vec(&self.payload).map_err(|_| e),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The nested JWT carried as the payload when the `cty` header is `JWT`,
+    /// or `None` for a regular JWT.
+    ///
+    /// The nested JWT may be the raw payload, as per RFC7519, or a JSON
+    /// string.
+    ///
+    /// # Errors
+    /// Returns an error if the payload cannot be decoded.
+    pub fn nested(&self) -> Result<Option<String>> {
+        let Some(signature) = self.signatures.first() else {
+            bail!("no signature found");
+        };
+        let is_nested =
+            signature.protected.cty.as_deref().is_some_and(|cty| cty.eq_ignore_ascii_case("JWT"));
+        if !is_nested {
+            return Ok(None);
+        }
+
+        let payload = String::from_utf8(self.payload_bytes(false)?)?;
+        if payload.starts_with('"') {
+            return Ok(Some(serde_json::from_str(&payload)?));
+        }
+        Ok(Some(payload))
+    }
+
+    /// Extracts the signer's DID from the `kid` of the first JWS signature.
+    ///
+    /// # Errors
+    /// LATER: Add errors
+    pub fn did(&self) -> Result<String> {
+        let Some(kid) = self.signatures[0].protected.kid() else {
+            return Err(anyhow!("Invalid `kid`"));
+        };
+        let Some(did) = kid.split('#').next() else {
+            return Err(anyhow!("Invalid DID"));
+        };
+        Ok(did.to_owned())
+    }
+}
+
+impl FromStr for Jws {
+    type Err = anyhow::Error;
+
+    // TODO: cater for different key types
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_compact_with_limits(s, &CompactLimits::default())
+    }
+}
+
+/// A compact JWS that is structurally invalid, detected before any segment is
+/// decoded.
+///
+/// Returned (wrapped in [`anyhow::Error`]) when parsing a compact JWS, so
+/// callers can `downcast_ref` to distinguish failure modes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompactError {
+    /// The token does not have exactly three `.`-separated segments.
+    Segments,
+
+    /// A required segment is empty.
+    Empty(Segment),
+
+    /// A segment exceeds its maximum encoded length.
+    TooLarge {
+        /// The oversized segment.
+        segment: Segment,
+
+        /// The maximum length, in bytes.
+        max: usize,
+    },
+
+    /// A segment contains characters outside the base64url alphabet.
+    InvalidBase64(Segment),
+}
+
+/// A segment of a compact JWS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// The protected header.
+    Header,
+
+    /// The payload.
+    Payload,
+
+    /// The signature.
+    Signature,
+}
+
+impl Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Segments => write!(f, "invalid Compact JWS format"),
+            Self::Empty(segment) => write!(f, "compact JWS {segment} is empty"),
+            Self::TooLarge { segment, max } => {
+                write!(f, "compact JWS {segment} exceeds {max} bytes")
+            }
+            Self::InvalidBase64(segment) => write!(f, "compact JWS {segment} is not base64url"),
+        }
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => write!(f, "header"),
+            Self::Payload => write!(f, "payload"),
+            Self::Signature => write!(f, "signature"),
+        }
+    }
+}
+
+/// A resolved key that does not match the thumbprint pinned using
+/// [`Jws::verify_pinned`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThumbprintMismatch {
+    /// The pinned thumbprint.
+    pub expected: String,
+
+    /// The resolved key's thumbprint.
+    pub actual: String,
+}
+
+impl Display for ThumbprintMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key thumbprint {} does not match pinned {}", self.actual, self.expected)
+    }
+}
+
+impl std::error::Error for ThumbprintMismatch {}
+
+/// The outcome of successfully verifying a JWS signature.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Verified {
+    /// The public key that verified the signature.
+    pub key: PublicKeyJwk,
+
+    /// The signature's protected header.
+    pub protected: Protected,
+
+    /// The index of the signature in the JWS `signatures` array.
+    pub signature_index: usize,
 }
 
 /// An entry of the `signatures` array in a general JWS.
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Signature {
-    /// The base64 url-encoded JWS protected header when the JWS protected
-    /// header is non-empty. Must have `alg` and `kid` properties set.
-    #[serde(with = "base64url")]
+    /// The JWS protected header, serialized as a base64 url-encoded
+    /// segment. Must have `alg` and `kid` properties set.
     pub protected: Protected,
 
+    /// The protected header segment exactly as received, set when the JWS
+    /// is parsed.
+    ///
+    /// The signature is computed over these bytes, so they are used for
+    /// verification (and re-serialization) rather than re-encoding
+    /// `protected`, whose member order and whitespace may differ. Ignored if
+    /// it no longer decodes to `protected`.
+    pub protected_b64: Option<String>,
+
     /// The base64 url-encoded JWS signature.
     pub signature: String,
+
+    /// JWS unprotected header. Members are not integrity protected (they are
+    /// never part of the signing input) and MUST be treated as untrusted.
+    ///
+    /// Only used as a hint, for example, to locate the verification key when
+    /// the protected header lacks a `kid`.
+    pub header: Option<HashMap<String, Value>>,
+}
+
+impl Signature {
+    /// The key ID used to resolve the signature's verification key.
+    ///
+    /// Taken from the protected header when set, falling back to the
+    /// (untrusted) unprotected header as a resolution hint.
+    #[must_use]
+    pub fn kid(&self) -> Option<&str> {
+        if let Some(kid) = self.protected.kid().filter(|kid| !kid.is_empty()) {
+            return Some(kid);
+        }
+        self.header.as_ref()?.get("kid")?.as_str()
+    }
+
+    // Record the header's `alg`, `typ`, and `kid` on the current span, if it
+    // has those fields.
+    fn record_header(&self) {
+        let span = tracing::Span::current();
+        span.record("alg", tracing::field::display(&self.protected.alg));
+        span.record("typ", self.protected.typ.as_str());
+        if let Some(kid) = self.kid() {
+            span.record("kid", kid);
+        }
+    }
+
+    // Verify the signature over the JWS payload using the provided public key,
+    // first checking the header `alg` is sanctioned for the key's curve.
+    pub(crate) fn verify(&self, payload: &str, public_jwk: &PublicKeyJwk) -> Result<()> {
+        self.verify_with_options(payload, public_jwk, &VerifyOptions::default())
+    }
+
+    pub(crate) fn verify_with_options(
+        &self, payload: &str, public_jwk: &PublicKeyJwk, options: &VerifyOptions,
+    ) -> Result<()> {
+        self.protected.check_crit()?;
+        self.protected.check_kid()?;
+        if options.reject_unknown_headers {
+            self.protected.check_unknown()?;
+        }
+
+        public_jwk.validate()?;
+        let alg = &self.protected.alg;
+        if !alg.is_compatible(&public_jwk.crv) {
+            bail!("algorithm {alg} is not valid for curve {:?}", public_jwk.crv);
+        }
+        public_jwk.check_alg(alg)?;
+
+        let header = self.encoded_protected()?;
+        let sig = verify::decode_segment(Segment::Signature, &self.signature)?;
+        public_jwk.verify_parts_with_options(&header, payload, &sig, options)
+    }
+
+    // The base64url-encoded protected header: the segment as received when
+    // it still matches `protected`, otherwise `protected` re-encoded.
+    fn encoded_protected(&self) -> Result<String> {
+        if let Some(encoded) = &self.protected_b64 {
+            if verify::decode_header(encoded).is_ok_and(|decoded| decoded == self.protected) {
+                return Ok(encoded.clone());
+            }
+        }
+        Ok(Base64UrlUnpadded::encode_string(&serde_json::to_vec(&self.protected)?))
+    }
+}
+
+// The JSON serialization of a `signatures` entry.
+#[derive(Serialize, Deserialize)]
+struct EncodedSignature {
+    protected: String,
+    signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header: Option<HashMap<String, Value>>,
+}
+
+impl Serialize for Signature {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EncodedSignature {
+            protected: self.encoded_protected().map_err(serde::ser::Error::custom)?,
+            signature: self.signature.clone(),
+            header: self.header.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = EncodedSignature::deserialize(deserializer)?;
+        Ok(Self {
+            protected: verify::decode_header(&encoded.protected)
+                .map_err(serde::de::Error::custom)?,
+            protected_b64: Some(encoded.protected),
+            signature: encoded.signature,
+            header: encoded.header,
+        })
+    }
 }
 
 /// JWS header.
 ///
 /// N.B. The following headers are not included as they are unnecessary
-/// for Vercre: `jku`, `x5u`, `x5t`, `x5t#S256`, `cty`, `crit`.
+/// for Vercre: `jku`, `x5u`, `x5t`.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Protected {
     /// Digital signature algorithm identifier as per IANA "JSON Web Signature
@@ -196,9 +1241,19 @@ pub struct Protected {
 
     /// Used to declare the media type [IANA.MediaTypes] of the JWS.
     ///
+    /// An empty string indicates the header is absent.
+    ///
     /// [IANA.MediaTypes]: (http://www.iana.org/assignments/media-types)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub typ: String,
 
+    /// The media type of the secured content. Set to `JWT` when the payload
+    /// is itself a JWT (a nested JWT).
+    ///
+    /// See [RFC7519 §5.2](https://www.rfc-editor.org/rfc/rfc7519#section-5.2).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cty: Option<String>,
+
     /// The key material for the public key.
     #[serde(flatten)]
     pub key: Key,
@@ -207,8 +1262,19 @@ pub struct Protected {
     /// used to sign the JWT. This element MAY be used to convey a key
     /// attestation. In such a case, the actual key certificate will contain
     /// attributes related to the key properties.
+    ///
+    /// Each entry is a standard base64 (not base64url) encoded DER
+    /// certificate, starting with the certificate containing the signing key.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub x5c: Option<String>,
+    pub x5c: Option<Vec<String>>,
+
+    /// The base64url-encoded SHA-256 thumbprint of the DER encoding of the
+    /// certificate containing the signing key, referencing the certificate
+    /// instead of (or as well as) embedding it in `x5c`.
+    ///
+    /// See [RFC7515 §4.1.8](https://www.rfc-editor.org/rfc/rfc7515#section-4.1.8).
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
 
     /// Contains an OpenID.Federation Trust Chain. This element MAY be used to
     /// convey key attestation, metadata, metadata policies, federation
@@ -216,79 +1282,121 @@ pub struct Protected {
     /// federation, if available in the chain.
     ///
     /// When used for signature verification, `kid` MUST be set.
+    ///
+    /// Each entry is an Entity Statement JWT, starting with the signer's
+    /// Entity Configuration. See [`federation`](crate::jose::federation).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_chain: Option<Vec<String>>,
+
+    /// Indicates extensions to the JWS specification are being used that MUST
+    /// be understood and processed. Verification fails if any listed header
+    /// is not understood or is absent from the protected header.
+    ///
+    /// See [RFC7515 §4.1.11](https://www.rfc-editor.org/rfc/rfc7515#section-4.1.11).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crit: Option<Vec<String>>,
+
+    /// Indicates whether the payload is base64url-encoded (`true` or absent)
+    /// or used as-is in the signing input (`false`). When used, `b64` MUST be
+    /// listed in `crit`.
+    ///
+    /// See [RFC7797](https://www.rfc-editor.org/rfc/rfc7797).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub trust_chain: Option<String>,
+    pub b64: Option<bool>,
+
+    /// Additional header parameters not modelled above, such as `nonce` or
+    /// application-specific parameters.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
+/// Header parameters represented by typed [`Protected`] fields and so not
+/// permitted as additional parameters.
+const TYPED_HEADERS: [&str; 10] =
+    ["alg", "typ", "cty", "kid", "jwk", "x5c", "x5t#S256", "trust_chain", "crit", "b64"];
+
+/// Header parameters this implementation understands and processes when
+/// listed in `crit`.
+const CRITICAL_HEADERS: [&str; 1] = ["b64"];
+
+/// The `typ` of an `OpenID4VCI` proof JWT.
+pub(crate) const PROOF_TYPE: &str = "openid4vci-proof+jwt";
+
 impl Protected {
-    /// Returns the `kid` if the key type is `KeyId`.
-    #[must_use]
-    pub fn kid(&self) -> Option<&str> {
-        match &self.key {
-            Key::KeyId(kid) => Some(kid.as_str()),
-            Key::Jwk(_) => None,
+    // Check every header listed in `crit` is understood and present, as per
+    // RFC7515 §4.1.11.
+    fn check_crit(&self) -> Result<()> {
+        let Some(crit) = &self.crit else {
+            if self.b64.is_some() {
+                bail!("`b64` header must be listed in `crit`");
+            }
+            return Ok(());
+        };
+        if crit.is_empty() {
+            bail!("`crit` must not be empty");
         }
-    }
 
-    /// Returns the `kid` if the key is type `KeyId`.
-    #[must_use]
-    pub const fn jwk(&self) -> Option<&PublicKeyJwk> {
-        match &self.key {
-            Key::Jwk(jwk) => Some(jwk),
-            Key::KeyId(_) => None,
+        for name in crit {
+            if !CRITICAL_HEADERS.contains(&name.as_str()) {
+                bail!("unsupported critical header: {name}");
+            }
+            let present = match name.as_str() {
+                "b64" => self.b64.is_some(),
+                _ => false,
+            };
+            if !present {
+                bail!("critical header {name} is missing");
+            }
         }
-    }
-}
 
-impl PublicKeyJwk {
-    /// Verify the signature of the provided message using the JWK.
-    ///
-    /// # Errors
-    ///
-    /// Will return an error if the signature is invalid, the JWK is invalid, or the
-    /// algorithm is unsupported.
-    pub fn verify(&self, msg: &str, sig: &[u8]) -> Result<()> {
-        match self.crv {
-            Curve::Es256K => self.verify_es256k(msg, sig),
-            Curve::Ed25519 => self.verify_eddsa(msg, sig),
-            Curve::X25519 => bail!("unsupported DSA curve"),
-        }
+        Ok(())
     }
 
-    // Verify the signature of the provided message using the ES256K algorithm.
-    fn verify_es256k(&self, msg: &str, sig: &[u8]) -> Result<()> {
-        use ecdsa::{Signature, VerifyingKey};
-        use k256::Secp256k1;
-
-        // build verifying key
-        let y = self.y.as_ref().ok_or_else(|| anyhow!("Proof JWT 'y' is invalid"))?;
-        let mut sec1 = vec![0x04]; // uncompressed format
-        sec1.append(&mut Base64UrlUnpadded::decode_vec(&self.x)?);
-        sec1.append(&mut Base64UrlUnpadded::decode_vec(y)?);
-
-        let verifying_key = VerifyingKey::<Secp256k1>::from_sec1_bytes(&sec1)?;
-        let signature: Signature<Secp256k1> = Signature::from_slice(sig)?;
-        let normalised = signature.normalize_s().unwrap_or(signature);
-
-        Ok(verifying_key.verify(msg.as_bytes(), &normalised)?)
+    // Check the header has no parameters other than those represented by
+    // typed fields. Depending on which is deserialized as `key`, `kid` or
+    // `jwk` may be held in `extra`.
+    fn check_unknown(&self) -> Result<()> {
+        if let Some(name) = self.extra.keys().find(|k| !TYPED_HEADERS.contains(&k.as_str())) {
+            bail!("unknown header parameter: {name}");
+        }
+        Ok(())
     }
 
-    // Verify the signature of the provided message using the EdDSA algorithm.
-    fn verify_eddsa(&self, msg: &str, sig_bytes: &[u8]) -> Result<()> {
-        use ed25519_dalek::{Signature, VerifyingKey};
+    // Check an embedded `jwk` accompanied by a `kid` matches it: the `kid`
+    // must be the key's JWK Thumbprint.
+    fn check_kid(&self) -> Result<()> {
+        let jwk = match &self.key {
+            Key::Jwk(jwk) => Some(jwk.clone()),
+            Key::KeyId(_) => {
+                self.extra.get("jwk").cloned().map(serde_json::from_value).transpose()?
+            }
+        };
+        let (Some(jwk), Some(kid)) = (jwk, self.kid()) else {
+            return Ok(());
+        };
+        if jwk.thumbprint()? != kid {
+            bail!("embedded `jwk` thumbprint does not match `kid`");
+        }
+        Ok(())
+    }
 
-        // build verifying key
-        let x_bytes = Base64UrlUnpadded::decode_vec(&self.x)
-            .map_err(|e| anyhow!("unable to base64 decode proof JWK 'x': {e}"))?;
-        let bytes = &x_bytes.try_into().map_err(|_| anyhow!("invalid public key length"))?;
-        let verifying_key = VerifyingKey::from_bytes(bytes)
-            .map_err(|e| anyhow!("unable to build verifying key: {e}"))?;
-        let signature = Signature::from_slice(sig_bytes)
-            .map_err(|e| anyhow!("unable to build signature: {e}"))?;
+    /// Returns the `kid`, whether the key type is `KeyId` or it accompanies an
+    /// embedded `jwk`.
+    #[must_use]
+    pub fn kid(&self) -> Option<&str> {
+        match &self.key {
+            Key::KeyId(kid) => Some(kid.as_str()),
+            Key::Jwk(_) => self.extra.get("kid").and_then(Value::as_str),
+        }
+    }
 
-        verifying_key
-            .verify(msg.as_bytes(), &signature)
-            .map_err(|e| anyhow!("unable to verify signature: {e}"))
+    /// Returns the `kid` if the key is type `KeyId`.
+    #[must_use]
+    pub const fn jwk(&self) -> Option<&PublicKeyJwk> {
+        match &self.key {
+            Key::Jwk(jwk) => Some(jwk),
+            Key::KeyId(_) => None,
+        }
     }
 }
 
@@ -315,8 +1423,18 @@ impl Default for Key {
 
 /// Options to use when creating a permission grant.
 #[derive(Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct JwsBuilder<P, S> {
     jwt_type: String,
+    content_type: Option<String>,
+    headers: Map<String, Value>,
+    per_method: bool,
+    embed_jwk: bool,
+    thumbprint_kid: bool,
+    kid: Option<String>,
+    proof_nonce: Option<String>,
+    prehashed: bool,
+    canonical: bool,
     payload: P,
     signers: S,
 }
@@ -332,8 +1450,8 @@ pub struct Payload<T: Serialize + Send>(T);
 /// Typestate generic for a JWS builder with no signer.
 pub struct NoSigners;
 #[doc(hidden)]
-/// Typestate generic for a JWS builder with a signer.
-pub struct Signers<'a, S: Signer>(pub Vec<&'a S>);
+/// Typestate generic for a JWS builder with one or more signers.
+pub struct Signers<'a>(Vec<&'a dyn DynSigner>);
 
 /// Builder for creating a permission grant.
 impl JwsBuilder<NoPayload, NoSigners> {
@@ -343,6 +1461,15 @@ impl JwsBuilder<NoPayload, NoSigners> {
         // set defaults
         Self {
             jwt_type: "jwt".into(),
+            content_type: None,
+            headers: Map::new(),
+            per_method: false,
+            embed_jwk: false,
+            thumbprint_kid: false,
+            kid: None,
+            proof_nonce: None,
+            prehashed: false,
+            canonical: false,
             payload: NoPayload,
             signers: NoSigners,
         }
@@ -353,6 +1480,15 @@ impl JwsBuilder<NoPayload, NoSigners> {
     pub fn payload<T: Serialize + Send>(self, payload: T) -> JwsBuilder<Payload<T>, NoSigners> {
         JwsBuilder {
             jwt_type: self.jwt_type,
+            content_type: self.content_type,
+            headers: self.headers,
+            per_method: self.per_method,
+            embed_jwk: self.embed_jwk,
+            thumbprint_kid: self.thumbprint_kid,
+            kid: self.kid,
+            proof_nonce: self.proof_nonce,
+            prehashed: self.prehashed,
+            canonical: self.canonical,
             payload: Payload(payload),
             signers: NoSigners,
         }
@@ -367,77 +1503,2135 @@ impl<P, S> JwsBuilder<P, S> {
         self
     }
 
+    /// Specify the `cty` header. Use `JWT` when the payload is itself a
+    /// compact JWS (a nested JWT).
+    #[must_use]
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Add a protected header parameter, such as `nonce` or `htm`. Parameters
+    /// are part of the signing input.
+    ///
+    /// Parameters represented by typed header fields (e.g. `alg` or `typ`)
+    /// cannot be set this way and cause the build to fail.
+    #[must_use]
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// Emit a signature for each of the signer's verification methods rather
+    /// than just the primary one.
+    ///
+    /// Supports key rotation: verifiers holding either the old or new key
+    /// can validate the JWS using [`Jws::verify_threshold`].
+    ///
+    /// Has no effect when the public key is embedded (see
+    /// [`JwsBuilder::embed_jwk`]): every method references the same key, so
+    /// each signer produces a single signature.
+    #[must_use]
+    pub const fn per_method(mut self, per_method: bool) -> Self {
+        self.per_method = per_method;
+        self
+    }
+
+    /// Embed the signer's public key in the header (`jwk`) rather than a
+    /// reference to its verification method (`kid`), producing a
+    /// self-contained JWS.
+    #[must_use]
+    pub const fn embed_jwk(mut self, embed_jwk: bool) -> Self {
+        self.embed_jwk = embed_jwk;
+        self
+    }
+
+    /// Sign the digest of the signing input, computed per the signer's
+    /// algorithm, using
+    /// [`Signer::try_sign_prehashed`](crate::Signer::try_sign_prehashed).
+    /// For signing backends that only sign digests. Not supported by `EdDSA`
+    /// or ML-DSA signers, which sign the full message.
+    #[must_use]
+    pub const fn prehashed(mut self, prehashed: bool) -> Self {
+        self.prehashed = prehashed;
+        self
+    }
+
+    /// Serialize the payload using the JSON Canonicalization Scheme
+    /// ([RFC8785]), so equivalent payloads are always encoded as the same
+    /// bytes, regardless of member order.
+    ///
+    /// [RFC8785]: https://www.rfc-editor.org/rfc/rfc8785
+    #[must_use]
+    pub const fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Embed the signer's public key in the header (`jwk`) along with a `kid`
+    /// set to the key's JWK Thumbprint ([RFC7638]).
+    ///
+    /// On verification, the `kid` is checked against the embedded key.
+    ///
+    /// [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
+    #[must_use]
+    pub const fn embed_jwk_with_kid(mut self) -> Self {
+        self.embed_jwk = true;
+        self.thumbprint_kid = true;
+        self
+    }
+
+    /// Use `kid` as the header `kid` rather than the signer's verification
+    /// method, which is then not retrieved. Intended for keys with no DID
+    /// verification method, such as internal or symmetric keys.
+    ///
+    /// Every signer uses the same `kid`. It is not used when the public key
+    /// is embedded (see [`JwsBuilder::embed_jwk`]).
+    #[must_use]
+    pub fn kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    /// Add the `iat` (the current time) and `nonce` claims required by an
+    /// `OpenID4VCI` proof JWT to the payload, which must be a JSON object.
+    ///
+    /// Requires the `typ` to be `openid4vci-proof+jwt` (see
+    /// [`JwsBuilder::jwt_type`]). Existing `iat` and `nonce` claims are
+    /// replaced.
+    #[must_use]
+    pub fn proof_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.proof_nonce = Some(nonce.into());
+        self
+    }
+}
+
+impl<P> JwsBuilder<P, NoSigners> {
     /// Logically (from user POV), sign the record.
     ///
     /// At this point, the builder simply captures the signer for use in the final
     /// build step. Can only be done if the content hasn't been signed yet.
     #[must_use]
-    pub fn add_signer(self, signer: &impl Signer) -> JwsBuilder<P, Signers<impl Signer>> {
+    pub fn add_signer(self, signer: &dyn DynSigner) -> JwsBuilder<P, Signers<'_>> {
         JwsBuilder {
             jwt_type: self.jwt_type,
+            content_type: self.content_type,
+            headers: self.headers,
+            per_method: self.per_method,
+            embed_jwk: self.embed_jwk,
+            thumbprint_kid: self.thumbprint_kid,
+            kid: self.kid,
+            proof_nonce: self.proof_nonce,
+            prehashed: self.prehashed,
+            canonical: self.canonical,
             payload: self.payload,
             signers: Signers(vec![signer]),
         }
     }
 }
 
-impl<T, S> JwsBuilder<Payload<T>, Signers<'_, S>>
+impl<'a, P> JwsBuilder<P, Signers<'a>> {
+    /// Add a further signer, producing an additional entry in the JWS
+    /// `signatures` array with the signer's own `alg` and `kid`.
+    ///
+    /// Signers may be of different types, for example a classical and a
+    /// post-quantum signer for a hybrid signature over the same payload.
+    /// Use [`Jws::verify_threshold`] to require all (or a quorum) verify.
+    #[must_use]
+    pub fn add_signer(mut self, signer: &'a dyn DynSigner) -> Self {
+        self.signers.0.push(signer);
+        self
+    }
+}
+
+impl<T, S> JwsBuilder<Payload<T>, S>
+where
+    T: Serialize + Send,
+{
+    // Additional header parameters, checked for collisions with typed fields.
+    fn extra_headers(&self) -> Result<Map<String, Value>> {
+        if let Some(key) = self.headers.keys().find(|k| TYPED_HEADERS.contains(&k.as_str())) {
+            bail!("header `{key}` cannot be set as an additional parameter");
+        }
+        Ok(self.headers.clone())
+    }
+
+    // The encoded payload, with `OpenID4VCI` proof claims added when a proof
+    // nonce is set.
+    fn encoded_payload(&self) -> Result<String> {
+        if self.proof_nonce.is_none() && !self.canonical {
+            return Ok(Base64UrlUnpadded::encode_string(&serde_json::to_vec(&self.payload.0)?));
+        }
+
+        let mut payload = serde_json::to_value(&self.payload.0)?;
+        if let Some(nonce) = &self.proof_nonce {
+            if !media_type(&self.jwt_type).eq_ignore_ascii_case(PROOF_TYPE) {
+                bail!("proof claims require the `typ` {PROOF_TYPE}");
+            }
+            let Value::Object(claims) = &mut payload else {
+                bail!("proof claims require a JSON object payload");
+            };
+            let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            claims.insert("iat".to_string(), iat.into());
+            claims.insert("nonce".to_string(), nonce.clone().into());
+        }
+
+        let bytes =
+            if self.canonical { jcs::to_vec(&payload)? } else { serde_json::to_vec(&payload)? };
+        Ok(Base64UrlUnpadded::encode_string(&bytes))
+    }
+
+    /// Generate the signing input (`base64url(header).base64url(payload)`)
+    /// for environments where signing is performed by a separate service.
+    ///
+    /// The returned protected header should be passed, along with the payload
+    /// and resulting signature, to [`Jws::from_parts`] to assemble the JWS.
+    ///
+    /// # Errors
+    /// Returns an error if the header or payload cannot be serialized.
+    pub fn signing_input(
+        &self, alg: Algorithm, verification_method: impl Into<String>,
+    ) -> Result<(Vec<u8>, Protected)> {
+        let protected = Protected {
+            alg,
+            typ: self.jwt_type.clone(),
+            cty: self.content_type.clone(),
+            key: Key::KeyId(verification_method.into()),
+            extra: self.extra_headers()?,
+            ..Protected::default()
+        };
+
+        let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected)?);
+        let payload = self.encoded_payload()?;
+
+        Ok((format!("{header}.{payload}").into_bytes(), protected))
+    }
+}
+
+impl<T> JwsBuilder<Payload<T>, Signers<'_>>
 where
     T: Serialize + Send,
-    S: Signer,
 {
-    /// Generate the JWS.
+    /// Generate the JWS, with a signature from each signer.
     ///
     /// # Errors
-    /// TODO: Add errors
+    /// Returns an error if a signer's key or verification method cannot be
+    /// retrieved, an embedded key does not match the signer's algorithm, or
+    /// signing fails.
+    #[tracing::instrument(level = "debug", skip_all, fields(alg, typ = %self.jwt_type, kid), err)]
     pub async fn build(self) -> Result<Jws> {
-        let Some(signer) = self.signers.0.first() else {
+        if self.signers.0.is_empty() {
             bail!("no signers found");
-        };
+        }
+
+        let extra = self.extra_headers()?;
+        let payload = self.encoded_payload()?;
+        let mut signatures = vec![];
+
+        for signer in &self.signers.0 {
+            let mut extra = extra.clone();
+            let alg = signer.algorithm();
+
+            // an embedded key is the same for every verification method
+            let keys = if self.embed_jwk {
+                let jwk = signer.public_key_jwk().await?;
+                if !alg.is_compatible(&jwk.crv) {
+                    bail!("algorithm {alg} is not valid for curve {:?}", jwk.crv);
+                }
+                if self.thumbprint_kid {
+                    extra.insert("kid".to_string(), Value::String(jwk.thumbprint()?));
+                }
+                vec![Key::Jwk(jwk)]
+            } else if let Some(kid) = &self.kid {
+                vec![Key::KeyId(kid.clone())]
+            } else if self.per_method {
+                signer.verification_methods().await?.into_iter().map(Key::KeyId).collect()
+            } else {
+                vec![Key::KeyId(signer.verification_method().await?)]
+            };
+
+            for key in keys {
+                let protected = Protected {
+                    alg: alg.clone(),
+                    typ: self.jwt_type.clone(),
+                    cty: self.content_type.clone(),
+                    key,
+                    extra: extra.clone(),
+                    ..Protected::default()
+                };
+
+                let span = tracing::Span::current();
+                span.record("alg", tracing::field::display(&protected.alg));
+                if let Some(kid) = protected.kid() {
+                    span.record("kid", kid);
+                }
+
+                let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected)?);
+                let signing_input = format!("{header}.{payload}");
+                let sig = if self.prehashed {
+                    signer.try_sign_prehashed(&alg.prehash(signing_input.as_bytes())?).await?
+                } else {
+                    signer.try_sign(signing_input.as_bytes()).await?
+                };
+
+                signatures.push(Signature {
+                    protected,
+                    protected_b64: Some(header),
+                    signature: Base64UrlUnpadded::encode_string(&sig),
+                    ..Signature::default()
+                });
+            }
+        }
+
+        Ok(Jws { payload, signatures })
+    }
+}
+
+/// Signs a large payload supplied incrementally, producing a JWS with a
+/// detached, unencoded payload ([RFC7797]).
+///
+/// The payload is written directly into the signing input without base64url
+/// encoding. How the signing input is held depends on the signer's algorithm:
+///
+/// - ECDSA (`ES256`, `ES256K`, `ES384`, `ES512`) signing inputs are hashed
+///   as chunks arrive and the digest is signed with
+///   [`DynSigner::try_sign_prehashed`], so memory use is constant. The
+///   signer must support prehashed signing.
+/// - `EdDSA` and ML-DSA sign the complete message, so the signing input is
+///   buffered and memory use remains proportional to the payload size.
+///
+/// The resulting JWS has an empty `payload`. To verify, set
+/// [`Jws::payload`] to the original (UTF-8) payload.
+///
+/// [RFC7797]: https://www.rfc-editor.org/rfc/rfc7797
+pub struct JwsStreamSigner<'a> {
+    signer: &'a dyn DynSigner,
+    protected: Protected,
+    header: String,
+    signing_input: SigningInput,
+}
+
+// Signing input accumulated by a `JwsStreamSigner`.
+enum SigningInput {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Buffered(Vec<u8>),
+}
+
+impl SigningInput {
+    fn new(alg: &Algorithm) -> Self {
+        match alg {
+            Algorithm::ES256 | Algorithm::ES256K => Self::Sha256(Sha256::new()),
+            Algorithm::ES384 => Self::Sha384(Sha384::new()),
+            Algorithm::ES512 => Self::Sha512(Sha512::new()),
+            Algorithm::EdDSA | Algorithm::MlDsa44 | Algorithm::MlDsa65 => {
+                Self::Buffered(Vec::new())
+            }
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(chunk),
+            Self::Sha384(hasher) => hasher.update(chunk),
+            Self::Sha512(hasher) => hasher.update(chunk),
+            Self::Buffered(buf) => buf.extend_from_slice(chunk),
+        }
+    }
+}
 
-        let verification_method = signer.verification_method().await?;
+impl<'a> JwsStreamSigner<'a> {
+    /// Start a new stream signed by `signer`, with `typ` set to `jwt_type`.
+    ///
+    /// # Errors
+    /// Returns an error if the signer's verification method cannot be
+    /// resolved or the header cannot be serialized.
+    pub async fn new(signer: &'a dyn DynSigner, jwt_type: impl Into<String>) -> Result<Self> {
         let protected = Protected {
             alg: signer.algorithm(),
-            typ: self.jwt_type,
-            key: Key::KeyId(verification_method),
+            typ: jwt_type.into(),
+            key: Key::KeyId(signer.verification_method().await?),
+            crit: Some(vec!["b64".to_string()]),
+            b64: Some(false),
             ..Protected::default()
         };
 
         let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected)?);
-        let payload = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&self.payload.0)?);
-        let sig = signer.try_sign(format!("{header}.{payload}").as_bytes()).await?;
+        let mut signing_input = SigningInput::new(&protected.alg);
+        signing_input.update(header.as_bytes());
+        signing_input.update(b".");
+
+        Ok(Self {
+            signer,
+            protected,
+            header,
+            signing_input,
+        })
+    }
+
+    /// Append a chunk of the payload.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.signing_input.update(chunk);
+    }
+
+    /// Sign the accumulated payload, returning a JWS with a detached payload.
+    ///
+    /// # Errors
+    /// Returns an error if signing fails, including when an ECDSA signer
+    /// does not support prehashed signing.
+    pub async fn finalize(self) -> Result<Jws> {
+        let sig = match self.signing_input {
+            SigningInput::Sha256(hasher) => {
+                self.signer.try_sign_prehashed(&hasher.finalize()).await?
+            }
+            SigningInput::Sha384(hasher) => {
+                self.signer.try_sign_prehashed(&hasher.finalize()).await?
+            }
+            SigningInput::Sha512(hasher) => {
+                self.signer.try_sign_prehashed(&hasher.finalize()).await?
+            }
+            SigningInput::Buffered(buf) => self.signer.try_sign(&buf).await?,
+        };
 
         Ok(Jws {
-            payload,
+            payload: String::new(),
             signatures: vec![Signature {
-                protected,
+                protected: self.protected,
+                protected_b64: Some(self.header),
                 signature: Base64UrlUnpadded::encode_string(&sig),
+                ..Signature::default()
             }],
         })
     }
 }
 
-mod base64url {
-    use base64ct::{Base64UrlUnpadded, Encoding};
-    use serde::de::DeserializeOwned;
-    use serde::{Deserialize, Serialize};
+impl io::Write for JwsStreamSigner<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
 
-    pub fn serialize<T, S>(value: T, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        T: Serialize,
-        S: serde::ser::Serializer,
-    {
-        let bytes = serde_json::to_vec(&value).map_err(serde::ser::Error::custom)?;
-        serializer.serialize_str(&Base64UrlUnpadded::encode_string(&bytes))
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
+}
 
-    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
-    where
-        T: DeserializeOwned,
-        D: serde::de::Deserializer<'de>,
-    {
-        let encoded = String::deserialize(deserializer)?;
-        let bytes = Base64UrlUnpadded::decode_vec(&encoded).map_err(serde::de::Error::custom)?;
-        serde_json::from_slice(&bytes).map_err(serde::de::Error::custom)
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+    use rand::rngs::OsRng;
+    use serde_json::{json, Value};
+    use x509_cert::der::Encode;
+
+    use super::*;
+    use crate::did::{DidDocument, VerificationMethod};
+    use crate::jose::jwk::KeyUse;
+    use crate::{Curve, KeyType, Signer, SyncSigner};
+
+    // Blocking signer wrapping an in-memory Ed25519 key.
+    struct Ed25519Signer {
+        signing_key: SigningKey,
+    }
+
+    impl Ed25519Signer {
+        fn new() -> Self {
+            Self {
+                signing_key: SigningKey::generate(&mut OsRng),
+            }
+        }
+
+        fn public_jwk(&self) -> PublicKeyJwk {
+            PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::Ed25519,
+                x: Base64UrlUnpadded::encode_string(self.signing_key.verifying_key().as_bytes()),
+                ..PublicKeyJwk::default()
+            }
+        }
+    }
+
+    impl SyncSigner for Ed25519Signer {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.signing_key.sign(msg).to_bytes().to_vec())
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            Ok(self.signing_key.verifying_key().as_bytes().to_vec())
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:alice#key-1".to_string())
+        }
+    }
+
+    // Blocking signer wrapping an in-memory secp256k1 key.
+    struct Es256kSigner {
+        signing_key: k256::ecdsa::SigningKey,
+    }
+
+    impl Es256kSigner {
+        fn new() -> Self {
+            Self {
+                signing_key: k256::ecdsa::SigningKey::random(&mut OsRng),
+            }
+        }
+
+        fn public_jwk(&self) -> PublicKeyJwk {
+            let point = self.signing_key.verifying_key().to_encoded_point(false);
+            PublicKeyJwk {
+                kty: KeyType::Ec,
+                crv: Curve::Es256K,
+                x: Base64UrlUnpadded::encode_string(point.x().expect("should have x")),
+                y: Some(Base64UrlUnpadded::encode_string(point.y().expect("should have y"))),
+                ..PublicKeyJwk::default()
+            }
+        }
+    }
+
+    impl SyncSigner for Es256kSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            let sig: k256::ecdsa::Signature = self.signing_key.sign(msg);
+            Ok(sig.to_vec())
+        }
+
+        fn try_sign_prehashed(&self, digest: &[u8]) -> Result<Vec<u8>> {
+            let sig: k256::ecdsa::Signature = self.signing_key.sign_prehash(digest)?;
+            Ok(sig.to_vec())
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            Ok(self.signing_key.verifying_key().to_sec1_bytes().to_vec())
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::ES256K
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:alice#key-1".to_string())
+        }
+    }
+
+    // Signer referencing both an old and a new verification method.
+    struct RotatingSigner(Ed25519Signer);
+
+    impl SyncSigner for RotatingSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            SyncSigner::try_sign(&self.0, msg)
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            SyncSigner::verifying_key(&self.0)
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:alice#key-2".to_string())
+        }
+
+        fn verification_methods(&self) -> Result<Vec<String>> {
+            Ok(vec!["did:example:alice#key-1".to_string(), "did:example:alice#key-2".to_string()])
+        }
+    }
+
+    // Signer with no verification method, such as an internal service key.
+    struct InternalSigner(Ed25519Signer);
+
+    impl SyncSigner for InternalSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            SyncSigner::try_sign(&self.0, msg)
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            SyncSigner::verifying_key(&self.0)
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Err(anyhow!("no verification method"))
+        }
+    }
+
+    #[tokio::test]
+    async fn explicit_kid() {
+        let signer = InternalSigner(Ed25519Signer::new());
+        let builder = JwsBuilder::new().payload("hello");
+        assert!(builder.add_signer(&signer).build().await.is_err());
+
+        let jws = JwsBuilder::new()
+            .payload("hello")
+            .kid("internal-1")
+            .add_signer(&signer)
+            .per_method(true)
+            .build()
+            .await
+            .expect("should build");
+        assert_eq!(jws.signatures.len(), 1);
+        assert_eq!(jws.signatures[0].kid(), Some("internal-1"));
+
+        let jwk = signer.0.public_jwk();
+        jws.verify(|kid| {
+            assert_eq!(kid, "internal-1");
+            let jwk = jwk.clone();
+            async { Ok(jwk) }
+        })
+        .await
+        .expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn rotation() {
+        let signer = RotatingSigner(Ed25519Signer::new());
+        let claims = json!({"iss": "did:example:alice"});
+
+        let jws = JwsBuilder::new()
+            .payload(&claims)
+            .add_signer(&signer)
+            .per_method(true)
+            .build()
+            .await
+            .expect("should build");
+        assert_eq!(jws.signatures.len(), 2);
+
+        // verifier only able to resolve the new key
+        let jwk = signer.0.public_jwk();
+        let resolver = |kid: String| {
+            let jwk = jwk.clone();
+            async move {
+                if kid == "did:example:alice#key-2" {
+                    return Ok(jwk);
+                }
+                bail!("unknown key")
+            }
+        };
+
+        jws.verify_threshold(resolver, 1).await.expect("should verify one");
+        assert!(jws.verify_threshold(resolver, 2).await.is_err());
+
+        // an embedded key is signed once, not once per method
+        let jws = JwsBuilder::new()
+            .payload(&claims)
+            .add_signer(&signer)
+            .per_method(true)
+            .embed_jwk(true)
+            .build()
+            .await
+            .expect("should build");
+        assert_eq!(jws.signatures.len(), 1);
+        assert_eq!(jws.signatures[0].protected.key, Key::Jwk(signer.0.public_jwk()));
+    }
+
+    #[tokio::test]
+    async fn detailed() {
+        let signer = RotatingSigner(Ed25519Signer::new());
+        let jws = JwsBuilder::new()
+            .payload(&json!({"iss": "did:example:alice"}))
+            .add_signer(&signer)
+            .per_method(true)
+            .build()
+            .await
+            .expect("should build");
+
+        let jwk = signer.0.public_jwk();
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let verified = jws.verify_detailed(resolver).await.expect("should verify");
+        assert_eq!(verified.len(), 2);
+        assert_eq!(verified[1].signature_index, 1);
+        assert_eq!(verified[1].key, signer.0.public_jwk());
+        assert_eq!(verified[1].protected.kid(), Some("did:example:alice#key-2"));
+    }
+
+    #[tokio::test]
+    async fn external_signer() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice"});
+
+        let builder = JwsBuilder::new().payload(&claims);
+        let (input, protected) = builder
+            .signing_input(Algorithm::EdDSA, "did:example:alice#key-1")
+            .expect("should build");
+
+        // sign out-of-band
+        let sig = signer.signing_key.sign(&input).to_bytes();
+
+        let jws = Jws::from_parts(protected, &claims, &sig).expect("should assemble");
+        let jwk = signer.public_jwk();
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn to_compact() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice"});
+
+        let jws = JwsBuilder::new()
+            .payload(&claims)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let compact = jws.to_compact().expect("should serialize");
+        let parsed: Jws = compact.parse().expect("should parse");
+        assert_eq!(parsed, jws);
+
+        let mut multi = jws.clone();
+        multi.signatures.push(jws.signatures[0].clone());
+        assert!(multi.to_compact().is_err());
+        assert!(Jws::default().to_compact().is_err());
+    }
+
+    #[tokio::test]
+    async fn with_key() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice"});
+        let token = encode(&claims, &signer).await.expect("should encode");
+
+        let jwt: Jwt<Value> = decode_with_key(&token, &signer.public_jwk()).expect("should decode");
+        assert_eq!(jwt.claims, claims);
+
+        let other = Ed25519Signer::new();
+        assert!(decode_with_key::<Value>(&token, &other.public_jwk()).is_err());
+
+        // encryption keys are refused
+        let enc_jwk = PublicKeyJwk {
+            use_: Some(KeyUse::Encryption),
+            ..signer.public_jwk()
+        };
+        assert!(decode_with_key::<Value>(&token, &enc_jwk).is_err());
+    }
+
+    struct Resolver(PublicKeyJwk);
+
+    impl DidResolver for Resolver {
+        fn resolve(&self, did: &str) -> impl Future<Output = Result<DidDocument>> + Send {
+            let document = (did == "did:example:alice").then(|| DidDocument {
+                id: did.to_string(),
+                verification_method: vec![VerificationMethod {
+                    id: "#key-1".to_string(),
+                    type_: "JsonWebKey2020".to_string(),
+                    controller: did.to_string(),
+                    public_key_jwk: Some(self.0.clone()),
+                    ..VerificationMethod::default()
+                }],
+            });
+            std::future::ready(document.ok_or_else(|| anyhow!("unknown DID {did}")))
+        }
+    }
+
+    #[tokio::test]
+    async fn did_resolver() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice"});
+        let token = encode(&claims, &signer).await.expect("should encode");
+
+        let resolver = Resolver(signer.public_jwk());
+        let jwt: Jwt<Value> = decode_with_resolver(&token, &resolver).await.expect("should decode");
+        assert_eq!(jwt.claims, claims);
+
+        let resolver = Resolver(Ed25519Signer::new().public_jwk());
+        assert!(decode_with_resolver::<Value>(&token, &resolver).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn typed() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "alice"});
+        let token = JwsBuilder::new()
+            .jwt_type("openid4vci-proof+jwt")
+            .payload(&claims)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+
+        let jwk = signer.public_jwk();
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let expected = ["application/openid4vci-proof+JWT"];
+        let jwt: Jwt<Value> =
+            decode_typed(&token, resolver, &expected).await.expect("should decode");
+        assert_eq!(jwt.claims, claims);
+
+        // a general-purpose JWT is rejected
+        let token = encode(&claims, &signer).await.expect("should encode");
+        let err = decode_typed::<_, _, Value>(&token, resolver, &expected)
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "unexpected `typ` header: jwt");
+    }
+
+    #[tokio::test]
+    async fn extra_headers() {
+        let signer = Ed25519Signer::new();
+        let token = JwsBuilder::new()
+            .header("nonce", "n-0S6_WzA2Mj")
+            .header("htm", "POST")
+            .payload(&json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+
+        let jwt: Jwt<Value> = decode_with_key(&token, &signer.public_jwk()).expect("should decode");
+        assert_eq!(jwt.header.extra["nonce"], "n-0S6_WzA2Mj");
+        assert_eq!(jwt.header.extra["htm"], "POST");
+        assert!(!jwt.header.extra.contains_key("kid"));
+
+        let result = JwsBuilder::new()
+            .header("alg", "none")
+            .payload(&json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await;
+        assert_eq!(
+            result.expect_err("should fail").to_string(),
+            "header `alg` cannot be set as an additional parameter"
+        );
+    }
+
+    #[test]
+    fn unknown_type() {
+        let header = Base64UrlUnpadded::encode_string(
+            br#"{"alg":"EdDSA","typ":"dc+sd-jwt","kid":"did:example:alice#key-1"}"#,
+        );
+        let protected = peek_header(&format!("{header}.e30.c2ln")).expect("should parse");
+        assert_eq!(protected.typ, "dc+sd-jwt");
+
+        let json = serde_json::to_value(&protected).expect("should serialize");
+        assert_eq!(json["typ"], "dc+sd-jwt");
+    }
+
+    #[tokio::test]
+    async fn peek() {
+        let signer = Ed25519Signer::new();
+        let token = encode(&json!({"iss": "alice"}), &signer).await.expect("should encode");
+
+        let header = peek_header(&token).expect("should peek");
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        assert_eq!(header.kid(), Some("did:example:alice#key-1"));
+        assert!(peek_header("not-a-jws").is_err());
+
+        let claims: Value = peek_payload(&token).expect("should peek");
+        assert_eq!(claims["iss"], "alice");
+    }
+
+    #[tokio::test]
+    async fn sync_verify() {
+        let signer = Ed25519Signer::new();
+        let token = encode(&json!({"iss": "alice"}), &signer).await.expect("should encode");
+
+        verify_sync(&token, &signer.public_jwk()).expect("should verify");
+        assert!(verify_sync(&token, &Ed25519Signer::new().public_jwk()).is_err());
+
+        // the key is validated before it is used
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let weak = PublicKeyJwk {
+            x: Base64UrlUnpadded::encode_string(&identity),
+            ..signer.public_jwk()
+        };
+        let err = verify_sync(&token, &weak).expect_err("should fail");
+        assert_eq!(err.to_string(), "Ed25519 key is a small-order point");
+    }
+
+    #[tokio::test]
+    async fn eddsa_ed25519() {
+        let signer = Ed25519Signer::new();
+        let token = encode(&json!({"iss": "alice"}), &signer).await.expect("should encode");
+        decode_with_key::<Value>(&token, &signer.public_jwk()).expect("should decode");
+    }
+
+    #[tokio::test]
+    async fn es256k_secp256k1() {
+        let signer = Es256kSigner::new();
+        let token = encode(&json!({"iss": "alice"}), &signer).await.expect("should encode");
+        decode_with_key::<Value>(&token, &signer.public_jwk()).expect("should decode");
+    }
+
+    #[tokio::test]
+    async fn es256k_low_s() {
+        let signer = Es256kSigner::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        // flip to the (equally valid) high-S form
+        let sig = Base64UrlUnpadded::decode_vec(&jws.signatures[0].signature).unwrap();
+        let sig = k256::ecdsa::Signature::from_slice(&sig).unwrap();
+        let (r, s) = sig.split_scalars();
+        let high_s = k256::ecdsa::Signature::from_scalars(r, -s).unwrap();
+        let mut malleated = jws.clone();
+        malleated.signatures[0].signature = Base64UrlUnpadded::encode_string(&high_s.to_bytes());
+
+        let jwk = signer.public_jwk();
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+        let strict = VerifyOptions::default().require_low_s(true);
+
+        malleated.verify(resolver).await.expect("should normalize by default");
+        jws.verify_with_options(resolver, &strict).await.expect("should verify low-S");
+        let err =
+            malleated.verify_with_options(resolver, &strict).await.expect_err("should reject");
+        assert_eq!(err.to_string(), "signature is not low-S normalized");
+    }
+
+    // RFC6979 §A.2.6 known-answer vector: P-384, SHA-384, message "sample".
+    const P384_X: &str = "EC3A4E415B4E19A4568618029F427FA5DA9A8BC4AE92E02E06AAE5286B300C64DEF8F0EA9055866064A254515480BC13";
+    const P384_Y: &str = "8015D9B72D7D57244EA8EF9AC0C621896708A59367F9DFB9F54CA84B3F1C9DB1288B231C3AE0D4FE7344FD2533264720";
+    const P384_R: &str = "94EDBB92A5ECB8AAD4736E56C691916B3F88140666CE9FA73D64C4EA95AD133C81A648152E44ACF96E36DD1E80FABE46";
+    const P384_S: &str = "99EF4AEB15F178CEA1FE40DB2603138F130E740A19624526203B6351D0A3A94FA329C145786E679E7B82C71A38628AC8";
+
+    #[test]
+    fn es384_p384() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::P384,
+            x: Base64UrlUnpadded::encode_string(&hex::decode(P384_X).unwrap()),
+            y: Some(Base64UrlUnpadded::encode_string(&hex::decode(P384_Y).unwrap())),
+            ..PublicKeyJwk::default()
+        };
+        let sig = hex::decode(format!("{P384_R}{P384_S}")).unwrap();
+
+        jwk.verify("sample", &sig).expect("should verify");
+        assert!(jwk.verify("test", &sig).is_err());
+    }
+
+    #[test]
+    fn es256_p256() {
+        let signing_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let sec1 = signing_key.verifying_key().to_encoded_point(true);
+        let jwk =
+            PublicKeyJwk::from_bytes(sec1.as_bytes(), &Algorithm::ES256).expect("should convert");
+
+        let builder = JwsBuilder::new().payload(json!({"iss": "alice"}));
+        let (input, protected) = builder
+            .signing_input(Algorithm::ES256, "did:example:alice#key-1")
+            .expect("should build");
+        let sig: p256::ecdsa::Signature = signing_key.sign(&input);
+        let token = Jws::from_parts(protected, &json!({"iss": "alice"}), &sig.to_bytes())
+            .and_then(|jws| jws.to_compact())
+            .expect("should assemble");
+
+        decode_with_key::<Value>(&token, &jwk).expect("should decode");
+    }
+
+    #[test]
+    fn es512_p521() {
+        let signing_key = p521::ecdsa::SigningKey::random(&mut OsRng);
+        let sec1 = p521::ecdsa::VerifyingKey::from(&signing_key).to_encoded_point(true);
+        let jwk =
+            PublicKeyJwk::from_bytes(sec1.as_bytes(), &Algorithm::ES512).expect("should convert");
+
+        let builder = JwsBuilder::new().payload(json!({"iss": "alice"}));
+        let (input, protected) = builder
+            .signing_input(Algorithm::ES512, "did:example:alice#key-1")
+            .expect("should build");
+        let sig: p521::ecdsa::Signature = signing_key.sign(&input);
+        let token = Jws::from_parts(protected, &json!({"iss": "alice"}), &sig.to_bytes())
+            .and_then(|jws| jws.to_compact())
+            .expect("should assemble");
+
+        decode_with_key::<Value>(&token, &jwk).expect("should decode");
+    }
+
+    // Ed25519 key presented while the header claims ES256K.
+    #[tokio::test]
+    async fn alg_curve_mismatch() {
+        let signer = Ed25519Signer::new();
+        let builder = JwsBuilder::new().payload(json!({"iss": "alice"}));
+        let (input, protected) = builder
+            .signing_input(Algorithm::ES256K, "did:example:alice#key-1")
+            .expect("should build");
+        let sig = signer.signing_key.sign(&input).to_bytes();
+        let token = Jws::from_parts(protected, &json!({"iss": "alice"}), &sig)
+            .and_then(|jws| jws.to_compact())
+            .expect("should assemble");
+
+        let err = decode_with_key::<Value>(&token, &signer.public_jwk())
+            .expect_err("should reject mismatched algorithm");
+        assert_eq!(err.to_string(), "algorithm ES256K is not valid for curve Ed25519");
+    }
+
+    // Sign using an arbitrary protected header and (unencoded) payload.
+    fn sign_raw(signer: &Ed25519Signer, protected: Protected, payload: &str) -> Jws {
+        let header = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&protected).unwrap());
+        let sig = signer.signing_key.sign(format!("{header}.{payload}").as_bytes());
+        Jws {
+            payload: payload.to_string(),
+            signatures: vec![Signature {
+                protected,
+                signature: Base64UrlUnpadded::encode_string(&sig.to_bytes()),
+                ..Signature::default()
+            }],
+        }
+    }
+
+    // The signature covers the header as received, not as this crate would
+    // serialize it.
+    #[tokio::test]
+    async fn non_canonical_header() {
+        let signer = Ed25519Signer::new();
+        let header = Base64UrlUnpadded::encode_string(
+            br#"{ "typ": "JWT", "kid": "did:example:alice#key-1", "alg": "EdDSA" }"#,
+        );
+        let payload = Base64UrlUnpadded::encode_string(br#"{"iss":"alice"}"#);
+        let sig = signer.signing_key.sign(format!("{header}.{payload}").as_bytes());
+        let token =
+            format!("{header}.{payload}.{}", Base64UrlUnpadded::encode_string(&sig.to_bytes()));
+
+        let jwk = signer.public_jwk();
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+        let jwt: Jwt<Value> = decode(&token, resolver).await.expect("should decode");
+        assert_eq!(jwt.claims, json!({"iss": "alice"}));
+        decode_with_key::<Value>(&token, &jwk).expect("should decode");
+
+        // re-serializing keeps the received header
+        let jws: Jws = token.parse().expect("should parse");
+        assert_eq!(jws.to_compact().expect("should serialize"), token);
+        let json = Jws::from_json_str(&jws.to_json_string().expect("should serialize"))
+            .expect("should parse");
+        json.verify(resolver).await.expect("should verify");
+
+        // a modified header is re-encoded, invalidating the signature
+        let mut tampered = jws;
+        tampered.signatures[0].protected.typ = "dpop+jwt".to_string();
+        assert!(tampered.verify(resolver).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn crit_b64() {
+        let signer = Ed25519Signer::new();
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            key: Key::KeyId("did:example:alice#key-1".to_string()),
+            crit: Some(vec!["b64".to_string()]),
+            b64: Some(false),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected, r#"{"iss":"alice"}"#);
+
+        let jwk = signer.public_jwk();
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+
+        let jwt: Jwt<Value> = jws.jwt(false).expect("should decode claims");
+        assert_eq!(jwt.claims, json!({"iss": "alice"}));
+    }
+
+    #[tokio::test]
+    async fn signer_algorithm() {
+        // a non-default algorithm must not be replaced by the default
+        let signer = Es256kSigner::new();
+        assert_ne!(SyncSigner::algorithm(&signer), Algorithm::default());
+
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+        let compact = jws.to_compact().expect("should serialize");
+        let parsed: Jws = compact.parse().expect("should parse");
+        assert_eq!(parsed.signatures[0].protected.alg, Algorithm::ES256K);
+
+        let batch = batch_sign(&[json!({"iss": "alice"})], &signer).await.expect("should sign");
+        assert_eq!(batch[0].signatures[0].protected.alg, Algorithm::ES256K);
+
+        let stream = JwsStreamSigner::new(&signer, "jwt").await.expect("should create");
+        let jws = stream.finalize().await.expect("should sign");
+        assert_eq!(jws.signatures[0].protected.alg, Algorithm::ES256K);
+    }
+
+    #[tokio::test]
+    async fn verification_key() {
+        let signer = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let multibase = signer.public_jwk().to_multibase().expect("should encode");
+        jws.verify_verification_key(|_| {
+            let key = VerificationKey::Multibase(multibase.clone());
+            async move { Ok(key) }
+        })
+        .await
+        .expect("should verify multibase key");
+
+        let jwk = signer.public_jwk();
+        jws.verify_verification_key(|_| {
+            let key = VerificationKey::from(jwk.clone());
+            async move { Ok(key) }
+        })
+        .await
+        .expect("should verify JWK");
+
+        let other = Ed25519Signer::new().public_jwk().to_multibase().expect("should encode");
+        assert!(jws
+            .verify_verification_key(|_| {
+                let key = VerificationKey::Multibase(other.clone());
+                async move { Ok(key) }
+            })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn pinned() {
+        let signer = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let jwk = signer.public_jwk();
+        let pin = jwk.thumbprint().expect("should compute thumbprint");
+        jws.verify_pinned(|_| async { Ok(signer.public_jwk()) }, &pin)
+            .await
+            .expect("should verify");
+
+        // a valid signature from an unpinned key is rejected
+        let other = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&other)
+            .build()
+            .await
+            .expect("should build");
+        let err = jws
+            .verify_pinned(|_| async { Ok(other.public_jwk()) }, &pin)
+            .await
+            .expect_err("should not verify");
+        let mismatch =
+            err.downcast::<ThumbprintMismatch>().expect("should be a ThumbprintMismatch");
+        assert_eq!(mismatch.expected, pin);
+    }
+
+    #[tokio::test]
+    async fn prehashed() {
+        let signer = Es256kSigner::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .prehashed(true)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+        let compact = jws.to_compact().expect("should serialize");
+        decode_with_key::<Value>(&compact, &signer.public_jwk()).expect("should verify");
+
+        // EdDSA signs the full message
+        let err = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .prehashed(true)
+            .add_signer(&Ed25519Signer::new())
+            .build()
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "EdDSA does not support prehashed signing");
+    }
+
+    #[tokio::test]
+    async fn canonical() {
+        #[derive(Serialize)]
+        struct Forward {
+            iss: &'static str,
+            amount: f64,
+        }
+        #[derive(Serialize)]
+        struct Reverse {
+            amount: f64,
+            iss: &'static str,
+        }
+
+        let signer = Ed25519Signer::new();
+        let forward = JwsBuilder::new()
+            .payload(Forward {
+                iss: "alice",
+                amount: 1e21,
+            })
+            .canonical(true)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+        let reverse = JwsBuilder::new()
+            .payload(Reverse {
+                amount: 1e21,
+                iss: "alice",
+            })
+            .canonical(true)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        assert_eq!(forward.payload, reverse.payload);
+        let payload = Base64UrlUnpadded::decode_vec(&forward.payload).expect("should decode");
+        assert_eq!(payload, br#"{"amount":1e+21,"iss":"alice"}"#);
+    }
+
+    #[tokio::test]
+    async fn stream_signer() {
+        let signer = Ed25519Signer::new();
+        let payload = r#"{"iss":"alice","manifest":["a","b","c"]}"#;
+
+        let mut stream = JwsStreamSigner::new(&signer, "jwt").await.expect("should create");
+        for chunk in payload.as_bytes().chunks(7) {
+            stream.update(chunk);
+        }
+        let mut jws = stream.finalize().await.expect("should sign");
+        assert_eq!(jws.payload, "");
+        assert!(jws.to_compact().expect("should serialize").contains(".."));
+
+        // reattach the detached payload to verify
+        let jwk = signer.public_jwk();
+        jws.payload = payload.to_string();
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+
+        jws.payload = payload.replace("alice", "bob");
+        assert!(jws.verify(|_| async { Ok(jwk.clone()) }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_signer_prehashed() {
+        let signer = Es256kSigner::new();
+        let payload = r#"{"iss":"alice","manifest":["a","b","c"]}"#;
+
+        let mut stream = JwsStreamSigner::new(&signer, "jwt").await.expect("should create");
+        for chunk in payload.as_bytes().chunks(7) {
+            stream.update(chunk);
+        }
+        let mut jws = stream.finalize().await.expect("should sign");
+        assert_eq!(jws.signatures[0].protected.alg, Algorithm::ES256K);
+
+        let jwk = signer.public_jwk();
+        jws.payload = payload.to_string();
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+
+        jws.payload = payload.replace("alice", "bob");
+        assert!(jws.verify(|_| async { Ok(jwk.clone()) }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_detached() {
+        let signer = Ed25519Signer::new();
+        let jwk = signer.public_jwk();
+        let payload = br#"{"iss":"alice"}"#;
+
+        // unencoded (b64: false)
+        let mut stream = JwsStreamSigner::new(&signer, "jwt").await.expect("should create");
+        stream.update(payload);
+        let jws = stream.finalize().await.expect("should sign");
+        jws.verify_detached(payload, |_| async { Ok(jwk.clone()) }).await.expect("should verify");
+        assert!(jws.verify_detached(b"{}", |_| async { Ok(jwk.clone()) }).await.is_err());
+
+        // base64url encoded
+        let mut jws = JwsBuilder::new()
+            .payload(json!({"iss": "alice"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+        let err = jws
+            .verify_detached(payload, |_| async { Ok(jwk.clone()) })
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "detached JWS must not carry an inline payload");
+
+        jws.payload = String::new();
+        jws.verify_detached(payload, |_| async { Ok(jwk.clone()) }).await.expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn multi_key() {
+        let signer = Ed25519Signer::new();
+        let current = signer.public_jwk();
+        let retired = Ed25519Signer::new().public_jwk();
+        let token = encode(&json!({"exp": 1}), &signer).await.expect("should encode");
+
+        let candidates = vec![retired.clone(), current];
+        let jwt: Jwt<Value> = decode_multi_key(&token, |_| async { Ok(candidates.clone()) })
+            .await
+            .expect("should decode");
+        assert_eq!(jwt.claims, json!({"exp": 1}));
+
+        let result =
+            decode_multi_key::<_, _, Value>(&token, |_| async { Ok(vec![retired.clone()]) }).await;
+        assert!(result.is_err());
+
+        let err = decode_multi_key::<_, _, Value>(&token, |_| async { Ok(vec![]) })
+            .await
+            .expect_err("should fail");
+        assert!(err.to_string().starts_with("no keys resolved for"));
+    }
+
+    #[tokio::test]
+    async fn duplicate_members() {
+        let signer = Ed25519Signer::new();
+        let jwk = signer.public_jwk();
+        let compact = |header: &str, payload: &str| {
+            let header = Base64UrlUnpadded::encode_string(header.as_bytes());
+            let payload = Base64UrlUnpadded::encode_string(payload.as_bytes());
+            let sig = signer.signing_key.sign(format!("{header}.{payload}").as_bytes());
+            format!("{header}.{payload}.{}", Base64UrlUnpadded::encode_string(&sig.to_bytes()))
+        };
+
+        let token = encode(&json!({"exp": 1}), &signer).await.expect("should encode");
+        let jwt: Jwt<Value> =
+            decode_strict(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(jwt.claims, json!({"exp": 1}));
+
+        let token = compact(
+            r#"{"alg":"EdDSA","typ":"jwt","alg":"ES256K","kid":"did:example:alice#key-1"}"#,
+            r#"{"exp":1}"#,
+        );
+        let err = decode_strict::<_, _, Value>(&token, |_| async { Ok(jwk.clone()) })
+            .await
+            .expect_err("should fail");
+        assert!(err.to_string().starts_with("invalid header: duplicate member `alg`"));
+
+        // duplicates are detected in nested objects
+        let token = compact(
+            r#"{"alg":"EdDSA","typ":"jwt","kid":"did:example:alice#key-1"}"#,
+            r#"{"vc":{"exp":1,"exp":9999999999}}"#,
+        );
+        let err = decode_strict::<_, _, Value>(&token, |_| async { Ok(jwk.clone()) })
+            .await
+            .expect_err("should fail");
+        assert!(err.to_string().starts_with("invalid claims: duplicate member `exp`"));
+
+        // the default decode silently keeps the last value
+        let token = compact(
+            r#"{"alg":"EdDSA","typ":"jwt","kid":"did:example:alice#key-1"}"#,
+            r#"{"exp":1,"exp":9999999999}"#,
+        );
+        let jwt: Jwt<Value> =
+            decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(jwt.claims, json!({"exp": 9_999_999_999_u64}));
+    }
+
+    #[tokio::test]
+    async fn crit_unknown() {
+        let signer = Ed25519Signer::new();
+        let jwk = signer.public_jwk();
+
+        // unsupported critical header
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            key: Key::KeyId("did:example:alice#key-1".to_string()),
+            crit: Some(vec!["exp".to_string()]),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected.clone(), "e30");
+        let err = jws.verify(|_| async { Ok(jwk.clone()) }).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "unsupported critical header: exp");
+
+        // listed but absent
+        let protected = Protected {
+            crit: Some(vec!["b64".to_string()]),
+            ..protected
+        };
+        let jws = sign_raw(&signer, protected.clone(), "e30");
+        let err = jws.verify(|_| async { Ok(jwk.clone()) }).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "critical header b64 is missing");
+
+        // empty
+        let protected = Protected {
+            crit: Some(vec![]),
+            ..protected
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        assert!(jws.verify(|_| async { Ok(jwk.clone()) }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn jwk_alg() {
+        use crate::jose::jwe::KeyAlgorithm;
+        use crate::jose::jwk::JwkAlgorithm;
+
+        let signer = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .payload("hello")
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let jwk = PublicKeyJwk {
+            alg: Some(Algorithm::EdDSA.into()),
+            ..signer.public_jwk()
+        };
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+
+        // a key declared for another algorithm is rejected
+        let jwk = PublicKeyJwk {
+            alg: Some(KeyAlgorithm::EcdhEs.into()),
+            ..jwk
+        };
+        let err = jws.verify(|_| async { Ok(jwk.clone()) }).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "key management `alg` is not valid for curve Ed25519");
+
+        let compact = jws.to_compact().expect("should encode");
+        assert!(verify_compact(&compact, &jwk, &VerifyOptions::default()).is_err());
+
+        // `alg` deserializes as either kind of algorithm
+        let json = serde_json::to_value(&jwk).expect("should serialize");
+        assert_eq!(json["alg"], "ECDH-ES");
+        let alg: JwkAlgorithm = serde_json::from_value("EdDSA".into()).expect("should deserialize");
+        assert_eq!(alg, JwkAlgorithm::Signature(Algorithm::EdDSA));
+    }
+
+    #[tokio::test]
+    async fn unknown_headers() {
+        let signer = Ed25519Signer::new();
+        let jwk = signer.public_jwk();
+        let strict = VerifyOptions::default().reject_unknown_headers(true);
+
+        let jws = JwsBuilder::new()
+            .payload("hello")
+            .header("alg2", "none")
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        // ignored by default
+        jws.verify(|_| async { Ok(jwk.clone()) }).await.expect("should verify");
+
+        let err = jws
+            .verify_with_options(|_| async { Ok(jwk.clone()) }, &strict)
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "unknown header parameter: alg2");
+
+        let compact = jws.to_compact().expect("should encode");
+        let err = verify_compact(&compact, &jwk, &strict).expect_err("should fail");
+        assert_eq!(err.to_string(), "unknown header parameter: alg2");
+
+        // `kid` alongside an embedded `jwk` is a defined parameter
+        let jws = JwsBuilder::new()
+            .payload("hello")
+            .embed_jwk_with_kid()
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+        let compact = jws.to_compact().expect("should encode");
+        verify_compact(&compact, &jwk, &strict).expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn unprotected_kid() {
+        let signer = Ed25519Signer::new();
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            ..Protected::default()
+        };
+        let mut jws = sign_raw(&signer, protected, "e30");
+
+        let jwk = signer.public_jwk();
+        let resolver = |kid: String| {
+            let jwk = jwk.clone();
+            async move {
+                assert_eq!(kid, "did:example:alice#key-1");
+                Ok(jwk)
+            }
+        };
+        assert!(jws.verify(resolver).await.is_err());
+
+        // kid hint in unprotected header is not part of the signing input
+        jws.signatures[0].header =
+            Some(HashMap::from([("kid".to_string(), json!("did:example:alice#key-1"))]));
+        jws.verify(resolver).await.expect("should verify");
+
+        let json = serde_json::to_value(&jws).expect("should serialize");
+        assert_eq!(json["signatures"][0]["header"]["kid"], "did:example:alice#key-1");
+    }
+
+    #[tokio::test]
+    async fn lenient_payload() {
+        let signer = Ed25519Signer::new();
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            key: Key::KeyId("did:example:alice#key-1".to_string()),
+            ..Protected::default()
+        };
+        let claims = json!({"iss": "alice!"});
+        let payload = Base64::encode_string(&serde_json::to_vec(&claims).unwrap());
+        assert!(payload.ends_with('='));
+
+        let token = sign_raw(&signer, protected, &payload).to_compact().expect("should encode");
+        let jwk = signer.public_jwk();
+
+        assert!(decode::<_, _, Value>(&token, |_| async { Ok(jwk.clone()) }).await.is_err());
+
+        let jwt: Jwt<Value> =
+            decode_lenient(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(jwt.claims, claims);
+    }
+
+    #[tokio::test]
+    async fn validated() {
+        let signer = Ed25519Signer::new();
+        let jwk = signer.public_jwk();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let claims = json!({"iss": "alice", "exp": now - 3});
+        let token = encode(&claims, &signer).await.expect("should encode");
+
+        let validation = Validation::default();
+        let result: Result<Jwt<Value>> =
+            decode_validated(&token, |_| async { Ok(jwk.clone()) }, &validation).await;
+        assert_eq!(result.expect_err("should be expired").to_string(), "token has expired");
+
+        let validation = Validation {
+            leeway: Duration::from_secs(30),
+            ..Validation::default()
+        };
+        let jwt: Jwt<Value> = decode_validated(&token, |_| async { Ok(jwk.clone()) }, &validation)
+            .await
+            .expect("should decode");
+        assert_eq!(jwt.claims, claims);
+    }
+
+    #[test]
+    fn x5c() {
+        let signer = Ed25519Signer::new();
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let root = x509::tests::root(&ca_key, "CN=Root CA");
+        let leaf = x509::tests::issue(&ca_key, "CN=Root CA", "CN=Issuer", &signer.signing_key);
+
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            x5c: Some(vec![Base64::encode_string(&leaf.to_der().unwrap())]),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        jws.verify_x5c(&[root]).expect("should verify");
+
+        // untrusted anchor
+        let other = x509::tests::root(&p256::ecdsa::SigningKey::random(&mut OsRng), "CN=Other");
+        assert!(jws.verify_x5c(&[other]).is_err());
+    }
+
+    #[test]
+    fn x5c_wrong_key() {
+        let signer = Ed25519Signer::new();
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let root = x509::tests::root(&ca_key, "CN=Root CA");
+
+        // certificate for a different key
+        let other = SigningKey::generate(&mut OsRng);
+        let leaf = x509::tests::issue(&ca_key, "CN=Root CA", "CN=Issuer", &other);
+
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            x5c: Some(vec![Base64::encode_string(&leaf.to_der().unwrap())]),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        assert!(jws.verify_x5c(&[root]).is_err());
+    }
+
+    #[test]
+    fn x5t_s256() {
+        let signer = Ed25519Signer::new();
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let root = x509::tests::root(&ca_key, "CN=Root CA");
+        let leaf = x509::tests::issue(&ca_key, "CN=Root CA", "CN=Issuer", &signer.signing_key);
+        let thumbprint = x509::thumbprint_s256(&leaf).expect("should hash");
+
+        // serialized using the registered member name
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            x5c: Some(vec![Base64::encode_string(&leaf.to_der().unwrap())]),
+            x5t_s256: Some(thumbprint.clone()),
+            ..Protected::default()
+        };
+        let json = serde_json::to_value(&protected).expect("should serialize");
+        assert_eq!(json["x5t#S256"], thumbprint);
+
+        // confirms the `x5c` leaf certificate
+        let jws = sign_raw(&signer, protected.clone(), "e30");
+        jws.verify_x5c(slice::from_ref(&root)).expect("should verify");
+
+        let mismatched = Protected {
+            x5t_s256: x509::thumbprint_s256(&root).ok(),
+            ..protected
+        };
+        let jws = sign_raw(&signer, mismatched, "e30");
+        let err = jws.verify_x5c(slice::from_ref(&root)).expect_err("should fail");
+        assert_eq!(err.to_string(), "`x5t#S256` does not match the `x5c` certificate");
+
+        // without `x5c`, selects the pinned certificate
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            x5t_s256: Some(thumbprint),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        jws.verify_x5c(&[root.clone(), leaf]).expect("should verify");
+        let err = jws.verify_x5c(&[root]).expect_err("should fail");
+        assert_eq!(err.to_string(), "no trusted certificate matches `x5t#S256`");
+    }
+
+    #[test]
+    fn trust_chain() {
+        use crate::jose::federation::tests::{chain, jwk};
+
+        let signer = Ed25519Signer::new();
+        let anchor_key = SigningKey::generate(&mut OsRng);
+        let anchors = Jwks {
+            keys: vec![jwk(&anchor_key, "ta-1")],
+        };
+
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            key: Key::KeyId("leaf-1".to_string()),
+            trust_chain: Some(chain(&signer.signing_key, &anchor_key, &json!({}))),
+            ..Protected::default()
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        jws.verify_trust_chain(&anchors).expect("should verify");
+
+        // signed by a key other than the one attested by the chain
+        let protected = Protected {
+            trust_chain: Some(chain(&SigningKey::generate(&mut OsRng), &anchor_key, &json!({}))),
+            ..jws.signatures[0].protected.clone()
+        };
+        let jws = sign_raw(&signer, protected, "e30");
+        assert!(jws.verify_trust_chain(&anchors).is_err());
+    }
+
+    #[tokio::test]
+    async fn embed_jwk() {
+        let signer = Es256kSigner::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"sub": "alice"}))
+            .embed_jwk(true)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let protected = &jws.signatures[0].protected;
+        assert_eq!(protected.jwk(), Some(&signer.public_jwk()));
+        assert_eq!(protected.kid(), None);
+
+        let compact = jws.to_compact().expect("should serialize");
+        let jwt: Jwt<Value> =
+            decode_with_key(&compact, &signer.public_jwk()).expect("should decode");
+        assert_eq!(jwt.claims, json!({"sub": "alice"}));
+    }
+
+    #[tokio::test]
+    async fn embed_jwk_with_kid() {
+        let signer = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .payload(json!({"sub": "alice"}))
+            .embed_jwk_with_kid()
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let thumbprint = signer.public_jwk().thumbprint().expect("should compute");
+        let protected = &jws.signatures[0].protected;
+        assert_eq!(protected.jwk(), Some(&signer.public_jwk()));
+        assert_eq!(protected.kid(), Some(thumbprint.as_str()));
+
+        let compact = jws.to_compact().expect("should serialize");
+        decode_with_key::<Value>(&compact, &signer.public_jwk()).expect("should decode");
+        verify_sync(&compact, &signer.public_jwk()).expect("should verify");
+
+        // `kid` not matching the embedded key
+        let mut protected = protected.clone();
+        protected.extra.insert("kid".to_string(), Value::String("other".to_string()));
+        let jws = sign_raw(&signer, protected, "e30");
+        let err = decode_with_key::<Value>(&jws.to_compact().unwrap(), &signer.public_jwk())
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "embedded `jwk` thumbprint does not match `kid`");
+    }
+
+    // Signer whose embedded key does not match its algorithm.
+    struct MismatchedSigner(Ed25519Signer);
+
+    impl Signer for MismatchedSigner {
+        fn try_sign(&self, msg: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send {
+            Signer::try_sign(&self.0, msg)
+        }
+
+        fn verifying_key(&self) -> impl Future<Output = Result<Vec<u8>>> + Send {
+            Signer::verifying_key(&self.0)
+        }
+
+        fn public_key_jwk(&self) -> impl Future<Output = Result<PublicKeyJwk>> + Send {
+            std::future::ready(Ok(self.0.public_jwk()))
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::ES256K
+        }
+
+        fn verification_method(&self) -> impl Future<Output = Result<String>> + Send {
+            Signer::verification_method(&self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn embed_jwk_mismatch() {
+        let signer = MismatchedSigner(Ed25519Signer::new());
+        let err = JwsBuilder::new()
+            .payload(json!({"sub": "alice"}))
+            .embed_jwk(true)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "algorithm ES256K is not valid for curve Ed25519");
+    }
+
+    #[tokio::test]
+    async fn batch() {
+        let signer = Ed25519Signer::new();
+        let payloads = [json!({"sub": "alice"}), json!({"sub": "bob"})];
+        let batch = batch_sign(&payloads, &signer).await.expect("should sign");
+        assert_eq!(batch.len(), 2);
+        assert_ne!(batch[0].signatures[0].signature, batch[1].signatures[0].signature);
+
+        for (jws, expected) in batch.iter().zip(&payloads) {
+            let compact = jws.to_compact().expect("should serialize");
+            let jwt: Jwt<Value> =
+                decode_with_key(&compact, &signer.public_jwk()).expect("should decode");
+            assert_eq!(&jwt.claims, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn replace_signature() {
+        let issuer = Ed25519Signer::new();
+
+        // a payload whose bytes would change if re-serialized
+        let payload = Base64UrlUnpadded::encode_string(br#"{ "b": 1, "a": 2.50 }"#);
+        let protected = Protected {
+            alg: Algorithm::EdDSA,
+            typ: "vc+jwt".to_string(),
+            key: Key::KeyId("did:example:issuer#key-1".to_string()),
+            ..Protected::default()
+        };
+        let mut jws = sign_raw(&issuer, protected, &payload);
+
+        let rotated = Es256kSigner::new();
+        jws.replace_signature(&rotated).await.expect("should re-sign");
+        assert_eq!(jws.payload, payload);
+        assert_eq!(jws.signatures.len(), 1);
+
+        let header = &jws.signatures[0].protected;
+        assert_eq!(header.alg, Algorithm::ES256K);
+        assert_eq!(header.typ, "vc+jwt");
+        assert_eq!(header.kid(), Some("did:example:alice#key-1"));
+
+        let compact = jws.to_compact().expect("should serialize");
+        assert_eq!(compact.split('.').nth(1), Some(payload.as_str()));
+        decode_with_key::<Value>(&compact, &rotated.public_jwk()).expect("should verify");
+        assert!(decode_with_key::<Value>(&compact, &issuer.public_jwk()).is_err());
+    }
+
+    #[tokio::test]
+    async fn dyn_signers() {
+        // signers of different types held in a registry keyed by controller
+        let registry: HashMap<&str, Box<dyn DynSigner>> = HashMap::from([
+            ("did:example:alice", Box::new(Ed25519Signer::new()) as Box<dyn DynSigner>),
+            ("did:example:bob", Box::new(Es256kSigner::new())),
+        ]);
+
+        for (controller, signer) in &registry {
+            let claims = json!({"iss": controller});
+            let token = encode(&claims, signer.as_ref()).await.expect("should encode");
+
+            let jwk = signer.public_key_jwk().await.expect("should get key");
+            let jwt: Jwt<Value> = decode_with_key(&token, &jwk).expect("should decode");
+            assert_eq!(jwt.claims, claims);
+            assert_eq!(jwt.header.alg, signer.algorithm());
+        }
+    }
+
+    #[tokio::test]
+    async fn decode_batch() {
+        let signer = Ed25519Signer::new();
+        let payloads = [json!({"sub": "alice"}), json!({"sub": "bob"})];
+        let batch = batch_sign(&payloads, &signer).await.expect("should sign");
+        let mut tokens =
+            batch.iter().map(|jws| jws.to_compact().expect("should serialize")).collect::<Vec<_>>();
+        tokens.insert(1, "not-a-jws".to_string());
+
+        let resolutions = std::sync::atomic::AtomicUsize::new(0);
+        let jwk = signer.public_jwk();
+        let resolver = |_kid: String| {
+            resolutions.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let tokens = tokens.iter().map(String::as_str).collect::<Vec<_>>();
+        let results: Vec<Result<Jwt<Value>>> = super::decode_batch(&tokens, resolver).await;
+        assert_eq!(resolutions.into_inner(), 1);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().expect("should decode").claims, payloads[0]);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().expect("should decode").claims, payloads[1]);
+    }
+
+    #[tokio::test]
+    async fn nested() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice", "vc": {"type": ["VerifiableCredential"]}});
+        let inner = encode(&claims, &signer).await.expect("should encode");
+
+        let outer = JwsBuilder::new()
+            .content_type("JWT")
+            .payload(&inner)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+
+        let jwk = signer.public_jwk();
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let nested: NestedJwt<Value> =
+            decode_nested(&outer, resolver, 1).await.expect("should decode");
+        assert_eq!(nested.outer.len(), 1);
+        assert_eq!(nested.outer[0].cty.as_deref(), Some("JWT"));
+        assert_eq!(nested.inner.claims, claims);
+
+        let err = decode_nested::<_, _, Value>(&outer, resolver, 0).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "JWT nesting exceeds maximum depth of 0");
+
+        // a regular JWT has no outer layers
+        let nested: NestedJwt<Value> =
+            decode_nested(&inner, resolver, 0).await.expect("should decode");
+        assert_eq!(nested.outer.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn content_type() {
+        let signer = Ed25519Signer::new();
+
+        // `cty` is omitted entirely when not set
+        let token = encode(&json!({"iss": "alice"}), &signer).await.expect("should encode");
+        let header: Value = serde_json::from_slice(
+            &Base64UrlUnpadded::decode_vec(token.split('.').next().unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert!(header.get("cty").is_none());
+
+        let token = JwsBuilder::new()
+            .content_type("JWT")
+            .payload(&token)
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+        assert_eq!(peek_header(&token).expect("should peek").cty.as_deref(), Some("JWT"));
+    }
+
+    #[tokio::test]
+    async fn sync_signer() {
+        let signer = Ed25519Signer::new();
+        let claims = json!({"iss": "did:example:alice", "sub": "bob"});
+
+        let token = encode(&claims, &signer).await.expect("should encode");
+
+        let jwk = signer.public_jwk();
+        let jwt: Jwt<Value> =
+            decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(jwt.claims, claims);
+        assert_eq!(jwt.header.kid(), Some("did:example:alice#key-1"));
+    }
+
+    #[tokio::test]
+    async fn proof_claims() {
+        let signer = Ed25519Signer::new();
+        let jws = JwsBuilder::new()
+            .jwt_type("openid4vci-proof+jwt")
+            .proof_nonce("n-0S6_WzA2Mj")
+            .payload(json!({"aud": "https://issuer.example.com"}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect("should build");
+
+        let jwk = signer.public_jwk();
+        let jwt: Jwt<Value> =
+            decode(&jws.to_compact().expect("should serialize"), |_| async { Ok(jwk.clone()) })
+                .await
+                .expect("should decode");
+        assert_eq!(jwt.claims["nonce"], "n-0S6_WzA2Mj");
+        assert!(jwt.claims["iat"].as_u64().is_some_and(|iat| iat > 0));
+        assert_eq!(jwt.claims["aud"], "https://issuer.example.com");
+
+        // the payload must be a JSON object
+        let err = JwsBuilder::new()
+            .jwt_type("openid4vci-proof+jwt")
+            .proof_nonce("n-0S6_WzA2Mj")
+            .payload("not an object")
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "proof claims require a JSON object payload");
+
+        // and the `typ` a proof JWT
+        let err = JwsBuilder::new()
+            .proof_nonce("n-0S6_WzA2Mj")
+            .payload(json!({}))
+            .add_signer(&signer)
+            .build()
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "proof claims require the `typ` openid4vci-proof+jwt");
+    }
+
+    #[tokio::test]
+    async fn resign() {
+        let issuer = Ed25519Signer::new();
+        let token = JwsBuilder::new()
+            .jwt_type("openid4vci-proof+jwt")
+            .payload(json!({"iss": "did:example:alice", "aud": "bob"}))
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+
+        let jwk = issuer.public_jwk();
+        let mut jwt: Jwt<Value> =
+            decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        jwt.claims["aud"] = json!("carol");
+
+        let proxy = Es256kSigner::new();
+        let reissued = jwt.resign(&proxy).await.expect("should resign");
+
+        let jwk = proxy.public_jwk();
+        let jwt: Jwt<Value> =
+            decode(&reissued, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(jwt.claims["aud"], "carol");
+        assert_eq!(jwt.header.alg, Algorithm::ES256K);
+        assert_eq!(jwt.header.typ, "openid4vci-proof+jwt");
+
+        // the original key no longer verifies
+        assert!(decode_with_key::<Value>(&reissued, &issuer.public_jwk()).is_err());
+    }
+
+    #[test]
+    fn compact_errors() {
+        let error = |token: &str| {
+            let err = token.parse::<Jws>().expect_err("should fail");
+            err.downcast::<CompactError>().expect("should be a CompactError")
+        };
+
+        assert_eq!(error("a.b"), CompactError::Segments);
+        assert_eq!(error("a.b.c.d"), CompactError::Segments);
+        assert_eq!(error(".b.c"), CompactError::Empty(Segment::Header));
+        assert_eq!(error("a.b."), CompactError::Empty(Segment::Signature));
+        assert_eq!(error("a\0.b.c"), CompactError::InvalidBase64(Segment::Header));
+        assert_eq!(error("a.b.c+/="), CompactError::InvalidBase64(Segment::Signature));
+
+        let oversized = format!("{}.b.c", "a".repeat(64 * 1024 + 1));
+        assert_eq!(
+            error(&oversized),
+            CompactError::TooLarge {
+                segment: Segment::Header,
+                max: 64 * 1024
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn compact_limits() {
+        let signer = Ed25519Signer::new();
+        let token = encode(&json!({"iss": "alice", "data": "a".repeat(1024)}), &signer)
+            .await
+            .expect("should encode");
+        let jwk = signer.public_jwk();
+
+        let limits = CompactLimits::default().max_payload_bytes(512);
+        let err = decode_with_limits::<_, _, Value>(&token, |_| async { Ok(jwk.clone()) }, &limits)
+            .await
+            .expect_err("should fail");
+        assert_eq!(
+            err.downcast::<CompactError>().expect("should be a CompactError"),
+            CompactError::TooLarge {
+                segment: Segment::Payload,
+                max: 512
+            }
+        );
+
+        let limits = CompactLimits::default().max_header_bytes(16);
+        let err = Jws::from_compact_with_limits(&token, &limits).expect_err("should fail");
+        assert_eq!(
+            err.downcast::<CompactError>().expect("should be a CompactError"),
+            CompactError::TooLarge {
+                segment: Segment::Header,
+                max: 16
+            }
+        );
+
+        let jwt: Jwt<Value> =
+            decode_with_limits(&token, |_| async { Ok(jwk.clone()) }, &CompactLimits::default())
+                .await
+                .expect("should decode");
+        assert_eq!(jwt.claims["iss"], "alice");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_arbitrary_bytes(bytes in proptest::collection::vec(proptest::num::u8::ANY, 0..512)) {
+            let token = String::from_utf8_lossy(&bytes);
+            let _ = token.parse::<Jws>();
+            let _ = peek_header(&token);
+        }
+
+        #[test]
+        fn parse_arbitrary_segments(token in "[A-Za-z0-9_\\-.=+/\\x00]{0,256}") {
+            let _ = token.parse::<Jws>();
+            let _ = verify_compact(&token, &PublicKeyJwk::default(), &VerifyOptions::default());
+        }
+    }
+
+    // A second signer with a distinct verification method.
+    struct HybridSigner(Es256kSigner);
+
+    impl SyncSigner for HybridSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+            SyncSigner::try_sign(&self.0, msg)
+        }
+
+        fn verifying_key(&self) -> Result<Vec<u8>> {
+            SyncSigner::verifying_key(&self.0)
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            SyncSigner::algorithm(&self.0)
+        }
+
+        fn verification_method(&self) -> Result<String> {
+            Ok("did:example:alice#key-2".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn multiple_signers() {
+        let ed25519 = Ed25519Signer::new();
+        let es256k = HybridSigner(Es256kSigner::new());
+
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "did:example:alice"}))
+            .add_signer(&ed25519)
+            .add_signer(&es256k)
+            .build()
+            .await
+            .expect("should build");
+
+        assert_eq!(jws.signatures.len(), 2);
+        assert_eq!(jws.signatures[0].protected.alg, Algorithm::EdDSA);
+        assert_eq!(jws.signatures[1].protected.alg, Algorithm::ES256K);
+        assert_eq!(jws.signatures[1].kid(), Some("did:example:alice#key-2"));
+
+        let keys = [ed25519.public_jwk(), es256k.0.public_jwk()];
+        let resolver = |kid: String| {
+            let key = if kid.ends_with("key-1") { keys[0].clone() } else { keys[1].clone() };
+            async move { Ok(key) }
+        };
+        jws.verify_threshold(resolver, 2).await.expect("both should verify");
+
+        // only one signature verifies when a key is unavailable
+        let resolver = |kid: String| {
+            let key = keys[0].clone();
+            async move {
+                if kid.ends_with("key-1") {
+                    Ok(key)
+                } else {
+                    bail!("unknown key")
+                }
+            }
+        };
+        assert!(jws.verify_threshold(resolver, 2).await.is_err());
+        jws.verify_threshold(resolver, 1).await.expect("quorum of one should verify");
+    }
+
+    #[tokio::test]
+    async fn json_serialization() {
+        let ed25519 = Ed25519Signer::new();
+        let es256k = Es256kSigner::new();
+
+        let jws = JwsBuilder::new()
+            .payload(json!({"iss": "did:example:alice"}))
+            .add_signer(&ed25519)
+            .add_signer(&es256k)
+            .build()
+            .await
+            .expect("should build");
+        assert!(jws.to_compact().is_err());
+
+        let json = jws.to_json_string().expect("should serialize");
+        let value: Value = serde_json::from_str(&json).expect("should be JSON");
+        assert!(value["signatures"][1]["protected"].is_string());
+
+        let parsed = Jws::from_json_str(&json).expect("should parse");
+        assert_eq!(parsed, jws);
+        let jwk = ed25519.public_jwk();
+        parsed.signatures[0].verify(&parsed.payload, &jwk).expect("should verify");
+
+        // compact and JSON forms are not interchangeable
+        assert!(Jws::from_json_str("e30.e30.e30").is_err());
+        assert!(json.parse::<Jws>().is_err());
     }
 }
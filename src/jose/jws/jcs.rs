@@ -0,0 +1,157 @@
+//! # JSON Canonicalization Scheme (JCS)
+//!
+//! Serializes JSON values as per [RFC8785], producing identical bytes for
+//! equivalent values regardless of object member order or number formatting
+//! in the source:
+//!
+//! - object members are sorted by the UTF-16 code units of their names,
+//! - numbers are serialized as `ECMAScript` (`Number.prototype.toString`)
+//!   would serialize the equivalent IEEE 754 double, and
+//! - no insignificant whitespace is emitted.
+//!
+//! [RFC8785]: https://www.rfc-editor.org/rfc/rfc8785
+
+use anyhow::Result;
+use serde_json::{Number, Value};
+
+/// Serialize `value` as canonical JSON.
+///
+/// # Errors
+/// Returns an error if a string cannot be serialized.
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write_value(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::Number(n) => out.extend_from_slice(number(n).as_bytes()),
+        // string escaping is as per JCS: only `"`, `\`, and control characters
+        Value::String(s) => serde_json::to_writer(&mut *out, s)?,
+        Value::Array(array) => {
+            out.push(b'[');
+            for (i, item) in array.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(object) => {
+            let mut members = object.iter().collect::<Vec<_>>();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push(b'{');
+            for (i, (name, item)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, name)?;
+                out.push(b':');
+                write_value(out, item)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+// Serialize a number as `ECMAScript` would serialize the equivalent double.
+fn number(n: &Number) -> String {
+    // integers exactly representable as a double are serialized as-is
+    const MAX_SAFE: u64 = 1 << 53;
+    if let Some(u) = n.as_u64().filter(|u| *u <= MAX_SAFE) {
+        return u.to_string();
+    }
+    if let Some(i) = n.as_i64().filter(|i| i.unsigned_abs() <= MAX_SAFE) {
+        return i.to_string();
+    }
+    n.as_f64().map_or_else(|| n.to_string(), double)
+}
+
+// `ECMAScript` `Number.prototype.toString` for a finite double.
+fn double(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if f < 0.0 { "-" } else { "" };
+
+    // Rust's `{:e}` yields the shortest round-trip digits, e.g. `1.2345e3`
+    let sci = format!("{:e}", f.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits = mantissa.replace('.', "");
+    let k = i32::try_from(digits.len()).unwrap_or(i32::MAX);
+    let n = exponent.parse::<i32>().unwrap_or_default() + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k).unsigned_abs() as usize))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n.unsigned_abs() as usize);
+        format!("{int}.{frac}")
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(n.unsigned_abs() as usize))
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let dot = if rest.is_empty() { "" } else { "." };
+        let exp_sign = if n > 0 { "+" } else { "-" };
+        format!("{first}{dot}{rest}e{exp_sign}{}", (n - 1).abs())
+    };
+    format!("{sign}{formatted}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // example from RFC8785 section 3.2.2
+    #[test]
+    #[allow(clippy::excessive_precision)]
+    fn rfc8785() {
+        let value = serde_json::json!({
+            "numbers": [333_333_333.333_333_29, 1e30, 4.50, 2e-3, 1e-27],
+            "string": "\u{20ac}$\u{f}\nA'B\"\\\\\"/",
+            "literals": [null, true, false]
+        });
+
+        let canonical = to_vec(&value).expect("should serialize");
+        assert_eq!(
+            String::from_utf8(canonical).expect("should be UTF-8"),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        for (f, expected) in [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1.0, "1"),
+            (-1.5, "-1.5"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (1e-6, "0.000001"),
+            (1e-7, "1e-7"),
+            (123.456, "123.456"),
+            (9_007_199_254_740_994.0, "9007199254740994"),
+        ] {
+            assert_eq!(double(f), expected, "{f}");
+        }
+        assert_eq!(number(&Number::from(u64::MAX)), "18446744073709552000");
+        assert_eq!(number(&Number::from(-42)), "-42");
+    }
+
+    #[test]
+    fn utf16_key_order() {
+        // U+1F600 sorts before U+FB01 by UTF-16 code unit, but not by UTF-8
+        let value = serde_json::json!({"\u{fb01}": 1, "\u{1f600}": 2});
+        let canonical = to_vec(&value).expect("should serialize");
+        assert_eq!(
+            String::from_utf8(canonical).expect("should be UTF-8"),
+            "{\"\u{1f600}\":2,\"\u{fb01}\":1}"
+        );
+    }
+}
@@ -0,0 +1,707 @@
+//! # Verification Core
+//!
+//! Synchronous JWS signature verification: decoding the compact form, parsing
+//! the protected header, and verifying the signature with a known key.
+//!
+//! Verification here is synchronous and free of `tracing`, for callers that
+//! already hold the key and cannot resolve it asynchronously.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use ecdsa::signature::Verifier as _;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::jose::jwk::{Jwk, KeyUse, PublicKeyJwk};
+use crate::jose::jws::{CompactError, Protected, Segment};
+use crate::Curve;
+
+/// Options controlling signature verification strictness.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Reject ECDSA signatures whose `s` value is in the upper half of the
+    /// curve order rather than normalizing them. Required by profiles that
+    /// must guard against signature malleability.
+    pub require_low_s: bool,
+
+    /// Reject JWS headers containing parameters this implementation does not
+    /// define, for profiles forbidding undefined header parameters. By
+    /// default unknown parameters are ignored.
+    pub reject_unknown_headers: bool,
+
+    /// Verify `EdDSA` signatures as Ed25519ph ([RFC8032 §5.1]): the signature
+    /// is over the SHA-512 digest of the signing input, with an empty
+    /// context, rather than the signing input itself.
+    ///
+    /// Ed25519ph is not registered for JOSE and there is no header to signal
+    /// its use, so both parties must agree on it out of band (e.g. by
+    /// profile). Signers must produce Ed25519ph signatures, for example using
+    /// `ed25519_dalek::SigningKey::sign_prehashed`. Pure Ed25519 signatures
+    /// do not verify when set, and vice versa.
+    ///
+    /// [RFC8032 §5.1]: https://www.rfc-editor.org/rfc/rfc8032#section-5.1
+    pub ed25519ph: bool,
+}
+
+impl VerifyOptions {
+    /// Reject (`true`) or normalize (`false`, the default) high-S ECDSA
+    /// signatures.
+    #[must_use]
+    pub const fn require_low_s(mut self, require_low_s: bool) -> Self {
+        self.require_low_s = require_low_s;
+        self
+    }
+
+    /// Reject (`true`) or ignore (`false`, the default) header parameters
+    /// this implementation does not define.
+    #[must_use]
+    pub const fn reject_unknown_headers(mut self, reject_unknown_headers: bool) -> Self {
+        self.reject_unknown_headers = reject_unknown_headers;
+        self
+    }
+
+    /// Verify `EdDSA` signatures as Ed25519ph (`true`) or pure Ed25519
+    /// (`false`, the default). See [`VerifyOptions::ed25519ph`] for interop
+    /// caveats.
+    #[must_use]
+    pub const fn ed25519ph(mut self, ed25519ph: bool) -> Self {
+        self.ed25519ph = ed25519ph;
+        self
+    }
+}
+
+/// Limits on the encoded length of compact JWS segments, checked before any
+/// segment is decoded so oversized tokens are rejected without allocating.
+///
+/// Exceeding a limit returns [`CompactError::TooLarge`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactLimits {
+    /// The maximum length, in bytes, of the encoded protected header.
+    /// Defaults to 65,536 bytes, allowing for large `x5c` certificate chains.
+    pub max_header_bytes: usize,
+
+    /// The maximum length, in bytes, of the encoded payload. Defaults to
+    /// 4,194,304 bytes.
+    pub max_payload_bytes: usize,
+}
+
+impl Default for CompactLimits {
+    fn default() -> Self {
+        Self {
+            max_header_bytes: MAX_HEADER_LEN,
+            max_payload_bytes: MAX_PAYLOAD_LEN,
+        }
+    }
+}
+
+impl CompactLimits {
+    /// Set the maximum encoded header length, in bytes.
+    #[must_use]
+    pub const fn max_header_bytes(mut self, max_header_bytes: usize) -> Self {
+        self.max_header_bytes = max_header_bytes;
+        self
+    }
+
+    /// Set the maximum encoded payload length, in bytes.
+    #[must_use]
+    pub const fn max_payload_bytes(mut self, max_payload_bytes: usize) -> Self {
+        self.max_payload_bytes = max_payload_bytes;
+        self
+    }
+}
+
+/// Verify a compact JWS using the provided public key.
+///
+/// The signature is verified over the signing input exactly as received,
+/// after checking any `crit` headers are understood, the key is valid (see
+/// [`PublicKeyJwk::validate`]), and the header `alg` is valid for the key's
+/// curve and matches the key's `alg`, if declared. Key identifiers in the
+/// header are not used. Unknown header parameters are
+/// rejected when [`VerifyOptions::reject_unknown_headers`] is set.
+///
+/// # Errors
+/// Returns an error if the token is not a compact JWS, its header cannot be
+/// decoded, the key is invalid, or the signature does not verify under
+/// `options`.
+pub fn verify_compact(
+    compact_jws: &str, public_jwk: &PublicKeyJwk, options: &VerifyOptions,
+) -> Result<()> {
+    let [header, payload, signature] = split_compact(compact_jws, &CompactLimits::default())?;
+
+    let protected = decode_header(header)?;
+    protected.check_crit()?;
+    protected.check_kid()?;
+    if options.reject_unknown_headers {
+        protected.check_unknown()?;
+    }
+
+    public_jwk.validate()?;
+    let alg = &protected.alg;
+    if !alg.is_compatible(&public_jwk.crv) {
+        bail!("algorithm {alg} is not valid for curve {:?}", public_jwk.crv);
+    }
+    public_jwk.check_alg(alg)?;
+
+    let sig = decode_segment(Segment::Signature, signature)?;
+    public_jwk.verify_parts_with_options(header, payload, &sig, options)
+}
+
+/// Verify a batch of Ed25519 signatures, each provided as a public key,
+/// signing input, and signature.
+///
+/// Batch verification is significantly faster than verifying each signature
+/// in turn. Should the batch fail, each signature is verified individually to
+/// identify the first that is invalid. Headers are not checked: use
+/// [`verify_compact`] to verify a single JWS fully.
+///
+/// # Errors
+/// Returns an error identifying the first item whose key is not an Ed25519
+/// key, or whose signature is malformed or does not verify.
+pub fn verify_batch_ed25519(items: &[(PublicKeyJwk, &str, &[u8])]) -> Result<()> {
+    use ed25519_dalek::{Signature, VerifyingKey};
+
+    let mut messages = Vec::with_capacity(items.len());
+    let mut signatures = Vec::with_capacity(items.len());
+    let mut verifying_keys = Vec::with_capacity(items.len());
+
+    for (index, (public_jwk, msg, sig)) in items.iter().enumerate() {
+        if !matches!(
+            Jwk::try_from(public_jwk)?,
+            Jwk::Okp {
+                crv: Curve::Ed25519,
+                ..
+            }
+        ) {
+            bail!("item {index}: batch verification requires an Ed25519 key");
+        }
+        let bytes = public_jwk
+            .to_raw_bytes()?
+            .try_into()
+            .map_err(|_| anyhow!("item {index}: invalid public key length"))?;
+        verifying_keys.push(
+            VerifyingKey::from_bytes(&bytes)
+                .map_err(|e| anyhow!("item {index}: unable to build verifying key: {e}"))?,
+        );
+        signatures.push(
+            Signature::from_slice(sig)
+                .map_err(|e| anyhow!("item {index}: unable to build signature: {e}"))?,
+        );
+        messages.push(msg.as_bytes());
+    }
+
+    if ed25519_dalek::verify_batch(&messages, &signatures, &verifying_keys).is_ok() {
+        return Ok(());
+    }
+
+    // identify the invalid signature
+    for (index, (public_jwk, msg, sig)) in items.iter().enumerate() {
+        public_jwk.verify_eddsa(msg, sig, false).map_err(|e| anyhow!("item {index}: {e}"))?;
+    }
+    bail!("batch verification failed")
+}
+
+/// The default maximum length of an encoded protected header. Allows for
+/// large `x5c` certificate chains.
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+/// The default maximum length of an encoded payload.
+const MAX_PAYLOAD_LEN: usize = 4 * 1024 * 1024;
+
+/// The maximum length of an encoded signature. Allows for RSA signatures with
+/// keys of up to 8192 bits.
+const MAX_SIGNATURE_LEN: usize = 2048;
+
+// Split a compact JWS into its header, payload, and signature segments,
+// checking each is within bounds before any decoding is attempted.
+//
+// The header and signature must be non-empty base64url. The payload may be
+// empty (detached) and is not checked against the base64url alphabet as it
+// may be unencoded (`b64: false`).
+pub(super) fn split_compact<'a>(
+    compact_jws: &'a str, limits: &CompactLimits,
+) -> Result<[&'a str; 3], CompactError> {
+    let mut parts = compact_jws.splitn(4, '.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(CompactError::Segments);
+    };
+
+    for (segment, value, max) in [
+        (Segment::Header, header, limits.max_header_bytes),
+        (Segment::Payload, payload, limits.max_payload_bytes),
+        (Segment::Signature, signature, MAX_SIGNATURE_LEN),
+    ] {
+        if value.len() > max {
+            return Err(CompactError::TooLarge { segment, max });
+        }
+        if segment == Segment::Payload {
+            continue;
+        }
+        if value.is_empty() {
+            return Err(CompactError::Empty(segment));
+        }
+        if !value.bytes().all(is_base64url) {
+            return Err(CompactError::InvalidBase64(segment));
+        }
+    }
+
+    Ok([header, payload, signature])
+}
+
+// Check a JSON document has no duplicate member names at any depth.
+// `serde_json` otherwise silently keeps the last of duplicated members.
+pub(super) fn check_unique_members(json: &[u8]) -> serde_json::Result<()> {
+    serde_json::from_slice::<UniqueMembers>(json).map(|_| ())
+}
+
+// Deserializes any JSON value, failing on an object with duplicate members.
+struct UniqueMembers;
+
+impl<'de> Deserialize<'de> for UniqueMembers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(Self)
+    }
+}
+
+impl<'de> Visitor<'de> for UniqueMembers {
+    type Value = Self;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self, E> {
+        Ok(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self, A::Error> {
+        while seq.next_element::<Self>()?.is_some() {}
+        Ok(self)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self, A::Error> {
+        let mut members = BTreeSet::new();
+        while let Some(name) = map.next_key::<String>()? {
+            map.next_value::<Self>()?;
+            if !members.insert(name.clone()) {
+                return Err(de::Error::custom(format!("duplicate member `{name}`")));
+            }
+        }
+        Ok(self)
+    }
+}
+
+// Decode a base64url-encoded protected header.
+pub(super) fn decode_header(encoded: &str) -> Result<Protected> {
+    let decoded = decode_segment(Segment::Header, encoded)?;
+    serde_json::from_slice(&decoded).map_err(|e| anyhow!("issue deserializing header: {e}"))
+}
+
+// Decode a base64url-encoded JWS segment. On failure, the error names the
+// segment and the byte offset of the first character outside the base64url
+// alphabet, or of the last character when the length or trailing bits are
+// invalid.
+pub(super) fn decode_segment(segment: Segment, encoded: &str) -> Result<Vec<u8>> {
+    Base64UrlUnpadded::decode_vec(encoded).map_err(|e| {
+        let offset = encoded
+            .bytes()
+            .position(|b| !is_base64url(b))
+            .unwrap_or_else(|| encoded.len().saturating_sub(1));
+        anyhow!("issue decoding {segment} at byte {offset}: {e}")
+    })
+}
+
+const fn is_base64url(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+impl PublicKeyJwk {
+    /// Verify the signature of the provided message using the JWK.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the signature is invalid, the JWK is invalid, or the
+    /// algorithm is unsupported.
+    pub fn verify(&self, msg: &str, sig: &[u8]) -> Result<()> {
+        self.verify_with_options(msg, sig, &VerifyOptions::default())
+    }
+
+    /// Verify a JWS signature over a signing input assembled from its
+    /// encoded protected header and payload (`protected_b64.payload_b64`),
+    /// for callers holding the parts separately, such as a detached header.
+    ///
+    /// The header is not decoded or checked: use [`verify_compact`] to also
+    /// check the header `alg` and `crit` parameters.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `protected_b64` is not base64url, or the
+    /// signature does not verify (see [`PublicKeyJwk::verify`]).
+    pub fn verify_parts(&self, protected_b64: &str, payload_b64: &str, sig: &[u8]) -> Result<()> {
+        self.verify_parts_with_options(protected_b64, payload_b64, sig, &VerifyOptions::default())
+    }
+
+    /// Verify a JWS signature over its encoded protected header and payload
+    /// using the provided verification options (see
+    /// [`PublicKeyJwk::verify_parts`]).
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `protected_b64` is not base64url, or the
+    /// signature does not verify under `options`.
+    pub fn verify_parts_with_options(
+        &self, protected_b64: &str, payload_b64: &str, sig: &[u8], options: &VerifyOptions,
+    ) -> Result<()> {
+        // a `.` in the header would shift the boundary with the payload
+        if !protected_b64.bytes().all(is_base64url) {
+            bail!("protected header is not base64url");
+        }
+        self.verify_with_options(&format!("{protected_b64}.{payload_b64}"), sig, options)
+    }
+
+    /// Verify the signature of the provided message using the JWK and the
+    /// provided verification options.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the signature is invalid (including a high-S
+    /// ECDSA signature when low-S is required), the JWK is invalid or not
+    /// intended for signature verification, or the algorithm is unsupported.
+    pub fn verify_with_options(
+        &self, msg: &str, sig: &[u8], options: &VerifyOptions,
+    ) -> Result<()> {
+        self.check_use(&KeyUse::Signature)?;
+
+        match Jwk::try_from(self)? {
+            Jwk::Okp {
+                crv: Curve::Ed25519, ..
+            } => self.verify_eddsa(msg, sig, options.ed25519ph),
+            Jwk::Ec {
+                crv: Curve::Es256K, ..
+            } => self.verify_es256k(msg, sig, options.require_low_s),
+            Jwk::Ec { crv: Curve::P256, .. } => self.verify_es256(msg, sig),
+            Jwk::Ec { crv: Curve::P384, .. } => self.verify_es384(msg, sig),
+            Jwk::Ec { crv: Curve::P521, .. } => self.verify_es512(msg, sig),
+            Jwk::Akp { crv, .. } => verify_ml_dsa(&crv, &self.to_raw_bytes()?, msg, sig),
+            _ => bail!("unsupported DSA curve"),
+        }
+    }
+
+    // Verify the signature of the provided message using the ES256K algorithm.
+    fn verify_es256k(&self, msg: &str, sig: &[u8], require_low_s: bool) -> Result<()> {
+        use ecdsa::{Signature, VerifyingKey};
+        use k256::Secp256k1;
+
+        let verifying_key = VerifyingKey::<Secp256k1>::from_sec1_bytes(&self.to_sec1()?)
+            .map_err(|_| invalid_point())?;
+        let signature: Signature<Secp256k1> = Signature::from_slice(sig)?;
+
+        // `normalize_s` returns `Some` only for a high-S signature
+        let normalised = match signature.normalize_s() {
+            Some(_) if require_low_s => bail!("signature is not low-S normalized"),
+            Some(normalised) => normalised,
+            None => signature,
+        };
+
+        Ok(verifying_key.verify(msg.as_bytes(), &normalised)?)
+    }
+
+    // Verify the signature of the provided message using the ES256 algorithm
+    // (P-256 with SHA-256).
+    fn verify_es256(&self, msg: &str, sig: &[u8]) -> Result<()> {
+        use p256::ecdsa::{Signature, VerifyingKey};
+
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&self.to_sec1()?).map_err(|_| invalid_point())?;
+        let signature = Signature::from_slice(sig)?;
+
+        Ok(verifying_key.verify(msg.as_bytes(), &signature)?)
+    }
+
+    // Verify the signature of the provided message using the ES384 algorithm
+    // (P-384 with SHA-384).
+    fn verify_es384(&self, msg: &str, sig: &[u8]) -> Result<()> {
+        use p384::ecdsa::{Signature, VerifyingKey};
+
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&self.to_sec1()?).map_err(|_| invalid_point())?;
+        let signature = Signature::from_slice(sig)?;
+
+        Ok(verifying_key.verify(msg.as_bytes(), &signature)?)
+    }
+
+    // Verify the signature of the provided message using the ES512 algorithm
+    // (P-521 with SHA-512).
+    fn verify_es512(&self, msg: &str, sig: &[u8]) -> Result<()> {
+        use p521::ecdsa::{Signature, VerifyingKey};
+
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&self.to_sec1()?).map_err(|_| invalid_point())?;
+        let signature = Signature::from_slice(sig)?;
+
+        Ok(verifying_key.verify(msg.as_bytes(), &signature)?)
+    }
+
+    // Verify the signature of the provided message using the EdDSA algorithm,
+    // as Ed25519ph when `prehash` is set.
+    fn verify_eddsa(&self, msg: &str, sig_bytes: &[u8], prehash: bool) -> Result<()> {
+        use ed25519_dalek::{Digest, Sha512, Signature, VerifyingKey};
+
+        let bytes =
+            &self.to_raw_bytes()?.try_into().map_err(|_| anyhow!("invalid public key length"))?;
+        let verifying_key = VerifyingKey::from_bytes(bytes)
+            .map_err(|e| anyhow!("unable to build verifying key: {e}"))?;
+        let signature = Signature::from_slice(sig_bytes)
+            .map_err(|e| anyhow!("unable to build signature: {e}"))?;
+
+        if prehash {
+            let prehashed = Sha512::new().chain_update(msg.as_bytes());
+            return verifying_key
+                .verify_prehashed(prehashed, None, &signature)
+                .map_err(|e| anyhow!("unable to verify signature: {e}"));
+        }
+        verifying_key
+            .verify(msg.as_bytes(), &signature)
+            .map_err(|e| anyhow!("unable to verify signature: {e}"))
+    }
+}
+
+// Verify the signature of the provided message using ML-DSA (FIPS 204) with
+// an empty context string.
+#[cfg(feature = "pqc")]
+fn verify_ml_dsa(crv: &Curve, key: &[u8], msg: &str, sig: &[u8]) -> Result<()> {
+    use ml_dsa::{EncodedVerifyingKey, MlDsa44, MlDsa65, MlDsaParams, Signature, VerifyingKey};
+
+    fn verify<P: MlDsaParams>(key: &[u8], msg: &str, sig: &[u8]) -> Result<()> {
+        let encoded = EncodedVerifyingKey::<P>::try_from(key)
+            .map_err(|_| anyhow!("invalid ML-DSA public key length"))?;
+        let verifying_key = VerifyingKey::<P>::decode(&encoded);
+        let signature =
+            Signature::<P>::try_from(sig).map_err(|_| anyhow!("unable to build signature"))?;
+
+        if !verifying_key.verify_with_context(msg.as_bytes(), &[], &signature) {
+            bail!("unable to verify signature");
+        }
+        Ok(())
+    }
+
+    match crv {
+        Curve::MlDsa44 => verify::<MlDsa44>(key, msg, sig),
+        Curve::MlDsa65 => verify::<MlDsa65>(key, msg, sig),
+        _ => bail!("unsupported ML-DSA parameter set"),
+    }
+}
+
+#[cfg(not(feature = "pqc"))]
+fn verify_ml_dsa(_: &Curve, _: &[u8], _: &str, _: &[u8]) -> Result<()> {
+    bail!("ML-DSA verification requires the `pqc` feature")
+}
+
+// An EC key whose coordinates (or compressed `x`) are not a point on the curve.
+fn invalid_point() -> anyhow::Error {
+    anyhow!("EC public key is not a valid point on the curve")
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::jose::jws::Jws;
+    use crate::KeyType;
+
+    #[test]
+    fn compact() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let public_jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(signing_key.verifying_key().as_bytes()),
+            ..PublicKeyJwk::default()
+        };
+
+        let header =
+            Base64UrlUnpadded::encode_string(br#"{"alg":"EdDSA","typ":"JWT","kid":"key-1"}"#);
+        let payload = Base64UrlUnpadded::encode_string(br#"{"sub":"alice"}"#);
+        let sig = signing_key.sign(format!("{header}.{payload}").as_bytes());
+        let sig = Base64UrlUnpadded::encode_string(&sig.to_bytes());
+
+        let options = VerifyOptions::default();
+        verify_compact(&format!("{header}.{payload}.{sig}"), &public_jwk, &options)
+            .expect("should verify");
+
+        let tampered = Base64UrlUnpadded::encode_string(br#"{"sub":"mallory"}"#);
+        assert!(
+            verify_compact(&format!("{header}.{tampered}.{sig}"), &public_jwk, &options).is_err()
+        );
+        assert!(verify_compact(&format!("{header}.{payload}"), &public_jwk, &options).is_err());
+    }
+
+    #[test]
+    fn parts() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let public_jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(signing_key.verifying_key().as_bytes()),
+            ..PublicKeyJwk::default()
+        };
+
+        let header = Base64UrlUnpadded::encode_string(br#"{"alg":"EdDSA"}"#);
+        let payload = Base64UrlUnpadded::encode_string(br#"{"sub":"alice"}"#);
+        let sig = signing_key.sign(format!("{header}.{payload}").as_bytes()).to_bytes();
+
+        public_jwk.verify_parts(&header, &payload, &sig).expect("should verify");
+        assert!(public_jwk.verify_parts(&header, "e30", &sig).is_err());
+
+        // the header cannot absorb part of the payload
+        let (first, rest) = payload.split_at(4);
+        let shifted = format!("{header}.{first}");
+        let err = public_jwk.verify_parts(&shifted, rest, &sig).expect_err("should fail");
+        assert_eq!(err.to_string(), "protected header is not base64url");
+    }
+
+    #[test]
+    fn segment_errors() {
+        let err =
+            decode_segment(Segment::Payload, "eyJzdWIi+iJhbGljZSJ9").expect_err("should fail");
+        assert!(err.to_string().starts_with("issue decoding payload at byte 8:"), "{err}");
+
+        // invalid length is reported at the last character
+        let err = decode_segment(Segment::Signature, "abcde").expect_err("should fail");
+        assert!(err.to_string().starts_with("issue decoding signature at byte 4:"), "{err}");
+
+        // a compact JWS payload is only decoded once the header is parsed
+        let header = Base64UrlUnpadded::encode_string(br#"{"alg":"EdDSA","kid":"key-1"}"#);
+        let jws: Jws = format!("{header}.e30*.c2ln").parse().expect("should parse");
+        let err = jws.jwt::<serde_json::Value>(false).expect_err("should fail");
+        assert!(err.to_string().starts_with("issue decoding payload at byte 3:"), "{err}");
+    }
+
+    #[test]
+    fn batch_ed25519() {
+        let items = (0..8)
+            .map(|i| {
+                let signing_key = SigningKey::generate(&mut OsRng);
+                let public_jwk = PublicKeyJwk {
+                    kty: KeyType::Okp,
+                    crv: Curve::Ed25519,
+                    x: Base64UrlUnpadded::encode_string(signing_key.verifying_key().as_bytes()),
+                    ..PublicKeyJwk::default()
+                };
+                let msg = format!("header.payload-{i}");
+                let sig = signing_key.sign(msg.as_bytes()).to_bytes().to_vec();
+                (public_jwk, msg, sig)
+            })
+            .collect::<Vec<_>>();
+        let batch = |items: &[(PublicKeyJwk, String, Vec<u8>)]| {
+            let items = items
+                .iter()
+                .map(|(jwk, msg, sig)| (jwk.clone(), msg.as_str(), sig.as_slice()))
+                .collect::<Vec<_>>();
+            verify_batch_ed25519(&items)
+        };
+
+        batch(&items).expect("should verify");
+        batch(&[]).expect("should verify empty batch");
+
+        // the invalid signature is identified
+        let mut tampered = items;
+        tampered[5].1 = "header.tampered".to_string();
+        let err = batch(&tampered).expect_err("should fail");
+        assert!(err.to_string().starts_with("item 5:"), "{err}");
+    }
+
+    #[test]
+    fn ed25519ph() {
+        use ed25519_dalek::{Digest, Sha512};
+
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let public_jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(signing_key.verifying_key().as_bytes()),
+            ..PublicKeyJwk::default()
+        };
+        let prehash = VerifyOptions::default().ed25519ph(true);
+
+        let prehashed = Sha512::new().chain_update(b"message");
+        let sig = signing_key.sign_prehashed(prehashed, None).expect("should sign").to_bytes();
+        public_jwk.verify_with_options("message", &sig, &prehash).expect("should verify");
+        assert!(public_jwk.verify("message", &sig).is_err());
+
+        // pure Ed25519 signatures are not accepted in prehash mode
+        let sig = signing_key.sign(b"message").to_bytes();
+        assert!(public_jwk.verify_with_options("message", &sig, &prehash).is_err());
+    }
+
+    #[test]
+    fn compressed_ec_key() {
+        use k256::ecdsa::{Signature, SigningKey};
+
+        let signing_key = SigningKey::random(&mut OsRng);
+        let compressed = signing_key.verifying_key().to_encoded_point(true);
+        let mut public_jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: Base64UrlUnpadded::encode_string(compressed.as_bytes()),
+            ..PublicKeyJwk::default()
+        };
+
+        let sig: Signature = signing_key.sign(b"message");
+        public_jwk.verify("message", &sig.to_bytes()).expect("should verify");
+
+        // x-coordinate with no corresponding point on the curve
+        public_jwk.x = Base64UrlUnpadded::encode_string(&[&[0x02][..], &[0xff; 32]].concat());
+        let err = public_jwk.verify("message", &sig.to_bytes()).expect_err("should fail");
+        assert_eq!(err.to_string(), "EC public key is not a valid point on the curve");
+    }
+
+    #[cfg(feature = "pqc")]
+    #[test]
+    fn ml_dsa() {
+        use ml_dsa::{Keypair as _, MlDsa44, Signer as _};
+
+        use crate::Algorithm;
+
+        let signing_key = ml_dsa::SigningKey::<MlDsa44>::from_seed(&[7; 32].into());
+        let verifying_key = signing_key.verifying_key().encode();
+        let public_jwk = PublicKeyJwk::from_bytes(&verifying_key, &Algorithm::MlDsa44)
+            .expect("should build JWK");
+        assert_eq!(public_jwk.kty, KeyType::Akp);
+
+        let sig = signing_key.sign(b"message").encode();
+        public_jwk.verify("message", &sig).expect("should verify");
+        assert!(public_jwk.verify("tampered", &sig).is_err());
+
+        // round-trips through JSON using the `pub` member
+        let json = serde_json::to_value(&public_jwk).expect("should serialize");
+        assert!(json.get("pub").is_some() && json.get("x").is_none());
+        let parsed: PublicKeyJwk = serde_json::from_value(json).expect("should deserialize");
+        parsed.verify("message", &sig).expect("should verify");
+    }
+}
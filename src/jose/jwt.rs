@@ -8,9 +8,13 @@
 //! signed or integrity protected with a Message Authentication Code
 //! (MAC) and/or encrypted.
 
-use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::jose::jws::Protected;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::jose::jws::{media_type, JwsBuilder, Protected};
+use crate::DynSigner;
 
 /// Represents a JWT as used for proof and credential presentation.
 #[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
@@ -21,3 +25,295 @@ pub struct Jwt<T> {
     /// The JWT claims.
     pub claims: T,
 }
+
+impl<T: Serialize + Send + Sync> Jwt<T> {
+    /// Sign the (possibly modified) claims using `signer`, returning a new
+    /// compact JWS.
+    ///
+    /// The original signature is discarded: the token is re-issued by
+    /// `signer`, which provides the `alg` and `kid` headers. Only `typ` and
+    /// `cty` are carried over from the original header.
+    ///
+    /// # Errors
+    /// Returns an error if the claims cannot be serialized or signing fails.
+    pub async fn resign(&self, signer: &dyn DynSigner) -> Result<String> {
+        let mut builder = JwsBuilder::new().jwt_type(self.header.typ.clone());
+        if let Some(cty) = &self.header.cty {
+            builder = builder.content_type(cty.clone());
+        }
+        builder.payload(&self.claims).add_signer(signer).build().await?.to_compact()
+    }
+}
+
+/// The registered claims defined by [RFC7519 §4.1] used when validating a
+/// JWT. Unregistered claims are ignored.
+///
+/// [RFC7519 §4.1]: https://www.rfc-editor.org/rfc/rfc7519#section-4.1
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RegisteredClaims {
+    /// The principal that issued the JWT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+
+    /// The principal that is the subject of the JWT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+
+    /// The recipient(s) the JWT is intended for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+
+    /// The time, in seconds since the Unix epoch, on or after which the JWT
+    /// MUST NOT be accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+
+    /// The time, in seconds since the Unix epoch, before which the JWT MUST
+    /// NOT be accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+
+    /// The time, in seconds since the Unix epoch, at which the JWT was issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+
+    /// A unique identifier for the JWT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+/// The `aud` claim: either a single string or an array of strings.
+///
+/// Both forms are normalized to a list. A single audience is serialized as a
+/// string, otherwise as an array.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Audience(pub Vec<String>);
+
+impl Audience {
+    /// Returns `true` if `aud` is one of the audiences.
+    #[must_use]
+    pub fn contains(&self, aud: &str) -> bool {
+        self.0.iter().any(|a| a == aud)
+    }
+}
+
+impl From<String> for Audience {
+    fn from(aud: String) -> Self {
+        Self(vec![aud])
+    }
+}
+
+impl From<&str> for Audience {
+    fn from(aud: &str) -> Self {
+        Self(vec![aud.to_string()])
+    }
+}
+
+impl From<Vec<String>> for Audience {
+    fn from(aud: Vec<String>) -> Self {
+        Self(aud)
+    }
+}
+
+impl Serialize for Audience {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [aud] => aud.serialize(serializer),
+            auds => auds.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Audience {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(aud) => Self(vec![aud]),
+            OneOrMany::Many(auds) => Self(auds),
+        })
+    }
+}
+
+/// Options used to validate a JWT's registered claims.
+#[derive(Clone, Debug, Default)]
+pub struct Validation {
+    /// Leeway applied symmetrically to `exp` and `nbf` comparisons to allow
+    /// for clock skew between the issuer and verifier.
+    pub leeway: Duration,
+
+    /// When set, the JWT's `aud` claim must include this audience.
+    pub audience: Option<String>,
+
+    /// When set, the JWT's `typ` header must be one of these types. As per
+    /// RFC7515 §4.1.9, types are compared case-insensitively and the
+    /// `application/` prefix is optional.
+    ///
+    /// See [RFC8725 §3.11](https://www.rfc-editor.org/rfc/rfc8725#section-3.11).
+    pub allowed_types: Option<Vec<String>>,
+
+    /// Reject a JWT without a `typ` header.
+    pub require_typ: bool,
+}
+
+impl Validation {
+    /// Validate the temporal claims (`exp` and `nbf`) against the current
+    /// time, allowing for the configured leeway, and the `aud` claim against
+    /// the expected audience, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the JWT has expired, is not yet valid, if `nbf`
+    /// is later than `exp`, or if the expected audience is not in `aud`.
+    pub fn validate(&self, claims: &RegisteredClaims) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.validate_at(claims, i64::try_from(now)?)
+    }
+
+    /// Validate the JWT's `typ` header against the allowed types, if any. An
+    /// empty `typ` is treated as absent.
+    ///
+    /// # Errors
+    /// Returns an error if `typ` is absent and required, or is present and
+    /// not one of the allowed types.
+    pub fn validate_typ(&self, typ: &str) -> Result<()> {
+        if typ.is_empty() {
+            if self.require_typ {
+                bail!("token is missing `typ` header");
+            }
+            return Ok(());
+        }
+        if let Some(allowed) = &self.allowed_types {
+            if !allowed.iter().any(|a| media_type(a).eq_ignore_ascii_case(media_type(typ))) {
+                bail!("unexpected `typ` header: {typ}");
+            }
+        }
+        Ok(())
+    }
+
+    // Validate claims against the provided time (seconds since Unix epoch).
+    fn validate_at(&self, claims: &RegisteredClaims, now: i64) -> Result<()> {
+        let leeway = i64::try_from(self.leeway.as_secs())?;
+
+        if let (Some(nbf), Some(exp)) = (claims.nbf, claims.exp) {
+            if nbf > exp {
+                bail!("`nbf` is later than `exp`");
+            }
+        }
+        if claims.exp.is_some_and(|exp| now >= exp.saturating_add(leeway)) {
+            bail!("token has expired");
+        }
+        if claims.nbf.is_some_and(|nbf| now < nbf.saturating_sub(leeway)) {
+            bail!("token is not yet valid");
+        }
+        if let Some(audience) = &self.audience {
+            if !claims.aud.as_ref().is_some_and(|aud| aud.contains(audience)) {
+                bail!("token audience does not include {audience}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn leeway() {
+        let claims = RegisteredClaims {
+            exp: Some(NOW - 3),
+            ..RegisteredClaims::default()
+        };
+
+        assert!(Validation::default().validate_at(&claims, NOW).is_err());
+
+        let validation = Validation {
+            leeway: Duration::from_secs(30),
+            ..Validation::default()
+        };
+        validation.validate_at(&claims, NOW).expect("should be valid within leeway");
+
+        // not yet valid
+        let claims = RegisteredClaims {
+            nbf: Some(NOW + 10),
+            ..RegisteredClaims::default()
+        };
+        assert!(Validation::default().validate_at(&claims, NOW).is_err());
+        validation.validate_at(&claims, NOW).expect("should be valid within leeway");
+    }
+
+    #[test]
+    fn nbf_after_exp() {
+        let claims = RegisteredClaims {
+            nbf: Some(NOW + 20),
+            exp: Some(NOW + 10),
+            ..RegisteredClaims::default()
+        };
+        let validation = Validation {
+            leeway: Duration::from_secs(30),
+            ..Validation::default()
+        };
+        let err = validation.validate_at(&claims, NOW).expect_err("should be invalid");
+        assert_eq!(err.to_string(), "`nbf` is later than `exp`");
+    }
+
+    #[test]
+    fn typ() {
+        Validation::default().validate_typ("").expect("should allow missing typ");
+
+        let validation = Validation {
+            allowed_types: Some(vec!["application/openid4vci-proof+jwt".to_string()]),
+            require_typ: true,
+            ..Validation::default()
+        };
+        validation.validate_typ("openid4vci-proof+JWT").expect("should be allowed");
+        let err = validation.validate_typ("dc+sd-jwt").expect_err("should be rejected");
+        assert_eq!(err.to_string(), "unexpected `typ` header: dc+sd-jwt");
+        let err = validation.validate_typ("").expect_err("should be rejected");
+        assert_eq!(err.to_string(), "token is missing `typ` header");
+    }
+
+    #[test]
+    fn audience() {
+        let claims: RegisteredClaims =
+            serde_json::from_str(r#"{"aud":"https://verifier.example.com"}"#).unwrap();
+        assert_eq!(claims.aud, Some(Audience::from("https://verifier.example.com")));
+        assert_eq!(
+            serde_json::to_string(&claims).unwrap(),
+            r#"{"aud":"https://verifier.example.com"}"#
+        );
+
+        let claims: RegisteredClaims =
+            serde_json::from_str(r#"{"aud":["https://a.example.com","https://b.example.com"]}"#)
+                .unwrap();
+        assert_eq!(
+            serde_json::to_string(&claims).unwrap(),
+            r#"{"aud":["https://a.example.com","https://b.example.com"]}"#
+        );
+
+        let validation = Validation {
+            audience: Some("https://b.example.com".to_string()),
+            ..Validation::default()
+        };
+        validation.validate_at(&claims, NOW).expect("should be valid");
+
+        let validation = Validation {
+            audience: Some("https://c.example.com".to_string()),
+            ..Validation::default()
+        };
+        let err = validation.validate_at(&claims, NOW).expect_err("should be invalid");
+        assert_eq!(err.to_string(), "token audience does not include https://c.example.com");
+        let err = validation
+            .validate_at(&RegisteredClaims::default(), NOW)
+            .expect_err("should be invalid");
+        assert_eq!(err.to_string(), "token audience does not include https://c.example.com");
+    }
+}
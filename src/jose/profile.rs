@@ -0,0 +1,247 @@
+//! # Signing Profiles
+//!
+//! A [`Profile`] captures the `typ`, `cty`, and `alg` header constraints a
+//! credential format or protocol message mandates. Signing with
+//! [`sign_with_profile`] and verifying with [`verify_with_profile`] ensures
+//! issuers and verifiers apply the same constraints, rather than each
+//! service setting (and checking) headers independently.
+//!
+//! Profiles for common `OpenID4VCI` and `OpenID4VP` JWTs are provided as
+//! constants.
+
+use std::future::Future;
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::jose::jws::{media_type, Jws, JwsBuilder, Protected, PROOF_TYPE};
+use crate::jose::jwt::Jwt;
+use crate::jose::{supported_signing_algorithms, PublicKeyJwk};
+use crate::{Algorithm, DynSigner};
+
+/// The header constraints for a type of JWT.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// The required `typ` header.
+    pub typ: &'static str,
+
+    /// The required `cty` header, or `None` if `cty` must be absent.
+    pub cty: Option<&'static str>,
+
+    /// The permitted signing algorithms.
+    pub algorithms: &'static [Algorithm],
+}
+
+/// An `OpenID4VCI` proof of possession JWT.
+pub const OPENID4VCI_PROOF: Profile = Profile {
+    typ: PROOF_TYPE,
+    cty: None,
+    algorithms: supported_signing_algorithms(),
+};
+
+/// A Verifiable Credential secured as a JWT, as per [VC-JOSE-COSE].
+///
+/// [VC-JOSE-COSE]: https://www.w3.org/TR/vc-jose-cose/#securing-with-jose
+pub const VC_JWT: Profile = Profile {
+    typ: "vc+jwt",
+    cty: Some("vc"),
+    algorithms: supported_signing_algorithms(),
+};
+
+/// A Verifiable Presentation secured as a JWT, as per [VC-JOSE-COSE].
+///
+/// [VC-JOSE-COSE]: https://www.w3.org/TR/vc-jose-cose/#securing-with-jose
+pub const VP_JWT: Profile = Profile {
+    typ: "vp+jwt",
+    cty: Some("vp"),
+    algorithms: supported_signing_algorithms(),
+};
+
+/// An `OpenID4VP` Authorization Request Object ([RFC9101]).
+///
+/// [RFC9101]: https://www.rfc-editor.org/rfc/rfc9101
+pub const AUTHORIZATION_REQUEST: Profile = Profile {
+    typ: "oauth-authz-req+jwt",
+    cty: None,
+    algorithms: supported_signing_algorithms(),
+};
+
+/// An SD-JWT Key Binding JWT presented to an `OpenID4VP` Verifier.
+pub const KEY_BINDING: Profile = Profile {
+    typ: "kb+jwt",
+    cty: None,
+    algorithms: supported_signing_algorithms(),
+};
+
+impl Profile {
+    /// Check a JWS protected header against the profile.
+    ///
+    /// As per RFC7515 §4.1.9 and §4.1.10, `typ` and `cty` are compared
+    /// case-insensitively and the `application/` prefix is optional.
+    ///
+    /// # Errors
+    /// Returns an error if the `typ`, `cty`, or `alg` header does not match
+    /// the profile.
+    pub fn validate(&self, header: &Protected) -> Result<()> {
+        if !media_type_eq(self.typ, &header.typ) {
+            bail!("unexpected `typ` header: {}", header.typ);
+        }
+        match (self.cty, header.cty.as_deref()) {
+            (None, None) => {}
+            (Some(expected), Some(cty)) if media_type_eq(expected, cty) => {}
+            (_, Some(cty)) => bail!("unexpected `cty` header: {cty}"),
+            (Some(expected), None) => bail!("missing `cty` header: expected {expected}"),
+        }
+        self.check_algorithm(&header.alg)
+    }
+
+    fn check_algorithm(&self, alg: &Algorithm) -> Result<()> {
+        if !self.algorithms.contains(alg) {
+            bail!("algorithm {alg} is not permitted for {}", self.typ);
+        }
+        Ok(())
+    }
+}
+
+// Compare media types, ignoring case and the optional `application/` prefix.
+fn media_type_eq(expected: &str, actual: &str) -> bool {
+    media_type(expected).eq_ignore_ascii_case(media_type(actual))
+}
+
+/// Sign `payload` using `signer`, returning a compact JWS with the `typ` and
+/// `cty` headers required by `profile`.
+///
+/// # Errors
+/// Returns an error if the signer's algorithm is not permitted by the
+/// profile, the payload cannot be serialized, or signing fails.
+pub async fn sign_with_profile<T>(
+    payload: &T, signer: &dyn DynSigner, profile: &Profile,
+) -> Result<String>
+where
+    T: Serialize + Send + Sync,
+{
+    tracing::debug!("sign_with_profile");
+
+    profile.check_algorithm(&signer.algorithm())?;
+
+    let mut builder = JwsBuilder::new().jwt_type(profile.typ);
+    if let Some(cty) = profile.cty {
+        builder = builder.content_type(cty);
+    }
+    builder.payload(payload).add_signer(signer).build().await?.to_compact()
+}
+
+/// Decode and verify a compact JWS, requiring the `typ`, `cty`, and `alg`
+/// headers of each signature to satisfy `profile`, and return the claims.
+///
+/// Headers are checked before the signature is verified, so `resolver` is
+/// not called for tokens of the wrong type.
+///
+/// # Errors
+/// Returns an error if a header does not satisfy the profile (see
+/// [`Profile::validate`]), or the token cannot be decoded and verified.
+pub async fn verify_with_profile<F, Fut, T>(
+    compact_jws: &str, resolver: F, profile: &Profile,
+) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("verify_with_profile");
+
+    let jws: Jws = compact_jws.parse()?;
+    for signature in &jws.signatures {
+        profile.validate(&signature.protected)?;
+    }
+    jws.verify(resolver).await?;
+    jws.jwt(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_utils::{InMemoryKeyOps, InMemorySigner};
+
+    fn new_issuer() -> InMemorySigner {
+        let key_ops = InMemoryKeyOps::new();
+        key_ops.register("did:example:issuer", &Algorithm::EdDSA).expect("should register");
+        key_ops.signer("did:example:issuer").expect("should get signer")
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let issuer = new_issuer();
+        let jwk = issuer.public_jwk().expect("should get key");
+        let resolver = |_| {
+            let jwk = jwk.clone();
+            async move { Ok(jwk) }
+        };
+
+        let claims = json!({"iss": "did:example:issuer", "type": ["VerifiableCredential"]});
+        let token = sign_with_profile(&claims, &issuer, &VC_JWT).await.expect("should sign");
+
+        let jwt: Jwt<Value> =
+            verify_with_profile(&token, resolver, &VC_JWT).await.expect("should verify");
+        assert_eq!(jwt.header.typ, "vc+jwt");
+        assert_eq!(jwt.header.cty.as_deref(), Some("vc"));
+        assert_eq!(jwt.claims, claims);
+
+        // a credential is not a presentation
+        let err = verify_with_profile::<_, _, Value>(&token, resolver, &VP_JWT)
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "unexpected `typ` header: vc+jwt");
+    }
+
+    #[tokio::test]
+    async fn algorithm() {
+        let issuer = new_issuer();
+        let es256k_only = Profile {
+            algorithms: &[Algorithm::ES256K],
+            ..OPENID4VCI_PROOF
+        };
+
+        let err =
+            sign_with_profile(&json!({}), &issuer, &es256k_only).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "algorithm EdDSA is not permitted for openid4vci-proof+jwt");
+
+        let token =
+            sign_with_profile(&json!({}), &issuer, &OPENID4VCI_PROOF).await.expect("should sign");
+        let err = verify_with_profile::<_, _, Value>(
+            &token,
+            |_| async { bail!("should not resolve") },
+            &es256k_only,
+        )
+        .await
+        .expect_err("should fail");
+        assert_eq!(err.to_string(), "algorithm EdDSA is not permitted for openid4vci-proof+jwt");
+    }
+
+    #[test]
+    fn validate() {
+        let header = Protected {
+            typ: "application/VC+JWT".to_string(),
+            cty: Some("vc".to_string()),
+            ..Protected::default()
+        };
+        VC_JWT.validate(&header).expect("should be valid");
+
+        let missing_cty = Protected {
+            cty: None,
+            ..header.clone()
+        };
+        let err = VC_JWT.validate(&missing_cty).expect_err("should fail");
+        assert_eq!(err.to_string(), "missing `cty` header: expected vc");
+
+        let unexpected_cty = Protected {
+            typ: "kb+jwt".to_string(),
+            ..header
+        };
+        let err = KEY_BINDING.validate(&unexpected_cty).expect_err("should fail");
+        assert_eq!(err.to_string(), "unexpected `cty` header: vc");
+    }
+}
@@ -0,0 +1,333 @@
+//! # `OpenID4VCI` Proof JWTs
+//!
+//! A proof JWT ([OpenID4VCI §8.2.1.1]) demonstrates possession of the key a
+//! Credential is to be bound to. The holder signs the proof over the Credential
+//! Issuer's identifier (`aud`) and the `c_nonce` provided by the issuer
+//! (`nonce`), with the key identified by the `kid` header or embedded in the
+//! `jwk` header.
+//!
+//! Proofs are created using [`JwsBuilder::proof_nonce`] and verified using
+//! [`verify_proof`].
+//!
+//! [OpenID4VCI §8.2.1.1]: https://openid.net/specs/openid-4-verifiable-credential-issuance-1_0.html#section-8.2.1.1
+//! [`JwsBuilder::proof_nonce`]: crate::jose::jws::JwsBuilder::proof_nonce
+
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::jose::jws::{media_type, Jws, Key, PROOF_TYPE};
+use crate::jose::jwt::Audience;
+use crate::jose::PublicKeyJwk;
+
+/// The maximum age, in seconds, of a proof's `iat`.
+const MAX_AGE: i64 = 300;
+
+/// Allowance, in seconds, for the holder's clock running ahead of ours.
+const CLOCK_SKEW: i64 = 60;
+
+/// The claims of an `OpenID4VCI` proof JWT.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProofClaims {
+    /// The `client_id` of the Client making the Credential request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+
+    /// The Credential Issuer Identifier.
+    pub aud: Audience,
+
+    /// The time, in seconds since the Unix epoch, the proof was created.
+    pub iat: i64,
+
+    /// The `c_nonce` provided by the Credential Issuer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// The proof's `nonce` claim is missing or does not match the `c_nonce`
+/// issued by the Credential Issuer.
+///
+/// Returned (wrapped in [`anyhow::Error`]) by [`verify_proof`], so issuers
+/// can `downcast_ref` to respond with an `invalid_nonce` error and a fresh
+/// `c_nonce`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceMismatch {
+    /// The `c_nonce` issued by the Credential Issuer.
+    pub expected: String,
+
+    /// The proof's `nonce` claim, if any.
+    pub actual: Option<String>,
+}
+
+impl Display for NonceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.actual {
+            Some(actual) => write!(f, "proof `nonce` {actual} does not match `c_nonce`"),
+            None => write!(f, "proof is missing `nonce`"),
+        }
+    }
+}
+
+impl std::error::Error for NonceMismatch {}
+
+/// Verify an `OpenID4VCI` proof JWT, returning the holder's key for binding
+/// to the Credential.
+///
+/// The proof must have the `typ` `openid4vci-proof+jwt`. Its signature is
+/// verified using the embedded `jwk` or, when the proof has a `kid`, the key
+/// returned by `resolver`. The `nonce` claim must equal `expected_nonce`,
+/// `aud` must contain `expected_aud`, and the proof is rejected if its `iat`
+/// is older than 5 minutes.
+///
+/// # Errors
+/// Returns a [`NonceMismatch`] error if the `nonce` claim is missing or does
+/// not match. Returns an error if the proof is malformed, has the wrong
+/// `typ`, its key cannot be resolved, it fails signature verification, or
+/// its `aud` or `iat` claims are invalid.
+pub async fn verify_proof<F, Fut>(
+    token: &str, resolver: F, expected_nonce: &str, expected_aud: &str,
+) -> Result<Key>
+where
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("verify_proof");
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let now = i64::try_from(now).map_err(|e| anyhow!("invalid time: {e}"))?;
+    verify_at(token, resolver, expected_nonce, expected_aud, now).await
+}
+
+// Verify the proof against the provided time (seconds since Unix epoch).
+async fn verify_at<F, Fut>(
+    token: &str, resolver: F, expected_nonce: &str, expected_aud: &str, now: i64,
+) -> Result<Key>
+where
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    let jws: Jws = token.parse()?;
+    let [signature] = jws.signatures.as_slice() else {
+        bail!("proof JWT must have a single signature");
+    };
+
+    let typ = &signature.protected.typ;
+    if !media_type(typ).eq_ignore_ascii_case(PROOF_TYPE) {
+        bail!("unexpected `typ` header: {typ}");
+    }
+    match &signature.protected.key {
+        Key::Jwk(jwk) => signature.verify(&jws.payload, jwk)?,
+        Key::KeyId(kid) => signature.verify(&jws.payload, &resolver(kid.clone()).await?)?,
+    }
+
+    let claims = jws.jwt::<ProofClaims>(false)?.claims;
+    if claims.nonce.as_deref() != Some(expected_nonce) {
+        return Err(NonceMismatch {
+            expected: expected_nonce.to_string(),
+            actual: claims.nonce,
+        }
+        .into());
+    }
+    if !claims.aud.contains(expected_aud) {
+        bail!("`aud` does not contain the Credential Issuer");
+    }
+    // `iat` is holder-chosen: reject values too extreme to compare
+    let Some(age) = now.checked_sub(claims.iat) else {
+        bail!("proof `iat` is out of range");
+    };
+    if age > MAX_AGE {
+        bail!("proof has expired");
+    }
+    if age < -CLOCK_SKEW {
+        bail!("proof `iat` is in the future");
+    }
+
+    Ok(signature.protected.key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use base64ct::{Base64UrlUnpadded, Encoding};
+    use serde_json::json;
+
+    use super::*;
+    use crate::jose::jws::JwsBuilder;
+    use crate::test_utils::{InMemoryKeyOps, InMemorySigner};
+    use crate::{Algorithm, SyncSigner};
+
+    fn new_holder() -> InMemorySigner {
+        let key_ops = InMemoryKeyOps::new();
+        key_ops.register("did:example:holder", &Algorithm::EdDSA).expect("should register");
+        key_ops.signer("did:example:holder").expect("should get signer")
+    }
+
+    const ISSUER: &str = "https://issuer.example.com";
+    const NONCE: &str = "tZignsnFbp";
+
+    async fn proof(holder: &InMemorySigner, embed_jwk: bool) -> String {
+        JwsBuilder::new()
+            .jwt_type(PROOF_TYPE)
+            .embed_jwk(embed_jwk)
+            .proof_nonce(NONCE)
+            .payload(json!({"aud": ISSUER}))
+            .add_signer(holder)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize")
+    }
+
+    #[tokio::test]
+    async fn key_id() {
+        let holder = new_holder();
+        let jwk = holder.public_jwk().expect("should get key");
+        let resolver = |kid: String| {
+            let jwk = jwk.clone();
+            async move {
+                assert_eq!(kid, "did:example:holder#key-0");
+                Ok(jwk)
+            }
+        };
+
+        let token = proof(&holder, false).await;
+        let key = verify_proof(&token, resolver, NONCE, ISSUER).await.expect("should verify");
+        assert_eq!(key, Key::KeyId("did:example:holder#key-0".to_string()));
+
+        let err = verify_proof(&token, resolver, NONCE, "https://other.example.com")
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "`aud` does not contain the Credential Issuer");
+
+        let err = verify_at(&token, resolver, NONCE, ISSUER, i64::MAX / 2)
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "proof has expired");
+
+        // the proof must be signed by the resolved key
+        let other = new_holder();
+        let other_jwk = other.public_jwk().expect("should get key");
+        let wrong_key = |_| {
+            let jwk = other_jwk.clone();
+            async move { Ok(jwk) }
+        };
+        assert!(verify_proof(&token, wrong_key, NONCE, ISSUER).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn embedded_jwk() {
+        let holder = new_holder();
+        let token = proof(&holder, true).await;
+
+        let key = verify_proof(&token, |_| async { bail!("should not resolve") }, NONCE, ISSUER)
+            .await
+            .expect("should verify");
+        let jwk = holder.public_jwk().expect("should get key");
+        let Key::Jwk(embedded) = key else {
+            panic!("should be an embedded JWK");
+        };
+        assert_eq!(embedded.thumbprint().unwrap(), jwk.thumbprint().unwrap());
+    }
+
+    // wallets commonly place `typ` before `alg`
+    #[tokio::test]
+    async fn header_order() {
+        let holder = new_holder();
+        let jwk = holder.public_jwk().expect("should get key");
+        let header = format!(
+            r#"{{"typ":"{PROOF_TYPE}","alg":"EdDSA","jwk":{}}}"#,
+            serde_json::to_string(&jwk).expect("should serialize")
+        );
+        let header = Base64UrlUnpadded::encode_string(header.as_bytes());
+        let claims = json!({"aud": ISSUER, "iat": 0, "nonce": NONCE});
+        let payload = Base64UrlUnpadded::encode_string(claims.to_string().as_bytes());
+        let sig = SyncSigner::try_sign(&holder, format!("{header}.{payload}").as_bytes())
+            .expect("should sign");
+        let token = format!("{header}.{payload}.{}", Base64UrlUnpadded::encode_string(&sig));
+
+        let key = verify_at(&token, |_| async { bail!("should not resolve") }, NONCE, ISSUER, 0)
+            .await
+            .expect("should verify");
+        assert_eq!(key, Key::Jwk(jwk));
+    }
+
+    #[tokio::test]
+    async fn multiple_audiences() {
+        let holder = new_holder();
+        let token = JwsBuilder::new()
+            .jwt_type(PROOF_TYPE)
+            .embed_jwk(true)
+            .proof_nonce(NONCE)
+            .payload(json!({"aud": ["https://other.example.com", ISSUER]}))
+            .add_signer(&holder)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+        let resolver = |_| async { bail!("should not resolve") };
+
+        verify_proof(&token, resolver, NONCE, ISSUER).await.expect("should verify");
+        verify_proof(&token, resolver, NONCE, "https://other.example.com")
+            .await
+            .expect("should verify");
+
+        let err = verify_proof(&token, resolver, NONCE, "https://unknown.example.com")
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "`aud` does not contain the Credential Issuer");
+    }
+
+    #[tokio::test]
+    async fn extreme_iat() {
+        let holder = new_holder();
+        let resolver = |_| async { bail!("should not resolve") };
+
+        for (iat, expected) in
+            [(i64::MIN, "proof `iat` is out of range"), (i64::MAX, "proof `iat` is in the future")]
+        {
+            let token = JwsBuilder::new()
+                .jwt_type(PROOF_TYPE)
+                .embed_jwk(true)
+                .payload(json!({"aud": ISSUER, "iat": iat, "nonce": NONCE}))
+                .add_signer(&holder)
+                .build()
+                .await
+                .expect("should build")
+                .to_compact()
+                .expect("should serialize");
+            let err = verify_proof(&token, resolver, NONCE, ISSUER).await.expect_err("should fail");
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn nonce_mismatch() {
+        let holder = new_holder();
+        let token = proof(&holder, true).await;
+        let resolver = |_| async { bail!("should not resolve") };
+
+        let err = verify_proof(&token, resolver, "other", ISSUER).await.expect_err("should fail");
+        let mismatch = err.downcast::<NonceMismatch>().expect("should be a NonceMismatch");
+        assert_eq!(mismatch.expected, "other");
+        assert_eq!(mismatch.actual.as_deref(), Some(NONCE));
+
+        // a proof without a `nonce` is also a mismatch
+        let token = JwsBuilder::new()
+            .jwt_type(PROOF_TYPE)
+            .embed_jwk(true)
+            .payload(json!({"aud": ISSUER, "iat": 0}))
+            .add_signer(&holder)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+        let err = verify_proof(&token, resolver, NONCE, ISSUER).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "proof is missing `nonce`");
+        assert!(err.is::<NonceMismatch>());
+    }
+}
@@ -0,0 +1,1425 @@
+//! # Selective Disclosure for JWTs (SD-JWT)
+//!
+//! SD-JWT ([SD-JWT]) allows an issuer to sign a JWT where individual claims
+//! are replaced by digests of their "disclosures". The holder can then reveal
+//! only a subset of claims to a verifier by presenting the corresponding
+//! disclosures alongside the issuer-signed JWT.
+//!
+//! An SD-JWT is serialized as the issuer-signed JWT followed by each
+//! disclosure, separated (and terminated) by `~`:
+//!
+//! ```text
+//! <issuer-jwt>~<disclosure 1>~<disclosure 2>~...~<disclosure N>~
+//! ```
+//!
+//! Claims nested within objects are made selectively disclosable using a
+//! dotted (`address.street`) or JSON pointer (`/address/street`) path. Where
+//! both a parent and child are disclosable, the child's digest is carried
+//! within the parent's disclosure, so the holder can disclose the parent
+//! without the child, or both.
+//!
+//! Array elements are made selectively disclosable by index
+//! (`/nationalities/1`). Each is replaced by a `{"...": "<digest>"}` object,
+//! and undisclosed elements are removed from the array on verification.
+//!
+//! Holders use [`SdJwtPresentation`] to select the disclosures to present
+//! and, optionally, append a Key Binding JWT (KB-JWT) proving possession of
+//! the key in the credential's `cnf` claim:
+//!
+//! ```text
+//! <issuer-jwt>~<disclosure 1>~...~<disclosure N>~<kb-jwt>
+//! ```
+//!
+//! [SD-JWT]: https://datatracker.ietf.org/doc/draft-ietf-oauth-selective-disclosure-jwt
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::crypto::ct_eq;
+use crate::jose::cnf;
+use crate::jose::jwk::PublicKeyJwk;
+use crate::jose::jws::{self, Jws, JwsBuilder, Key};
+use crate::jose::jwt::Jwt;
+use crate::DynSigner;
+
+/// The `typ` header of a Key Binding JWT.
+const KB_JWT_TYPE: &str = "kb+jwt";
+
+/// The maximum age, in seconds, of a KB-JWT's `iat`.
+const KB_JWT_MAX_AGE: i64 = 300;
+
+/// Allowance, in seconds, for the holder's clock running ahead of ours.
+const CLOCK_SKEW: i64 = 60;
+
+/// A disclosure of a single, selectively disclosable claim or array element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disclosure {
+    /// Random salt ensuring the digest cannot be guessed from the claim value.
+    pub salt: String,
+
+    /// The claim name, or `None` for an array element.
+    pub name: Option<String>,
+
+    /// The claim value.
+    pub value: Value,
+}
+
+impl Disclosure {
+    /// Create a disclosure for the claim using a new random salt.
+    #[must_use]
+    pub fn new(name: impl Into<String>, value: Value) -> Self {
+        Self {
+            salt: Base64UrlUnpadded::encode_string(&random_bytes::<16>()),
+            name: Some(name.into()),
+            value,
+        }
+    }
+
+    /// Create a disclosure for an array element using a new random salt.
+    #[must_use]
+    pub fn element(value: Value) -> Self {
+        Self {
+            salt: Base64UrlUnpadded::encode_string(&random_bytes::<16>()),
+            name: None,
+            value,
+        }
+    }
+
+    /// Encode the disclosure as a base64url-encoded JSON array of salt, claim
+    /// name, and claim value. The claim name is omitted for array elements.
+    ///
+    /// # Errors
+    /// Returns an error if the disclosure cannot be serialized.
+    pub fn encode(&self) -> Result<String> {
+        let mut array = vec![Value::String(self.salt.clone())];
+        if let Some(name) = &self.name {
+            array.push(Value::String(name.clone()));
+        }
+        array.push(self.value.clone());
+        Ok(Base64UrlUnpadded::encode_string(&serde_json::to_vec(&array)?))
+    }
+
+    /// Parse an encoded disclosure.
+    ///
+    /// # Errors
+    /// Returns an error if the disclosure is not a base64url-encoded JSON array
+    /// of salt, claim name, and claim value, or of salt and array element.
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let bytes = Base64UrlUnpadded::decode_vec(encoded)
+            .map_err(|e| anyhow!("issue decoding disclosure: {e}"))?;
+        let array: Vec<Value> = serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow!("issue deserializing disclosure: {e}"))?;
+
+        let (salt, name, value) = match <[Value; 3]>::try_from(array) {
+            Ok([Value::String(salt), Value::String(name), value]) => (salt, Some(name), value),
+            Ok(_) => bail!("invalid disclosure"),
+            Err(array) => match <[Value; 2]>::try_from(array) {
+                Ok([Value::String(salt), value]) => (salt, None, value),
+                Ok(_) => bail!("invalid disclosure"),
+                Err(_) => bail!("disclosure must have 2 or 3 elements"),
+            },
+        };
+
+        Ok(Self { salt, name, value })
+    }
+}
+
+/// The digest of an encoded disclosure as it appears in an `_sd` array, using
+/// the default `_sd_alg` (SHA-256). See [`SdAlg::digest`].
+#[must_use]
+pub fn digest(encoded: &str) -> String {
+    SdAlg::Sha256.digest(encoded)
+}
+
+/// The hash algorithm used to compute disclosure digests, declared by the
+/// issuer in the `_sd_alg` claim using its IANA "Named Information Hash
+/// Algorithm" name.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SdAlg {
+    /// SHA-256, assumed when `_sd_alg` is absent.
+    #[default]
+    #[serde(rename = "sha-256")]
+    Sha256,
+
+    /// SHA-384.
+    #[serde(rename = "sha-384")]
+    Sha384,
+
+    /// SHA-512.
+    #[serde(rename = "sha-512")]
+    Sha512,
+}
+
+impl SdAlg {
+    /// The base64url-encoded digest of an encoded disclosure (or SD-JWT, for
+    /// `sd_hash`).
+    #[must_use]
+    pub fn digest(&self, encoded: &str) -> String {
+        Base64UrlUnpadded::encode_string(&self.hash(encoded.as_bytes()))
+    }
+
+    fn hash(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(bytes).to_vec(),
+            Self::Sha384 => Sha384::digest(bytes).to_vec(),
+            Self::Sha512 => Sha512::digest(bytes).to_vec(),
+        }
+    }
+}
+
+/// Formats the algorithm using its `_sd_alg` name.
+impl Display for SdAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Sha256 => "sha-256",
+            Self::Sha384 => "sha-384",
+            Self::Sha512 => "sha-512",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Parses an `_sd_alg` name.
+impl FromStr for SdAlg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sha-256" => Ok(Self::Sha256),
+            "sha-384" => Ok(Self::Sha384),
+            "sha-512" => Ok(Self::Sha512),
+            _ => Err(anyhow!("unsupported `_sd_alg`: {s}")),
+        }
+    }
+}
+
+/// An SD-JWT: the issuer-signed JWT, its encoded disclosures, and (when
+/// presented by a holder) an optional Key Binding JWT.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SdJwt {
+    /// The issuer-signed JWT in compact JWS form.
+    pub issuer_jwt: String,
+
+    /// Encoded disclosures.
+    pub disclosures: Vec<String>,
+
+    /// The Key Binding JWT in compact JWS form.
+    pub key_binding: Option<String>,
+}
+
+impl SdJwt {
+    /// Verify the SD-JWT, returning the issuer-signed claims with disclosed
+    /// claims restored, and the claims that were disclosed.
+    ///
+    /// Verification is as per [`decode`].
+    ///
+    /// # Errors
+    /// Returns an error if the SD-JWT cannot be verified (see [`decode`]).
+    pub async fn verify<F, Fut>(&self, resolver: F) -> Result<VerifiedSdJwt>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        tracing::debug!("SdJwt::verify");
+
+        let jws: Jws = self.issuer_jwt.parse()?;
+        jws.verify(resolver).await?;
+
+        let Jwt { mut claims, .. } = jws.jwt::<Map<String, Value>>(false)?;
+        let sd_alg = parse_sd_alg(claims.remove("_sd_alg").as_ref())?;
+        let (claims, disclosed) = disclose(claims, &self.disclosures, &sd_alg)?;
+        let key_binding = match &self.key_binding {
+            Some(kb_jwt) => Some(verify_key_binding(kb_jwt, &self.sd_hash_with(&sd_alg), &claims)?),
+            None => None,
+        };
+
+        Ok(VerifiedSdJwt {
+            claims: Value::Object(claims),
+            disclosed,
+            key_binding,
+        })
+    }
+
+    /// Verify a holder's presentation of the SD-JWT to a Verifier.
+    ///
+    /// In addition to [`SdJwt::verify`], the presentation must have a Key
+    /// Binding JWT whose `aud` is `expected_aud`, whose `nonce` is
+    /// `expected_nonce`, and that was issued within the last 5 minutes,
+    /// preventing a presentation being replayed.
+    ///
+    /// # Errors
+    /// Returns an error if the SD-JWT cannot be verified (see [`decode`]), it
+    /// has no KB-JWT, or the KB-JWT's `aud`, `nonce`, or `iat` claims are
+    /// invalid.
+    pub async fn verify_presentation<F, Fut>(
+        &self, resolver: F, expected_aud: &str, expected_nonce: &str,
+    ) -> Result<VerifiedSdJwt>
+    where
+        F: Fn(String) -> Fut + Send,
+        Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+    {
+        tracing::debug!("SdJwt::verify_presentation");
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let now = i64::try_from(now).map_err(|e| anyhow!("invalid time: {e}"))?;
+
+        let verified = self.verify(resolver).await?;
+        let Some(key_binding) = &verified.key_binding else {
+            bail!("presentation is missing a KB-JWT");
+        };
+        check_key_binding(key_binding, expected_aud, expected_nonce, now)?;
+        Ok(verified)
+    }
+
+    /// The `sd_hash` of the SD-JWT as bound by a Key Binding JWT: the digest
+    /// of the issuer-signed JWT and disclosures, excluding any KB-JWT.
+    ///
+    /// The digest is computed using the issuer-signed JWT's `_sd_alg`, or
+    /// SHA-256 if it is absent or cannot be read.
+    #[must_use]
+    pub fn sd_hash(&self) -> String {
+        let claims = self.issuer_jwt.parse::<Jws>().and_then(|jws| jws.jwt::<Value>(false));
+        let sd_alg =
+            claims.and_then(|jwt| parse_sd_alg(jwt.claims.get("_sd_alg"))).unwrap_or_default();
+        self.sd_hash_with(&sd_alg)
+    }
+
+    fn sd_hash_with(&self, sd_alg: &SdAlg) -> String {
+        let unbound = Self {
+            key_binding: None,
+            ..self.clone()
+        };
+        sd_alg.digest(&unbound.to_string())
+    }
+}
+
+/// A verified SD-JWT's claims.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifiedSdJwt {
+    /// The issuer-signed claims with disclosed claims restored and `_sd`
+    /// digests removed. Undisclosed claims do not appear.
+    pub claims: Value,
+
+    /// JSON pointers ([RFC6901]) to each disclosed claim, in sorted order.
+    /// For example, `/address/street`.
+    ///
+    /// [RFC6901]: https://www.rfc-editor.org/rfc/rfc6901
+    pub disclosed: Vec<String>,
+
+    /// The claims of the verified Key Binding JWT, when presented. See
+    /// [`SdJwt::verify_presentation`] to also check its `aud`, `nonce`, and
+    /// `iat`.
+    pub key_binding: Option<KeyBindingClaims>,
+}
+
+/// Serializes the SD-JWT using `~` separators.
+impl Display for SdJwt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}~", self.issuer_jwt)?;
+        for disclosure in &self.disclosures {
+            write!(f, "{disclosure}~")?;
+        }
+        if let Some(key_binding) = &self.key_binding {
+            write!(f, "{key_binding}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SdJwt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('~').map(ToString::to_string).collect::<Vec<_>>();
+        if parts.len() < 2 {
+            bail!("SD-JWT must be `~` separated");
+        }
+
+        // the final part is empty unless a KB-JWT is present
+        let key_binding = parts.pop().filter(|kb| !kb.is_empty());
+        let issuer_jwt = parts.remove(0);
+        if issuer_jwt.is_empty() {
+            bail!("missing issuer-signed JWT");
+        }
+
+        Ok(Self {
+            issuer_jwt,
+            disclosures: parts,
+            key_binding,
+        })
+    }
+}
+
+/// Claims of a Key Binding JWT.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct KeyBindingClaims {
+    /// The time, in seconds since the Unix epoch, the KB-JWT was issued.
+    pub iat: i64,
+
+    /// The intended verifier.
+    pub aud: String,
+
+    /// Verifier-provided nonce ensuring freshness.
+    pub nonce: String,
+
+    /// Digest of the presented SD-JWT (see [`SdJwt::sd_hash`]).
+    pub sd_hash: String,
+}
+
+/// Decode and verify an SD-JWT, returning the issuer-signed JWT with the
+/// disclosed claims restored.
+///
+/// Digests are resolved recursively: disclosed values may themselves contain
+/// `_sd` digests for nested claims. Digests without a corresponding
+/// disclosure (undisclosed claims or decoys) are ignored, and undisclosed
+/// array elements are removed from their array.
+///
+/// When a Key Binding JWT is present, its signature is verified using the
+/// `cnf.jwk` claim and its `sd_hash` checked against the presentation. Use
+/// [`SdJwt::verify_presentation`] to also check its `aud`, `nonce`, and `iat`.
+///
+/// # Errors
+/// Returns an error if the issuer-signed JWT cannot be verified, a disclosure
+/// does not match a digest in the JWT (including a nested claim whose parent
+/// was not disclosed), a digest appears more than once, a disclosed claim
+/// would overwrite an existing claim, a claim disclosure is used for an array
+/// element (or vice versa), or a KB-JWT is present and cannot be verified.
+pub async fn decode<F, Fut, T>(sd_jwt: &str, resolver: F) -> Result<Jwt<T>>
+where
+    T: DeserializeOwned + Send,
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("sd_jwt::decode");
+
+    let sd_jwt: SdJwt = sd_jwt.parse()?;
+    let jws: Jws = sd_jwt.issuer_jwt.parse()?;
+    jws.verify(resolver).await?;
+
+    let Jwt { header, mut claims } = jws.jwt::<Map<String, Value>>(false)?;
+    let sd_alg = parse_sd_alg(claims.remove("_sd_alg").as_ref())?;
+    let (claims, _) = disclose(claims, &sd_jwt.disclosures, &sd_alg)?;
+    if let Some(key_binding) = &sd_jwt.key_binding {
+        verify_key_binding(key_binding, &sd_jwt.sd_hash_with(&sd_alg), &claims)?;
+    }
+
+    Ok(Jwt {
+        header,
+        claims: serde_json::from_value(Value::Object(claims))?,
+    })
+}
+
+// The digest algorithm declared by an `_sd_alg` claim, defaulting to SHA-256
+// when absent.
+fn parse_sd_alg(claim: Option<&Value>) -> Result<SdAlg> {
+    match claim {
+        None => Ok(SdAlg::default()),
+        Some(Value::String(name)) => name.parse(),
+        Some(other) => bail!("unsupported `_sd_alg`: {other}"),
+    }
+}
+
+// Replace `_sd` digests with their disclosed claims, returning the claims
+// and JSON pointers to the disclosed claims.
+fn disclose(
+    claims: Map<String, Value>, disclosures: &[String], sd_alg: &SdAlg,
+) -> Result<(Map<String, Value>, Vec<String>)> {
+    let mut by_digest = HashMap::with_capacity(disclosures.len());
+    for encoded in disclosures {
+        if by_digest.insert(sd_alg.digest(encoded), Disclosure::decode(encoded)?).is_some() {
+            bail!("digest is disclosed more than once");
+        }
+    }
+
+    let mut claims = Value::Object(claims);
+    let mut disclosed = vec![];
+    restore(&mut claims, "", &mut by_digest, &mut HashSet::new(), &mut disclosed)?;
+    if !by_digest.is_empty() {
+        bail!("disclosure does not match any digest");
+    }
+    disclosed.sort();
+
+    let Value::Object(claims) = claims else {
+        bail!("claims must be an object");
+    };
+    Ok((claims, disclosed))
+}
+
+// Verify the KB-JWT was signed by the holder's `cnf` key over the presented
+// SD-JWT, returning its claims.
+fn verify_key_binding(
+    key_binding: &str, sd_hash: &str, claims: &Map<String, Value>,
+) -> Result<KeyBindingClaims> {
+    let Ok(Key::Jwk(holder_jwk)) = cnf::confirmation_key(&Value::Object(claims.clone())) else {
+        bail!("key binding requires a `cnf.jwk` claim");
+    };
+
+    let kb_jwt = jws::decode_with_key::<KeyBindingClaims>(key_binding, &holder_jwk)?;
+    if kb_jwt.header.typ != KB_JWT_TYPE {
+        bail!("invalid KB-JWT `typ`: {}", kb_jwt.header.typ);
+    }
+    if !ct_eq(kb_jwt.claims.sd_hash.as_bytes(), sd_hash.as_bytes()) {
+        bail!("KB-JWT `sd_hash` does not match the presentation");
+    }
+    Ok(kb_jwt.claims)
+}
+
+// Check the KB-JWT was issued for the Verifier's request at the provided time
+// (seconds since Unix epoch).
+fn check_key_binding(
+    claims: &KeyBindingClaims, expected_aud: &str, expected_nonce: &str, now: i64,
+) -> Result<()> {
+    if claims.aud != expected_aud {
+        bail!("KB-JWT `aud` does not match the Verifier");
+    }
+    if claims.nonce != expected_nonce {
+        bail!("KB-JWT `nonce` does not match the request");
+    }
+
+    // `iat` is holder-chosen: reject values too extreme to compare
+    let Some(age) = now.checked_sub(claims.iat) else {
+        bail!("KB-JWT `iat` is out of range");
+    };
+    if age > KB_JWT_MAX_AGE {
+        bail!("KB-JWT has expired");
+    }
+    if age < -CLOCK_SKEW {
+        bail!("KB-JWT `iat` is in the future");
+    }
+    Ok(())
+}
+
+// Recursively restore disclosed claims, consuming each matched disclosure and
+// recording the JSON pointer of each restored claim in `disclosed`.
+fn restore(
+    value: &mut Value, path: &str, disclosures: &mut HashMap<String, Disclosure>,
+    seen: &mut HashSet<String>, disclosed: &mut Vec<String>,
+) -> Result<()> {
+    match value {
+        Value::Object(object) => {
+            let digests = match object.remove("_sd") {
+                Some(Value::Array(digests)) => digests,
+                Some(_) => bail!("`_sd` must be an array"),
+                None => vec![],
+            };
+
+            for (name, child) in object.iter_mut() {
+                restore(child, &pointer(path, name), disclosures, seen, disclosed)?;
+            }
+
+            for digest in digests {
+                let Value::String(digest) = digest else {
+                    bail!("`_sd` digests must be strings");
+                };
+                if !seen.insert(digest.clone()) {
+                    bail!("digest {digest} appears more than once");
+                }
+                let Some(disclosure) = disclosures.remove(&digest) else {
+                    continue;
+                };
+                let Some(name) = disclosure.name else {
+                    bail!("array element disclosed as a claim");
+                };
+                if name == "_sd" || name == "..." {
+                    bail!("disclosed claim name {name} is not permitted");
+                }
+                if object.contains_key(&name) {
+                    bail!("disclosed claim {name} already exists");
+                }
+                let path = pointer(path, &name);
+                let mut value = disclosure.value;
+                restore(&mut value, &path, disclosures, seen, disclosed)?;
+                object.insert(name, value);
+                disclosed.push(path);
+            }
+        }
+        Value::Array(array) => {
+            let mut restored = Vec::with_capacity(array.len());
+            for mut item in std::mem::take(array) {
+                let item_path = pointer(path, &restored.len().to_string());
+
+                // an array element digest is an object with a single `...` key
+                let Some(digest) = element_digest(&item) else {
+                    restore(&mut item, &item_path, disclosures, seen, disclosed)?;
+                    restored.push(item);
+                    continue;
+                };
+                if !seen.insert(digest.to_string()) {
+                    bail!("digest {digest} appears more than once");
+                }
+                let Some(disclosure) = disclosures.remove(digest) else {
+                    continue;
+                };
+                if disclosure.name.is_some() {
+                    bail!("claim disclosed as an array element");
+                }
+                let mut value = disclosure.value;
+                restore(&mut value, &item_path, disclosures, seen, disclosed)?;
+                restored.push(value);
+                disclosed.push(item_path);
+            }
+            *array = restored;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// The digest of an array element digest object: `{"...": "<digest>"}`.
+fn element_digest(item: &Value) -> Option<&str> {
+    match item {
+        Value::Object(object) if object.len() == 1 => object.get("...")?.as_str(),
+        _ => None,
+    }
+}
+
+// Append `token` to a JSON pointer, escaping as per RFC6901.
+fn pointer(path: &str, token: &str) -> String {
+    format!("{path}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Builds an SD-JWT from a set of claims, making the nominated claims
+/// selectively disclosable.
+pub struct SdJwtBuilder<S> {
+    jwt_type: String,
+    claims: Map<String, Value>,
+    disclosable: Vec<String>,
+    decoys: usize,
+    sd_alg: SdAlg,
+    signer: S,
+}
+
+#[doc(hidden)]
+/// Typestate generic for an SD-JWT builder with no signer.
+pub struct NoSigner;
+#[doc(hidden)]
+/// Typestate generic for an SD-JWT builder with a signer.
+pub struct HasSigner<'a>(&'a dyn DynSigner);
+
+impl Default for SdJwtBuilder<NoSigner> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SdJwtBuilder<NoSigner> {
+    /// Create a new SD-JWT builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            jwt_type: "sd+jwt".into(),
+            claims: Map::new(),
+            disclosable: vec![],
+            decoys: 0,
+            sd_alg: SdAlg::default(),
+            signer: NoSigner,
+        }
+    }
+
+    /// Set the signer used to sign the issuer JWT.
+    #[must_use]
+    pub fn add_signer(self, signer: &dyn DynSigner) -> SdJwtBuilder<HasSigner<'_>> {
+        SdJwtBuilder {
+            jwt_type: self.jwt_type,
+            claims: self.claims,
+            disclosable: self.disclosable,
+            decoys: self.decoys,
+            sd_alg: self.sd_alg,
+            signer: HasSigner(signer),
+        }
+    }
+}
+
+impl<S> SdJwtBuilder<S> {
+    /// Specify JWT `typ` header.
+    #[must_use]
+    pub fn jwt_type(mut self, jwt_type: impl Into<String>) -> Self {
+        self.jwt_type = jwt_type.into();
+        self
+    }
+
+    /// Set the claims to be issued.
+    #[must_use]
+    pub fn claims(mut self, claims: Map<String, Value>) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    /// Make the claim at `path` selectively disclosable. Nested claims are
+    /// identified using a dotted (`address.street`) or JSON pointer
+    /// (`/address/street`) path. Array elements are identified by index
+    /// (`/nationalities/1`) and replaced by a `{"...": "<digest>"}` object.
+    #[must_use]
+    pub fn disclose(mut self, path: impl Into<String>) -> Self {
+        self.disclosable.push(path.into());
+        self
+    }
+
+    /// Add `decoys` random digests to each `_sd` array so verifiers cannot
+    /// infer the number of undisclosed claims.
+    #[must_use]
+    pub const fn with_decoys(mut self, decoys: usize) -> Self {
+        self.decoys = decoys;
+        self
+    }
+
+    /// Set the hash algorithm used to compute disclosure digests, declared
+    /// in the `_sd_alg` claim. Defaults to SHA-256.
+    #[must_use]
+    pub const fn sd_alg(mut self, sd_alg: SdAlg) -> Self {
+        self.sd_alg = sd_alg;
+        self
+    }
+}
+
+impl SdJwtBuilder<HasSigner<'_>> {
+    /// Build and sign the SD-JWT.
+    ///
+    /// # Errors
+    /// Returns an error if a nominated claim is missing or its parent is not
+    /// an object or array, or the issuer JWT cannot be signed.
+    pub async fn build(self) -> Result<SdJwt> {
+        let mut paths = self.disclosable.iter().map(|p| parse_path(p)).collect::<Vec<_>>();
+
+        // disclose the deepest claims first so a child's digest is captured
+        // in its parent's disclosure
+        paths.sort_by_key(|path| std::cmp::Reverse(path.len()));
+
+        let mut claims = Value::Object(self.claims);
+        let mut disclosures = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            let Some((name, parents)) = path.split_last() else {
+                bail!("empty claim path");
+            };
+            let mut parent = &mut claims;
+            for key in parents {
+                let Some(child) = child_mut(parent, key) else {
+                    bail!("claim {} is not an object or array", path.join("."));
+                };
+                parent = child;
+            }
+
+            let encoded = match parent {
+                Value::Object(object) => {
+                    let Some(mut value) = object.remove(name) else {
+                        bail!("claim {} not found", path.join("."));
+                    };
+                    finalize(&mut value, self.decoys, &self.sd_alg);
+                    let encoded = Disclosure::new(name, value).encode()?;
+
+                    let sd = object.entry("_sd").or_insert_with(|| Value::Array(vec![]));
+                    if let Value::Array(digests) = sd {
+                        digests.push(self.sd_alg.digest(&encoded).into());
+                    }
+                    encoded
+                }
+                Value::Array(array) => {
+                    // replace the element in place so sibling indexes are unchanged
+                    let Some(item) = name.parse::<usize>().ok().and_then(|i| array.get_mut(i))
+                    else {
+                        bail!("claim {} not found", path.join("."));
+                    };
+                    let mut value = item.take();
+                    finalize(&mut value, self.decoys, &self.sd_alg);
+                    let encoded = Disclosure::element(value).encode()?;
+                    *item = serde_json::json!({"...": self.sd_alg.digest(&encoded)});
+                    encoded
+                }
+                _ => bail!("claim {} is not an object or array", path.join(".")),
+            };
+            disclosures.push(encoded);
+        }
+
+        if self.decoys > 0 {
+            if let Value::Object(object) = &mut claims {
+                object.entry("_sd").or_insert_with(|| Value::Array(vec![]));
+            }
+        }
+        finalize(&mut claims, self.decoys, &self.sd_alg);
+        let Value::Object(mut claims) = claims else {
+            bail!("claims must be an object");
+        };
+        if claims.contains_key("_sd") || self.sd_alg != SdAlg::default() {
+            claims.insert("_sd_alg".into(), self.sd_alg.to_string().into());
+        }
+
+        let jws = JwsBuilder::new()
+            .jwt_type(self.jwt_type)
+            .payload(Value::Object(claims))
+            .add_signer(self.signer.0)
+            .build()
+            .await?;
+
+        Ok(SdJwt {
+            issuer_jwt: jws.to_compact()?,
+            disclosures,
+            ..SdJwt::default()
+        })
+    }
+}
+
+/// Builds a holder's presentation of an issued SD-JWT, selecting the
+/// disclosures to reveal and optionally binding the presentation to the
+/// holder's key.
+pub struct SdJwtPresentation<K> {
+    sd_jwt: SdJwt,
+    paths: Vec<String>,
+    key_binding: K,
+}
+
+/// Builder has no key binding.
+#[doc(hidden)]
+pub struct NoKeyBinding;
+/// Builder has a key binding signer, audience, and nonce.
+#[doc(hidden)]
+pub struct KeyBinding<'a> {
+    signer: &'a dyn DynSigner,
+    aud: String,
+    nonce: String,
+}
+
+impl SdJwtPresentation<NoKeyBinding> {
+    /// Create a presentation of an issued SD-JWT holding the full set of
+    /// disclosures.
+    #[must_use]
+    pub const fn new(issued: SdJwt) -> Self {
+        Self {
+            sd_jwt: issued,
+            paths: vec![],
+            key_binding: NoKeyBinding,
+        }
+    }
+
+    /// Bind the presentation to the holder's key, appending a KB-JWT for the
+    /// specified audience and nonce.
+    #[must_use]
+    pub fn key_bind(
+        self, signer: &dyn DynSigner, aud: impl Into<String>, nonce: impl Into<String>,
+    ) -> SdJwtPresentation<KeyBinding<'_>> {
+        SdJwtPresentation {
+            sd_jwt: self.sd_jwt,
+            paths: self.paths,
+            key_binding: KeyBinding {
+                signer,
+                aud: aud.into(),
+                nonce: nonce.into(),
+            },
+        }
+    }
+
+    /// Build the presentation without key binding.
+    ///
+    /// # Errors
+    /// Returns an error if a requested claim is not present in the credential.
+    pub fn build(self) -> Result<SdJwt> {
+        self.select()
+    }
+}
+
+impl<K> SdJwtPresentation<K> {
+    /// Reveal the claims at the specified dotted or JSON pointer paths. The
+    /// disclosures of any selectively disclosable parent claims are included
+    /// automatically.
+    #[must_use]
+    pub fn disclose(mut self, paths: &[&str]) -> Self {
+        self.paths.extend(paths.iter().map(ToString::to_string));
+        self
+    }
+
+    // Select the disclosures required to reveal the requested claims.
+    fn select(&self) -> Result<SdJwt> {
+        let jws: Jws = self.sd_jwt.issuer_jwt.parse()?;
+        let claims = jws.jwt::<Value>(false)?.claims;
+        let sd_alg = parse_sd_alg(claims.get("_sd_alg"))?;
+
+        let mut by_digest = HashMap::with_capacity(self.sd_jwt.disclosures.len());
+        for encoded in &self.sd_jwt.disclosures {
+            by_digest.insert(sd_alg.digest(encoded), Disclosure::decode(encoded)?);
+        }
+
+        let mut selected = HashSet::new();
+        for path in &self.paths {
+            let mut current = &claims;
+            for key in parse_path(path) {
+                if let Value::Array(array) = current {
+                    let Some(item) = key.parse::<usize>().ok().and_then(|i| array.get(i)) else {
+                        bail!("claim {path} not found");
+                    };
+                    let Some(digest) = element_digest(item) else {
+                        current = item;
+                        continue;
+                    };
+                    let Some(disclosure) = by_digest.get(digest) else {
+                        bail!("claim {path} not found");
+                    };
+                    selected.insert(digest.to_string());
+                    current = &disclosure.value;
+                    continue;
+                }
+
+                if let Some(value) = current.get(&key) {
+                    current = value;
+                    continue;
+                }
+                let digests = current.get("_sd").and_then(Value::as_array);
+                let found = digests.into_iter().flatten().filter_map(Value::as_str).find_map(|d| {
+                    by_digest
+                        .get(d)
+                        .filter(|disclosure| disclosure.name.as_deref() == Some(key.as_str()))
+                        .map(|disclosure| (d, disclosure))
+                });
+                let Some((digest, disclosure)) = found else {
+                    bail!("claim {path} not found");
+                };
+                selected.insert(digest.to_string());
+                current = &disclosure.value;
+            }
+        }
+
+        Ok(SdJwt {
+            issuer_jwt: self.sd_jwt.issuer_jwt.clone(),
+            disclosures: self
+                .sd_jwt
+                .disclosures
+                .iter()
+                .filter(|encoded| selected.contains(&sd_alg.digest(encoded)))
+                .cloned()
+                .collect(),
+            key_binding: None,
+        })
+    }
+}
+
+impl SdJwtPresentation<KeyBinding<'_>> {
+    /// Build the presentation, signing a KB-JWT over the selected
+    /// disclosures.
+    ///
+    /// # Errors
+    /// Returns an error if a requested claim is not present in the credential
+    /// or the KB-JWT cannot be signed.
+    pub async fn build(self) -> Result<SdJwt> {
+        let mut presentation = self.select()?;
+
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = KeyBindingClaims {
+            iat: i64::try_from(iat)?,
+            aud: self.key_binding.aud,
+            nonce: self.key_binding.nonce,
+            sd_hash: presentation.sd_hash(),
+        };
+        let kb_jwt = JwsBuilder::new()
+            .jwt_type(KB_JWT_TYPE)
+            .payload(claims)
+            .add_signer(self.key_binding.signer)
+            .build()
+            .await?;
+
+        presentation.key_binding = Some(kb_jwt.to_compact()?);
+        Ok(presentation)
+    }
+}
+
+// Split a dotted or JSON pointer claim path into its components.
+fn parse_path(path: &str) -> Vec<String> {
+    path.strip_prefix('/').map_or_else(
+        || path.split('.').map(ToString::to_string).collect(),
+        |pointer| pointer.split('/').map(|key| key.replace("~1", "/").replace("~0", "~")).collect(),
+    )
+}
+
+// The child of an object or array at `key`, where `key` is an array index.
+fn child_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Object(object) => object.get_mut(key),
+        Value::Array(array) => array.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+// Add decoys to, and sort, each `_sd` array in the value (and nested values)
+// so decoys cannot be distinguished by position.
+fn finalize(value: &mut Value, decoys: usize, sd_alg: &SdAlg) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Array(digests)) = object.get_mut("_sd") {
+                add_decoys(digests, decoys, sd_alg);
+            }
+            for (key, child) in object.iter_mut() {
+                if key != "_sd" {
+                    finalize(child, decoys, sd_alg);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for item in array {
+                finalize(item, decoys, sd_alg);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Add `count` decoy digests, then sort the digests.
+fn add_decoys(digests: &mut Vec<Value>, count: usize, sd_alg: &SdAlg) {
+    let mut added = 0;
+    while added < count {
+        let decoy = Base64UrlUnpadded::encode_string(&sd_alg.hash(&random_bytes::<32>()));
+        if !digests.iter().any(|d| d.as_str() == Some(decoy.as_str())) {
+            digests.push(decoy.into());
+            added += 1;
+        }
+    }
+    digests.sort_unstable_by(|a, b| a.as_str().cmp(&b.as_str()));
+}
+
+// Cryptographically random bytes.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{InMemoryKeyOps, InMemorySigner};
+    use crate::Algorithm;
+
+    fn new_signer(controller: &str) -> InMemorySigner {
+        let key_ops = InMemoryKeyOps::new();
+        key_ops.register(controller, &Algorithm::EdDSA).expect("should register");
+        key_ops.signer(controller).expect("should get signer")
+    }
+
+    fn resolver(
+        issuer: &InMemorySigner,
+    ) -> impl Fn(String) -> std::future::Ready<Result<PublicKeyJwk>> {
+        let jwk = issuer.public_jwk().expect("should get key");
+        move |_| std::future::ready(Ok(jwk.clone()))
+    }
+
+    fn claims() -> Map<String, Value> {
+        json!({"iss": "https://issuer.example.com", "given_name": "Alice", "family_name": "Smith"})
+            .as_object()
+            .cloned()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(claims())
+            .disclose("given_name")
+            .disclose("family_name")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+        assert_eq!(sd_jwt.disclosures.len(), 2);
+
+        // disclose all
+        let jwt: Jwt<Value> =
+            decode(&sd_jwt.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(Value::Object(claims()), jwt.claims);
+
+        // disclose only `given_name`
+        let partial = SdJwt {
+            disclosures: vec![sd_jwt.disclosures[0].clone()],
+            ..sd_jwt
+        };
+        let jwt: Jwt<Value> =
+            decode(&partial.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(jwt.claims, json!({"iss": "https://issuer.example.com", "given_name": "Alice"}));
+    }
+
+    #[tokio::test]
+    async fn decoys() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(claims())
+            .disclose("given_name")
+            .with_decoys(3)
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let jws: Jws = sd_jwt.issuer_jwt.parse().expect("should parse");
+        let payload = jws.jwt::<Value>(false).expect("should decode").claims;
+        let digests = payload["_sd"].as_array().expect("should have `_sd`");
+        assert_eq!(digests.len(), 4);
+        assert!(digests.contains(&Value::String(digest(&sd_jwt.disclosures[0]))));
+
+        // decoys are never matched
+        let jwt: Jwt<Value> =
+            decode(&sd_jwt.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(jwt.claims["given_name"], "Alice");
+    }
+
+    #[tokio::test]
+    async fn sd_alg() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(claims())
+            .disclose("given_name")
+            .disclose("family_name")
+            .with_decoys(1)
+            .sd_alg(SdAlg::Sha384)
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let jws: Jws = sd_jwt.issuer_jwt.parse().expect("should parse");
+        let payload = jws.jwt::<Value>(false).expect("should decode").claims;
+        assert_eq!(payload["_sd_alg"], "sha-384");
+        let digests = payload["_sd"].as_array().expect("should have `_sd`");
+        assert!(digests.iter().all(|d| d.as_str().is_some_and(|d| d.len() == 64)));
+        assert!(digests.contains(&Value::String(SdAlg::Sha384.digest(&sd_jwt.disclosures[0]))));
+
+        // digests are compared using the declared algorithm
+        let presentation = SdJwtPresentation::new(sd_jwt)
+            .disclose(&["family_name"])
+            .build()
+            .expect("should build");
+        assert_eq!(presentation.disclosures.len(), 1);
+        assert_eq!(presentation.sd_hash(), SdAlg::Sha384.digest(&presentation.to_string()));
+        let verified = presentation.verify(resolver(&issuer)).await.expect("should verify");
+        assert_eq!(verified.disclosed, ["/family_name"]);
+
+        // unsupported algorithms are rejected
+        let mut claims = claims();
+        claims.insert("_sd_alg".into(), "md5".into());
+        let issuer_jwt = JwsBuilder::new()
+            .jwt_type("sd+jwt")
+            .payload(Value::Object(claims))
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build")
+            .to_compact()
+            .expect("should serialize");
+        let err = decode::<_, _, Value>(&format!("{issuer_jwt}~"), resolver(&issuer))
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "unsupported `_sd_alg`: md5");
+    }
+
+    fn address() -> Map<String, Value> {
+        json!({
+            "iss": "https://issuer.example.com",
+            "address": {"street": "123 Main St", "locality": "Anytown"},
+        })
+        .as_object()
+        .cloned()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nested() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(address())
+            .disclose("/address/street")
+            .disclose("address")
+            .with_decoys(1)
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        // `street` is disclosed first, then `address`
+        let street = Disclosure::decode(&sd_jwt.disclosures[0]).expect("should decode");
+        let addr = Disclosure::decode(&sd_jwt.disclosures[1]).expect("should decode");
+        assert_eq!(street.name.as_deref(), Some("street"));
+        assert_eq!(addr.name.as_deref(), Some("address"));
+        assert_eq!(addr.value["_sd"].as_array().map(Vec::len), Some(2));
+
+        // both disclosed
+        let jwt: Jwt<Value> =
+            decode(&sd_jwt.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(Value::Object(address()), jwt.claims);
+
+        // parent disclosed, child withheld
+        let parent_only = SdJwt {
+            disclosures: vec![sd_jwt.disclosures[1].clone()],
+            ..sd_jwt.clone()
+        };
+        let jwt: Jwt<Value> =
+            decode(&parent_only.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(jwt.claims["address"], json!({"locality": "Anytown"}));
+
+        // child disclosed without its parent
+        let child_only = SdJwt {
+            disclosures: vec![sd_jwt.disclosures[0].clone()],
+            ..sd_jwt
+        };
+        let err = decode::<_, _, Value>(&child_only.to_string(), resolver(&issuer))
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "disclosure does not match any digest");
+    }
+
+    #[tokio::test]
+    async fn verify() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(address())
+            .disclose("/address/street")
+            .disclose("address")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let verified = sd_jwt.verify(resolver(&issuer)).await.expect("should verify");
+        assert_eq!(verified.claims, Value::Object(address()));
+        assert_eq!(verified.disclosed, vec!["/address", "/address/street"]);
+
+        // undisclosed claims do not appear
+        let parent_only = SdJwt {
+            disclosures: vec![sd_jwt.disclosures[1].clone()],
+            ..sd_jwt
+        };
+        let verified = parent_only.verify(resolver(&issuer)).await.expect("should verify");
+        assert_eq!(
+            verified.claims,
+            json!({"iss": "https://issuer.example.com", "address": {"locality": "Anytown"}})
+        );
+        assert_eq!(verified.disclosed, vec!["/address"]);
+
+        let other = new_signer("did:example:issuer");
+        assert!(parent_only.verify(resolver(&other)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn nested_child_only() {
+        let issuer = new_signer("did:example:issuer");
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(address())
+            .disclose("address.street")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let withheld = SdJwt {
+            disclosures: vec![],
+            ..sd_jwt.clone()
+        };
+        let jwt: Jwt<Value> =
+            decode(&withheld.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(jwt.claims["address"], json!({"locality": "Anytown"}));
+
+        let jwt: Jwt<Value> =
+            decode(&sd_jwt.to_string(), resolver(&issuer)).await.expect("should decode");
+        assert_eq!(Value::Object(address()), jwt.claims);
+    }
+
+    #[tokio::test]
+    async fn array_elements() {
+        let issuer = new_signer("did:example:issuer");
+        let claims = json!({
+            "iss": "https://issuer.example.com",
+            "nationalities": ["US", "DE", "FR"],
+        });
+        let sd_jwt = SdJwtBuilder::new()
+            .claims(claims.as_object().cloned().unwrap())
+            .disclose("/nationalities/1")
+            .disclose("nationalities.2")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        // disclosable elements are replaced by `...` digests
+        let jws: Jws = sd_jwt.issuer_jwt.parse().expect("should parse");
+        let payload = jws.jwt::<Value>(false).expect("should decode").claims;
+        let nationalities = payload["nationalities"].as_array().expect("should be an array");
+        assert_eq!(nationalities[0], "US");
+        assert_eq!(nationalities[1], json!({"...": digest(&sd_jwt.disclosures[0])}));
+
+        let element = Disclosure::decode(&sd_jwt.disclosures[0]).expect("should decode");
+        assert_eq!(element.name, None);
+        assert_eq!(element.value, "DE");
+
+        // all disclosed
+        let verified = sd_jwt.verify(resolver(&issuer)).await.expect("should verify");
+        assert_eq!(verified.claims, claims);
+        assert_eq!(verified.disclosed, vec!["/nationalities/1", "/nationalities/2"]);
+
+        // undisclosed elements are removed
+        let presented = SdJwtPresentation::new(sd_jwt)
+            .disclose(&["/nationalities/2"])
+            .build()
+            .expect("should build");
+        assert_eq!(presented.disclosures.len(), 1);
+        let verified = presented.verify(resolver(&issuer)).await.expect("should verify");
+        assert_eq!(verified.claims["nationalities"], json!(["US", "FR"]));
+        assert_eq!(verified.disclosed, vec!["/nationalities/1"]);
+    }
+
+    #[tokio::test]
+    async fn presentation() {
+        let issuer = new_signer("did:example:issuer");
+        let holder = new_signer("did:example:holder");
+
+        let mut claims = address();
+        claims.insert("given_name".into(), "Alice".into());
+        claims.insert("cnf".into(), json!({"jwk": holder.public_jwk().expect("should get key")}));
+        let credential = SdJwtBuilder::new()
+            .claims(claims)
+            .disclose("given_name")
+            .disclose("address")
+            .disclose("address.street")
+            .disclose("address.locality")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let presented = SdJwtPresentation::new(credential.clone())
+            .disclose(&["given_name", "address.street"])
+            .key_bind(&holder, "https://verifier.example.com", "abc123")
+            .build()
+            .await
+            .expect("should build");
+
+        // `address` is included as the parent of `address.street`
+        assert_eq!(presented.disclosures.len(), 3);
+        let serialized = presented.to_string();
+        assert_eq!(serialized.parse::<SdJwt>().expect("should parse"), presented);
+
+        let jwt: Jwt<Value> = decode(&serialized, resolver(&issuer)).await.expect("should decode");
+        assert_eq!(jwt.claims["given_name"], "Alice");
+        assert_eq!(jwt.claims["address"], json!({"street": "123 Main St"}));
+
+        let kb_jwt = presented.key_binding.clone().expect("should have KB-JWT");
+        let kb_claims = Jws::from_str(&kb_jwt)
+            .and_then(|jws| jws.jwt::<KeyBindingClaims>(false))
+            .expect("should decode")
+            .claims;
+        assert_eq!(kb_claims.nonce, "abc123");
+        assert_eq!(kb_claims.aud, "https://verifier.example.com");
+
+        // the verified KB-JWT claims are returned
+        let verified = presented
+            .verify_presentation(resolver(&issuer), "https://verifier.example.com", "abc123")
+            .await
+            .expect("should verify");
+        assert_eq!(verified.key_binding, Some(kb_claims.clone()));
+
+        let err = presented
+            .verify_presentation(resolver(&issuer), "https://other.example.com", "abc123")
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "KB-JWT `aud` does not match the Verifier");
+        let err = presented
+            .verify_presentation(resolver(&issuer), "https://verifier.example.com", "replayed")
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "KB-JWT `nonce` does not match the request");
+
+        let check = |now| {
+            check_key_binding(&kb_claims, "https://verifier.example.com", "abc123", now)
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(check(kb_claims.iat + KB_JWT_MAX_AGE + 1), Err("KB-JWT has expired".into()));
+        assert_eq!(check(i64::MIN), Err("KB-JWT `iat` is out of range".into()));
+
+        let err = credential
+            .verify_presentation(resolver(&issuer), "https://verifier.example.com", "abc123")
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "presentation is missing a KB-JWT");
+
+        // KB-JWT does not cover additional disclosures
+        let tampered = SdJwt {
+            key_binding: Some(kb_jwt),
+            ..credential
+        };
+        let err = decode::<_, _, Value>(&tampered.to_string(), resolver(&issuer))
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "KB-JWT `sd_hash` does not match the presentation");
+    }
+
+    #[tokio::test]
+    async fn presentation_unknown_claim() {
+        let issuer = new_signer("did:example:issuer");
+        let credential = SdJwtBuilder::new()
+            .claims(claims())
+            .disclose("given_name")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let err = SdJwtPresentation::new(credential)
+            .disclose(&["address.street"])
+            .build()
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "claim address.street not found");
+    }
+
+    #[tokio::test]
+    async fn unknown_disclosure() {
+        let issuer = new_signer("did:example:issuer");
+        let mut sd_jwt = SdJwtBuilder::new()
+            .claims(claims())
+            .disclose("given_name")
+            .add_signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+        sd_jwt.disclosures.push(Disclosure::new("age", json!(42)).encode().unwrap());
+
+        let err = decode::<_, _, Value>(&sd_jwt.to_string(), resolver(&issuer))
+            .await
+            .expect_err("should fail");
+        assert_eq!(err.to_string(), "disclosure does not match any digest");
+    }
+
+    #[tokio::test]
+    async fn reserved_claim_name() {
+        let issuer = new_signer("did:example:issuer");
+
+        for name in ["_sd", "..."] {
+            let disclosure = Disclosure::new(name, json!(["digest"])).encode().unwrap();
+            let issuer_jwt = JwsBuilder::new()
+                .jwt_type("sd+jwt")
+                .payload(json!({"iss": "https://issuer.example.com", "_sd": [digest(&disclosure)]}))
+                .add_signer(&issuer)
+                .build()
+                .await
+                .expect("should build")
+                .to_compact()
+                .expect("should serialize");
+            let sd_jwt = SdJwt {
+                issuer_jwt,
+                disclosures: vec![disclosure],
+                key_binding: None,
+            };
+
+            let err = sd_jwt.verify(resolver(&issuer)).await.expect_err("should fail");
+            assert_eq!(err.to_string(), format!("disclosed claim name {name} is not permitted"));
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! # Token Status List
+//!
+//! A Token Status List ([Token Status List]) is a JWT, signed by the issuer,
+//! holding the status of each credential it has issued as a compressed array
+//! of 1, 2, 4, or 8-bit entries. A credential references its entry using the
+//! `status.status_list` claim:
+//!
+//! ```json
+//! "status": {
+//!   "status_list": {
+//!     "idx": 0,
+//!     "uri": "https://example.com/statuslists/1"
+//!   }
+//! }
+//! ```
+//!
+//! Issuers publish the list using [`StatusListBuilder`]. Verifiers fetch the
+//! list from `uri`, verify it using [`decode`], and look up the credential's
+//! status using [`StatusList::status`].
+//!
+//! [Token Status List]: https://datatracker.ietf.org/doc/draft-ietf-oauth-status-list
+
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde::{Deserialize, Serialize};
+
+use crate::jose::jwk::PublicKeyJwk;
+use crate::jose::jws::{self, JwsBuilder};
+use crate::DynSigner;
+
+/// The `typ` header of a status list JWT.
+const STATUS_LIST_TYPE: &str = "statuslist+jwt";
+
+/// The maximum length, in bytes, of a decompressed status list.
+const MAX_LIST_LEN: usize = 4 * 1024 * 1024;
+
+/// The status of a valid credential.
+pub const VALID: u8 = 0x00;
+
+/// The status of a revoked credential.
+pub const INVALID: u8 = 0x01;
+
+/// The status of a temporarily suspended credential.
+pub const SUSPENDED: u8 = 0x02;
+
+/// The claims of a status list JWT.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StatusListClaims {
+    /// The URI of the status list, matching the `uri` credentials use to
+    /// reference it.
+    pub sub: String,
+
+    /// The time, in seconds since the Unix epoch, the list was issued.
+    pub iat: i64,
+
+    /// The time, in seconds since the Unix epoch, after which the list must
+    /// not be used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+
+    /// The maximum time, in seconds, the list may be cached before fetching
+    /// a fresh copy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+
+    /// The status list.
+    pub status_list: StatusListClaim,
+}
+
+/// The `status_list` claim of a status list JWT.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StatusListClaim {
+    /// The number of bits per entry: 1, 2, 4, or 8.
+    pub bits: u8,
+
+    /// The base64url-encoded, zlib compressed status entries.
+    pub lst: String,
+}
+
+/// A credential's `status` claim.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Status {
+    /// The credential's entry in a status list.
+    pub status_list: StatusListReference,
+}
+
+/// A reference to a credential's entry in a status list.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StatusListReference {
+    /// The index of the credential's entry.
+    pub idx: usize,
+
+    /// The URI of the status list.
+    pub uri: String,
+}
+
+/// A verified, decompressed status list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusList {
+    /// The URI of the status list (its `sub` claim).
+    pub uri: String,
+
+    bits: u8,
+    entries: Vec<u8>,
+}
+
+/// Decode and verify a status list JWT, returning the decompressed list.
+///
+/// The JWT must have the `typ` `statuslist+jwt` and must not have expired.
+/// Its signature is verified using the key returned by `resolver`, as per
+/// [`jws::decode`].
+///
+/// # Errors
+/// Returns an error if the JWT has the wrong `typ`, cannot be verified, has
+/// expired, or its status list is invalid (see [`StatusList::new`]).
+pub async fn decode<F, Fut>(status_list_jwt: &str, resolver: F) -> Result<StatusList>
+where
+    F: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<PublicKeyJwk>> + Send,
+{
+    tracing::debug!("status::decode");
+
+    let jwt =
+        jws::decode_typed::<_, _, StatusListClaims>(status_list_jwt, resolver, &[STATUS_LIST_TYPE])
+            .await?;
+    let claims = jwt.claims;
+
+    if let Some(exp) = claims.exp {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if i64::try_from(now)? >= exp {
+            bail!("status list has expired");
+        }
+    }
+
+    StatusList::new(claims.sub, &claims.status_list)
+}
+
+impl StatusList {
+    /// Create a status list at `uri` with room for at least `capacity`
+    /// entries of `bits` bits each, all set to [`VALID`].
+    ///
+    /// # Errors
+    /// Returns an error if `bits` is not 1, 2, 4, or 8, or the list would
+    /// exceed 4,194,304 bytes.
+    pub fn with_capacity(uri: impl Into<String>, capacity: usize, bits: u8) -> Result<Self> {
+        check_bits(bits)?;
+        let len = capacity.saturating_mul(usize::from(bits)).div_ceil(8);
+        if len > MAX_LIST_LEN {
+            bail!("status list capacity is too large");
+        }
+
+        Ok(Self {
+            uri: uri.into(),
+            bits,
+            entries: vec![0; len],
+        })
+    }
+
+    /// Decompress the `status_list` claim of the status list at `uri`.
+    ///
+    /// # Errors
+    /// Returns an error if `bits` is not 1, 2, 4, or 8, or `lst` is not
+    /// base64url-encoded zlib data that decompresses to at most 4,194,304
+    /// bytes.
+    pub fn new(uri: impl Into<String>, claim: &StatusListClaim) -> Result<Self> {
+        check_bits(claim.bits)?;
+        let compressed = Base64UrlUnpadded::decode_vec(&claim.lst)
+            .map_err(|e| anyhow!("issue decoding status list: {e}"))?;
+        let entries =
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&compressed, MAX_LIST_LEN)
+                .map_err(|e| anyhow!("issue decompressing status list: {e}"))?;
+
+        Ok(Self {
+            uri: uri.into(),
+            bits: claim.bits,
+            entries,
+        })
+    }
+
+    /// The number of entries in the list.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len() * 8 / usize::from(self.bits)
+    }
+
+    /// Returns `true` if the list has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The status at `index`. Entries are packed least significant bits
+    /// first within each byte.
+    ///
+    /// # Errors
+    /// Returns an error if `index` is beyond the end of the list.
+    pub fn status_at(&self, index: usize) -> Result<u8> {
+        if index >= self.len() {
+            bail!("status index {index} is out of range");
+        }
+        let bits = usize::from(self.bits);
+        let byte = self.entries[index * bits / 8];
+        let shift = index * bits % 8;
+        Ok((byte >> shift) & self.mask())
+    }
+
+    /// Set the status at `index`, leaving other entries unchanged.
+    ///
+    /// # Errors
+    /// Returns an error if `index` is beyond the end of the list or `status`
+    /// does not fit in the list's `bits`.
+    pub fn set(&mut self, index: usize, status: u8) -> Result<()> {
+        if index >= self.len() {
+            bail!("status index {index} is out of range");
+        }
+        if status > self.mask() {
+            bail!("status {status} does not fit in {} bits", self.bits);
+        }
+        let bits = usize::from(self.bits);
+        let shift = index * bits % 8;
+        let mask = self.mask() << shift;
+        let byte = &mut self.entries[index * bits / 8];
+        *byte = (*byte & !mask) | (status << shift);
+        Ok(())
+    }
+
+    /// Compress the list into a `status_list` claim.
+    #[must_use]
+    pub fn to_claim(&self) -> StatusListClaim {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&self.entries, 9);
+        StatusListClaim {
+            bits: self.bits,
+            lst: Base64UrlUnpadded::encode_string(&compressed),
+        }
+    }
+
+    // The mask for a single entry.
+    const fn mask(&self) -> u8 {
+        u8::MAX >> (8 - self.bits)
+    }
+
+    /// The status of the credential entry referenced by a credential's
+    /// `status.status_list` claim.
+    ///
+    /// # Errors
+    /// Returns an error if the reference is to a different status list, or
+    /// its index is beyond the end of the list.
+    pub fn status(&self, reference: &StatusListReference) -> Result<u8> {
+        if reference.uri != self.uri {
+            bail!("credential references a different status list: {}", reference.uri);
+        }
+        self.status_at(reference.idx)
+    }
+}
+
+fn check_bits(bits: u8) -> Result<()> {
+    if !matches!(bits, 1 | 2 | 4 | 8) {
+        bail!("status list `bits` must be 1, 2, 4, or 8");
+    }
+    Ok(())
+}
+
+/// Builds and signs a status list JWT.
+///
+/// The builder holds the uncompressed list, so a single entry can be updated
+/// using [`StatusListBuilder::set`] and the list re-signed using
+/// [`StatusListBuilder::build`] without rebuilding it.
+pub struct StatusListBuilder<S> {
+    list: StatusList,
+    ttl: Option<u64>,
+    exp: Option<i64>,
+    signer: S,
+}
+
+#[doc(hidden)]
+/// Typestate generic for a status list builder with no signer.
+pub struct NoSigner;
+#[doc(hidden)]
+/// Typestate generic for a status list builder with a signer.
+pub struct HasSigner<'a>(&'a dyn DynSigner);
+
+impl StatusListBuilder<NoSigner> {
+    /// Create a builder for the status list at `uri` with room for at least
+    /// `capacity` entries of `bits` bits each, all set to [`VALID`].
+    ///
+    /// # Errors
+    /// Returns an error if `bits` is not 1, 2, 4, or 8, or the list would be
+    /// too large (see [`StatusList::with_capacity`]).
+    pub fn new(uri: impl Into<String>, capacity: usize, bits: u8) -> Result<Self> {
+        Ok(Self {
+            list: StatusList::with_capacity(uri, capacity, bits)?,
+            ttl: None,
+            exp: None,
+            signer: NoSigner,
+        })
+    }
+
+    /// Set the signer used to sign the status list JWT.
+    #[must_use]
+    pub fn add_signer(self, signer: &dyn DynSigner) -> StatusListBuilder<HasSigner<'_>> {
+        StatusListBuilder {
+            list: self.list,
+            ttl: self.ttl,
+            exp: self.exp,
+            signer: HasSigner(signer),
+        }
+    }
+}
+
+impl<S> StatusListBuilder<S> {
+    /// Set the maximum time, in seconds, verifiers may cache the list.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: u64) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set the time, in seconds since the Unix epoch, the list expires.
+    #[must_use]
+    pub const fn exp(mut self, exp: i64) -> Self {
+        self.exp = Some(exp);
+        self
+    }
+
+    /// Set the status at `index`.
+    ///
+    /// # Errors
+    /// Returns an error if `index` is beyond the list's capacity or `status`
+    /// does not fit in the list's `bits`.
+    pub fn set(&mut self, index: usize, status: u8) -> Result<()> {
+        self.list.set(index, status)
+    }
+}
+
+impl StatusListBuilder<HasSigner<'_>> {
+    /// Compress and sign the current list, returning a compact status list
+    /// JWT. May be called again after updating entries to re-sign the list.
+    ///
+    /// # Errors
+    /// Returns an error if the JWT cannot be signed.
+    pub async fn build(&self) -> Result<String> {
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = StatusListClaims {
+            sub: self.list.uri.clone(),
+            iat: i64::try_from(iat)?,
+            exp: self.exp,
+            ttl: self.ttl,
+            status_list: self.list.to_claim(),
+        };
+
+        JwsBuilder::new()
+            .jwt_type(STATUS_LIST_TYPE)
+            .payload(claims)
+            .add_signer(self.signer.0)
+            .build()
+            .await?
+            .to_compact()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{InMemoryKeyOps, InMemorySigner};
+    use crate::Algorithm;
+
+    fn new_issuer() -> InMemorySigner {
+        let key_ops = InMemoryKeyOps::new();
+        key_ops.register("did:example:issuer", &Algorithm::EdDSA).expect("should register");
+        key_ops.signer("did:example:issuer").expect("should get signer")
+    }
+
+    const URI: &str = "https://example.com/statuslists/1";
+
+    fn claim(bits: u8, entries: &[u8]) -> StatusListClaim {
+        StatusListClaim {
+            bits,
+            lst: Base64UrlUnpadded::encode_string(&miniz_oxide::deflate::compress_to_vec_zlib(
+                entries, 6,
+            )),
+        }
+    }
+
+    // example from the Token Status List specification
+    #[test]
+    fn bits() {
+        let list = StatusList::new(URI, &claim(1, &[0xb9, 0xa3])).expect("should decompress");
+        assert_eq!(list.len(), 16);
+        let statuses = (0..16).map(|i| list.status_at(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(statuses, [1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 0, 0, 0, 1, 0, 1]);
+        assert!(list.status_at(16).is_err());
+
+        let list = StatusList::new(URI, &claim(2, &[0xc9, 0x44, 0xf9])).expect("should decompress");
+        let statuses = (0..12).map(|i| list.status_at(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(statuses, [1, 2, 0, 3, 0, 1, 0, 1, 1, 2, 3, 3]);
+
+        let list = StatusList::new(URI, &claim(4, &[0x21])).expect("should decompress");
+        assert_eq!((list.status_at(0).unwrap(), list.status_at(1).unwrap()), (1, 2));
+
+        let list = StatusList::new(URI, &claim(8, &[0x00, 0x7f])).expect("should decompress");
+        assert_eq!(list.status_at(1).unwrap(), 0x7f);
+
+        assert!(StatusList::new(URI, &claim(3, &[0x00])).is_err());
+    }
+
+    #[test]
+    fn size_bound() {
+        let err =
+            StatusList::new(URI, &claim(1, &vec![0; MAX_LIST_LEN + 1])).expect_err("should fail");
+        assert!(err.to_string().starts_with("issue decompressing status list"));
+    }
+
+    #[tokio::test]
+    async fn verify() {
+        let issuer = new_issuer();
+        let jwk = issuer.public_jwk().expect("should get key");
+        let claims = StatusListClaims {
+            sub: URI.to_string(),
+            iat: 1_700_000_000,
+            status_list: claim(2, &[0x09]),
+            ..StatusListClaims::default()
+        };
+        let build = |jwt_type: &'static str, claims: StatusListClaims| {
+            let issuer = &issuer;
+            async move {
+                JwsBuilder::new()
+                    .jwt_type(jwt_type)
+                    .payload(claims)
+                    .add_signer(issuer)
+                    .build()
+                    .await
+                    .expect("should build")
+                    .to_compact()
+                    .expect("should serialize")
+            }
+        };
+
+        let token = build(STATUS_LIST_TYPE, claims.clone()).await;
+        let list = decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+
+        let status: Status =
+            serde_json::from_value(json!({"status_list": {"idx": 1, "uri": URI}})).unwrap();
+        assert_eq!(list.status(&status.status_list).expect("should have status"), SUSPENDED);
+        let other = StatusListReference {
+            idx: 0,
+            uri: "https://example.com/statuslists/2".to_string(),
+        };
+        assert!(list.status(&other).is_err());
+
+        let token = build("jwt", claims.clone()).await;
+        assert!(decode(&token, |_| async { Ok(jwk.clone()) }).await.is_err());
+
+        let expired = StatusListClaims {
+            exp: Some(1_700_000_001),
+            ..claims
+        };
+        let token = build(STATUS_LIST_TYPE, expired).await;
+        let err = decode(&token, |_| async { Ok(jwk.clone()) }).await.expect_err("should fail");
+        assert_eq!(err.to_string(), "status list has expired");
+    }
+
+    #[tokio::test]
+    async fn builder() {
+        let issuer = new_issuer();
+        let jwk = issuer.public_jwk().expect("should get key");
+
+        let mut builder = StatusListBuilder::new(URI, 1000, 2)
+            .expect("should create")
+            .ttl(3600)
+            .add_signer(&issuer);
+        builder.set(3, INVALID).expect("should set");
+        builder.set(999, SUSPENDED).expect("should set");
+        assert!(builder.set(4, 4).is_err());
+        assert!(builder.set(1000, INVALID).is_err());
+
+        let token = builder.build().await.expect("should build");
+        let list = decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(list.len(), 1000);
+        assert_eq!(list.status_at(3).unwrap(), INVALID);
+        assert_eq!(list.status_at(999).unwrap(), SUSPENDED);
+        assert_eq!(list.status_at(4).unwrap(), VALID);
+
+        // update a single entry and re-sign
+        builder.set(3, VALID).expect("should set");
+        let token = builder.build().await.expect("should build");
+        let list = decode(&token, |_| async { Ok(jwk.clone()) }).await.expect("should decode");
+        assert_eq!(list.status_at(3).unwrap(), VALID);
+        assert_eq!(list.status_at(999).unwrap(), SUSPENDED);
+
+        assert!(StatusListBuilder::new(URI, 10, 3).is_err());
+    }
+}
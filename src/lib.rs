@@ -1,16 +1,20 @@
-#![feature(let_chains)]
-
 //! # Data Security for Vercre
 //!
 //! This crate provides common utilities for the Vercre project and is not
 //! intended to be used directly.
 
 pub mod cose;
+pub mod crypto;
+pub mod did;
 pub mod jose;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod x509;
 
-use std::future::{Future, IntoFuture};
+use std::future::{self, Future, IntoFuture};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 pub use crate::jose::jwa::Algorithm;
@@ -31,12 +35,29 @@ pub trait Signer: Send + Sync {
     /// `TrySign` is the fallible version of Sign.
     fn try_sign(&self, msg: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send;
 
+    /// Sign a message digest computed using [`Algorithm::prehash`], for
+    /// backends (such as a KMS) that only sign digests.
+    ///
+    /// The default returns an error: signers must opt in.
+    fn try_sign_prehashed(&self, digest: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let _ = digest;
+        future::ready(Err(anyhow!("signer does not support prehashed signing")))
+    }
+
     /// The verifying key (public key) from the signing keypair.
     ///
     /// The possibility of key rotation mean this key should only be referenced
     /// at the point of verifying a signature.
     fn verifying_key(&self) -> impl Future<Output = Result<Vec<u8>>> + Send;
 
+    /// The verifying key as a JWK, built from [`Signer::verifying_key`] and
+    /// [`Signer::algorithm`].
+    ///
+    /// Used to embed the key in a JWS header for self-contained tokens.
+    fn public_key_jwk(&self) -> impl Future<Output = Result<PublicKeyJwk>> + Send {
+        async { PublicKeyJwk::from_bytes(&self.verifying_key().await?, &self.algorithm()) }
+    }
+
     /// Signature algorithm used by the signer.
     fn algorithm(&self) -> Algorithm;
 
@@ -46,6 +67,153 @@ pub trait Signer: Send + Sync {
     /// Async and fallible because the client may need to access key information
     /// to construct the method reference.
     fn verification_method(&self) -> impl Future<Output = Result<String>> + Send;
+
+    /// All verification methods currently referencing the signer's key.
+    ///
+    /// During key rotation an issuer may want both the old and new methods
+    /// referenced so verifiers that cached either continue to validate. The
+    /// default returns the single [`Signer::verification_method`].
+    fn verification_methods(&self) -> impl Future<Output = Result<Vec<String>>> + Send {
+        async { Ok(vec![self.verification_method().await?]) }
+    }
+}
+
+/// `SyncSigner` is a synchronous (blocking) variant of [`Signer`] for
+/// implementers whose signing backend only offers blocking calls, such as an
+/// in-process HSM library.
+///
+/// A blanket implementation adapts every `SyncSigner` to the async [`Signer`]
+/// trait so it can be used anywhere a `Signer` is accepted (e.g.
+/// `jws::encode` or `JwsBuilder::build`) without requiring an async runtime
+/// to wrap the blocking calls.
+pub trait SyncSigner: Send + Sync {
+    /// Sign the message, returning the signature bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the signing backend fails to sign the message.
+    fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>>;
+
+    /// Sign a message digest computed using [`Algorithm::prehash`]. The
+    /// default returns an error: signers must opt in.
+    ///
+    /// # Errors
+    /// Returns an error if the signer does not support prehashed signing or
+    /// the signing backend fails to sign the digest.
+    fn try_sign_prehashed(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        let _ = digest;
+        Err(anyhow!("signer does not support prehashed signing"))
+    }
+
+    /// The verifying key (public key) from the signing keypair.
+    ///
+    /// # Errors
+    /// Returns an error if the signing backend cannot provide the key.
+    fn verifying_key(&self) -> Result<Vec<u8>>;
+
+    /// Signature algorithm used by the signer.
+    fn algorithm(&self) -> Algorithm;
+
+    /// The verification method the verifier should use to verify the signer's
+    /// signature. This is typically a DID URL + # + verification key ID.
+    ///
+    /// # Errors
+    /// Returns an error if the verification method cannot be constructed.
+    fn verification_method(&self) -> Result<String>;
+
+    /// All verification methods currently referencing the signer's key. The
+    /// default returns the single [`SyncSigner::verification_method`].
+    ///
+    /// # Errors
+    /// Returns an error if the verification methods cannot be constructed.
+    fn verification_methods(&self) -> Result<Vec<String>> {
+        Ok(vec![SyncSigner::verification_method(self)?])
+    }
+}
+
+impl<T: SyncSigner> Signer for T {
+    fn try_sign(&self, msg: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        future::ready(SyncSigner::try_sign(self, msg))
+    }
+
+    fn try_sign_prehashed(&self, digest: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        future::ready(SyncSigner::try_sign_prehashed(self, digest))
+    }
+
+    fn verifying_key(&self) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        future::ready(SyncSigner::verifying_key(self))
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        SyncSigner::algorithm(self)
+    }
+
+    fn verification_method(&self) -> impl Future<Output = Result<String>> + Send {
+        future::ready(SyncSigner::verification_method(self))
+    }
+
+    fn verification_methods(&self) -> impl Future<Output = Result<Vec<String>>> + Send {
+        future::ready(SyncSigner::verification_methods(self))
+    }
+}
+
+/// `DynSigner` is an object-safe variant of [`Signer`], for use where signers
+/// of different types must be held as trait objects, such as a registry of
+/// `Box<dyn DynSigner>` keyed by controller.
+///
+/// A blanket implementation adapts every `Signer` to `DynSigner`, so a
+/// reference to any signer can be passed where a `&dyn DynSigner` is accepted
+/// (e.g. `jws::encode` or `JwsBuilder::add_signer`).
+pub trait DynSigner: Send + Sync {
+    /// See [`Signer::try_sign`].
+    fn try_sign<'a>(&'a self, msg: &'a [u8]) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// See [`Signer::try_sign_prehashed`].
+    fn try_sign_prehashed<'a>(&'a self, digest: &'a [u8]) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// See [`Signer::verifying_key`].
+    fn verifying_key(&self) -> BoxFuture<'_, Result<Vec<u8>>>;
+
+    /// See [`Signer::public_key_jwk`].
+    fn public_key_jwk(&self) -> BoxFuture<'_, Result<PublicKeyJwk>>;
+
+    /// See [`Signer::algorithm`].
+    fn algorithm(&self) -> Algorithm;
+
+    /// See [`Signer::verification_method`].
+    fn verification_method(&self) -> BoxFuture<'_, Result<String>>;
+
+    /// See [`Signer::verification_methods`].
+    fn verification_methods(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+}
+
+impl<S: Signer> DynSigner for S {
+    fn try_sign<'a>(&'a self, msg: &'a [u8]) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(Signer::try_sign(self, msg))
+    }
+
+    fn try_sign_prehashed<'a>(&'a self, digest: &'a [u8]) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(Signer::try_sign_prehashed(self, digest))
+    }
+
+    fn verifying_key(&self) -> BoxFuture<'_, Result<Vec<u8>>> {
+        Box::pin(Signer::verifying_key(self))
+    }
+
+    fn public_key_jwk(&self) -> BoxFuture<'_, Result<PublicKeyJwk>> {
+        Box::pin(Signer::public_key_jwk(self))
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        Signer::algorithm(self)
+    }
+
+    fn verification_method(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(Signer::verification_method(self))
+    }
+
+    fn verification_methods(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(Signer::verification_methods(self))
+    }
 }
 
 /// A Receiver (Recipient) is required to decrypt an encrypted message.
@@ -63,7 +231,8 @@ pub trait Receiver: Send + Sync {
     /// derivation functionality using ECDH-ES. The resultant `[SharedSecret]`
     /// is used in decrypting the JWE ciphertext.
     ///
-    /// `[SecretKey]` supports both X25519 and secp256k1 private keys.
+    /// `[SecretKey]` supports X25519, secp256k1, and P-256 private keys. The
+    /// sender's curve is taken from the JWE `epk` header.
     ///
     /// # Errors
     /// LATER: document errors
@@ -118,6 +287,14 @@ pub enum KeyType {
     /// Octet string
     #[serde(rename = "oct")]
     Oct,
+
+    /// RSA key pair
+    #[serde(rename = "RSA")]
+    Rsa,
+
+    /// Algorithm key pair, used for post-quantum keys such as ML-DSA
+    #[serde(rename = "AKP")]
+    Akp,
 }
 
 /// Cryptographic curve type.
@@ -133,4 +310,26 @@ pub enum Curve {
     /// secp256k1 curve.
     #[serde(rename = "ES256K", alias = "secp256k1")]
     Es256K,
+
+    /// NIST P-256 curve.
+    #[serde(rename = "P-256")]
+    P256,
+
+    /// NIST P-384 curve.
+    #[serde(rename = "P-384")]
+    P384,
+
+    /// NIST P-521 curve.
+    #[serde(rename = "P-521")]
+    P521,
+
+    /// ML-DSA-44 parameter set. Not a curve, but identifies the parameter
+    /// set of an `AKP` key.
+    #[serde(rename = "ML-DSA-44")]
+    MlDsa44,
+
+    /// ML-DSA-65 parameter set. Not a curve, but identifies the parameter
+    /// set of an `AKP` key.
+    #[serde(rename = "ML-DSA-65")]
+    MlDsa65,
 }
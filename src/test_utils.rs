@@ -0,0 +1,249 @@
+//! # Test Utilities
+//!
+//! An in-memory key store providing [`Signer`](crate::Signer),
+//! [`Receiver`], and [`DidResolver`] implementations backed by real keys, so
+//! tests exercise the same cryptographic paths as production code.
+//!
+//! Enabled with the `test-utils` feature, and always available to the
+//! crate's own tests. Keys are generated on registration and never
+//! persisted: do not use outside of tests.
+
+use std::collections::HashMap;
+use std::future::{self, Future};
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, bail, Result};
+use ed25519_dalek::Signer as _;
+use rand::rngs::OsRng;
+use x25519_dalek::StaticSecret;
+
+use crate::did::{DidDocument, DidResolver, VerificationMethod};
+use crate::{Algorithm, PublicKey, PublicKeyJwk, Receiver, SecretKey, SharedSecret, SyncSigner};
+
+/// The fragment identifying a controller's signing key.
+pub const SIGNING_KEY: &str = "#key-0";
+
+/// The fragment identifying a controller's X25519 key agreement key.
+pub const ENCRYPTION_KEY: &str = "#key-1";
+
+/// An in-memory store of keys, indexed by controller (typically a DID).
+///
+/// Each registered controller holds a signing key (Ed25519 or secp256k1)
+/// and an X25519 key agreement key. Cloning the store shares the keys.
+#[derive(Clone, Default)]
+pub struct InMemoryKeyOps {
+    keys: Arc<RwLock<HashMap<String, Keys>>>,
+}
+
+#[derive(Clone)]
+struct Keys {
+    signing: SigningKey,
+    encryption: StaticSecret,
+}
+
+#[derive(Clone)]
+enum SigningKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    Es256K(k256::ecdsa::SigningKey),
+}
+
+impl InMemoryKeyOps {
+    /// Create an empty key store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate and register keys for `controller`, replacing any existing
+    /// keys. The signing key is generated for `algorithm`.
+    ///
+    /// # Errors
+    /// Returns an error if `algorithm` is not `EdDSA` or `ES256K`.
+    pub fn register(&self, controller: impl Into<String>, algorithm: &Algorithm) -> Result<()> {
+        let signing = match algorithm {
+            Algorithm::EdDSA => {
+                SigningKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut OsRng))
+            }
+            Algorithm::ES256K => SigningKey::Es256K(k256::ecdsa::SigningKey::random(&mut OsRng)),
+            _ => bail!("unsupported signing algorithm: {algorithm:?}"),
+        };
+        let keys = Keys {
+            signing,
+            encryption: StaticSecret::random_from_rng(OsRng),
+        };
+        self.keys
+            .write()
+            .map_err(|_| anyhow!("key store poisoned"))?
+            .insert(controller.into(), keys);
+        Ok(())
+    }
+
+    /// A [`Signer`](crate::Signer) for the controller's signing key.
+    ///
+    /// # Errors
+    /// Returns an error if the controller is not registered.
+    pub fn signer(&self, controller: &str) -> Result<InMemorySigner> {
+        Ok(InMemorySigner {
+            verification_method: format!("{controller}{SIGNING_KEY}"),
+            key: self.keys(controller)?.signing,
+        })
+    }
+
+    /// A [`Receiver`] for the controller's key agreement key.
+    ///
+    /// # Errors
+    /// Returns an error if the controller is not registered.
+    pub fn receiver(&self, controller: &str) -> Result<InMemoryReceiver> {
+        Ok(InMemoryReceiver {
+            key_id: format!("{controller}{ENCRYPTION_KEY}"),
+            secret: self.keys(controller)?.encryption,
+        })
+    }
+
+    /// The controller's public signing key as a JWK.
+    ///
+    /// # Errors
+    /// Returns an error if the controller is not registered.
+    pub fn public_jwk(&self, controller: &str) -> Result<PublicKeyJwk> {
+        self.signer(controller)?.public_jwk()
+    }
+
+    /// The controller's public key agreement key, for adding the controller
+    /// as a JWE recipient.
+    ///
+    /// # Errors
+    /// Returns an error if the controller is not registered.
+    pub fn public_key(&self, controller: &str) -> Result<PublicKey> {
+        let secret = self.keys(controller)?.encryption;
+        Ok(PublicKey::from(x25519_dalek::PublicKey::from(&secret)))
+    }
+
+    /// A DID Document listing the controller's signing and key agreement
+    /// keys.
+    ///
+    /// # Errors
+    /// Returns an error if the controller is not registered.
+    pub fn did_document(&self, controller: &str) -> Result<DidDocument> {
+        let method = |fragment: &str, jwk: PublicKeyJwk| VerificationMethod {
+            id: format!("{controller}{fragment}"),
+            type_: "JsonWebKey2020".to_string(),
+            controller: controller.to_string(),
+            public_key_jwk: Some(jwk),
+            public_key_multibase: None,
+        };
+        Ok(DidDocument {
+            id: controller.to_string(),
+            verification_method: vec![
+                method(SIGNING_KEY, self.public_jwk(controller)?),
+                method(ENCRYPTION_KEY, self.public_key(controller)?.to_jwk()),
+            ],
+        })
+    }
+
+    fn keys(&self, controller: &str) -> Result<Keys> {
+        let keys = self.keys.read().map_err(|_| anyhow!("key store poisoned"))?;
+        keys.get(controller).cloned().ok_or_else(|| anyhow!("no keys for controller {controller}"))
+    }
+}
+
+impl DidResolver for InMemoryKeyOps {
+    fn resolve(&self, did: &str) -> impl Future<Output = Result<DidDocument>> + Send {
+        future::ready(self.did_document(did))
+    }
+}
+
+/// A signer backed by an in-memory key, created by
+/// [`InMemoryKeyOps::signer`].
+pub struct InMemorySigner {
+    verification_method: String,
+    key: SigningKey,
+}
+
+impl InMemorySigner {
+    /// The signer's public key as a JWK.
+    ///
+    /// # Errors
+    /// Returns an error if the public key cannot be converted to a JWK.
+    pub fn public_jwk(&self) -> Result<PublicKeyJwk> {
+        PublicKeyJwk::from_bytes(&SyncSigner::verifying_key(self)?, &SyncSigner::algorithm(self))
+    }
+}
+
+impl SyncSigner for InMemorySigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        match &self.key {
+            SigningKey::Ed25519(key) => Ok(key.sign(msg).to_vec()),
+            SigningKey::Es256K(key) => {
+                let sig: k256::ecdsa::Signature = key.sign(msg);
+                Ok(sig.to_vec())
+            }
+        }
+    }
+
+    fn verifying_key(&self) -> Result<Vec<u8>> {
+        match &self.key {
+            SigningKey::Ed25519(key) => Ok(key.verifying_key().as_bytes().to_vec()),
+            SigningKey::Es256K(key) => Ok(key.verifying_key().to_sec1_bytes().to_vec()),
+        }
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match &self.key {
+            SigningKey::Ed25519(_) => Algorithm::EdDSA,
+            SigningKey::Es256K(_) => Algorithm::ES256K,
+        }
+    }
+
+    fn verification_method(&self) -> Result<String> {
+        Ok(self.verification_method.clone())
+    }
+}
+
+/// A receiver backed by an in-memory X25519 key, created by
+/// [`InMemoryKeyOps::receiver`].
+pub struct InMemoryReceiver {
+    key_id: String,
+    secret: StaticSecret,
+}
+
+impl Receiver for InMemoryReceiver {
+    fn key_id(&self) -> String {
+        self.key_id.clone()
+    }
+
+    fn shared_secret(
+        &self, sender_public: PublicKey,
+    ) -> impl Future<Output = Result<SharedSecret>> + Send {
+        future::ready(SecretKey::from(self.secret.to_bytes()).shared_secret(sender_public))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jose::{jwe, jws};
+    use crate::Jwt;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let key_ops = InMemoryKeyOps::new();
+        key_ops.register("did:example:alice", &Algorithm::EdDSA).expect("should register");
+        key_ops.register("did:example:bob", &Algorithm::ES256K).expect("should register");
+
+        // sign as bob and verify using the resolver
+        let signer = key_ops.signer("did:example:bob").expect("should get signer");
+        let token = jws::encode(&"hello", &signer).await.expect("should encode");
+        let jwt: Jwt<String> =
+            jws::decode_with_resolver(&token, &key_ops).await.expect("should decode");
+        assert_eq!(jwt.claims, "hello");
+
+        // encrypt to alice and decrypt with her receiver
+        let public_key = key_ops.public_key("did:example:alice").expect("should get key");
+        let jwe = jwe::encrypt("secret", public_key).expect("should encrypt");
+        let receiver = key_ops.receiver("did:example:alice").expect("should get receiver");
+        let plaintext: String = jwe::decrypt(&jwe, &receiver).await.expect("should decrypt");
+        assert_eq!(plaintext, "secret");
+
+        assert!(key_ops.signer("did:example:carol").is_err());
+    }
+}
@@ -0,0 +1,421 @@
+//! # X.509 Certificates
+//!
+//! Support for verifying X.509 certificate chains ([RFC5280]) such as those
+//! conveyed in the JOSE `x5c` header ([RFC7515 §4.1.6]).
+//!
+//! Chain verification checks that each certificate is within its validity
+//! period, was issued (and signed) by the next certificate in the chain, and
+//! that the chain terminates at one of the caller-supplied trust anchors.
+//! Every issuing certificate must be a CA (`basicConstraints` `cA`), permitted
+//! to sign certificates (`keyUsage` `keyCertSign`), and within its
+//! `pathLenConstraint`.
+//!
+//! Supported certificate signature algorithms are ECDSA with SHA-256 (P-256
+//! and secp256k1 issuer keys) and Ed25519.
+//!
+//! [RFC5280]: https://www.rfc-editor.org/rfc/rfc5280
+//! [RFC7515 §4.1.6]: https://www.rfc-editor.org/rfc/rfc7515#section-4.1.6
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64, Base64UrlUnpadded, Encoding};
+use ecdsa::signature::Verifier as _;
+use sha2::{Digest, Sha256};
+use x509_cert::der::asn1::ObjectIdentifier;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage};
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+pub use x509_cert::Certificate;
+
+use crate::jose::jwk::PublicKeyJwk;
+use crate::{Curve, KeyType};
+
+const ID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const ID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const SECP256K1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+
+/// Parse an `x5c` header value: a list of standard base64 (not base64url)
+/// encoded DER certificates.
+///
+/// # Errors
+/// Returns an error if any entry is not a valid base64-encoded DER
+/// certificate.
+pub fn from_x5c(x5c: &[String]) -> Result<Vec<Certificate>> {
+    x5c.iter()
+        .map(|encoded| {
+            let der = Base64::decode_vec(encoded)
+                .map_err(|e| anyhow!("issue decoding certificate: {e}"))?;
+            Certificate::from_der(&der).map_err(|e| anyhow!("issue parsing certificate: {e}"))
+        })
+        .collect()
+}
+
+/// Verify a certificate chain, ordered leaf first, up to one of the provided
+/// trust anchors.
+///
+/// # Errors
+/// Returns an error if the chain is empty, any certificate is outside its
+/// validity period, any certificate was not issued by the next in the chain,
+/// or the chain does not terminate at a trust anchor.
+pub fn verify_chain(chain: &[Certificate], trust_anchors: &[Certificate]) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    verify_chain_at(chain, trust_anchors, now)
+}
+
+// Verify the chain at the provided time (duration since Unix epoch).
+fn verify_chain_at(
+    chain: &[Certificate], trust_anchors: &[Certificate], now: Duration,
+) -> Result<()> {
+    let Some(last) = chain.last() else {
+        bail!("certificate chain is empty");
+    };
+
+    for cert in chain {
+        check_validity(cert, now)?;
+    }
+    for (depth, pair) in chain.windows(2).enumerate() {
+        check_ca(&pair[1], depth)?;
+        verify_issued_by(&pair[0], &pair[1])?;
+    }
+
+    // chain must terminate at (or be issued by) a trust anchor
+    let mut error = None;
+    for anchor in trust_anchors {
+        if anchor == last {
+            return Ok(());
+        }
+        if anchor.tbs_certificate.subject != last.tbs_certificate.issuer {
+            continue;
+        }
+        // keep looking on failure: a rollover anchor may share the same name
+        let issued = check_validity(anchor, now)
+            .and_then(|()| check_ca(anchor, chain.len() - 1))
+            .and_then(|()| verify_issued_by(last, anchor));
+        match issued {
+            Ok(()) => return Ok(()),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    Err(error.unwrap_or_else(|| anyhow!("certificate chain does not terminate at a trust anchor")))
+}
+
+// Check `cert` is permitted to issue certificates, where `depth` is the
+// number of intermediate CA certificates between it and the leaf.
+fn check_ca(cert: &Certificate, depth: usize) -> Result<()> {
+    let tbs = &cert.tbs_certificate;
+    let Some((_, constraints)) = tbs.get::<BasicConstraints>()? else {
+        bail!("issuer certificate is missing basicConstraints");
+    };
+    if !constraints.ca {
+        bail!("issuer certificate is not a CA");
+    }
+    if constraints.path_len_constraint.is_some_and(|max| depth > usize::from(max)) {
+        bail!("certificate chain exceeds the issuer's pathLenConstraint");
+    }
+
+    let Some((_, key_usage)) = tbs.get::<KeyUsage>()? else {
+        bail!("issuer certificate is missing keyUsage");
+    };
+    if !key_usage.key_cert_sign() {
+        bail!("issuer certificate keyUsage does not include keyCertSign");
+    }
+    Ok(())
+}
+
+// Check the certificate is valid at the provided time.
+fn check_validity(cert: &Certificate, now: Duration) -> Result<()> {
+    let validity = &cert.tbs_certificate.validity;
+    if now < validity.not_before.to_unix_duration() {
+        bail!("certificate is not yet valid");
+    }
+    if now > validity.not_after.to_unix_duration() {
+        bail!("certificate has expired");
+    }
+    Ok(())
+}
+
+// Verify `cert` was issued and signed by `issuer`.
+fn verify_issued_by(cert: &Certificate, issuer: &Certificate) -> Result<()> {
+    if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        bail!("certificate issuer does not match the next certificate's subject");
+    }
+
+    let tbs = cert.tbs_certificate.to_der()?;
+    let Some(sig) = cert.signature.as_bytes() else {
+        bail!("invalid certificate signature");
+    };
+    let spki = &issuer.tbs_certificate.subject_public_key_info;
+    let Some(key) = spki.subject_public_key.as_bytes() else {
+        bail!("invalid issuer public key");
+    };
+
+    let alg = cert.signature_algorithm.oid;
+    if alg == ID_ED25519 {
+        let key = ed25519_dalek::VerifyingKey::try_from(key)
+            .map_err(|e| anyhow!("invalid issuer public key: {e}"))?;
+        let sig = ed25519_dalek::Signature::from_slice(sig)?;
+        return key.verify(&tbs, &sig).map_err(|e| anyhow!("invalid certificate signature: {e}"));
+    }
+    if alg != ECDSA_WITH_SHA256 {
+        bail!("unsupported certificate signature algorithm: {alg}");
+    }
+
+    match ec_curve(spki)? {
+        SECP256R1 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key)?;
+            let sig = p256::ecdsa::Signature::from_der(sig)?;
+            key.verify(&tbs, &sig).map_err(|e| anyhow!("invalid certificate signature: {e}"))
+        }
+        SECP256K1 => {
+            let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(key)?;
+            let sig = k256::ecdsa::Signature::from_der(sig)?;
+            key.verify(&tbs, &sig).map_err(|e| anyhow!("invalid certificate signature: {e}"))
+        }
+        crv => bail!("unsupported issuer curve: {crv}"),
+    }
+}
+
+// The named curve of an EC public key.
+fn ec_curve(spki: &SubjectPublicKeyInfoOwned) -> Result<ObjectIdentifier> {
+    if spki.algorithm.oid != ID_EC_PUBLIC_KEY {
+        bail!("unsupported public key algorithm: {}", spki.algorithm.oid);
+    }
+    let Some(params) = &spki.algorithm.parameters else {
+        bail!("missing EC curve parameters");
+    };
+    Ok(params.decode_as()?)
+}
+
+/// Convert the certificate's subject public key to a JWK.
+///
+/// # Errors
+/// Returns an error if the key type or curve is not supported.
+pub fn public_key_jwk(cert: &Certificate) -> Result<PublicKeyJwk> {
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    let Some(key) = spki.subject_public_key.as_bytes() else {
+        bail!("invalid public key");
+    };
+
+    if spki.algorithm.oid == ID_ED25519 {
+        return Ok(PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(key),
+            ..PublicKeyJwk::default()
+        });
+    }
+
+    match ec_curve(spki)? {
+        SECP256K1 => {
+            let point = k256::ecdsa::VerifyingKey::from_sec1_bytes(key)?.to_encoded_point(false);
+            let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                bail!("invalid public key");
+            };
+            Ok(PublicKeyJwk {
+                kty: KeyType::Ec,
+                crv: Curve::Es256K,
+                x: Base64UrlUnpadded::encode_string(x),
+                y: Some(Base64UrlUnpadded::encode_string(y)),
+                ..PublicKeyJwk::default()
+            })
+        }
+        SECP256R1 => {
+            let point = p256::ecdsa::VerifyingKey::from_sec1_bytes(key)?.to_encoded_point(false);
+            let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                bail!("invalid public key");
+            };
+            Ok(PublicKeyJwk {
+                kty: KeyType::Ec,
+                crv: Curve::P256,
+                x: Base64UrlUnpadded::encode_string(x),
+                y: Some(Base64UrlUnpadded::encode_string(y)),
+                ..PublicKeyJwk::default()
+            })
+        }
+        crv => bail!("unsupported public key curve: {crv}"),
+    }
+}
+
+/// The certificate's SHA-256 thumbprint, as used in the JOSE `x5t#S256`
+/// header: the base64url-encoded SHA-256 digest of the DER encoding.
+///
+/// # Errors
+/// Returns an error if the certificate cannot be DER encoded.
+pub fn thumbprint_s256(cert: &Certificate) -> Result<String> {
+    let der = cert.to_der().map_err(|e| anyhow!("issue encoding certificate: {e}"))?;
+    Ok(Base64UrlUnpadded::encode_string(&Sha256::digest(der)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::slice;
+    use std::str::FromStr;
+
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::der::asn1::BitString;
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::{AlgorithmIdentifierOwned, EncodePublicKey};
+    use x509_cert::time::Validity;
+
+    use super::*;
+
+    // Issue a certificate for an Ed25519 key, signed by the P-256 CA key.
+    pub fn issue(
+        ca_key: &p256::ecdsa::SigningKey, issuer: &str, subject: &str, key: &SigningKey,
+    ) -> Certificate {
+        let spki = SubjectPublicKeyInfoOwned {
+            algorithm: AlgorithmIdentifierOwned {
+                oid: ID_ED25519,
+                parameters: None,
+            },
+            subject_public_key: BitString::from_bytes(key.verifying_key().as_bytes()).unwrap(),
+        };
+        let profile = Profile::Leaf {
+            issuer: Name::from_str(issuer).unwrap(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        };
+        let validity = Validity::from_now(Duration::from_secs(3600)).unwrap();
+        let builder = CertificateBuilder::new(
+            profile,
+            SerialNumber::from(2u32),
+            validity,
+            Name::from_str(subject).unwrap(),
+            spki,
+            ca_key,
+        )
+        .unwrap();
+        builder.build::<p256::ecdsa::DerSignature>().unwrap()
+    }
+
+    // Self-signed P-256 root CA certificate.
+    pub fn root(ca_key: &p256::ecdsa::SigningKey, subject: &str) -> Certificate {
+        let der = ca_key.verifying_key().to_public_key_der().unwrap();
+        let spki = SubjectPublicKeyInfoOwned::from_der(der.as_bytes()).unwrap();
+        let validity = Validity::from_now(Duration::from_secs(3600)).unwrap();
+        let builder = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            validity,
+            Name::from_str(subject).unwrap(),
+            spki,
+            ca_key,
+        )
+        .unwrap();
+        builder.build::<p256::ecdsa::DerSignature>().unwrap()
+    }
+
+    // Issue a certificate for a P-256 key using the provided profile.
+    fn issue_p256(
+        ca_key: &p256::ecdsa::SigningKey, profile: Profile, subject: &str,
+        key: &p256::ecdsa::SigningKey,
+    ) -> Certificate {
+        let der = key.verifying_key().to_public_key_der().unwrap();
+        let spki = SubjectPublicKeyInfoOwned::from_der(der.as_bytes()).unwrap();
+        let validity = Validity::from_now(Duration::from_secs(3600)).unwrap();
+        let builder = CertificateBuilder::new(
+            profile,
+            SerialNumber::from(3u32),
+            validity,
+            Name::from_str(subject).unwrap(),
+            spki,
+            ca_key,
+        )
+        .unwrap();
+        builder.build::<p256::ecdsa::DerSignature>().unwrap()
+    }
+
+    #[test]
+    fn chain() {
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let root = root(&ca_key, "CN=Root CA");
+        let leaf_key = SigningKey::generate(&mut OsRng);
+        let leaf = issue(&ca_key, "CN=Root CA", "CN=Issuer", &leaf_key);
+
+        let anchors = [root.clone()];
+        verify_chain(slice::from_ref(&leaf), &anchors).expect("should verify leaf");
+        verify_chain(&[leaf.clone(), root], &anchors).expect("should verify full chain");
+
+        let jwk = public_key_jwk(&leaf).expect("should convert");
+        assert_eq!(jwk.x, Base64UrlUnpadded::encode_string(leaf_key.verifying_key().as_bytes()));
+
+        // expired
+        let later =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_secs(7200);
+        let err = verify_chain_at(&[leaf], &anchors, later).expect_err("should fail");
+        assert_eq!(err.to_string(), "certificate has expired");
+    }
+
+    #[test]
+    fn untrusted() {
+        let ca_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let leaf = issue(&ca_key, "CN=Root CA", "CN=Issuer", &SigningKey::generate(&mut OsRng));
+
+        // same name, different key
+        let other_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let other = root(&other_key, "CN=Root CA");
+        assert!(verify_chain(slice::from_ref(&leaf), &[other]).is_err());
+
+        let unrelated = root(&other_key, "CN=Other CA");
+        let err = verify_chain(&[leaf], &[unrelated]).expect_err("should fail");
+        assert_eq!(err.to_string(), "certificate chain does not terminate at a trust anchor");
+    }
+
+    #[test]
+    fn ca_constraints() {
+        let root_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let anchors = [root(&root_key, "CN=Root CA")];
+        let holder_key = SigningKey::generate(&mut OsRng);
+
+        // an end-entity certificate cannot issue certificates
+        let issuer_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let leaf_profile = Profile::Leaf {
+            issuer: Name::from_str("CN=Root CA").unwrap(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        };
+        let issuer = issue_p256(&root_key, leaf_profile, "CN=Issuer", &issuer_key);
+        let holder = issue(&issuer_key, "CN=Issuer", "CN=Holder", &holder_key);
+        let err = verify_chain(&[holder, issuer], &anchors).expect_err("should fail");
+        assert_eq!(err.to_string(), "issuer certificate is not a CA");
+
+        // an intermediate CA can
+        let sub_ca = |issuer: &str, path_len_constraint| Profile::SubCA {
+            issuer: Name::from_str(issuer).unwrap(),
+            path_len_constraint,
+        };
+        let intermediate =
+            issue_p256(&root_key, sub_ca("CN=Root CA", Some(0)), "CN=Issuer", &issuer_key);
+        let holder = issue(&issuer_key, "CN=Issuer", "CN=Holder", &holder_key);
+        verify_chain(&[holder, intermediate.clone()], &anchors).expect("should verify");
+
+        // but not beyond its `pathLenConstraint`
+        let nested_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let nested = issue_p256(&issuer_key, sub_ca("CN=Issuer", None), "CN=Nested", &nested_key);
+        let holder = issue(&nested_key, "CN=Nested", "CN=Holder", &holder_key);
+        let err = verify_chain(&[holder, nested, intermediate], &anchors).expect_err("should fail");
+        assert_eq!(err.to_string(), "certificate chain exceeds the issuer's pathLenConstraint");
+
+        // P-256 subject keys convert to a JWK
+        let jwk = public_key_jwk(&anchors[0]).expect("should convert");
+        assert_eq!(jwk.crv, Curve::P256);
+        jwk.validate().expect("should be valid");
+    }
+
+    #[test]
+    fn rollover() {
+        let old_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let new_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let anchors = [root(&old_key, "CN=Root CA"), root(&new_key, "CN=Root CA")];
+
+        let leaf = issue(&new_key, "CN=Root CA", "CN=Issuer", &SigningKey::generate(&mut OsRng));
+        verify_chain(&[leaf], &anchors).expect("should verify against the second anchor");
+    }
+}